@@ -3,11 +3,14 @@ use llm_devices::logging::{LoggingConfig, LoggingConfigTrait};
 use llm_interface::llms::{
     api::config::{ApiConfig, LlmApiConfigTrait},
     local::{
-        llama_cpp::{LlamaCppBackend, LlamaCppConfig},
+        llama_cpp::{
+            server::DraftModelConfig, supervisor::SupervisorConfig, LlamaCppBackend, LlamaCppConfig,
+        },
         LlmLocalTrait, LocalLlmConfig,
     },
     LlmBackend,
 };
+use llm_interface::requests::embedding::EmbeddingPooling;
 use llm_models::local_model::{
     gguf::{loaders::preset::GgufPresetLoader, GgufLoader},
     GgufLoaderTrait, GgufPresetTrait, HfTokenTrait,
@@ -27,6 +30,71 @@ impl LlamaCppBackendBuilder {
             LlamaCppBackend::new(self.config, self.local_config, self.llm_loader).await?,
         ))))
     }
+
+    /// How long to wait for the server to load the model on start up. Defaults to 30 seconds.
+    pub fn startup_timeout(mut self, startup_timeout: std::time::Duration) -> Self {
+        self.config.startup_timeout = startup_timeout;
+        self
+    }
+
+    /// Enables speculative decoding: the draft model proposes tokens that the main model verifies in one pass.
+    /// See [DraftModelConfig] for placing the draft model on the CPU or GPU.
+    pub fn draft_model(mut self, draft_model: DraftModelConfig) -> Self {
+        self.config.draft_model = Some(draft_model);
+        self
+    }
+
+    /// Starts the server with this many slots, and sends as many requests to it at once. The context size is split
+    /// between the slots.
+    pub fn parallel_slots(mut self, parallel_slots: u32) -> Self {
+        self.config = self.config.with_parallel_slots(parallel_slots);
+        self
+    }
+
+    /// The number of requests sent to the server at once. Further requests queue in the client in FIFO order.
+    /// Defaults to 1.
+    pub fn max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.config.max_concurrent_requests = max_concurrent_requests;
+        self
+    }
+
+    /// Requests arriving when this many are already queued fail instead of waiting. Defaults to unbounded.
+    pub fn max_queue_depth(mut self, max_queue_depth: usize) -> Self {
+        self.config.max_queue_depth = Some(max_queue_depth);
+        self
+    }
+
+    /// Restarts the server with backoff after repeated request failures. See [SupervisorConfig].
+    pub fn supervisor(mut self, supervisor: SupervisorConfig) -> Self {
+        self.config.supervisor = Some(supervisor);
+        self
+    }
+
+    /// Shares one server between the backends in this process that run the same model with the same device config,
+    /// instead of each spawning its own. The server shuts down when the last of them is dropped.
+    pub fn shared_server(mut self) -> Self {
+        self.config.shared_server = true;
+        self
+    }
+
+    /// How long, and how often, to check whether a server is already running before starting one. A running server
+    /// that doesn't answer within the timeout is replaced. Defaults to 650 milliseconds, checking every 200
+    /// milliseconds.
+    pub fn status_check(
+        mut self,
+        timeout: std::time::Duration,
+        interval: std::time::Duration,
+    ) -> Self {
+        self.config = self.config.with_status_check(timeout, interval);
+        self
+    }
+
+    /// Starts the server for embeddings instead of text generation, pooling each input's token embeddings as
+    /// given. See [LlmBackend::embeddings].
+    pub fn embeddings(mut self, pooling: EmbeddingPooling) -> Self {
+        self.config = self.config.with_embeddings(pooling);
+        self
+    }
 }
 
 impl LlmLocalTrait for LlamaCppBackendBuilder {
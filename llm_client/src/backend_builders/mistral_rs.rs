@@ -2,7 +2,7 @@ use crate::LlmClient;
 use llm_devices::logging::{LoggingConfig, LoggingConfigTrait};
 use llm_interface::llms::{
     local::{
-        mistral_rs::{MistralRsBackend, MistralRsConfig},
+        mistral_rs::{isq::MistralRsIsq, MistralRsBackend, MistralRsConfig},
         LlmLocalTrait, LocalLlmConfig,
     },
     LlmBackend,
@@ -25,6 +25,15 @@ impl MistralRsBackendBuilder {
             MistralRsBackend::new(self.config, self.llm_loader).await?,
         ))))
     }
+
+    /// Loads the unquantized safetensors model from the Hugging Face repo and quantizes it to `isq` while loading,
+    /// to fit a model in VRAM without a GGUF of the wanted size. The GGUF model selected by the loader still
+    /// provides the tokenizer, chat template and metadata, so it should be the same model.
+    pub fn isq<T: Into<String>>(mut self, isq: MistralRsIsq, unquantized_repo_id: T) -> Self {
+        self.config.isq = Some(isq);
+        self.config.unquantized_repo_id = Some(unquantized_repo_id.into());
+        self
+    }
 }
 
 impl LlmLocalTrait for MistralRsBackendBuilder {
@@ -1,7 +1,7 @@
 use llm_interface::{
     llms::LlmBackend,
     requests::{
-        completion::{CompletionRequest, CompletionResponse},
+        completion::{parse_json, CompletionRequest, CompletionResponse},
         logit_bias::{LogitBias, LogitBiasTrait},
         req_components::{RequestConfig, RequestConfigTrait},
     },
@@ -11,15 +11,30 @@ use llm_prompt::LlmPrompt;
 #[derive(Clone)]
 pub struct BasicCompletion {
     pub base_req: CompletionRequest,
+    /// Repair almost valid JSON before [BasicCompletion::run_json] parses it. See
+    /// [llm_interface::requests::completion::repair_json].
+    pub json_repair: bool,
 }
 
 impl BasicCompletion {
     pub fn new(backend: std::sync::Arc<LlmBackend>) -> Self {
         Self {
             base_req: CompletionRequest::new(backend),
+            json_repair: false,
         }
     }
 
+    /// Strip markdown fences, surrounding text and trailing commas from the response before [BasicCompletion::run_json]
+    /// parses it. Useful with API backends, which can't constrain the response with a grammar.
+    ///
+    /// # Default
+    ///
+    /// Defaults to `false`.
+    pub fn json_repair(&mut self, json_repair: bool) -> &mut Self {
+        self.json_repair = json_repair;
+        self
+    }
+
     pub fn prompt(&mut self) -> &mut LlmPrompt {
         &mut self.base_req.prompt
     }
@@ -41,6 +56,15 @@ impl BasicCompletion {
         Ok(res)
     }
 
+    /// Runs the request and parses the response as JSON, repairing it first if [BasicCompletion::json_repair] is set.
+    /// The prompt should ask for JSON.
+    pub async fn run_json(&mut self) -> crate::Result<serde_json::Value> {
+        let res = self.run().await?;
+        parse_json(res.content_trimmed(), self.json_repair).map_err(|e| {
+            anyhow::format_err!("Failed to parse the response as JSON: {e}\n{}", res.content)
+        })
+    }
+
     fn parse_response(&self, content: &str) -> crate::Result<String> {
         if content.is_empty() {
             return Err(anyhow::format_err!(
@@ -0,0 +1,56 @@
+use std::time::Instant;
+
+/// The time source used by [super::CascadeFlow] to measure its duration.
+///
+/// Defaults to [SystemClock]. Implement this to inject a fake clock in tests.
+pub trait CascadeClock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// The real monotonic clock, backed by [Instant::now].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl CascadeClock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::cascade::CascadeFlow;
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    struct FakeClock {
+        now: Mutex<Instant>,
+    }
+
+    impl FakeClock {
+        fn advance(&self, by: Duration) {
+            *self.now.lock().unwrap() += by;
+        }
+    }
+
+    impl CascadeClock for FakeClock {
+        fn now(&self) -> Instant {
+            *self.now.lock().unwrap()
+        }
+    }
+
+    #[test]
+    fn test_duration_with_fake_clock() {
+        let clock = Arc::new(FakeClock {
+            now: Mutex::new(Instant::now()),
+        });
+        let mut flow = CascadeFlow::new("test").with_clock(clock.clone());
+        flow.open_cascade();
+        clock.advance(Duration::from_millis(1500));
+        flow.close_cascade().unwrap();
+        assert_eq!(flow.duration, Duration::from_millis(1500));
+    }
+}
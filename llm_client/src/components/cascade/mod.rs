@@ -1,14 +1,20 @@
+pub mod clock;
+pub mod record;
 pub mod round;
 pub mod step;
 
+use crate::components::grammar::{Grammar, GrammarError};
 use anyhow::{anyhow, Result};
+use clock::{CascadeClock, SystemClock};
 use core::panic;
 use llm_interface::requests::{
-    completion::{CompletionFinishReason, CompletionRequest},
+    completion::{CompletionFinishReason, CompletionRequest, ResponseCache},
+    res_components::InferenceProbabilities,
     stop_sequence::StoppingSequence,
 };
 pub use round::CascadeRound;
 use step::InferenceStep;
+use tracing::Instrument;
 
 #[derive(Clone)]
 pub struct CascadeFlow {
@@ -17,19 +23,68 @@ pub struct CascadeFlow {
     pub result_can_be_none: bool,
     pub rounds: Vec<CascadeRound>,
     pub start_time: std::time::Instant,
+    pub clock: std::sync::Arc<dyn CascadeClock>,
+    /// Maximum total duration for [CascadeFlow::run_all_rounds]. Checked between rounds.
+    pub deadline: Option<std::time::Duration>,
+    /// Runs every round concurrently on its own copy of the base request, as if the rounds were independent nodes of
+    /// a dependency graph. See [CascadeFlow::independent_rounds].
+    pub independent_rounds: bool,
+    /// Whether the `Display` output and [CascadeFlow::write_to] use ANSI colors. Defaults to true, unless the
+    /// `NO_COLOR` environment variable is set or stdout isn't a terminal.
+    pub ansi_colors: bool,
 }
 
 impl CascadeFlow {
     pub fn new<T: Into<String>>(cascade_name: T) -> Self {
+        let clock: std::sync::Arc<dyn CascadeClock> = std::sync::Arc::new(SystemClock);
         Self {
             cascade_name: cascade_name.into(),
-            start_time: std::time::Instant::now(),
+            start_time: clock.now(),
             duration: std::time::Duration::default(),
             rounds: Vec::new(),
             result_can_be_none: false,
+            clock,
+            deadline: None,
+            independent_rounds: false,
+            ansi_colors: ansi_colors_supported(),
         }
     }
 
+    /// Replaces the time source used to measure [CascadeFlow::duration].
+    pub fn with_clock(mut self, clock: std::sync::Arc<dyn CascadeClock>) -> Self {
+        self.start_time = clock.now();
+        self.clock = clock;
+        self
+    }
+
+    /// Set the maximum total duration for [CascadeFlow::run_all_rounds].
+    ///
+    /// If the deadline has passed when a round finishes, the remaining rounds are skipped and a [CascadeTimeout] error is returned.
+    /// The completed rounds are kept on the flow.
+    pub fn deadline(&mut self, deadline: std::time::Duration) -> &mut Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Run the rounds concurrently without any context from each other, e.g. for map steps over separate inputs.
+    /// Rounds can still declare dependencies with [CascadeRound::depends_on].
+    pub fn independent_rounds(&mut self, independent_rounds: bool) -> &mut Self {
+        self.independent_rounds = independent_rounds;
+        self
+    }
+
+    /// Disable ANSI colors to write plain text, e.g. to a log file.
+    pub fn with_ansi_colors(mut self, ansi_colors: bool) -> Self {
+        self.ansi_colors = ansi_colors;
+        self
+    }
+
+    /// Writes the `Display` output of the cascade to `writer`, e.g. to tee a run to a file.
+    pub fn write_to(&self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+        write!(writer, "{self}")?;
+        writer.flush()
+    }
+
     pub fn new_round<T: Into<String>>(&mut self, task: T) -> &mut CascadeRound {
         let round = CascadeRound::new(task);
         self.rounds.push(round);
@@ -40,17 +95,162 @@ impl CascadeFlow {
         self.rounds.push(round);
     }
 
+    /// True if any round declared dependencies with [CascadeRound::depends_on].
+    pub fn has_dependencies(&self) -> bool {
+        self.rounds.iter().any(|r| !r.depends_on.is_empty())
+    }
+
+    fn runs_as_graph(&self) -> bool {
+        self.independent_rounds || self.has_dependencies()
+    }
+
+    /// The indices of the rounds grouped into the stages [CascadeFlow::run_all_rounds] runs them in. The rounds of
+    /// a stage only depend on rounds of earlier stages and run concurrently.
+    ///
+    /// Without any declared dependencies, and unless [CascadeFlow::independent_rounds] is set, every round is its own
+    /// stage, in the order added, so each round sees all earlier rounds. Errors if a dependency doesn't exist or the
+    /// dependencies form a cycle.
+    pub fn execution_order(&self) -> Result<Vec<Vec<usize>>> {
+        if !self.runs_as_graph() {
+            return Ok((0..self.rounds.len()).map(|i| vec![i]).collect());
+        }
+        for (i, round) in self.rounds.iter().enumerate() {
+            for &dependency in &round.depends_on {
+                if dependency >= self.rounds.len() {
+                    return Err(anyhow!(
+                        "Round {i} depends on round {dependency}, but the cascade only has {} rounds",
+                        self.rounds.len()
+                    ));
+                }
+                if dependency == i {
+                    return Err(anyhow!("Round {i} depends on itself"));
+                }
+            }
+        }
+        let mut done = vec![false; self.rounds.len()];
+        let mut stages = Vec::new();
+        while done.iter().any(|d| !d) {
+            let stage: Vec<usize> = (0..self.rounds.len())
+                .filter(|&i| !done[i] && self.rounds[i].depends_on.iter().all(|&d| done[d]))
+                .collect();
+            if stage.is_empty() {
+                let cycle: Vec<usize> = (0..self.rounds.len()).filter(|&i| !done[i]).collect();
+                return Err(anyhow!(
+                    "The dependencies between rounds {cycle:?} form a cycle"
+                ));
+            }
+            for &i in &stage {
+                done[i] = true;
+            }
+            stages.push(stage);
+        }
+        Ok(stages)
+    }
+
+    /// Runs the rounds in the order given by [CascadeFlow::execution_order].
+    ///
+    /// Without declared dependencies or [CascadeFlow::independent_rounds] the rounds run one after another on
+    /// `base_req`. Otherwise each round runs on a copy of `base_req` that only has the tasks and outcomes of the rounds
+    /// it depends on, directly or indirectly, and the rounds of a stage run concurrently. Afterwards every round's task
+    /// and outcome is added to `base_req` in execution order.
     pub async fn run_all_rounds(&mut self, base_req: &mut CompletionRequest) -> Result<()> {
-        self.start_time = std::time::Instant::now();
+        let span = cascade_span(&self.cascade_name, self.rounds.len());
+        self.run_rounds(base_req).instrument(span).await
+    }
 
-        for round in self.rounds.iter_mut() {
-            round.run_all_steps(base_req).await?;
+    async fn run_rounds(&mut self, base_req: &mut CompletionRequest) -> Result<()> {
+        self.start_time = self.clock.now();
+        if self.runs_as_graph() {
+            return self.run_round_graph(base_req).await;
         }
 
-        self.duration = self.start_time.elapsed();
+        let round_count = self.rounds.len();
+        for (i, round) in self.rounds.iter_mut().enumerate() {
+            round
+                .run_all_steps(base_req)
+                .instrument(round_span(i))
+                .await?;
+            let elapsed = self.clock.now().duration_since(self.start_time);
+            if let Some(deadline) = self.deadline {
+                if elapsed > deadline && i + 1 < round_count {
+                    self.duration = elapsed;
+                    return Err(CascadeTimeout {
+                        deadline,
+                        elapsed,
+                        completed_rounds: i + 1,
+                        total_rounds: round_count,
+                    }
+                    .into());
+                }
+            }
+        }
+
+        self.duration = self.clock.now().duration_since(self.start_time);
+        Ok(())
+    }
+
+    async fn run_round_graph(&mut self, base_req: &mut CompletionRequest) -> Result<()> {
+        let stages = self.execution_order()?;
+        let order: Vec<usize> = stages.iter().flatten().copied().collect();
+        let round_count = self.rounds.len();
+        let mut completed_rounds = 0;
+        for (stage_index, stage) in stages.iter().enumerate() {
+            let mut reqs = Vec::with_capacity(stage.len());
+            for &i in stage {
+                let req = base_req.clone();
+                for ancestor in order.iter().filter(|&&a| self.is_ancestor(a, i)) {
+                    self.rounds[*ancestor].add_context_messages(&req)?;
+                }
+                reqs.push(req);
+            }
+            let runs = self
+                .rounds
+                .iter_mut()
+                .enumerate()
+                .filter(|(i, _)| stage.contains(i))
+                .zip(reqs.iter_mut())
+                .map(|((i, round), req)| round.run_all_steps(req).instrument(round_span(i)));
+            for result in futures_util::future::join_all(runs).await {
+                result?;
+            }
+            completed_rounds += stage.len();
+
+            let elapsed = self.clock.now().duration_since(self.start_time);
+            if let Some(deadline) = self.deadline {
+                if elapsed > deadline && stage_index + 1 < stages.len() {
+                    self.duration = elapsed;
+                    return Err(CascadeTimeout {
+                        deadline,
+                        elapsed,
+                        completed_rounds,
+                        total_rounds: round_count,
+                    }
+                    .into());
+                }
+            }
+        }
+        for i in order {
+            self.rounds[i].add_context_messages(base_req)?;
+        }
+        self.duration = self.clock.now().duration_since(self.start_time);
         Ok(())
     }
 
+    /// True if round `round` depends on `ancestor`, directly or through other rounds.
+    fn is_ancestor(&self, ancestor: usize, round: usize) -> bool {
+        let mut stack = self.rounds[round].depends_on.clone();
+        let mut seen = vec![false; self.rounds.len()];
+        while let Some(dependency) = stack.pop() {
+            if dependency == ancestor {
+                return true;
+            }
+            if !std::mem::replace(&mut seen[dependency], true) {
+                stack.extend(&self.rounds[dependency].depends_on);
+            }
+        }
+        false
+    }
+
     pub fn last_round(&mut self) -> Result<&mut CascadeRound> {
         match self.rounds.last_mut() {
             Some(round) => Ok(round),
@@ -59,11 +259,11 @@ impl CascadeFlow {
     }
 
     pub fn open_cascade(&mut self) {
-        self.start_time = std::time::Instant::now();
+        self.start_time = self.clock.now();
     }
 
     pub fn close_cascade(&mut self) -> Result<()> {
-        self.duration = self.start_time.elapsed();
+        self.duration = self.clock.now().duration_since(self.start_time);
         Ok(())
     }
 
@@ -73,45 +273,242 @@ impl CascadeFlow {
             None => panic!("No rounds in cascade"),
         }
     }
+
+    pub fn primitive_probabilities(&self) -> Option<Vec<InferenceProbabilities>> {
+        match self.rounds.last() {
+            Some(round) => round.primitive_probabilities(),
+            None => panic!("No rounds in cascade"),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+#[error("cascade exceeded its deadline of {deadline:?} after {elapsed:?} ({completed_rounds} of {total_rounds} rounds completed)")]
+pub struct CascadeTimeout {
+    pub deadline: std::time::Duration,
+    pub elapsed: std::time::Duration,
+    pub completed_rounds: usize,
+    pub total_rounds: usize,
+}
+
+/// Returned when a step's output doesn't parse as the primitive set with [step::StepConfig::expect_primitive].
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+#[error("expected the step output to parse as {type_description} after {attempts} attempt(s): {error}. Raw output: {raw_content:?}")]
+pub struct InvalidPrimitiveError {
+    pub type_description: String,
+    pub attempts: u16,
+    pub error: String,
+    /// The unmodified content of the last response.
+    pub raw_content: String,
+}
+
+/// A span for a cascade, parenting the spans of its rounds. Only enabled with the `otel` feature.
+fn cascade_span(cascade_name: &str, round_count: usize) -> tracing::Span {
+    #[cfg(feature = "otel")]
+    {
+        tracing::info_span!(
+            "cascade",
+            otel.name = format!("cascade {cascade_name}"),
+            cascade.name = cascade_name,
+            cascade.rounds = round_count,
+        )
+    }
+    #[cfg(not(feature = "otel"))]
+    {
+        let _ = (cascade_name, round_count);
+        tracing::Span::none()
+    }
+}
+
+/// A span for a cascade round, parenting the spans of its completion requests. Only enabled with the `otel` feature.
+fn round_span(round_index: usize) -> tracing::Span {
+    #[cfg(feature = "otel")]
+    {
+        tracing::info_span!(
+            "cascade_round",
+            otel.name = format!("cascade round {round_index}"),
+            cascade.round = round_index,
+        )
+    }
+    #[cfg(not(feature = "otel"))]
+    {
+        let _ = round_index;
+        tracing::Span::none()
+    }
 }
 
 pub(crate) async fn cascade_request(
     base_req: &mut CompletionRequest,
     step: &mut InferenceStep,
 ) -> Result<()> {
-    let res = base_req.request().await?;
-    if matches!(
-        res.finish_reason,
-        CompletionFinishReason::MatchingStoppingSequence(StoppingSequence::NoResult(_))
-    ) {
+    let mut attempts: u16 = 0;
+    loop {
+        attempts += 1;
+        let res = base_req.request().await?;
+        if matches!(
+            res.finish_reason,
+            CompletionFinishReason::MatchingStoppingSequence(StoppingSequence::NoResult(_))
+        ) {
+            step.llm_content = None;
+            step.completion_probabilities = None;
+            return Ok(());
+        }
+
+        let content = if base_req.backend.supports_grammar() {
+            step.step_config.grammar.validate_clean(&res.content)
+        } else {
+            lenient_validate_clean(&step.step_config.grammar, &res.content)
+        };
+        let content = match content {
+            Ok(content) => {
+                step.llm_content = Some(content.clone());
+                step.completion_probabilities = res.completion_probabilities.clone();
+                Ok(content)
+            }
+            Err(e) => {
+                crate::info!(?e);
+                Err(e)
+            }
+        };
+        let Some(expected) = &step.step_config.expected_primitive else {
+            return Ok(());
+        };
+        let error = match content {
+            Ok(content) => match expected.validate(&content) {
+                Ok(()) => return Ok(()),
+                Err(e) => e.to_string(),
+            },
+            Err(e) => e.to_string(),
+        };
         step.llm_content = None;
-        return Ok(());
+        let retries = match expected.on_invalid {
+            step::OnInvalidPrimitive::Retry(retries) => retries,
+            step::OnInvalidPrimitive::Abort => 0,
+        };
+        if attempts > u16::from(retries) {
+            return Err(InvalidPrimitiveError {
+                type_description: expected.type_description.clone(),
+                attempts,
+                error,
+                raw_content: res.content,
+            }
+            .into());
+        }
+        crate::warn!(
+            "Step output {:?} is not a valid {}: {error}. Retrying.",
+            res.content,
+            expected.type_description
+        );
+        // The request is unchanged, so a cached response would return the same invalid output again.
+        if let Some(cache) = &base_req.response_cache {
+            if let Some(key) = ResponseCache::key(base_req) {
+                cache.remove(&key);
+            }
+        }
     }
+}
 
-    match step.step_config.grammar.validate_clean(&res.content) {
-        Ok(content) => {
-            step.llm_content = Some(content.clone());
-        }
-        Err(e) => {
-            crate::info!(?e);
+/// Validates output generated without a grammar. The model may wrap its answer in code fences or quotes, end it
+/// with a period, or add lines around it, so each of these variations is tried before giving up.
+pub(crate) fn lenient_validate_clean(
+    grammar: &Grammar,
+    content: &str,
+) -> std::result::Result<String, GrammarError> {
+    let strict = grammar.validate_clean(content);
+    if strict.is_ok() {
+        return strict;
+    }
+    let unwrapped = content
+        .trim()
+        .trim_matches('`')
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'')
+        .trim();
+    let candidates = [unwrapped, unwrapped.trim_end_matches('.')]
+        .into_iter()
+        .chain(unwrapped.lines().map(str::trim).filter(|l| !l.is_empty()));
+    for candidate in candidates {
+        if let Ok(cleaned) = grammar.validate_clean(candidate) {
+            return Ok(cleaned);
         }
     }
-    Ok(())
+    strict
 }
 
 impl std::fmt::Display for CascadeFlow {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ansi_colors {
+            self.write_colored(f)
+        } else {
+            let mut colored = String::new();
+            self.write_colored(&mut colored)?;
+            f.write_str(&strip_ansi(&colored))
+        }
+    }
+}
+
+impl CascadeFlow {
+    fn write_colored(&self, f: &mut dyn std::fmt::Write) -> std::fmt::Result {
         writeln!(f)?;
         writeln!(f, "\x1b[1m\x1B[38;2;92;244;37m{}\x1b[0m", self.cascade_name)?;
         writeln!(f)?;
-        for (i, round) in self.rounds.iter().enumerate() {
+        let order: Vec<usize> = match self.execution_order() {
+            Ok(stages) => stages.into_iter().flatten().collect(),
+            Err(_) => (0..self.rounds.len()).collect(),
+        };
+        for i in order {
+            let round = &self.rounds[i];
             let color = ROUND_GRADIENT[i % ROUND_GRADIENT.len()];
-            writeln!(f, "\x1b[1m{color}Round {}\x1b[0m", i + 1)?;
+            if round.depends_on.is_empty() {
+                writeln!(f, "\x1b[1m{color}Round {}\x1b[0m", i + 1)?;
+            } else {
+                let dependencies: Vec<String> = round
+                    .depends_on
+                    .iter()
+                    .map(|d| (d + 1).to_string())
+                    .collect();
+                writeln!(
+                    f,
+                    "\x1b[1m{color}Round {}\x1b[0m (after rounds {})",
+                    i + 1,
+                    dependencies.join(", ")
+                )?;
+            }
             writeln!(f, "{round}",)?;
         }
         Ok(())
     }
 }
+
+/// False if the `NO_COLOR` environment variable is set to a non empty value (see <https://no-color.org>), or if stdout
+/// is piped or redirected.
+pub fn ansi_colors_supported() -> bool {
+    use std::io::IsTerminal;
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    !no_color && std::io::stdout().is_terminal()
+}
+
+/// Removes ANSI escape sequences, such as the colors of the cascade `Display` output.
+pub fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            stripped.push(c);
+            continue;
+        }
+        // Control sequences are `ESC [`, parameters, and a final byte in `@..=~`.
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    stripped
+}
+
 static ROUND_GRADIENT: std::sync::LazyLock<Vec<&'static str>> = std::sync::LazyLock::new(|| {
     vec![
         "\x1B[38;2;230;175;45m",
@@ -131,3 +528,63 @@ static ROUND_GRADIENT: std::sync::LazyLock<Vec<&'static str>> = std::sync::LazyL
         "\x1B[38;2;65;60;88m",
     ]
 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(
+            strip_ansi("\x1b[1m\x1B[38;2;92;244;37mname\x1b[0m: 'step'"),
+            "name: 'step'"
+        );
+        let mut flow = CascadeFlow::new("name").with_ansi_colors(false);
+        flow.new_round("task");
+        let mut written = Vec::new();
+        flow.write_to(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(!written.contains('\x1b'));
+        assert!(written.contains("Round 1"));
+    }
+
+    #[test]
+    fn test_lenient_validate_clean() {
+        let grammar = Grammar::integer().upper_bound(100).wrap();
+        for content in ["42", "```\n42\n```", "\"42\"", "42.", "The answer is:\n42"] {
+            assert_eq!(
+                lenient_validate_clean(&grammar, content).unwrap(),
+                "42",
+                "{content:?}"
+            );
+        }
+        assert!(lenient_validate_clean(&grammar, "forty two").is_err());
+    }
+
+    #[test]
+    fn test_execution_order() {
+        let mut flow = CascadeFlow::new("test");
+        flow.new_round("a");
+        flow.new_round("b");
+        flow.new_round("c").depends_on(&[0, 1]);
+        flow.new_round("d").depends_on(&[0]);
+        flow.new_round("e").depends_on(&[2]);
+        assert_eq!(
+            flow.execution_order().unwrap(),
+            vec![vec![0, 1], vec![2, 3], vec![4]]
+        );
+        assert!(flow.is_ancestor(0, 4));
+        assert!(!flow.is_ancestor(3, 4));
+
+        flow.rounds[0].depends_on(&[4]);
+        assert!(flow.execution_order().is_err());
+
+        let mut flow = CascadeFlow::new("linear");
+        flow.new_round("a");
+        flow.new_round("b");
+        assert_eq!(flow.execution_order().unwrap(), vec![vec![0], vec![1]]);
+
+        flow.independent_rounds(true);
+        assert_eq!(flow.execution_order().unwrap(), vec![vec![0, 1]]);
+    }
+}
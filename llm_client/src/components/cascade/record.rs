@@ -0,0 +1,192 @@
+use super::{
+    step::{CascadeStep, StepConfig},
+    CascadeFlow, CascadeRound,
+};
+use serde::{Deserialize, Serialize};
+
+/// A serializable snapshot of a [CascadeFlow] for logging, auditing, and replay.
+///
+/// Timing is stored as elapsed milliseconds because [std::time::Instant] can't be serialized.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CascadeFlowRecord {
+    pub cascade_name: String,
+    pub duration_ms: u64,
+    pub result_can_be_none: bool,
+    pub rounds: Vec<CascadeRoundRecord>,
+    pub primitive_result: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CascadeRoundRecord {
+    pub task: String,
+    pub step_separator: Option<char>,
+    pub resolved_steps: Vec<CascadeStepRecord>,
+    pub unresolved_steps: Vec<CascadeStepRecord>,
+    pub outcome: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CascadeStepRecord {
+    pub kind: CascadeStepKind,
+    pub step_counter: usize,
+    pub step_prefix: Option<String>,
+    pub use_counter: bool,
+    pub stop_word_done: String,
+    pub stop_word_no_result: Option<String>,
+    pub grammar_string: Option<String>,
+    pub llm_content: Option<String>,
+    pub dynamic_suffix: Option<String>,
+    pub outcome: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CascadeStepKind {
+    Inference,
+    Guidance,
+}
+
+impl CascadeFlow {
+    pub fn to_record(&self) -> CascadeFlowRecord {
+        CascadeFlowRecord {
+            cascade_name: self.cascade_name.clone(),
+            duration_ms: u64::try_from(self.duration.as_millis()).unwrap_or(u64::MAX),
+            result_can_be_none: self.result_can_be_none,
+            rounds: self.rounds.iter().map(CascadeRoundRecord::new).collect(),
+            primitive_result: match self.rounds.last().and_then(|r| r.resolved_steps.back()) {
+                Some(CascadeStep::Inference(step)) => step.llm_content.clone(),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn to_json(&self) -> crate::Result<String> {
+        Ok(serde_json::to_string_pretty(&self.to_record())?)
+    }
+}
+
+impl CascadeFlowRecord {
+    pub fn from_json(json: &str) -> crate::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Rebuilds a [CascadeFlow] where every resolved step is a guidance step holding its recorded content.
+    ///
+    /// Running the flow re-creates the same prompts without generating new content, which is useful for replaying a decision.
+    /// Unresolved steps are not included.
+    pub fn replay_flow(&self) -> CascadeFlow {
+        let mut flow = CascadeFlow::new(&self.cascade_name);
+        flow.result_can_be_none = self.result_can_be_none;
+        for round_record in &self.rounds {
+            let mut round = CascadeRound::new(&round_record.task);
+            round.step_separator = round_record.step_separator;
+            for step_record in &round_record.resolved_steps {
+                let step_config = StepConfig {
+                    step_prefix: step_record.step_prefix.clone(),
+                    stop_word_done: step_record.stop_word_done.clone(),
+                    stop_word_no_result: step_record.stop_word_no_result.clone(),
+                    use_counter: step_record.use_counter,
+                    ..StepConfig::default()
+                };
+                let content = match (&step_record.llm_content, &step_record.stop_word_no_result) {
+                    (Some(llm_content), _) => format!(
+                        "{llm_content}{}",
+                        step_record.dynamic_suffix.as_deref().unwrap_or_default()
+                    ),
+                    (None, Some(stop_word_no_result)) => stop_word_no_result.clone(),
+                    (None, None) => String::new(),
+                };
+                round.add_guidance_step(&step_config, content);
+            }
+            flow.add_round(round);
+        }
+        flow
+    }
+}
+
+impl CascadeRoundRecord {
+    fn new(round: &CascadeRound) -> Self {
+        Self {
+            task: round.task.clone(),
+            step_separator: round.step_separator,
+            resolved_steps: round
+                .resolved_steps
+                .iter()
+                .map(CascadeStepRecord::new)
+                .collect(),
+            unresolved_steps: round
+                .unresolved_steps
+                .iter()
+                .map(CascadeStepRecord::new)
+                .collect(),
+            outcome: round.display_outcome().ok(),
+        }
+    }
+}
+
+impl CascadeStepRecord {
+    fn new(step: &CascadeStep) -> Self {
+        let outcome = step.display_step_outcome().ok();
+        match step {
+            CascadeStep::Inference(step) => Self {
+                kind: CascadeStepKind::Inference,
+                step_counter: step.step_counter,
+                step_prefix: step.step_config.step_prefix.clone(),
+                use_counter: step.step_config.use_counter,
+                stop_word_done: step.step_config.stop_word_done.clone(),
+                stop_word_no_result: step.step_config.stop_word_no_result.clone(),
+                grammar_string: Some(step.step_config.grammar.grammar_string()),
+                llm_content: step.llm_content.clone(),
+                dynamic_suffix: step.dynamic_suffix.clone(),
+                outcome,
+            },
+            CascadeStep::Guidance(step) => Self {
+                kind: CascadeStepKind::Guidance,
+                step_counter: step.step_counter,
+                step_prefix: step.step_config.step_prefix.clone(),
+                use_counter: step.step_config.use_counter,
+                stop_word_done: step.step_config.stop_word_done.clone(),
+                stop_word_no_result: step.step_config.stop_word_no_result.clone(),
+                grammar_string: None,
+                llm_content: Some(step.llm_content.clone()),
+                dynamic_suffix: None,
+                outcome,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_round_trip() {
+        let mut flow = CascadeFlow::new("test");
+        let step_config = StepConfig {
+            step_prefix: Some("The answer is: ".to_string()),
+            ..StepConfig::default()
+        };
+        flow.new_round("What is 2 + 2?")
+            .add_guidance_step(&step_config, "4");
+        let round = flow.last_round().unwrap();
+        let step = round.unresolved_steps.pop_front().unwrap();
+        round.resolved_steps.push_back(step);
+
+        let json = flow.to_json().unwrap();
+        let record = CascadeFlowRecord::from_json(&json).unwrap();
+        assert_eq!(record, flow.to_record());
+        assert_eq!(
+            record.rounds[0].outcome.as_deref(),
+            Some("The answer is: 4")
+        );
+
+        let replay = record.replay_flow();
+        assert_eq!(
+            replay.rounds[0].unresolved_steps[0]
+                .display_step_outcome()
+                .unwrap(),
+            "The answer is: 4"
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use super::step::{CascadeStep, StepConfig};
-use llm_interface::requests::completion::CompletionRequest;
+use llm_interface::requests::{
+    completion::CompletionRequest, res_components::InferenceProbabilities,
+};
 use std::collections::VecDeque;
 
 #[derive(Clone)]
@@ -8,6 +10,8 @@ pub struct CascadeRound {
     pub unresolved_steps: VecDeque<CascadeStep>,
     pub resolved_steps: VecDeque<CascadeStep>,
     pub step_separator: Option<char>,
+    /// Indices of the rounds in the [super::CascadeFlow] whose outcomes this round needs. See [CascadeRound::depends_on].
+    pub depends_on: Vec<usize>,
 }
 
 impl CascadeRound {
@@ -17,9 +21,21 @@ impl CascadeRound {
             unresolved_steps: VecDeque::new(),
             resolved_steps: VecDeque::new(),
             step_separator: Some(' '),
+            depends_on: Vec::new(),
         }
     }
 
+    /// Runs this round after the rounds at the given indices of the flow, with only their tasks and outcomes as
+    /// context. Rounds that don't depend on each other run concurrently. See [super::CascadeFlow::execution_order].
+    pub fn depends_on(&mut self, rounds: &[usize]) -> &mut Self {
+        for round in rounds {
+            if !self.depends_on.contains(round) {
+                self.depends_on.push(*round);
+            }
+        }
+        self
+    }
+
     pub fn step_separator(&mut self, separator: char) -> &mut Self {
         self.step_separator = Some(separator);
         self
@@ -95,7 +111,7 @@ impl CascadeRound {
     }
 
     pub async fn run_all_steps(&mut self, base_req: &mut CompletionRequest) -> crate::Result<()> {
-        base_req.prompt.add_user_message()?.set_content(&self.task);
+        self.add_task_message(base_req)?;
         while !self.unresolved_steps.is_empty() {
             match self.run_next_step(base_req).await {
                 Ok(_) => {}
@@ -142,8 +158,40 @@ impl CascadeRound {
         }
     }
 
+    pub fn primitive_probabilities(&self) -> Option<Vec<InferenceProbabilities>> {
+        self.resolved_steps
+            .back()
+            .and_then(|step| step.primitive_probabilities())
+    }
+
     pub fn open_round(&mut self, base_req: &mut CompletionRequest) -> crate::Result<()> {
+        self.add_task_message(base_req)
+    }
+
+    /// Adds the task as a user message. If the backend doesn't support grammars, the fallback instructions of the
+    /// inference steps are appended so the model still knows the expected format.
+    fn add_task_message(&self, base_req: &CompletionRequest) -> crate::Result<()> {
+        let message = base_req.prompt.add_user_message()?;
+        message.set_content(&self.task);
+        if !base_req.backend.supports_grammar() {
+            for step in &self.unresolved_steps {
+                if let CascadeStep::Inference(step) = step {
+                    if let Some(fallback_instructions) = &step.step_config.fallback_instructions {
+                        message.append_content(fallback_instructions);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the task and the outcome of this finished round to the prompt of `base_req`.
+    pub(crate) fn add_context_messages(&self, base_req: &CompletionRequest) -> crate::Result<()> {
         base_req.prompt.add_user_message()?.set_content(&self.task);
+        base_req
+            .prompt
+            .add_assistant_message()?
+            .set_content(self.display_outcome()?);
         Ok(())
     }
 
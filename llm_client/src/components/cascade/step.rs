@@ -1,6 +1,8 @@
 use super::cascade_request;
-use crate::components::grammar::Grammar;
-use llm_interface::requests::{completion::CompletionRequest, logit_bias::LogitBias};
+use crate::{components::grammar::Grammar, primitives::PrimitiveTrait};
+use llm_interface::requests::{
+    completion::CompletionRequest, logit_bias::LogitBias, res_components::InferenceProbabilities,
+};
 
 #[derive(Clone)]
 pub enum CascadeStep {
@@ -12,6 +14,7 @@ impl CascadeStep {
     pub fn new_inference_step(step_config: StepConfig, step_counter: usize) -> Self {
         CascadeStep::Inference(InferenceStep {
             llm_content: None,
+            completion_probabilities: None,
             dynamic_suffix: None,
             outcome: std::cell::RefCell::new(None),
             step_config,
@@ -85,11 +88,20 @@ impl CascadeStep {
             Self::Guidance(_) => panic!("GuidanceStep does not have primitive_result."),
         }
     }
+
+    pub fn primitive_probabilities(&self) -> Option<Vec<InferenceProbabilities>> {
+        match self {
+            Self::Inference(step) => step.completion_probabilities.clone(),
+            Self::Guidance(_) => panic!("GuidanceStep does not have primitive_probabilities."),
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct InferenceStep {
     pub llm_content: Option<String>, // raw, unformatted result from llm.
+    /// The logprobs of the generated tokens, if `top_logprobs` was set on the request.
+    pub completion_probabilities: Option<Vec<InferenceProbabilities>>,
     pub dynamic_suffix: Option<String>, // suffix to be added to the result.
     pub outcome: std::cell::RefCell<Option<String>>,
     pub step_config: StepConfig,
@@ -119,7 +131,17 @@ impl InferenceStep {
         self.step_config
             .grammar
             .set_stop_word_done(&self.step_config.stop_word_done);
-        base_req.grammar_string = Some(self.step_config.grammar.grammar_string());
+        if base_req.backend.supports_grammar() {
+            base_req.grammar_string = Some(self.step_config.grammar.grammar_string());
+        } else {
+            // Without a grammar the model won't generate the done stop word, and the output is parsed leniently.
+            crate::warn!(
+                "The {} backend doesn't support grammars. Falling back to prompt instructions and lenient parsing.",
+                base_req.backend.model_id()
+            );
+            base_req.grammar_string = None;
+            base_req.stop_sequences.required = false;
+        }
 
         // Request prompt
         if let Some(generation_prefix) = generation_prefix {
@@ -131,7 +153,7 @@ impl InferenceStep {
         // Request logit bias
         base_req.logit_bias = Some(self.step_config.logit_bias.clone());
 
-        base_req.config.cache_prompt = self.step_config.cache_prompt;
+        base_req.config.cache_prompt = Some(self.step_config.cache_prompt);
         cascade_request(base_req, self).await
     }
 
@@ -188,6 +210,11 @@ pub struct StepConfig {
     pub cache_prompt: bool,
     pub grammar: Grammar,
     pub logit_bias: LogitBias,
+    /// Set with [StepConfig::expect_primitive].
+    pub expected_primitive: Option<PrimitiveExpectation>,
+    /// Appended to the round's task for backends that don't support grammars, so the model is still told the
+    /// format the grammar would have enforced. See [llm_interface::llms::LlmBackend::supports_grammar].
+    pub fallback_instructions: Option<String>,
 }
 
 impl Default for StepConfig {
@@ -200,6 +227,8 @@ impl Default for StepConfig {
             cache_prompt: true,
             grammar: Grammar::default(),
             logit_bias: LogitBias::default(),
+            expected_primitive: None,
+            fallback_instructions: None,
         }
     }
 }
@@ -235,6 +264,49 @@ impl StepConfig {
         self
     }
 
+    pub fn fallback_instructions<T: Into<String>>(
+        &mut self,
+        fallback_instructions: T,
+    ) -> &mut Self {
+        self.fallback_instructions = Some(fallback_instructions.into());
+        self
+    }
+
+    /// Asserts the step's output parses as `primitive`.
+    ///
+    /// The primitive's grammar replaces [StepConfig::grammar] and its solution description is used as the
+    /// [StepConfig::fallback_instructions]. The cleaned output is parsed with
+    /// [PrimitiveTrait::parse_to_primitive]. If parsing fails the step is handled as set with
+    /// [StepConfig::on_invalid_primitive], which defaults to aborting with a [super::InvalidPrimitiveError].
+    /// A 'no result' stop word is not validated.
+    pub fn expect_primitive<P>(&mut self, primitive: P) -> &mut Self
+    where
+        P: PrimitiveTrait + Send + Sync + 'static,
+    {
+        self.grammar = primitive.grammar();
+        self.fallback_instructions = Some(fallback_instructions(&primitive, false));
+        let on_invalid = self
+            .expected_primitive
+            .as_ref()
+            .map_or(OnInvalidPrimitive::Abort, |e| e.on_invalid);
+        self.expected_primitive = Some(PrimitiveExpectation {
+            type_description: primitive.type_description(false).to_owned(),
+            on_invalid,
+            parse: std::sync::Arc::new(move |content| {
+                primitive.parse_to_primitive(content).map(|_| ())
+            }),
+        });
+        self
+    }
+
+    /// What to do when the output doesn't parse as the primitive set with [StepConfig::expect_primitive].
+    pub fn on_invalid_primitive(&mut self, on_invalid: OnInvalidPrimitive) -> &mut Self {
+        if let Some(expected_primitive) = &mut self.expected_primitive {
+            expected_primitive.on_invalid = on_invalid;
+        }
+        self
+    }
+
     fn display_prefix(&self, step_counter: usize) -> Option<String> {
         match (self.use_counter, &self.step_prefix) {
             (true, Some(step_prefix)) => Some(format!("{} {}", step_counter, step_prefix)),
@@ -244,3 +316,59 @@ impl StepConfig {
         }
     }
 }
+
+/// Instructions describing the output of `primitive`, for backends that can't enforce its grammar.
+pub fn fallback_instructions<P: PrimitiveTrait>(primitive: &P, result_can_be_none: bool) -> String {
+    format!(
+        "Respond with only {}, and nothing else.",
+        primitive.solution_description(result_can_be_none)
+    )
+}
+
+/// What a step does when its output doesn't parse as the expected primitive.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OnInvalidPrimitive {
+    /// Request again up to this many times before aborting.
+    Retry(u8),
+    Abort,
+}
+
+type PrimitiveParser = std::sync::Arc<dyn Fn(&str) -> crate::Result<()> + Send + Sync>;
+
+/// A primitive the output of a step must parse as. See [StepConfig::expect_primitive].
+#[derive(Clone)]
+pub struct PrimitiveExpectation {
+    pub type_description: String,
+    pub on_invalid: OnInvalidPrimitive,
+    parse: PrimitiveParser,
+}
+
+impl PrimitiveExpectation {
+    pub fn validate(&self, content: &str) -> crate::Result<()> {
+        (self.parse)(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::IntegerPrimitive;
+
+    #[test]
+    fn test_expect_primitive() {
+        let mut primitive = IntegerPrimitive::default();
+        primitive.upper_bound(10);
+        let grammar_string = primitive.grammar().grammar_string();
+
+        let mut step_config = StepConfig::default();
+        step_config
+            .expect_primitive(primitive)
+            .on_invalid_primitive(OnInvalidPrimitive::Retry(2));
+        assert_eq!(step_config.grammar.grammar_string(), grammar_string);
+
+        let expected = step_config.expected_primitive.as_ref().unwrap();
+        assert_eq!(expected.on_invalid, OnInvalidPrimitive::Retry(2));
+        assert!(expected.validate("7").is_ok());
+        assert!(expected.validate("seven").is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use super::{Grammar, GrammarError, GrammarSetterTrait};
+use super::{cached_grammar_string, gbnf_escape, Grammar, GrammarError, GrammarSetterTrait};
 use std::{cell::RefCell, str::FromStr};
 use url::Url;
 
@@ -27,7 +27,14 @@ impl BasicUrlGrammar {
     pub fn grammar_string(&self) -> String {
         let mut grammar_string = self.grammar_string.borrow_mut();
         if grammar_string.is_none() {
-            *grammar_string = Some(url_grammar(&self.stop_word_done, &self.stop_word_no_result));
+            *grammar_string = Some(cached_grammar_string(
+                (
+                    "basic_url",
+                    self.stop_word_done.clone(),
+                    self.stop_word_no_result.clone(),
+                ),
+                || url_grammar(&self.stop_word_done, &self.stop_word_no_result),
+            ));
         }
         grammar_string.as_ref().unwrap().clone()
     }
@@ -58,19 +65,19 @@ pub fn url_grammar<T: AsRef<str>>(
     match (stop_word_done, stop_word_no_result) {
         (Some(stop_word_done), Some(stop_word_no_result)) => format!(
             "root ::= \" \" ( url | \"{}\" ) \" {}\"\n{URL_GRAMMAR}",
-            stop_word_no_result.as_ref(),
-            stop_word_done.as_ref()
+            gbnf_escape(stop_word_no_result.as_ref()),
+            gbnf_escape(stop_word_done.as_ref())
         ),
         (None, Some(stop_word_no_result)) => {
             format!(
                 "root ::= \" \" ( url | \"{}\" )\n{URL_GRAMMAR}",
-                stop_word_no_result.as_ref()
+                gbnf_escape(stop_word_no_result.as_ref())
             )
         }
         (Some(stop_word_done), None) => {
             format!(
                 "root ::= \" \" url \" {}\"\n{URL_GRAMMAR}",
-                stop_word_done.as_ref()
+                gbnf_escape(stop_word_done.as_ref())
             )
         }
         (None, None) => format!("root ::= \" \" url\n{URL_GRAMMAR}"),
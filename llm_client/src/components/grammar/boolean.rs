@@ -1,4 +1,4 @@
-use super::{Grammar, GrammarError, GrammarSetterTrait};
+use super::{cached_grammar_string, gbnf_escape, Grammar, GrammarError, GrammarSetterTrait};
 use std::cell::RefCell;
 
 #[derive(Clone, Default, PartialEq)]
@@ -16,9 +16,13 @@ impl BooleanGrammar {
     pub fn grammar_string(&self) -> String {
         let mut grammar_string = self.grammar_string.borrow_mut();
         if grammar_string.is_none() {
-            *grammar_string = Some(boolean_grammar(
-                &self.stop_word_done,
-                &self.stop_word_no_result,
+            *grammar_string = Some(cached_grammar_string(
+                (
+                    "boolean",
+                    self.stop_word_done.clone(),
+                    self.stop_word_no_result.clone(),
+                ),
+                || boolean_grammar(&self.stop_word_done, &self.stop_word_no_result),
             ));
         }
         grammar_string.as_ref().unwrap().clone()
@@ -50,19 +54,19 @@ pub fn boolean_grammar<T: AsRef<str>>(
     match (stop_word_done, stop_word_no_result) {
         (Some(stop_word_done), Some(stop_word_no_result)) => format!(
             "root ::= \" \" ( \"true\" | \"false\" | \"{}\" ) \" {}\"",
-            stop_word_no_result.as_ref(),
-            stop_word_done.as_ref()
+            gbnf_escape(stop_word_no_result.as_ref()),
+            gbnf_escape(stop_word_done.as_ref())
         ),
         (None, Some(stop_word_no_result)) => {
             format!(
                 "root ::= \" \" ( \"true\" | \"false\" | \"{}\" ) ",
-                stop_word_no_result.as_ref()
+                gbnf_escape(stop_word_no_result.as_ref())
             )
         }
         (Some(stop_word_done), None) => {
             format!(
                 "root ::= \" \" ( \"true\" | \"false\" ) \" {}\"",
-                stop_word_done.as_ref()
+                gbnf_escape(stop_word_done.as_ref())
             )
         }
         (None, None) => "root ::= \" \" ( \"true\" | \"false\" )".to_owned(),
@@ -0,0 +1,242 @@
+use super::{cached_grammar_string, gbnf_escape, Grammar, GrammarError, GrammarSetterTrait};
+use std::cell::RefCell;
+
+/// The ISO-8601 calendar date format, e.g. `2024-02-29`.
+pub const ISO_8601_DATE: &str = "YYYY-MM-DD";
+/// The ISO-8601 date and time format without an offset, e.g. `2024-02-29T13:45:00`.
+pub const ISO_8601_DATE_TIME: &str = "YYYY-MM-DDThh:mm:ss";
+
+/// A date, or date and time, in a fixed format.
+///
+/// The format uses `YYYY` for the year, `MM` for the month, `DD` for the day, `hh` for the hour (24 hour clock),
+/// `mm` for the minute and `ss` for the second. Every other char must appear literally. Each field is zero padded.
+#[derive(Clone, PartialEq)]
+pub struct DateGrammar {
+    pub stop_word_done: Option<String>,
+    pub stop_word_no_result: Option<String>,
+    pub format: String,
+    grammar_string: RefCell<Option<String>>,
+}
+
+impl Default for DateGrammar {
+    fn default() -> Self {
+        Self {
+            stop_word_done: None,
+            stop_word_no_result: None,
+            format: ISO_8601_DATE.to_string(),
+            grammar_string: RefCell::new(None),
+        }
+    }
+}
+
+impl DateGrammar {
+    pub fn wrap(self) -> Grammar {
+        Grammar::Date(self)
+    }
+
+    pub fn format<T: Into<String>>(mut self, format: T) -> Self {
+        self.format = format.into();
+        self.grammar_string = RefCell::new(None);
+        self
+    }
+
+    pub fn grammar_string(&self) -> String {
+        let mut grammar_string = self.grammar_string.borrow_mut();
+        if grammar_string.is_none() {
+            *grammar_string = Some(cached_grammar_string(
+                (
+                    "date",
+                    self.format.clone(),
+                    self.stop_word_done.clone(),
+                    self.stop_word_no_result.clone(),
+                ),
+                || {
+                    date_grammar(
+                        &self.format,
+                        &self.stop_word_done,
+                        &self.stop_word_no_result,
+                    )
+                },
+            ));
+        }
+        grammar_string.as_ref().unwrap().clone()
+    }
+
+    pub fn validate_clean(&self, content: &str) -> Result<String, GrammarError> {
+        let content = content.trim();
+        self.grammar_parse(content)?;
+        Ok(content.to_string())
+    }
+
+    /// Parses the content as a date and time. Fields missing from the format default to midnight.
+    pub fn grammar_parse(&self, content: &str) -> Result<chrono::NaiveDateTime, GrammarError> {
+        date_parse(content, &self.format)
+    }
+}
+
+impl GrammarSetterTrait for DateGrammar {
+    fn stop_word_done_mut(&mut self) -> &mut Option<String> {
+        &mut self.stop_word_done
+    }
+
+    fn stop_word_no_result_mut(&mut self) -> &mut Option<String> {
+        &mut self.stop_word_no_result
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DateField {
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    Literal(char),
+}
+
+impl DateField {
+    fn width(&self) -> usize {
+        match self {
+            DateField::Year => 4,
+            DateField::Literal(_) => 1,
+            _ => 2,
+        }
+    }
+
+    fn gbnf(&self) -> String {
+        match self {
+            DateField::Year => "[0-9] [0-9] [0-9] [0-9]".to_string(),
+            DateField::Month => "( \"0\" [1-9] | \"1\" [0-2] )".to_string(),
+            DateField::Day => "( \"0\" [1-9] | [12] [0-9] | \"3\" [01] )".to_string(),
+            DateField::Hour => "( [01] [0-9] | \"2\" [0-3] )".to_string(),
+            DateField::Minute | DateField::Second => "[0-5] [0-9]".to_string(),
+            DateField::Literal(c) => format!("\"{}\"", gbnf_escape(&c.to_string())),
+        }
+    }
+}
+
+fn date_fields(format: &str) -> Vec<DateField> {
+    let mut fields = Vec::new();
+    let mut rest = format;
+    while !rest.is_empty() {
+        let field = [
+            ("YYYY", DateField::Year),
+            ("MM", DateField::Month),
+            ("DD", DateField::Day),
+            ("hh", DateField::Hour),
+            ("mm", DateField::Minute),
+            ("ss", DateField::Second),
+        ]
+        .into_iter()
+        .find(|(token, _)| rest.starts_with(token));
+        match field {
+            Some((token, field)) => {
+                fields.push(field);
+                rest = &rest[token.len()..];
+            }
+            None => {
+                let c = rest.chars().next().unwrap();
+                fields.push(DateField::Literal(c));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    fields
+}
+
+pub fn date_grammar<T: AsRef<str>>(
+    format: &str,
+    stop_word_done: &Option<T>,
+    stop_word_no_result: &Option<T>,
+) -> String {
+    let date = date_fields(format)
+        .iter()
+        .map(DateField::gbnf)
+        .collect::<Vec<_>>()
+        .join(" ");
+    match (stop_word_done, stop_word_no_result) {
+        (Some(stop_word_done), Some(stop_word_no_result)) => format!(
+            "root ::= \" \" ( {date} | \"{}\" ) \" {}\"",
+            gbnf_escape(stop_word_no_result.as_ref()),
+            gbnf_escape(stop_word_done.as_ref())
+        ),
+        (None, Some(stop_word_no_result)) => format!(
+            "root ::= \" \" ( {date} | \"{}\" )",
+            gbnf_escape(stop_word_no_result.as_ref())
+        ),
+        (Some(stop_word_done), None) => format!(
+            "root ::= \" \" {date} \" {}\"",
+            gbnf_escape(stop_word_done.as_ref())
+        ),
+        (None, None) => format!("root ::= \" \" {date}"),
+    }
+}
+
+/// Parses `content` in `format`, rejecting dates and times that don't exist, like `2024-02-30`.
+pub fn date_parse(content: &str, format: &str) -> Result<chrono::NaiveDateTime, GrammarError> {
+    let parse_error = || GrammarError::ParseValueError {
+        content: content.to_string(),
+        parse_type: format!("date ({format})"),
+    };
+    let mut rest = content.trim();
+    let (mut year, mut month, mut day) = (None, None, None);
+    let (mut hour, mut minute, mut second) = (0, 0, 0);
+    for field in date_fields(format) {
+        if let DateField::Literal(c) = field {
+            rest = rest.strip_prefix(c).ok_or_else(parse_error)?;
+            continue;
+        }
+        let digits = rest.get(..field.width()).ok_or_else(parse_error)?;
+        if !digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(parse_error());
+        }
+        let value: u16 = digits.parse().map_err(|_| parse_error())?;
+        rest = &rest[field.width()..];
+        match field {
+            DateField::Year => year = Some(value as i32),
+            DateField::Month => month = Some(value as u32),
+            DateField::Day => day = Some(value as u32),
+            DateField::Hour => hour = value as u32,
+            DateField::Minute => minute = value as u32,
+            DateField::Second => second = value as u32,
+            DateField::Literal(_) => unreachable!(),
+        }
+    }
+    if !rest.is_empty() {
+        return Err(parse_error());
+    }
+    let (Some(year), Some(month), Some(day)) = (year, month, day) else {
+        return Err(parse_error());
+    };
+    let date = chrono::NaiveDate::from_ymd_opt(year, month, day).ok_or_else(parse_error)?;
+    let time = chrono::NaiveTime::from_hms_opt(hour, minute, second).ok_or_else(parse_error)?;
+    Ok(chrono::NaiveDateTime::new(date, time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test() {
+        let mut grammar = Grammar::date();
+        let grammar_string = grammar.set_stop_word_done("stop").grammar_string();
+        assert_eq!(
+            grammar_string,
+            "root ::= \" \" [0-9] [0-9] [0-9] [0-9] \"-\" ( \"0\" [1-9] | \"1\" [0-2] ) \"-\" ( \"0\" [1-9] | [12] [0-9] | \"3\" [01] ) \" stop\""
+        );
+
+        let parsed = grammar.grammar_parse(" 2024-02-29 ").unwrap();
+        assert_eq!(parsed.date().to_string(), "2024-02-29");
+        assert!(grammar.grammar_parse("2024-02-30").is_err());
+        assert!(grammar.grammar_parse("2023-02-29").is_err());
+        assert!(grammar.grammar_parse("2024-2-9").is_err());
+        assert!(grammar.grammar_parse("2024-02-29T10:00").is_err());
+
+        let grammar = Grammar::date().format("DD.MM.YYYY hh:mm");
+        let parsed = grammar.grammar_parse("31.12.1999 23:59").unwrap();
+        assert_eq!(parsed.to_string(), "1999-12-31 23:59:00");
+        assert!(grammar.grammar_parse("31.12.1999 24:00").is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use super::{Grammar, GrammarError, GrammarSetterTrait};
+use super::{cached_grammar_string, gbnf_escape, Grammar, GrammarError, GrammarSetterTrait};
 use std::cell::RefCell;
 
 #[derive(Clone, Default, PartialEq)]
@@ -39,10 +39,20 @@ impl ExactStringGrammar {
         }
         let mut grammar_string = self.grammar_string.borrow_mut();
         if grammar_string.is_none() {
-            *grammar_string = Some(exact_string_grammar(
-                &self.exact_strings,
-                &self.stop_word_done,
-                &self.stop_word_no_result,
+            *grammar_string = Some(cached_grammar_string(
+                (
+                    "exact_string",
+                    self.exact_strings.clone(),
+                    self.stop_word_done.clone(),
+                    self.stop_word_no_result.clone(),
+                ),
+                || {
+                    exact_string_grammar(
+                        &self.exact_strings,
+                        &self.stop_word_done,
+                        &self.stop_word_no_result,
+                    )
+                },
             ));
         }
         grammar_string.as_ref().unwrap().clone()
@@ -79,23 +89,26 @@ pub fn exact_string_grammar<T: AsRef<str>>(
         } else {
             pattern.push('|');
         }
-        pattern.push_str(&format!(" \"{}\" ", text));
+        pattern.push_str(&format!(" \"{}\" ", gbnf_escape(text)));
     }
     pattern.push(')');
     match (stop_word_done, stop_word_no_result) {
         (Some(stop_word_done), Some(stop_word_no_result)) => format!(
             "root ::= ( {pattern} | \"{}\" ) \" {}\"",
-            stop_word_no_result.as_ref(),
-            stop_word_done.as_ref()
+            gbnf_escape(stop_word_no_result.as_ref()),
+            gbnf_escape(stop_word_done.as_ref())
         ),
         (None, Some(stop_word_no_result)) => {
             format!(
                 "root ::= ( {pattern} | \"{}\" )",
-                stop_word_no_result.as_ref()
+                gbnf_escape(stop_word_no_result.as_ref())
             )
         }
         (Some(stop_word_done), None) => {
-            format!("root ::= {pattern} \" {}\"", stop_word_done.as_ref())
+            format!(
+                "root ::= {pattern} \" {}\"",
+                gbnf_escape(stop_word_done.as_ref())
+            )
         }
         (None, None) => format!("root ::= {pattern}"),
     }
@@ -1,4 +1,4 @@
-use super::{Grammar, GrammarError, GrammarSetterTrait};
+use super::{cached_grammar_string, gbnf_escape, Grammar, GrammarError, GrammarSetterTrait};
 use std::cell::RefCell;
 
 #[derive(Clone)]
@@ -54,13 +54,26 @@ impl FauxUrlGrammar {
     pub fn grammar_string(&self) -> String {
         let mut grammar_string = self.grammar_string.borrow_mut();
         if grammar_string.is_none() {
-            *grammar_string = Some(faux_url_grammar(
-                self.min_count,
-                self.max_count,
-                self.word_char_length,
-                &self.base_url,
-                &self.stop_word_done,
-                &self.stop_word_no_result,
+            *grammar_string = Some(cached_grammar_string(
+                (
+                    "faux_url",
+                    self.min_count,
+                    self.max_count,
+                    self.word_char_length,
+                    self.base_url.clone(),
+                    self.stop_word_done.clone(),
+                    self.stop_word_no_result.clone(),
+                ),
+                || {
+                    faux_url_grammar(
+                        self.min_count,
+                        self.max_count,
+                        self.word_char_length,
+                        &self.base_url,
+                        &self.stop_word_done,
+                        &self.stop_word_no_result,
+                    )
+                },
             ));
         }
         grammar_string.as_ref().unwrap().clone()
@@ -99,19 +112,19 @@ pub fn faux_url_grammar<T: AsRef<str>>(
     match (stop_word_done, stop_word_no_result) {
         (Some(stop_word_done), Some(stop_word_no_result)) => format!(
             "root ::= \" \" ( \"{base_url}\" {range} | \"{}\" ) \" {}\"\n{item}\n{first}",
-            stop_word_no_result.as_ref(),
-            stop_word_done.as_ref()
+            gbnf_escape(stop_word_no_result.as_ref()),
+            gbnf_escape(stop_word_done.as_ref())
         ),
         (None, Some(stop_word_no_result)) => {
             format!(
                 "root ::= \" \" ( \"{base_url}\" {range} | \"{}\" )\n{item}\n{first}",
-                stop_word_no_result.as_ref()
+                gbnf_escape(stop_word_no_result.as_ref())
             )
         }
         (Some(stop_word_done), None) => {
             format!(
                 "root ::= \" \" \"{base_url}\" {range} \" {}\"\n{item}\n{first}",
-                stop_word_done.as_ref()
+                gbnf_escape(stop_word_done.as_ref())
             )
         }
         (None, None) => format!("root ::= \" \" \"{base_url}\" {range}\n{item}\n{first}"),
@@ -145,7 +158,7 @@ fn create_range<T: AsRef<str>>(min_count: u8, max_count: u8, stop_word_done: &Op
             if let Some(stop_word_done) = stop_word_done {
                 range.push_str(&format!(
                     "( \"{}\" | item ){{0,{opt_count}}}",
-                    stop_word_done.as_ref()
+                    gbnf_escape(stop_word_done.as_ref())
                 ))
             } else {
                 range.push_str(&format!("item{{0,{opt_count}}}"));
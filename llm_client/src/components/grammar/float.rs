@@ -0,0 +1,307 @@
+use super::{
+    cached_grammar_string, gbnf_escape, integer::ungroup_thousands, Grammar, GrammarError,
+    GrammarSetterTrait,
+};
+use std::cell::RefCell;
+
+/// A decimal number, e.g. `-12.5`.
+///
+/// The grammar limits the sign, the number of integer digits needed for the bounds, and the number of decimal
+/// places. The exact bounds are checked when parsing, since a grammar can't express an arbitrary decimal range.
+#[derive(Clone)]
+pub struct FloatGrammar {
+    pub stop_word_done: Option<String>,
+    pub stop_word_no_result: Option<String>,
+    pub lower_bound: Option<f64>,
+    pub upper_bound: Option<f64>,
+    pub decimal_places: Option<u32>,
+    pub decimal_separator: char,
+    pub thousands_separator: Option<char>,
+    pub allow_scientific: bool,
+    grammar_string: RefCell<Option<String>>,
+}
+
+impl Default for FloatGrammar {
+    fn default() -> Self {
+        Self {
+            stop_word_done: None,
+            stop_word_no_result: None,
+            lower_bound: None,
+            upper_bound: None,
+            decimal_places: None,
+            decimal_separator: '.',
+            thousands_separator: None,
+            allow_scientific: false,
+            grammar_string: RefCell::new(None),
+        }
+    }
+}
+
+impl FloatGrammar {
+    pub fn wrap(self) -> Grammar {
+        Grammar::Float(self)
+    }
+
+    pub fn lower_bound(mut self, lower_bound: f64) -> Self {
+        self.lower_bound = Some(lower_bound);
+        self.grammar_string = RefCell::new(None);
+        self
+    }
+
+    pub fn upper_bound(mut self, upper_bound: f64) -> Self {
+        self.upper_bound = Some(upper_bound);
+        self.grammar_string = RefCell::new(None);
+        self
+    }
+
+    /// The maximum number of digits after the decimal separator. `0` only allows whole numbers.
+    pub fn decimal_places(mut self, decimal_places: u32) -> Self {
+        self.decimal_places = Some(decimal_places);
+        self.grammar_string = RefCell::new(None);
+        self
+    }
+
+    /// The decimal separator used by the model's locale, e.g. ',' for "12,5".
+    pub fn decimal_separator(mut self, decimal_separator: char) -> Self {
+        self.decimal_separator = decimal_separator;
+        self.grammar_string = RefCell::new(None);
+        self
+    }
+
+    /// Accept integer parts grouped with this separator when parsing, e.g. '.' for "1.000,5".
+    /// The grammar itself only generates plain digits.
+    pub fn thousands_separator(mut self, thousands_separator: char) -> Self {
+        self.thousands_separator = Some(thousands_separator);
+        self
+    }
+
+    /// Allow an exponent, e.g. "1.5e-3".
+    pub fn allow_scientific(mut self, allow_scientific: bool) -> Self {
+        self.allow_scientific = allow_scientific;
+        self.grammar_string = RefCell::new(None);
+        self
+    }
+
+    pub fn grammar_string(&self) -> String {
+        let mut grammar_string = self.grammar_string.borrow_mut();
+        if grammar_string.is_none() {
+            *grammar_string = Some(cached_grammar_string(
+                (
+                    "float",
+                    self.lower_bound.map(f64::to_bits),
+                    self.upper_bound.map(f64::to_bits),
+                    self.decimal_places,
+                    self.decimal_separator,
+                    self.allow_scientific,
+                    self.stop_word_done.clone(),
+                    self.stop_word_no_result.clone(),
+                ),
+                || float_grammar(self, &self.stop_word_done, &self.stop_word_no_result),
+            ));
+        }
+        grammar_string.as_ref().unwrap().clone()
+    }
+
+    pub fn validate_clean(&self, content: &str) -> Result<String, GrammarError> {
+        let content = content.trim();
+        self.grammar_parse(content)?;
+        Ok(content.to_string())
+    }
+
+    pub fn grammar_parse(&self, content: &str) -> Result<f64, GrammarError> {
+        float_parse(content, self)
+    }
+
+    fn max_integer_digits(&self) -> Option<u32> {
+        let (lower_bound, upper_bound) = (self.lower_bound?, self.upper_bound?);
+        let max = lower_bound.abs().max(upper_bound.abs()).trunc();
+        if !max.is_finite() {
+            return None;
+        }
+        Some(if max < 1.0 {
+            1
+        } else {
+            max.log10().floor() as u32 + 1
+        })
+    }
+}
+
+impl GrammarSetterTrait for FloatGrammar {
+    fn stop_word_done_mut(&mut self) -> &mut Option<String> {
+        &mut self.stop_word_done
+    }
+
+    fn stop_word_no_result_mut(&mut self) -> &mut Option<String> {
+        &mut self.stop_word_no_result
+    }
+}
+
+fn digits(min: u32, max: Option<u32>) -> String {
+    match max {
+        Some(max) => {
+            let mut digits = vec!["[0-9]"; min as usize];
+            digits.extend(vec!["[0-9]?"; max.saturating_sub(min) as usize]);
+            digits.join(" ")
+        }
+        None => "[0-9]+".to_string(),
+    }
+}
+
+pub fn float_grammar<T: AsRef<str>>(
+    grammar: &FloatGrammar,
+    stop_word_done: &Option<T>,
+    stop_word_no_result: &Option<T>,
+) -> String {
+    if let (Some(lower_bound), Some(upper_bound)) = (grammar.lower_bound, grammar.upper_bound) {
+        if upper_bound < lower_bound {
+            panic!("Upper bound must be greater than or equal to lower bound.")
+        }
+    }
+    let mut number = String::new();
+    if grammar
+        .lower_bound
+        .is_none_or(|lower_bound| lower_bound < 0.0)
+    {
+        number.push_str("\"-\"? ");
+    }
+    number.push_str(&digits(1, grammar.max_integer_digits()));
+    if grammar.decimal_places != Some(0) {
+        number.push_str(&format!(
+            " ( \"{}\" {} )?",
+            gbnf_escape(&grammar.decimal_separator.to_string()),
+            digits(1, grammar.decimal_places)
+        ));
+    }
+    if grammar.allow_scientific {
+        number.push_str(" ( [eE] \"-\"? [0-9]+ )?");
+    }
+    match (stop_word_done, stop_word_no_result) {
+        (Some(stop_word_done), Some(stop_word_no_result)) => format!(
+            "root ::= \" \" ( {number} | \"{}\" ) \" {}\"",
+            gbnf_escape(stop_word_no_result.as_ref()),
+            gbnf_escape(stop_word_done.as_ref())
+        ),
+        (None, Some(stop_word_no_result)) => format!(
+            "root ::= \" \" ( {number} | \"{}\" )",
+            gbnf_escape(stop_word_no_result.as_ref())
+        ),
+        (Some(stop_word_done), None) => format!(
+            "root ::= \" \" {number} \" {}\"",
+            gbnf_escape(stop_word_done.as_ref())
+        ),
+        (None, None) => format!("root ::= \" \" {number}"),
+    }
+}
+
+/// Parses `content` with the grammar's separators, rejecting exponents unless they are allowed, more decimal places
+/// than allowed, and values outside the bounds.
+pub fn float_parse(content: &str, grammar: &FloatGrammar) -> Result<f64, GrammarError> {
+    let parse_error = || GrammarError::ParseValueError {
+        content: content.to_string(),
+        parse_type: "f64".to_string(),
+    };
+    let trimmed = content.trim();
+    let (negative, unsigned) = match trimmed.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, trimmed),
+    };
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some(_) if !grammar.allow_scientific => return Err(parse_error()),
+        Some((mantissa, exponent)) => (
+            mantissa,
+            exponent.parse::<i32>().map_err(|_| parse_error())?,
+        ),
+        None => (unsigned, 0),
+    };
+    let (integer, fraction) = match mantissa.split_once(grammar.decimal_separator) {
+        Some((integer, fraction)) if !fraction.is_empty() => (integer, fraction),
+        Some(_) => return Err(parse_error()),
+        None => (mantissa, ""),
+    };
+    let integer = match grammar.thousands_separator {
+        Some(separator) if integer.contains(separator) => {
+            ungroup_thousands(integer, separator).ok_or_else(parse_error)?
+        }
+        _ => integer.to_string(),
+    };
+    if integer.is_empty()
+        || !integer.chars().all(|c| c.is_ascii_digit())
+        || !fraction.chars().all(|c| c.is_ascii_digit())
+        || grammar
+            .decimal_places
+            .is_some_and(|decimal_places| fraction.len() > decimal_places as usize)
+    {
+        return Err(parse_error());
+    }
+    let sign = if negative { "-" } else { "" };
+    // The trailing zero keeps the fraction non-empty without changing the value.
+    let value: f64 = format!("{sign}{integer}.{fraction}0e{exponent}")
+        .parse()
+        .map_err(|_| parse_error())?;
+    if !value.is_finite()
+        || grammar
+            .lower_bound
+            .is_some_and(|lower_bound| value < lower_bound)
+        || grammar
+            .upper_bound
+            .is_some_and(|upper_bound| value > upper_bound)
+    {
+        return Err(parse_error());
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test() {
+        let mut grammar = Grammar::float()
+            .lower_bound(0.0)
+            .upper_bound(100.0)
+            .decimal_places(2);
+        let grammar_string = grammar.set_stop_word_done("stop").grammar_string();
+        assert_eq!(
+            grammar_string,
+            "root ::= \" \" [0-9] [0-9]? [0-9]? ( \".\" [0-9] [0-9]? )? \" stop\""
+        );
+        assert_eq!(12.5, grammar.grammar_parse(" 12.5 ").unwrap());
+        assert_eq!(100.0, grammar.grammar_parse("100").unwrap());
+        assert!(grammar.grammar_parse("100.01").is_err());
+        assert!(grammar.grammar_parse("1.234").is_err());
+        assert!(grammar.grammar_parse("-1").is_err());
+        assert!(grammar.grammar_parse("1e1").is_err());
+        assert!(grammar.grammar_parse("1.").is_err());
+
+        let grammar = Grammar::float();
+        assert_eq!(
+            grammar.grammar_string(),
+            "root ::= \" \" \"-\"? [0-9]+ ( \".\" [0-9]+ )?"
+        );
+        assert_eq!(-0.125, grammar.grammar_parse("-0.125").unwrap());
+        assert!(grammar.grammar_parse("1.5e3").is_err());
+        assert_eq!(
+            1500.0,
+            grammar
+                .allow_scientific(true)
+                .grammar_parse("1.5e3")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_separators() {
+        let grammar = Grammar::float()
+            .decimal_separator(',')
+            .thousands_separator('.');
+        assert_eq!(
+            grammar.grammar_string(),
+            "root ::= \" \" \"-\"? [0-9]+ ( \",\" [0-9]+ )?"
+        );
+        assert_eq!(1000.5, grammar.grammar_parse("1.000,5").unwrap());
+        assert_eq!(12.5, grammar.grammar_parse("12,5").unwrap());
+        assert!(grammar.grammar_parse("1.5").is_err());
+        assert!(grammar.grammar_parse("1,5,0").is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use super::{Grammar, GrammarError, GrammarSetterTrait};
+use super::{cached_grammar_string, gbnf_escape, Grammar, GrammarError, GrammarSetterTrait};
 use std::cell::RefCell;
 
 #[derive(Clone, Default)]
@@ -7,6 +7,7 @@ pub struct IntegerGrammar {
     pub stop_word_no_result: Option<String>,
     pub lower_bound: u32,
     pub upper_bound: u32,
+    pub thousands_separator: Option<char>,
     grammar_string: RefCell<Option<String>>,
 }
 
@@ -17,6 +18,7 @@ impl IntegerGrammar {
             stop_word_no_result: None,
             lower_bound: 1,
             upper_bound: 9,
+            thousands_separator: None,
             grammar_string: RefCell::new(None),
         }
     }
@@ -37,25 +39,44 @@ impl IntegerGrammar {
         self
     }
 
+    /// Accept numbers grouped with this separator when parsing, e.g. '.' for "1.000" or ',' for "1,000".
+    /// The grammar itself only generates plain digits.
+    pub fn thousands_separator(mut self, thousands_separator: char) -> Self {
+        self.thousands_separator = Some(thousands_separator);
+
+        self
+    }
+
     pub fn grammar_string(&self) -> String {
         let mut grammar_string = self.grammar_string.borrow_mut();
         if grammar_string.is_none() {
-            *grammar_string = Some(integer_grammar(
-                self.lower_bound,
-                self.upper_bound,
-                &self.stop_word_done,
-                &self.stop_word_no_result,
+            *grammar_string = Some(cached_grammar_string(
+                (
+                    "integer",
+                    self.lower_bound,
+                    self.upper_bound,
+                    self.stop_word_done.clone(),
+                    self.stop_word_no_result.clone(),
+                ),
+                || {
+                    integer_grammar(
+                        self.lower_bound,
+                        self.upper_bound,
+                        &self.stop_word_done,
+                        &self.stop_word_no_result,
+                    )
+                },
             ));
         }
         grammar_string.as_ref().unwrap().clone()
     }
 
     pub fn validate_clean(&self, content: &str) -> Result<String, GrammarError> {
-        integer_validate_clean(content)
+        integer_validate_clean(content, self.thousands_separator)
     }
 
     pub fn grammar_parse(&self, content: &str) -> Result<u32, GrammarError> {
-        integer_parse(content)
+        integer_parse(content, self.thousands_separator)
     }
 }
 
@@ -87,17 +108,20 @@ pub fn integer_grammar<T: AsRef<str>>(
     match (stop_word_done, stop_word_no_result) {
         (Some(stop_word_done), Some(stop_word_no_result)) => format!(
             "root ::= \" \" ( {range} | \"{}\" ) \" {}\"",
-            stop_word_no_result.as_ref(),
-            stop_word_done.as_ref()
+            gbnf_escape(stop_word_no_result.as_ref()),
+            gbnf_escape(stop_word_done.as_ref())
         ),
         (None, Some(stop_word_no_result)) => {
             format!(
                 "root ::= \" \" ( {range} | \"{}\" )",
-                stop_word_no_result.as_ref()
+                gbnf_escape(stop_word_no_result.as_ref())
             )
         }
         (Some(stop_word_done), None) => {
-            format!("root ::= \" \" {range} \" {}\"", stop_word_done.as_ref())
+            format!(
+                "root ::= \" \" {range} \" {}\"",
+                gbnf_escape(stop_word_done.as_ref())
+            )
         }
         (None, None) => format!("root ::= \" \" {range}"),
     }
@@ -119,7 +143,10 @@ fn create_range<T: AsRef<str>>(
     for i in 1..=digits {
         if i > 1 && 10_u32.pow(i - 1) > lower_bound {
             if let Some(stop_word_done) = stop_word_done {
-                range.push_str(&format!("([0-9] | \" {}\")", stop_word_done.as_ref()));
+                range.push_str(&format!(
+                    "([0-9] | \" {}\")",
+                    gbnf_escape(stop_word_done.as_ref())
+                ));
             } else {
                 range.push_str("[0-9]?");
             }
@@ -130,9 +157,12 @@ fn create_range<T: AsRef<str>>(
     range
 }
 
-pub fn integer_validate_clean(content: &str) -> Result<String, GrammarError> {
+pub fn integer_validate_clean(
+    content: &str,
+    thousands_separator: Option<char>,
+) -> Result<String, GrammarError> {
     let content: &str = content.trim();
-    if integer_parse(content).is_ok() {
+    if integer_parse(content, thousands_separator).is_ok() {
         Ok(content.to_string())
     } else {
         Err(GrammarError::ParseValueError {
@@ -142,14 +172,40 @@ pub fn integer_validate_clean(content: &str) -> Result<String, GrammarError> {
     }
 }
 
-pub fn integer_parse(content: &str) -> Result<u32, GrammarError> {
-    content
-        .trim()
-        .parse::<u32>()
-        .map_err(|_| GrammarError::ParseValueError {
-            content: content.to_string(),
-            parse_type: "u32".to_string(),
-        })
+pub fn integer_parse(
+    content: &str,
+    thousands_separator: Option<char>,
+) -> Result<u32, GrammarError> {
+    let parse_error = || GrammarError::ParseValueError {
+        content: content.to_string(),
+        parse_type: "u32".to_string(),
+    };
+    let trimmed = content.trim();
+    let digits = match thousands_separator {
+        Some(separator) if trimmed.contains(separator) => {
+            ungroup_thousands(trimmed, separator).ok_or_else(parse_error)?
+        }
+        _ => trimmed.to_string(),
+    };
+    digits.parse::<u32>().map_err(|_| parse_error())
+}
+
+/// Removes the thousands separators from a grouped number. Every group after the first must be exactly three digits,
+/// so "1,5" is rejected rather than read as 15.
+pub(super) fn ungroup_thousands(grouped: &str, separator: char) -> Option<String> {
+    let mut groups = grouped.split(separator);
+    let first = groups.next()?;
+    if first.is_empty() || first.len() > 3 {
+        return None;
+    }
+    let mut digits = first.to_string();
+    for group in groups {
+        if group.len() != 3 {
+            return None;
+        }
+        digits.push_str(group);
+    }
+    Some(digits)
 }
 
 #[cfg(test)]
@@ -195,4 +251,24 @@ mod tests {
         );
         assert_eq!(5555, grammar.grammar_parse(" 5555 ").unwrap());
     }
+
+    #[test]
+    fn test_thousands_separator() {
+        let grammar = Grammar::integer()
+            .lower_bound(0)
+            .upper_bound(10000)
+            .thousands_separator('.');
+
+        assert_eq!(1000, grammar.grammar_parse(" 1.000 ").unwrap());
+        assert_eq!(1000, grammar.grammar_parse("1000").unwrap());
+        assert!(grammar.grammar_parse("1,5").is_err());
+        assert!(grammar.grammar_parse("1.5").is_err());
+
+        let grammar = Grammar::integer()
+            .lower_bound(0)
+            .upper_bound(10000)
+            .thousands_separator(',');
+        assert_eq!(1234567, grammar.grammar_parse("1,234,567").unwrap());
+        assert!(grammar.grammar_parse("1,5").is_err());
+    }
 }
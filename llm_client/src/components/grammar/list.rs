@@ -0,0 +1,232 @@
+use super::{cached_grammar_string, gbnf_escape, Grammar, GrammarError, GrammarSetterTrait};
+use std::cell::RefCell;
+
+/// A bracketed, comma separated list like `[item, item]`, where each item matches the item grammar.
+///
+/// Items are split on commas when parsing, so item grammars that can generate a comma are not supported.
+#[derive(Clone)]
+pub struct ListGrammar {
+    pub item: Box<Grammar>,
+    pub min_count: u8,
+    pub max_count: u8,
+    pub stop_word_done: Option<String>,
+    pub stop_word_no_result: Option<String>,
+    grammar_string: RefCell<Option<String>>,
+}
+
+impl Default for ListGrammar {
+    fn default() -> Self {
+        Self {
+            item: Box::new(Grammar::default()),
+            min_count: 1,
+            max_count: 5,
+            stop_word_done: None,
+            stop_word_no_result: None,
+            grammar_string: RefCell::new(None),
+        }
+    }
+}
+
+impl ListGrammar {
+    pub fn wrap(self) -> Grammar {
+        Grammar::List(self)
+    }
+
+    pub fn item(mut self, item: Grammar) -> Self {
+        self.item = Box::new(item);
+        self.grammar_string = RefCell::new(None);
+        self
+    }
+
+    pub fn min_count(mut self, min_count: u8) -> Self {
+        self.min_count = min_count;
+        self.grammar_string = RefCell::new(None);
+        self
+    }
+
+    pub fn max_count(mut self, max_count: u8) -> Self {
+        self.max_count = max_count;
+        self.grammar_string = RefCell::new(None);
+        self
+    }
+
+    pub fn grammar_string(&self) -> String {
+        let mut grammar_string = self.grammar_string.borrow_mut();
+        if grammar_string.is_none() {
+            *grammar_string = Some(cached_grammar_string(
+                (
+                    "list",
+                    self.item.grammar_string(),
+                    self.min_count,
+                    self.max_count,
+                    self.stop_word_done.clone(),
+                    self.stop_word_no_result.clone(),
+                ),
+                || {
+                    list_grammar(
+                        &self.item.grammar_string(),
+                        self.min_count,
+                        self.max_count,
+                        &self.stop_word_done,
+                        &self.stop_word_no_result,
+                    )
+                },
+            ));
+        }
+        grammar_string.as_ref().unwrap().clone()
+    }
+
+    pub fn validate_clean(&self, content: &str) -> Result<String, GrammarError> {
+        let items = self
+            .split_items(content)?
+            .into_iter()
+            .map(|item| self.item.validate_clean(item))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(format!("[{}]", items.join(", ")))
+    }
+
+    /// Splits the content into the unparsed items and checks the item count.
+    pub fn split_items<'a>(&self, content: &'a str) -> Result<Vec<&'a str>, GrammarError> {
+        let parse_error = || GrammarError::ParseValueError {
+            content: content.to_string(),
+            parse_type: format!("list of {}-{} items", self.min_count, self.max_count),
+        };
+        let inner = content
+            .trim()
+            .strip_prefix('[')
+            .and_then(|c| c.strip_suffix(']'))
+            .ok_or_else(parse_error)?;
+        let items: Vec<&str> = if inner.trim().is_empty() {
+            Vec::new()
+        } else {
+            inner.split(',').map(str::trim).collect()
+        };
+        if items.len() < self.min_count as usize || items.len() > self.max_count as usize {
+            return Err(parse_error());
+        }
+        Ok(items)
+    }
+}
+
+impl GrammarSetterTrait for ListGrammar {
+    fn stop_word_done_mut(&mut self) -> &mut Option<String> {
+        &mut self.stop_word_done
+    }
+
+    fn stop_word_no_result_mut(&mut self) -> &mut Option<String> {
+        &mut self.stop_word_no_result
+    }
+}
+
+pub fn list_grammar<T: AsRef<str>>(
+    item_grammar: &str,
+    min_count: u8,
+    max_count: u8,
+    stop_word_done: &Option<T>,
+    stop_word_no_result: &Option<T>,
+) -> String {
+    if max_count == 0 || min_count > max_count {
+        panic!("max_count must be greater than 0 and at least min_count.")
+    }
+    let rest = match (min_count.max(1) - 1, max_count - 1) {
+        (_, 0) => String::new(),
+        (min, max) => format!(" ( \",\" item-root ){{{min},{max}}}"),
+    };
+    let list = if min_count == 0 {
+        format!("\"[\" ( item-root{rest} )? \"]\"")
+    } else {
+        format!("\"[\" item-root{rest} \"]\"")
+    };
+    let item_rules = prefix_rule_names(item_grammar, "item-");
+    match (stop_word_done, stop_word_no_result) {
+        (Some(stop_word_done), Some(stop_word_no_result)) => format!(
+            "root ::= \" \" ( {list} | \"{}\" ) \" {}\"\n\n{item_rules}",
+            gbnf_escape(stop_word_no_result.as_ref()),
+            gbnf_escape(stop_word_done.as_ref())
+        ),
+        (None, Some(stop_word_no_result)) => format!(
+            "root ::= \" \" ( {list} | \"{}\" )\n\n{item_rules}",
+            gbnf_escape(stop_word_no_result.as_ref())
+        ),
+        (Some(stop_word_done), None) => format!(
+            "root ::= \" \" {list} \" {}\"\n\n{item_rules}",
+            gbnf_escape(stop_word_done.as_ref())
+        ),
+        (None, None) => format!("root ::= \" \" {list}\n\n{item_rules}"),
+    }
+}
+
+/// Prefixes every rule name in `grammar`, so its rules can be embedded in another grammar without name clashes.
+/// Literals, character classes and repetition counts are left unchanged.
+fn prefix_rule_names(grammar: &str, prefix: &str) -> String {
+    let mut prefixed = String::with_capacity(grammar.len());
+    let mut chars = grammar.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' | '[' => {
+                let end = if c == '"' { '"' } else { ']' };
+                prefixed.push(c);
+                while let Some(c) = chars.next() {
+                    prefixed.push(c);
+                    if c == '\\' {
+                        if let Some(escaped) = chars.next() {
+                            prefixed.push(escaped);
+                        }
+                    } else if c == end {
+                        break;
+                    }
+                }
+            }
+            c if c.is_ascii_alphabetic() => {
+                prefixed.push_str(prefix);
+                prefixed.push(c);
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                        break;
+                    }
+                    prefixed.push(c);
+                    chars.next();
+                }
+            }
+            c => prefixed.push(c),
+        }
+    }
+    prefixed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test() {
+        let mut grammar = Grammar::list()
+            .item(Grammar::integer().lower_bound(0).upper_bound(9).wrap())
+            .min_count(1)
+            .max_count(3);
+        let grammar_string = grammar.set_stop_word_done("stop").grammar_string();
+        assert_eq!(
+            grammar_string,
+            "root ::= \" \" \"[\" item-root ( \",\" item-root ){0,2} \"]\" \" stop\"\n\nitem-root ::= \" \" [0-9]"
+        );
+
+        assert_eq!(grammar.validate_clean("[ 1, 2,3]").unwrap(), "[1, 2, 3]");
+        assert!(grammar.validate_clean("[]").is_err());
+        assert!(grammar.validate_clean("[1, 2, 3, 4]").is_err());
+        assert!(grammar.validate_clean("[1, x]").is_err());
+
+        let grammar = Grammar::list()
+            .item(
+                Grammar::exact_string()
+                    .add_exact_strings(&["a[b]", "item"])
+                    .wrap(),
+            )
+            .min_count(0);
+        let grammar_string = grammar.grammar_string();
+        assert!(grammar_string.contains("( item-root ( \",\" item-root ){0,4} )?"));
+        assert!(grammar_string.contains("item-root ::= "));
+        assert!(grammar_string.contains("\"a[b]\""));
+        assert!(grammar_string.contains("\"item\""));
+        assert_eq!(grammar.split_items("[]").unwrap(), Vec::<&str>::new());
+    }
+}
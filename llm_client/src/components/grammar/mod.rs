@@ -1,18 +1,25 @@
+use std::collections::HashMap;
 use text::text_list::TextListGrammar;
 use thiserror::Error;
 pub mod basic_url;
 pub mod boolean;
+pub mod date;
 pub mod exact_string;
 pub mod faux_url;
+pub mod float;
 pub mod integer;
+pub mod list;
 
 pub mod text;
 
 pub use basic_url::BasicUrlGrammar;
 pub use boolean::BooleanGrammar;
+pub use date::DateGrammar;
 pub use exact_string::ExactStringGrammar;
 pub use faux_url::FauxUrlGrammar;
+pub use float::FloatGrammar;
 pub use integer::IntegerGrammar;
+pub use list::ListGrammar;
 pub use text::sentences::SentencesGrammar;
 pub use text::text::TextGrammar;
 pub use text::words::WordsGrammar;
@@ -21,6 +28,7 @@ pub use text::words::WordsGrammar;
 pub enum Grammar {
     Boolean(BooleanGrammar),
     Integer(IntegerGrammar),
+    Float(FloatGrammar),
     Text(TextGrammar),
     Sentences(SentencesGrammar),
     Words(WordsGrammar),
@@ -28,6 +36,8 @@ pub enum Grammar {
     BasicUrl(BasicUrlGrammar),
     ExactString(ExactStringGrammar),
     FauxUrl(FauxUrlGrammar),
+    Date(DateGrammar),
+    List(ListGrammar),
 }
 
 macro_rules! grammar_default {
@@ -90,6 +100,7 @@ grammar_default! {
     Grammar {
         Boolean => boolean: BooleanGrammar,
         Integer => integer: IntegerGrammar,
+        Float => float: FloatGrammar,
         Text => text: TextGrammar,
         Sentences => sentences: SentencesGrammar,
         Words => words: WordsGrammar,
@@ -97,6 +108,8 @@ grammar_default! {
         BasicUrl => basic_url: BasicUrlGrammar,
         ExactString => exact_string: ExactStringGrammar,
         FauxUrl => faux_url: FauxUrlGrammar,
+        Date => date: DateGrammar,
+        List => list: ListGrammar,
     }
 }
 
@@ -132,6 +145,93 @@ pub trait GrammarSetterTrait {
     }
 }
 
+/// The most grammar strings kept in [cached_grammar_string]'s cache. The cache is cleared when it is full.
+const GRAMMAR_CACHE_CAPACITY: usize = 1024;
+
+/// Grammar strings built in this process, keyed by the grammar type and the arguments they were built from. Keys of
+/// different types are kept in separate maps, so the full key is compared and configs can't collide.
+static GRAMMAR_CACHE: std::sync::LazyLock<std::sync::Mutex<GrammarCache>> =
+    std::sync::LazyLock::new(Default::default);
+
+#[derive(Default)]
+struct GrammarCache {
+    maps: HashMap<std::any::TypeId, Box<dyn std::any::Any + Send>>,
+    len: usize,
+}
+
+impl GrammarCache {
+    fn map<K: std::hash::Hash + Eq + Send + 'static>(&mut self) -> &mut HashMap<K, String> {
+        self.maps
+            .entry(std::any::TypeId::of::<K>())
+            .or_insert_with(|| Box::new(HashMap::<K, String>::new()))
+            .downcast_mut()
+            .expect("grammar cache maps are keyed by their key type")
+    }
+
+    fn insert<K: std::hash::Hash + Eq + Send + 'static>(&mut self, key: K, grammar_string: String) {
+        if self.len >= GRAMMAR_CACHE_CAPACITY {
+            self.clear();
+        }
+        if self.map().insert(key, grammar_string).is_none() {
+            self.len += 1;
+        }
+    }
+
+    fn clear(&mut self) {
+        self.maps.clear();
+        self.len = 0;
+    }
+}
+
+/// Returns the grammar string built for `key`, or builds and caches it.
+///
+/// Primitives build a new grammar for every request, so the per grammar cache doesn't help when the same primitive
+/// runs over a batch of items. `key` must include everything the grammar string is built from.
+pub(crate) fn cached_grammar_string<K: std::hash::Hash + Eq + Send + 'static>(
+    key: K,
+    build: impl FnOnce() -> String,
+) -> String {
+    if let Some(grammar_string) = grammar_cache().map::<K>().get(&key) {
+        return grammar_string.clone();
+    }
+    let grammar_string = build();
+    grammar_cache().insert(key, grammar_string.clone());
+    grammar_string
+}
+
+/// Clears the grammar strings cached by all grammars in this process.
+pub fn clear_grammar_cache() {
+    grammar_cache().clear();
+}
+
+fn grammar_cache() -> std::sync::MutexGuard<'static, GrammarCache> {
+    GRAMMAR_CACHE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Escapes a string for use inside a double quoted GBNF literal, so the literal matches exactly that string.
+pub fn gbnf_escape(literal: &str) -> String {
+    literal
+        .chars()
+        .map(|c| gbnf_escape_char(c, false))
+        .collect()
+}
+
+/// Escapes a char for use inside a double quoted GBNF literal or, if `in_class`, inside a `[...]` character class.
+pub fn gbnf_escape_char(c: char, in_class: bool) -> String {
+    match c {
+        '\\' => "\\\\".to_string(),
+        '\n' => "\\n".to_string(),
+        '\r' => "\\r".to_string(),
+        '\t' => "\\t".to_string(),
+        '"' if !in_class => "\\\"".to_string(),
+        '[' | ']' if in_class => format!("\\{c}"),
+        '-' | '^' if in_class => format!("\\x{:02X}", c as u32),
+        // Control chars are all below U+0100, so they fit the two digit hex escape.
+        c if c.is_control() => format!("\\x{:02X}", c as u32),
+        c => c.to_string(),
+    }
+}
+
 #[derive(Error, Debug, PartialEq)]
 pub enum GrammarError {
     #[error("grammar not set")]
@@ -171,4 +271,111 @@ mod tests {
         let res: bool = grammar.grammar_parse("true").unwrap();
         assert!(res);
     }
+
+    /// Reads the double quoted GBNF literals in `grammar` back into the strings they match, following llama.cpp's
+    /// grammar parser.
+    fn parse_gbnf_literals(grammar: &str) -> Vec<String> {
+        let mut literals = Vec::new();
+        let mut chars = grammar.chars();
+        while let Some(c) = chars.next() {
+            if c != '"' {
+                continue;
+            }
+            let mut literal = String::new();
+            loop {
+                match chars.next().expect("unterminated literal") {
+                    '"' => break,
+                    '\\' => match chars.next().expect("dangling escape") {
+                        'n' => literal.push('\n'),
+                        'r' => literal.push('\r'),
+                        't' => literal.push('\t'),
+                        'x' => {
+                            let hex: String = chars.by_ref().take(2).collect();
+                            let code = u32::from_str_radix(&hex, 16).expect("invalid hex escape");
+                            literal.push(char::from_u32(code).unwrap());
+                        }
+                        c @ ('\\' | '"' | '[' | ']') => literal.push(c),
+                        c => panic!("unsupported escape \\{c}"),
+                    },
+                    c => {
+                        assert!(!c.is_control(), "raw control char {c:?} in literal");
+                        literal.push(c);
+                    }
+                }
+            }
+            literals.push(literal);
+        }
+        literals
+    }
+
+    fn random_literal(state: &mut u64) -> String {
+        const ALPHABET: &[char] = &[
+            'a', 'Z', '0', ' ', '"', '\\', '[', ']', '(', ')', '|', '-', '^', '\n', '\r', '\t',
+            '\0', '\u{1b}', '\u{7f}', 'é', '•', '漢', '🦀',
+        ];
+        let mut next = || {
+            // xorshift64, so the fuzz cases are reproducible without an extra dependency.
+            *state ^= *state << 13;
+            *state ^= *state >> 7;
+            *state ^= *state << 17;
+            *state
+        };
+        let len = (next() % 12) as usize + 1;
+        (0..len)
+            .map(|_| ALPHABET[(next() % ALPHABET.len() as u64) as usize])
+            .collect()
+    }
+
+    #[test]
+    fn test_gbnf_escape_fuzz() {
+        let mut state = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..2000 {
+            let literal = random_literal(&mut state);
+            let grammar = format!("root ::= \"{}\"", gbnf_escape(&literal));
+            assert_eq!(parse_gbnf_literals(&grammar), vec![literal]);
+        }
+    }
+
+    #[test]
+    fn test_exact_string_escaping_fuzz() {
+        let mut state = 0x9e37_79b9_7f4a_7c15;
+        for _ in 0..500 {
+            let options: Vec<String> = (0..3).map(|_| random_literal(&mut state)).collect();
+            let stop_word_done = random_literal(&mut state);
+            let stop_word_no_result = random_literal(&mut state);
+            let grammar = exact_string::exact_string_grammar(
+                &options,
+                &Some(&stop_word_done),
+                &Some(&stop_word_no_result),
+            );
+            let mut expected = options.clone();
+            expected.push(stop_word_no_result);
+            expected.push(format!(" {stop_word_done}"));
+            assert_eq!(parse_gbnf_literals(&grammar), expected);
+        }
+    }
+
+    #[test]
+    fn test_cached_grammar_string() {
+        let builds = std::cell::Cell::new(0);
+        let build = || {
+            builds.set(builds.get() + 1);
+            "root ::= \"a\"".to_string()
+        };
+        let key = ("test_cached_grammar_string", 1, Some("done"));
+        assert_eq!(cached_grammar_string(key, build), "root ::= \"a\"");
+        assert_eq!(cached_grammar_string(key, build), "root ::= \"a\"");
+        assert_eq!(builds.get(), 1);
+        cached_grammar_string(("test_cached_grammar_string", 2, Some("done")), build);
+        assert_eq!(builds.get(), 2);
+        cached_grammar_string(("test_cached_grammar_string", 1u64, Some("done")), build);
+        assert_eq!(builds.get(), 3);
+
+        let grammar = |upper_bound| Grammar::integer().lower_bound(0).upper_bound(upper_bound);
+        assert_eq!(grammar(100).grammar_string(), grammar(100).grammar_string());
+        assert_ne!(
+            grammar(100).grammar_string(),
+            grammar(1000).grammar_string()
+        );
+    }
 }
@@ -3,7 +3,9 @@ pub mod text;
 pub mod text_list;
 pub mod words;
 
-use super::{Grammar, GrammarError, GrammarSetterTrait};
+use super::{
+    cached_grammar_string, gbnf_escape, gbnf_escape_char, Grammar, GrammarError, GrammarSetterTrait,
+};
 use std::cell::RefCell;
 
 // const NEWLINE_CHARS: [char; 8] = [
@@ -19,7 +21,13 @@ fn build_disallowed(disallowed_chars: &[char]) -> String {
     disallowed.sort();
     disallowed.dedup();
 
-    format!("[^{}]", disallowed.iter().collect::<String>())
+    format!(
+        "[^{}]",
+        disallowed
+            .iter()
+            .map(|c| gbnf_escape_char(*c, true))
+            .collect::<String>()
+    )
 }
 
 fn build_quotes(disallowed_chars: &Vec<char>) -> Option<String> {
@@ -65,7 +73,7 @@ fn create_range<T: AsRef<str>>(
                 if let Some(stop_word_done) = stop_word_done {
                     range.push_str(&format!(
                         "( first | \"{}\" ){{0,1}}",
-                        stop_word_done.as_ref()
+                        gbnf_escape(stop_word_done.as_ref())
                     ))
                 } else {
                     range.push_str(&format!("first{{0,1}}"));
@@ -81,7 +89,7 @@ fn create_range<T: AsRef<str>>(
             if let Some(stop_word_done) = stop_word_done {
                 range.push_str(&format!(
                     "( item | \"{}\" ){{0,{opt_count}}}",
-                    stop_word_done.as_ref()
+                    gbnf_escape(stop_word_done.as_ref())
                 ))
             } else {
                 range.push_str(&format!("item{{0,{opt_count}}}"));
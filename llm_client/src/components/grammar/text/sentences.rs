@@ -1,6 +1,6 @@
 use super::{
-    build_disallowed, build_quotes, create_range, Grammar, GrammarError, GrammarSetterTrait,
-    RefCell, NEWLINE_CHARS,
+    build_disallowed, build_quotes, cached_grammar_string, create_range, gbnf_escape, Grammar,
+    GrammarError, GrammarSetterTrait, RefCell, NEWLINE_CHARS,
 };
 
 #[derive(Clone)]
@@ -74,15 +74,30 @@ impl SentencesGrammar {
     pub fn grammar_string(&self) -> String {
         let mut grammar_string = self.grammar_string.borrow_mut();
         if grammar_string.is_none() {
-            *grammar_string = Some(sentences_grammar(
-                self.min_count,
-                self.max_count,
-                self.sentence_token_length,
-                self.capitalize_first,
-                &self.concatenator,
-                &self.stop_word_done,
-                &self.stop_word_no_result,
-                &self.disallowed_chars,
+            *grammar_string = Some(cached_grammar_string(
+                (
+                    "sentences",
+                    self.min_count,
+                    self.max_count,
+                    self.sentence_token_length,
+                    self.capitalize_first,
+                    self.concatenator.clone(),
+                    self.stop_word_done.clone(),
+                    self.stop_word_no_result.clone(),
+                    self.disallowed_chars.clone(),
+                ),
+                || {
+                    sentences_grammar(
+                        self.min_count,
+                        self.max_count,
+                        self.sentence_token_length,
+                        self.capitalize_first,
+                        &self.concatenator,
+                        &self.stop_word_done,
+                        &self.stop_word_no_result,
+                        &self.disallowed_chars,
+                    )
+                },
             ));
         }
         grammar_string.as_ref().unwrap().clone()
@@ -131,19 +146,19 @@ pub fn sentences_grammar<T: AsRef<str>>(
         match (stop_word_done, stop_word_no_result) {
             (Some(stop_word_done), Some(stop_word_no_result)) => format!(
                 "root ::= ( {range} | \"{}\" ) \" {}\"\n\n{sentence_item}",
-                stop_word_no_result.as_ref(),
-                stop_word_done.as_ref()
+                gbnf_escape(stop_word_no_result.as_ref()),
+                gbnf_escape(stop_word_done.as_ref())
             ),
             (None, Some(stop_word_no_result)) => {
                 format!(
                     "root ::= ( {range} | \"{}\" )\n\n{sentence_item}",
-                    stop_word_no_result.as_ref()
+                    gbnf_escape(stop_word_no_result.as_ref())
                 )
             }
             (Some(stop_word_done), None) => {
                 format!(
                     "root ::= {range} \" {}\"\n\n{sentence_item}",
-                    stop_word_done.as_ref()
+                    gbnf_escape(stop_word_done.as_ref())
                 )
             }
             (None, None) => format!("root ::= {range}\n\n{sentence_item}"),
@@ -161,19 +176,19 @@ pub fn sentences_grammar<T: AsRef<str>>(
         match (stop_word_done, stop_word_no_result) {
             (Some(stop_word_done), Some(stop_word_no_result)) => format!(
                 "root ::= ( {range} | \"{}\" ) \" {}\"\n\n{first_item}\n\n{sentence_item}",
-                stop_word_no_result.as_ref(),
-                stop_word_done.as_ref()
+                gbnf_escape(stop_word_no_result.as_ref()),
+                gbnf_escape(stop_word_done.as_ref())
             ),
             (None, Some(stop_word_no_result)) => {
                 format!(
                     "root ::= ( {range} | \"{}\" )\n\n{first_item}\n\n{sentence_item}",
-                    stop_word_no_result.as_ref()
+                    gbnf_escape(stop_word_no_result.as_ref())
                 )
             }
             (Some(stop_word_done), None) => {
                 format!(
                     "root ::= {range} \" {}\"\n\n{first_item}\n\n{sentence_item}",
-                    stop_word_done.as_ref()
+                    gbnf_escape(stop_word_done.as_ref())
                 )
             }
             (None, None) => format!("root ::= {range}\n\n{first_item}\n\n{sentence_item}"),
@@ -1,4 +1,7 @@
-use super::{build_disallowed, Grammar, GrammarError, GrammarSetterTrait, RefCell, NEWLINE_CHARS};
+use super::{
+    build_disallowed, cached_grammar_string, gbnf_escape, Grammar, GrammarError,
+    GrammarSetterTrait, RefCell, NEWLINE_CHARS,
+};
 
 #[derive(Clone)]
 pub struct TextGrammar {
@@ -51,12 +54,24 @@ impl TextGrammar {
     pub fn grammar_string(&self) -> String {
         let mut grammar_string = self.grammar_string.borrow_mut();
         if grammar_string.is_none() {
-            *grammar_string = Some(text_grammar(
-                self.item_token_length,
-                &self.stop_word_done,
-                &self.stop_word_no_result,
-                self.allow_newline,
-                &self.disallowed_chars,
+            *grammar_string = Some(cached_grammar_string(
+                (
+                    "text",
+                    self.item_token_length,
+                    self.stop_word_done.clone(),
+                    self.stop_word_no_result.clone(),
+                    self.allow_newline,
+                    self.disallowed_chars.clone(),
+                ),
+                || {
+                    text_grammar(
+                        self.item_token_length,
+                        &self.stop_word_done,
+                        &self.stop_word_no_result,
+                        self.allow_newline,
+                        &self.disallowed_chars,
+                    )
+                },
             ));
         }
         grammar_string.as_ref().unwrap().clone()
@@ -95,6 +110,8 @@ pub fn text_grammar(
         disallowed.extend(NEWLINE_CHARS.iter());
         build_disallowed(&disallowed)
     };
+    let stop_word_done = stop_word_done.as_deref().map(gbnf_escape);
+    let stop_word_no_result = stop_word_no_result.as_deref().map(gbnf_escape);
     match (stop_word_done, stop_word_no_result) {
         (Some(stop_word_done), Some(stop_word_no_result)) => {
             format!(
@@ -106,7 +123,6 @@ pub fn text_grammar(
             format!(
                 "root ::= item{{1,{}}} \" {stop_word_done}\"\nitem ::= {disallowed}",
                 (item_token_length as f32 * 4.5).floor() as u32,
-      
             )
         }
         (None, Some(stop_word_no_result)) => {
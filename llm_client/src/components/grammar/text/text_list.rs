@@ -1,6 +1,6 @@
 use super::{
-    build_disallowed, create_range, Grammar, GrammarError, GrammarSetterTrait, RefCell,
-    NEWLINE_CHARS,
+    build_disallowed, cached_grammar_string, create_range, gbnf_escape, Grammar, GrammarError,
+    GrammarSetterTrait, RefCell, NEWLINE_CHARS,
 };
 
 #[derive(Clone)]
@@ -77,14 +77,28 @@ impl TextListGrammar {
     pub fn grammar_string(&self) -> String {
         let mut grammar_string = self.grammar_string.borrow_mut();
         if grammar_string.is_none() {
-            *grammar_string = Some(list_grammar(
-                self.min_count,
-                self.max_count,
-                self.item_token_length,
-                &self.item_prefix,
-                &self.stop_word_done,
-                &self.stop_word_no_result,
-                &self.disallowed_chars,
+            *grammar_string = Some(cached_grammar_string(
+                (
+                    "text_list",
+                    self.min_count,
+                    self.max_count,
+                    self.item_token_length,
+                    self.item_prefix.clone(),
+                    self.stop_word_done.clone(),
+                    self.stop_word_no_result.clone(),
+                    self.disallowed_chars.clone(),
+                ),
+                || {
+                    list_grammar(
+                        self.min_count,
+                        self.max_count,
+                        self.item_token_length,
+                        &self.item_prefix,
+                        &self.stop_word_done,
+                        &self.stop_word_no_result,
+                        &self.disallowed_chars,
+                    )
+                },
             ));
         }
         grammar_string.as_ref().unwrap().clone()
@@ -127,7 +141,7 @@ pub fn list_grammar(
     let list_item = match item_prefix {
         Some(item_prefix) => format!(
             "item ::= \"• \" \"{}\" {disallowed}{{1,{}}} \"\\n\"",
-            item_prefix,
+            gbnf_escape(item_prefix),
             (item_token_length as f32 * 4.5).floor() as u32,
         ),
         None => format!(
@@ -139,16 +153,20 @@ pub fn list_grammar(
     match (stop_word_done, stop_word_no_result) {
         (Some(stop_word_done), Some(stop_word_no_result)) => format!(
             "root ::= ( {range} | \"{}\" ) \" {}\"\n\n{list_item}",
-            stop_word_no_result, stop_word_done
+            gbnf_escape(stop_word_no_result),
+            gbnf_escape(stop_word_done)
         ),
         (None, Some(stop_word_no_result)) => {
             format!(
                 "root ::= ( {range} | \"{}\" )\n\n{list_item}",
-                stop_word_no_result
+                gbnf_escape(stop_word_no_result)
             )
         }
         (Some(stop_word_done), None) => {
-            format!("root ::= {range} \" {}\"\n\n{list_item}", stop_word_done)
+            format!(
+                "root ::= {range} \" {}\"\n\n{list_item}",
+                gbnf_escape(stop_word_done)
+            )
         }
         (None, None) => format!("root ::= {range}\n\n{list_item}"),
     }
@@ -1,4 +1,7 @@
-use super::{create_range, Grammar, GrammarError, GrammarSetterTrait, RefCell};
+use super::{
+    cached_grammar_string, create_range, gbnf_escape, Grammar, GrammarError, GrammarSetterTrait,
+    RefCell,
+};
 
 #[derive(Clone, Default)]
 pub struct WordsGrammar {
@@ -53,13 +56,26 @@ impl WordsGrammar {
     pub fn grammar_string(&self) -> String {
         let mut grammar_string = self.grammar_string.borrow_mut();
         if grammar_string.is_none() {
-            *grammar_string = Some(words_grammar(
-                self.min_count,
-                self.max_count,
-                self.word_char_length,
-                &self.concatenator,
-                &self.stop_word_done,
-                &self.stop_word_no_result,
+            *grammar_string = Some(cached_grammar_string(
+                (
+                    "words",
+                    self.min_count,
+                    self.max_count,
+                    self.word_char_length,
+                    self.concatenator.clone(),
+                    self.stop_word_done.clone(),
+                    self.stop_word_no_result.clone(),
+                ),
+                || {
+                    words_grammar(
+                        self.min_count,
+                        self.max_count,
+                        self.word_char_length,
+                        &self.concatenator,
+                        &self.stop_word_done,
+                        &self.stop_word_no_result,
+                    )
+                },
             ));
         }
         grammar_string.as_ref().unwrap().clone()
@@ -84,6 +100,41 @@ impl GrammarSetterTrait for WordsGrammar {
     }
 }
 
+/// The number of hyphens or apostrophes the grammar allows within a word, enough for "mother-in-law".
+const MAX_WORD_JOINERS: u8 = 2;
+
+/// Splits text into words by the rule [WordsGrammar] generates them with.
+///
+/// A word is a run of letters and digits, so numbers are words. A hyphen or apostrophe between two letters or digits
+/// joins them into one word, so "well-known" and "don't" are each one word. All other characters, including a
+/// hyphen or apostrophe at the edge of a word, separate words.
+pub fn split_words(content: &str) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = content.char_indices().collect();
+    let is_joiner = |c: char| c == '-' || c == '\'' || c == '\u{2019}';
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, &(position, c)) in chars.iter().enumerate() {
+        let in_word = c.is_alphanumeric()
+            || (is_joiner(c)
+                && start.is_some()
+                && chars
+                    .get(i + 1)
+                    .is_some_and(|(_, next)| next.is_alphanumeric()));
+        match (in_word, start) {
+            (true, None) => start = Some(position),
+            (false, Some(word_start)) => {
+                words.push(&content[word_start..position]);
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    if let Some(word_start) = start {
+        words.push(&content[word_start..]);
+    }
+    words
+}
+
 pub fn words_grammar<T: AsRef<str>>(
     min_count: u8,
     max_count: u8,
@@ -93,23 +144,25 @@ pub fn words_grammar<T: AsRef<str>>(
     stop_word_no_result: &Option<T>,
 ) -> String {
     let range = create_range(false, min_count, max_count, stop_word_done);
-    let item = format!("item ::= [a-z]{{1,{word_char_length}}} \"{concatenator}\"",);
+    let item = format!(
+        "item ::= word \"{concatenator}\"\nword ::= [a-z0-9]{{1,{word_char_length}}} ( [-'] [a-z0-9]{{1,{word_char_length}}} ){{0,{MAX_WORD_JOINERS}}}",
+    );
     match (stop_word_done, stop_word_no_result) {
         (Some(stop_word_done), Some(stop_word_no_result)) => format!(
             "root ::= \" \" ( {range} | \"{}\" ) \" {}\"\n{item}",
-            stop_word_no_result.as_ref(),
-            stop_word_done.as_ref()
+            gbnf_escape(stop_word_no_result.as_ref()),
+            gbnf_escape(stop_word_done.as_ref())
         ),
         (None, Some(stop_word_no_result)) => {
             format!(
                 "root ::= \" \" ( {range} | \"{}\" )\n{item}",
-                stop_word_no_result.as_ref()
+                gbnf_escape(stop_word_no_result.as_ref())
             )
         }
         (Some(stop_word_done), None) => {
             format!(
                 "root ::= \" \" {range} \" {}\"\n{item}",
-                stop_word_done.as_ref()
+                gbnf_escape(stop_word_done.as_ref())
             )
         }
         (None, None) => format!("root ::= \" \" {range}\n{item}"),
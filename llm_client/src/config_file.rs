@@ -0,0 +1,326 @@
+use crate::LlmClient;
+use llm_interface::requests::req_components::{RequestConfig, SamplerPreset};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::path::Path;
+
+/// The backend built by [LlmClientConfig::build].
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigBackend {
+    LlamaCpp,
+    MistralRs,
+    #[serde(rename = "openai")]
+    OpenAi,
+    Anthropic,
+    Perplexity,
+}
+
+impl ConfigBackend {
+    pub fn is_local(&self) -> bool {
+        matches!(self, ConfigBackend::LlamaCpp | ConfigBackend::MistralRs)
+    }
+}
+
+/// A client configuration loaded from a TOML or JSON file, so the model can be changed without recompiling.
+///
+/// ```toml
+/// backend = "llama_cpp"
+/// model_path = "/models/Meta-Llama-3-8B-Instruct.Q6_K.gguf"
+/// ctx_size = 8192
+///
+/// [gpu]
+/// cuda_devices = [0, 1]
+///
+/// [sampling]
+/// temperature = 0.7
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LlmClientConfig {
+    pub backend: ConfigBackend,
+    /// The model id, e.g. `gpt-4o` or `claude-3-5-sonnet`. For local backends it is only used for display.
+    pub model_id: Option<String>,
+    /// The local path to a quantized GGUF file. Local backends only.
+    pub model_path: Option<String>,
+    /// The Hugging Face url of a quantized GGUF file. Local backends only.
+    pub model_url: Option<String>,
+    /// See [llm_interface::llms::local::LocalLlmConfig::inference_ctx_size]. Local backends only.
+    pub ctx_size: Option<u64>,
+    #[serde(default)]
+    pub gpu: GpuFileConfig,
+    #[serde(default)]
+    pub sampling: SamplingFileConfig,
+}
+
+/// Device selection for local backends.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GpuFileConfig {
+    /// Set to `false` to run on the CPU only. Defaults to using the GPU when one is available.
+    pub use_gpu: Option<bool>,
+    /// Ordinals of the CUDA devices to use. Defaults to all devices.
+    #[serde(default)]
+    pub cuda_devices: Vec<u32>,
+    /// The main CUDA device ordinal. Must be one of `cuda_devices` if both are set.
+    pub main_gpu: Option<u32>,
+}
+
+/// Defaults applied to every request made through the client. See [RequestConfig] for each field.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SamplingFileConfig {
+    /// Applied first, so the other settings override it. One of `deterministic`, `balanced` or `creative`.
+    pub preset: Option<SamplerPreset>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub frequency_penalty: Option<f32>,
+    pub presence_penalty: Option<f32>,
+    pub max_tokens: Option<u64>,
+}
+
+impl SamplingFileConfig {
+    pub fn apply(&self, config: &mut RequestConfig) {
+        if let Some(preset) = self.preset {
+            preset.apply(config);
+        }
+        if let Some(temperature) = self.temperature {
+            config.temperature = temperature;
+        }
+        if let Some(top_p) = self.top_p {
+            config.top_p = Some(top_p);
+        }
+        if let Some(frequency_penalty) = self.frequency_penalty {
+            config.frequency_penalty = Some(frequency_penalty);
+        }
+        if let Some(presence_penalty) = self.presence_penalty {
+            config.presence_penalty = presence_penalty;
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            config.requested_response_tokens = Some(max_tokens);
+        }
+    }
+
+    fn validate(&self) -> crate::Result<()> {
+        check_range("sampling.temperature", self.temperature, 0.0..=2.0)?;
+        check_range("sampling.top_p", self.top_p, 0.0..=1.0)?;
+        check_range(
+            "sampling.frequency_penalty",
+            self.frequency_penalty,
+            RequestConfig::PENALTY_RANGE,
+        )?;
+        check_range(
+            "sampling.presence_penalty",
+            self.presence_penalty,
+            RequestConfig::PENALTY_RANGE,
+        )?;
+        if self.max_tokens == Some(0) {
+            crate::bail!("`sampling.max_tokens` must be greater than 0");
+        }
+        Ok(())
+    }
+}
+
+fn check_range(
+    field: &str,
+    value: Option<f32>,
+    range: std::ops::RangeInclusive<f32>,
+) -> crate::Result<()> {
+    match value {
+        Some(value) if !range.contains(&value) => crate::bail!(
+            "`{field}` must be between {} and {}, got {value}",
+            range.start(),
+            range.end()
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Deserializes a `.toml` or `.json` file, chosen by its extension.
+pub(crate) fn parse_file<T: DeserializeOwned>(path: &Path) -> crate::Result<T> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| crate::anyhow!("Failed to read config file {}: {e}", path.display()))?;
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::from_str(&contents)
+            .map_err(|e| crate::anyhow!("Invalid config file {}: {e}", path.display())),
+        Some("json") => serde_json::from_str(&contents)
+            .map_err(|e| crate::anyhow!("Invalid config file {}: {e}", path.display())),
+        _ => crate::bail!(
+            "Unsupported config file {}. Expected a .toml or .json file",
+            path.display()
+        ),
+    }
+}
+
+impl LlmClientConfig {
+    /// Loads and validates a config file. The format is chosen by the `.toml` or `.json` extension.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let config: Self = parse_file(path)?;
+        config
+            .validate()
+            .map_err(|e| crate::anyhow!("Invalid config file {}: {e}", path.display()))?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> crate::Result<()> {
+        if self.backend.is_local() {
+            match (&self.model_path, &self.model_url) {
+                (None, None) => crate::bail!(
+                    "`model_path` or `model_url` is required for the {:?} backend",
+                    self.backend
+                ),
+                (Some(_), Some(_)) => {
+                    crate::bail!("Only one of `model_path` and `model_url` can be set")
+                }
+                _ => (),
+            }
+            if self.ctx_size == Some(0) {
+                crate::bail!("`ctx_size` must be greater than 0");
+            }
+            if let Some(main_gpu) = self.gpu.main_gpu {
+                if !self.gpu.cuda_devices.is_empty() && !self.gpu.cuda_devices.contains(&main_gpu) {
+                    crate::bail!(
+                        "`gpu.main_gpu` ({main_gpu}) must be one of `gpu.cuda_devices` ({:?})",
+                        self.gpu.cuda_devices
+                    );
+                }
+            }
+        } else {
+            if self.model_id.is_none() {
+                crate::bail!("`model_id` is required for the {:?} backend", self.backend);
+            }
+            for (field, is_set) in [
+                ("model_path", self.model_path.is_some()),
+                ("model_url", self.model_url.is_some()),
+                ("ctx_size", self.ctx_size.is_some()),
+                ("gpu", self.gpu != GpuFileConfig::default()),
+            ] {
+                if is_set {
+                    crate::bail!(
+                        "`{field}` is only supported by local backends, not {:?}",
+                        self.backend
+                    );
+                }
+            }
+        }
+        self.sampling.validate()
+    }
+
+    /// Builds the client for the configured backend. The sampling settings are applied to every request.
+    pub async fn build(&self) -> crate::Result<LlmClient> {
+        let mut client = match self.backend {
+            #[cfg(feature = "llama_cpp_backend")]
+            ConfigBackend::LlamaCpp => self.configure_local(LlmClient::llama_cpp()).init().await?,
+            #[cfg(feature = "mistral_rs_backend")]
+            ConfigBackend::MistralRs => {
+                self.configure_local(LlmClient::mistral_rs()).init().await?
+            }
+            ConfigBackend::OpenAi => {
+                crate::backend_builders::openai::OpenAiBackendBuilder::with_model_id(
+                    self.model_id.as_deref().unwrap_or_default(),
+                )
+                .init()?
+            }
+            ConfigBackend::Anthropic => {
+                use llm_models::api_model::anthropic::AnthropicModelTrait;
+                LlmClient::anthropic()
+                    .model_id_str(self.model_id.as_deref().unwrap_or_default())
+                    .init()?
+            }
+            ConfigBackend::Perplexity => {
+                use llm_models::api_model::perplexity::PerplexityModelTrait;
+                LlmClient::perplexity()
+                    .model_id_str(self.model_id.as_deref().unwrap_or_default())
+                    .init()?
+            }
+            #[allow(unreachable_patterns)]
+            backend => crate::bail!(
+                "The {backend:?} backend is not enabled. Enable its cargo feature to use it"
+            ),
+        };
+        client.default_sampling = Some(self.sampling.clone());
+        Ok(client)
+    }
+
+    #[cfg(any(feature = "llama_cpp_backend", feature = "mistral_rs_backend"))]
+    fn configure_local<B>(&self, mut builder: B) -> B
+    where
+        B: llm_models::local_model::GgufLoaderTrait + llm_interface::llms::local::LlmLocalTrait,
+    {
+        if let Some(model_id) = &self.model_id {
+            builder.model_id(model_id);
+        }
+        if let Some(model_path) = &self.model_path {
+            builder.local_quant_file_path(model_path);
+        }
+        if let Some(model_url) = &self.model_url {
+            builder.hf_quant_file_url(model_url);
+        }
+        if let Some(ctx_size) = self.ctx_size {
+            builder = builder.inference_ctx_size(ctx_size);
+        }
+        if let Some(use_gpu) = self.gpu.use_gpu {
+            builder = builder.use_gpu(use_gpu);
+        }
+        #[cfg(any(target_os = "linux", target_os = "windows"))]
+        if !self.gpu.cuda_devices.is_empty() || self.gpu.main_gpu.is_some() {
+            let mut cuda_config = llm_devices::devices::CudaConfig::new_from_cuda_devices(
+                self.gpu.cuda_devices.clone(),
+            );
+            cuda_config.main_gpu = self.gpu.main_gpu;
+            builder = builder.cuda_config(cuda_config);
+        }
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_validate() {
+        let config: LlmClientConfig = toml::from_str(
+            r#"
+            backend = "llama_cpp"
+            model_path = "/models/model.gguf"
+            ctx_size = 4096
+
+            [gpu]
+            cuda_devices = [0, 1]
+            main_gpu = 1
+
+            [sampling]
+            preset = "balanced"
+            temperature = 0.5
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.backend, ConfigBackend::LlamaCpp);
+        assert_eq!(config.sampling.temperature, Some(0.5));
+        assert_eq!(config.sampling.preset, Some(SamplerPreset::Balanced));
+        let mut request_config = RequestConfig::new(4096, 4096);
+        config.sampling.apply(&mut request_config);
+        assert_eq!(request_config.temperature, 0.5);
+        assert_eq!(request_config.top_p, Some(0.9));
+
+        let config: LlmClientConfig = serde_json::from_str(
+            r#"{"backend": "openai", "model_id": "gpt-4o", "sampling": {"temperature": 3.5}}"#,
+        )
+        .unwrap();
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("`sampling.temperature`"), "{err}");
+
+        let config: LlmClientConfig =
+            toml::from_str("backend = \"anthropic\"\nmodel_id = \"claude\"\nctx_size = 100")
+                .unwrap();
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("`ctx_size`"), "{err}");
+
+        let err = toml::from_str::<LlmClientConfig>("backend = \"openai\"\ntempreature = 1.0")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("tempreature"), "{err}");
+    }
+}
@@ -0,0 +1,321 @@
+use crate::{basic_completion::BasicCompletion, primitives::total_token_logprob, LlmClient};
+use llm_interface::requests::{
+    completion::{CompletionError, CompletionResponse},
+    req_components::RequestConfigTrait,
+    res_components::InferenceProbabilities,
+};
+
+/// The geometric mean token probability below which the default check escalates a cheap response.
+pub const DEFAULT_MIN_CONFIDENCE: f32 = 0.5;
+
+/// Asks a cheap model first and only asks an expensive model when the cheap response isn't good enough.
+///
+/// A request escalates when the cheap model's response fails to parse or validate, or when `should_escalate` returns
+/// true for it. Other errors, such as authentication, moderation or request building errors, would fail the same way
+/// on the expensive model and are returned instead. The default check escalates empty responses and responses whose mean token probability is below
+/// [DEFAULT_MIN_CONFIDENCE]. Logprobs are requested from the cheap model so the confidence can be measured.
+///
+/// ```ignore
+/// let client = EscalatingClient::new(
+///     LlmClient::llama_cpp().llama3_1_8b_instruct().init().await?,
+///     LlmClient::openai().gpt_4_o().init()?,
+/// );
+/// let res = client
+///     .basic_completion(|completion| {
+///         completion.prompt().add_user_message().unwrap().set_content("Is the sky blue?");
+///     })
+///     .await?;
+/// ```
+pub struct EscalatingClient {
+    pub cheap: LlmClient,
+    pub expensive: LlmClient,
+    pub should_escalate: Box<dyn Fn(&CompletionResponse) -> bool + Send + Sync>,
+}
+
+/// The response of an [EscalatingClient] request.
+#[derive(Clone)]
+pub struct EscalationResponse {
+    pub response: CompletionResponse,
+    /// True if the response is from the expensive model.
+    pub escalated: bool,
+}
+
+impl EscalatingClient {
+    pub fn new(cheap: LlmClient, expensive: LlmClient) -> Self {
+        Self {
+            cheap,
+            expensive,
+            should_escalate: Box::new(|res| {
+                should_escalate_below_confidence(res, DEFAULT_MIN_CONFIDENCE)
+            }),
+        }
+    }
+
+    /// Replaces the default check that decides whether a cheap response is escalated.
+    pub fn with_should_escalate<F>(mut self, should_escalate: F) -> Self
+    where
+        F: Fn(&CompletionResponse) -> bool + Send + Sync + 'static,
+    {
+        self.should_escalate = Box::new(should_escalate);
+        self
+    }
+
+    /// Keeps the default check, but with a different confidence threshold between 0.0 and 1.0.
+    pub fn with_min_confidence(self, min_confidence: f32) -> Self {
+        self.with_should_escalate(move |res| should_escalate_below_confidence(res, min_confidence))
+    }
+
+    /// Runs a basic completion on the cheap model, and on the expensive model if it escalates. `configure` sets the
+    /// prompt and request config, and is called once for each model.
+    pub async fn basic_completion<F>(&self, configure: F) -> crate::Result<EscalationResponse>
+    where
+        F: Fn(&mut BasicCompletion),
+    {
+        let mut cheap = self.cheap.basic_completion();
+        configure(&mut cheap);
+        if cheap.config().top_logprobs.is_none() {
+            cheap.top_logprobs(1);
+        }
+        match cheap.run().await {
+            Ok(response) if !(self.should_escalate)(&response) => {
+                return Ok(EscalationResponse {
+                    response,
+                    escalated: false,
+                })
+            }
+            Ok(_) => crate::info!("Escalating the request to the expensive model."),
+            Err(e) if escalates_on_error(&e) => {
+                crate::info!("Escalating the request to the expensive model after: {e}")
+            }
+            Err(e) => return Err(e),
+        }
+        let mut expensive = self.expensive.basic_completion();
+        configure(&mut expensive);
+        Ok(EscalationResponse {
+            response: expensive.run().await?,
+            escalated: true,
+        })
+    }
+
+    pub fn shutdown(&self) {
+        self.cheap.shutdown();
+        self.expensive.shutdown();
+    }
+}
+
+/// Escalates empty responses and responses with a mean token probability below `min_confidence`. Responses without
+/// logprobs are only escalated if empty.
+pub fn should_escalate_below_confidence(res: &CompletionResponse, min_confidence: f32) -> bool {
+    if res.content_trimmed().is_empty() {
+        return true;
+    }
+    match res
+        .completion_probabilities
+        .as_deref()
+        .and_then(mean_token_confidence)
+    {
+        Some(confidence) => confidence < min_confidence,
+        None => false,
+    }
+}
+
+/// True if the cheap model's error is about its response rather than the request, i.e. a response that failed to
+/// parse or didn't pass validation. Errors that aren't a [CompletionError] come from parsing the response.
+fn escalates_on_error(e: &crate::Error) -> bool {
+    match e.downcast_ref::<CompletionError>() {
+        Some(e) => is_response_error(e),
+        None => true,
+    }
+}
+
+fn is_response_error(e: &CompletionError) -> bool {
+    match e {
+        CompletionError::ReponseContentEmpty
+        | CompletionError::StopLimitRetry
+        | CompletionError::NoRequiredStopSequence
+        | CompletionError::NonMatchingStopSequence(_)
+        | CompletionError::ToolCallParseError(_) => true,
+        CompletionError::ExceededRetryCount { errors, .. } => {
+            !errors.is_empty() && errors.iter().all(is_response_error)
+        }
+        _ => false,
+    }
+}
+
+/// The geometric mean probability of the generated tokens. Unlike the joint probability it doesn't shrink with the
+/// length of the response. Returns `None` if there are no tokens, or if a generated token is missing from its top
+/// probabilities.
+pub fn mean_token_confidence(logprobs: &[InferenceProbabilities]) -> Option<f32> {
+    total_token_logprob(logprobs).map(|total_logprob| (total_logprob / logprobs.len() as f32).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use llm_interface::{
+        llms::api::{config::LlmApiConfigTrait, error::ClientError},
+        requests::{
+            completion::{CompletionFinishReason, CompletionRequest},
+            res_components::TopProbabilities,
+        },
+        LlmInterface,
+    };
+
+    fn logprobs(probs: &[f32]) -> Vec<InferenceProbabilities> {
+        probs
+            .iter()
+            .map(|prob| InferenceProbabilities {
+                content: Some("a".to_string()),
+                top_probs: vec![TopProbabilities {
+                    token: "a".to_string(),
+                    prob: prob.ln(),
+                }],
+            })
+            .collect()
+    }
+
+    /// Serves an OpenAI style response with `content`, or an error body if `status` isn't 200, to every connection on
+    /// a local port.
+    fn serve(status: u16, content: &str) -> u16 {
+        let body = if status == 200 {
+            serde_json::json!({
+                "id": "chatcmpl-1",
+                "created": 0,
+                "model": "llama3",
+                "choices": [{
+                    "index": 0,
+                    "finish_reason": "stop",
+                    "message": {"role": "assistant", "content": content},
+                }],
+            })
+        } else {
+            serde_json::json!({"error": {"message": content, "type": "invalid_request_error"}})
+        };
+        llm_testing::serve_json(status, body.to_string(), true)
+    }
+
+    fn client(port: u16) -> LlmClient {
+        LlmClient::new(
+            LlmInterface::ollama()
+                .with_api_host("127.0.0.1")
+                .with_api_port(port.to_string())
+                .init()
+                .unwrap(),
+        )
+    }
+
+    fn escalating_client(cheap_status: u16) -> EscalatingClient {
+        EscalatingClient::new(
+            client(serve(cheap_status, "cheap")),
+            client(serve(200, "expensive")),
+        )
+    }
+
+    async fn run(client: &EscalatingClient) -> crate::Result<EscalationResponse> {
+        client
+            .basic_completion(|completion| {
+                completion
+                    .prompt()
+                    .add_user_message()
+                    .unwrap()
+                    .set_content("Is the sky blue?");
+            })
+            .await
+    }
+
+    #[test]
+    fn test_mean_token_confidence() {
+        assert!((mean_token_confidence(&logprobs(&[0.9, 0.4])).unwrap() - 0.6).abs() < 1e-5);
+        assert_eq!(mean_token_confidence(&[]), None);
+    }
+
+    #[test]
+    fn test_should_escalate_below_confidence() {
+        let backend = client(0).backend;
+        let req = CompletionRequest::new(backend);
+        let response = |content: &str, probs: Option<&[f32]>| {
+            let mut res = CompletionResponse::new_from_stream(
+                &req,
+                "test",
+                content.to_string(),
+                CompletionFinishReason::Eos,
+            );
+            res.completion_probabilities = probs.map(logprobs);
+            res
+        };
+        assert!(should_escalate_below_confidence(&response(" ", None), 0.5));
+        assert!(!should_escalate_below_confidence(
+            &response("yes", None),
+            0.5
+        ));
+        assert!(should_escalate_below_confidence(
+            &response("yes", Some(&[0.9, 0.2])),
+            0.5
+        ));
+        assert!(!should_escalate_below_confidence(
+            &response("yes", Some(&[0.9, 0.8])),
+            0.5
+        ));
+    }
+
+    #[test]
+    fn test_escalates_on_response_errors_only() {
+        assert!(escalates_on_error(&crate::anyhow!(
+            "parse_response error: content.is_empty()"
+        )));
+        assert!(escalates_on_error(
+            &CompletionError::NoRequiredStopSequence.into()
+        ));
+        assert!(escalates_on_error(
+            &CompletionError::ExceededRetryCount {
+                message: String::new(),
+                errors: vec![
+                    CompletionError::ReponseContentEmpty,
+                    CompletionError::NonMatchingStopSequence("stop".to_string()),
+                ],
+            }
+            .into()
+        ));
+        assert!(!escalates_on_error(
+            &CompletionError::ExceededRetryCount {
+                message: String::new(),
+                errors: vec![
+                    CompletionError::ReponseContentEmpty,
+                    CompletionError::LocalClientError("crashed".to_string()),
+                ],
+            }
+            .into()
+        ));
+        assert!(!escalates_on_error(
+            &CompletionError::RequestBuilderError("no prompt".to_string()).into()
+        ));
+        assert!(!escalates_on_error(
+            &CompletionError::ModerationBlocked { categories: vec![] }.into()
+        ));
+        assert!(!escalates_on_error(
+            &CompletionError::ClientError(ClientError::ServiceUnavailable {
+                message: "down".to_string()
+            })
+            .into()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_escalation_flow() {
+        let res = run(&escalating_client(200)).await.unwrap();
+        assert_eq!(
+            (res.response.content.as_str(), res.escalated),
+            ("cheap", false)
+        );
+
+        let client = escalating_client(200).with_should_escalate(|_| true);
+        let res = run(&client).await.unwrap();
+        assert_eq!(
+            (res.response.content.as_str(), res.escalated),
+            ("expensive", true)
+        );
+
+        // A rejected request would be rejected by the expensive model too, so it's returned instead.
+        assert!(run(&escalating_client(401)).await.is_err());
+    }
+}
@@ -1,11 +1,15 @@
 pub mod backend_builders;
 pub mod basic_completion;
 pub mod components;
+pub mod config_file;
+pub mod escalation;
 pub mod prelude;
 pub mod primitives;
+pub mod registry;
+pub mod router;
 pub mod workflows;
 #[allow(unused_imports)]
-pub(crate) use anyhow::{Error, anyhow, bail, Result};
+pub(crate) use anyhow::{anyhow, bail, Error, Result};
 #[allow(unused_imports)]
 pub(crate) use llm_devices::logging::{i_ln, i_lns, i_nln, i_nlns};
 pub use prelude::*;
@@ -14,6 +18,10 @@ pub(crate) use tracing::{debug, error, info, span, trace, warn, Level};
 
 pub struct LlmClient {
     pub backend: std::sync::Arc<llm_interface::llms::LlmBackend>,
+    /// Sampling defaults applied to the requests of workflows created by the client. Set by [LlmClient::from_config_file].
+    pub default_sampling: Option<config_file::SamplingFileConfig>,
+    /// Shared by the requests of workflows created by the client. Set with [LlmClient::with_response_cache].
+    pub response_cache: Option<std::sync::Arc<llm_interface::requests::completion::ResponseCache>>,
 }
 
 impl LlmClient {
@@ -25,7 +33,11 @@ impl LlmClient {
                 colorful::RGB::new(94, 244, 39)
             ))
         );
-        Self { backend }
+        Self {
+            backend,
+            default_sampling: None,
+            response_cache: None,
+        }
     }
     #[cfg(feature = "llama_cpp_backend")]
     /// Creates a new instance of the [`LlamaCppBackendBuilder`]. This builder that allows you to specify the model and other parameters. It is converted to an `LlmClient` instance using the `init` method.
@@ -54,27 +66,140 @@ impl LlmClient {
         backend_builders::perplexity::PerplexityBackendBuilder::default()
     }
 
+    /// Build a client from a single URI such as `openai://gpt-4o` or `llamacpp:///path/to/model.gguf`.
+    ///
+    /// See [llm_interface::LlmInterface::from_uri] for the supported schemes.
+    pub async fn from_uri(uri: &str) -> crate::Result<Self> {
+        Ok(Self::new(llm_interface::LlmInterface::from_uri(uri).await?))
+    }
+
+    /// Build a client from a TOML or JSON config file. See [config_file::LlmClientConfig] for the format.
+    pub async fn from_config_file<P: AsRef<std::path::Path>>(path: P) -> crate::Result<Self> {
+        config_file::LlmClientConfig::from_file(path)?.build().await
+    }
+
+    /// Reuses responses for identical deterministic requests instead of calling the model again.
+    /// See [llm_interface::requests::completion::ResponseCache].
+    pub fn with_response_cache(
+        mut self,
+        response_cache: llm_interface::requests::completion::ResponseCache,
+    ) -> Self {
+        self.response_cache = Some(std::sync::Arc::new(response_cache));
+        self
+    }
+
+    /// The hit, miss and eviction counts of the response cache, or `None` if the client has no cache.
+    pub fn cache_stats(&self) -> Option<llm_interface::requests::completion::CacheStats> {
+        self.response_cache
+            .as_ref()
+            .map(|cache| cache.cache_stats())
+    }
+
+    /// Removes every response from the response cache. The hit and miss counters are kept.
+    pub fn clear_response_cache(&self) {
+        if let Some(cache) = &self.response_cache {
+            cache.clear();
+        }
+    }
+
     pub fn basic_completion(&self) -> basic_completion::BasicCompletion {
-        basic_completion::BasicCompletion::new(self.backend.clone())
+        let mut basic_completion = basic_completion::BasicCompletion::new(self.backend.clone());
+        self.apply_request_defaults(&mut basic_completion.base_req);
+        basic_completion
     }
 
     pub fn basic_primitive(&self) -> workflows::basic_primitive::BasicPrimitiveWorkflowBuilder {
-        workflows::basic_primitive::BasicPrimitiveWorkflowBuilder::new(self.backend.clone())
+        let mut builder =
+            workflows::basic_primitive::BasicPrimitiveWorkflowBuilder::new(self.backend.clone());
+        self.apply_request_defaults(&mut builder.base_req);
+        builder
     }
 
     pub fn reason(&self) -> workflows::reason::ReasonWorkflowBuilder {
-        workflows::reason::ReasonWorkflowBuilder::new(self.backend.clone())
+        let mut builder = workflows::reason::ReasonWorkflowBuilder::new(self.backend.clone());
+        self.apply_request_defaults(&mut builder.base_req);
+        builder
+    }
+
+    /// Summarizes text too long for one prompt by summarizing its chunks and combining the summaries.
+    pub fn map_reduce_summarize(&self) -> workflows::summarize::MapReduceSummarize {
+        workflows::summarize::MapReduceSummarize::new(self.base_request())
     }
 
     pub fn nlp(&self) -> workflows::nlp::Nlp {
-        workflows::nlp::Nlp::new(self.backend.clone())
+        let mut nlp = workflows::nlp::Nlp::new(self.backend.clone());
+        self.apply_request_defaults(&mut nlp.base_req);
+        nlp
+    }
+
+    /// Switches an API backend to a different model without rebuilding the client. Workflows created before the
+    /// switch keep using the previous model. See [llm_interface::llms::LlmBackend::with_model_id].
+    pub fn set_model(&mut self, model_id: &str) -> crate::Result<()> {
+        self.backend = std::sync::Arc::new(self.backend.with_model_id(model_id)?);
+        Ok(())
+    }
+
+    /// Preloads the model during startup so the first request doesn't wait for it. See [llm_interface::llms::LlmBackend::warm_up].
+    pub async fn warm_up(&self) -> crate::Result<()> {
+        Ok(self.backend.warm_up().await?)
+    }
+
+    /// The layers allocated to each GPU and the VRAM in use after the model loaded. See [llm_interface::llms::LlmBackend::gpu_utilization].
+    pub fn gpu_utilization(&self) -> Vec<llm_devices::devices::gpu::GpuDevice> {
+        self.backend.gpu_utilization()
+    }
+
+    /// The number of free slots on the server, for routing requests between servers. Requires a local llama.cpp backend.
+    /// See [llm_interface::llms::LlmBackend::available_slots].
+    pub async fn available_slots(&self) -> crate::Result<usize> {
+        Ok(self.backend.available_slots().await?)
+    }
+
+    /// The average log probability and perplexity of `continuation` following `prompt`. Requires a local llama.cpp backend.
+    pub async fn score_text(
+        &self,
+        prompt: &str,
+        continuation: &str,
+    ) -> crate::Result<llm_interface::requests::score::TextScore> {
+        Ok(self.backend.score_text(prompt, continuation).await?)
+    }
+
+    /// The pooled embedding of each input. Requires a local llama.cpp backend started with
+    /// [backend_builders::llama_cpp::LlamaCppBackendBuilder::embeddings].
+    pub async fn embeddings(
+        &self,
+        inputs: &[&str],
+        normalize: bool,
+    ) -> crate::Result<Vec<Vec<f32>>> {
+        Ok(self.backend.embeddings(inputs, normalize).await?)
     }
 
     pub fn shutdown(&self) {
         self.backend.shutdown();
     }
 
+    /// Drains the in-flight requests before shutting down. See [llm_interface::llms::LlmBackend::shutdown_graceful].
+    pub async fn shutdown_graceful(&self, timeout: std::time::Duration) {
+        self.backend.shutdown_graceful(timeout).await;
+    }
+
     pub fn base_request(&self) -> llm_interface::requests::completion::request::CompletionRequest {
-        llm_interface::requests::completion::request::CompletionRequest::new(self.backend.clone())
+        let mut request = llm_interface::requests::completion::request::CompletionRequest::new(
+            self.backend.clone(),
+        );
+        self.apply_request_defaults(&mut request);
+        request
+    }
+
+    fn apply_request_defaults(
+        &self,
+        request: &mut llm_interface::requests::completion::request::CompletionRequest,
+    ) {
+        if let Some(default_sampling) = &self.default_sampling {
+            default_sampling.apply(&mut request.config);
+        }
+        if let Some(response_cache) = &self.response_cache {
+            request.with_response_cache(response_cache.clone());
+        }
     }
 }
@@ -1,4 +1,4 @@
-use super::PrimitiveTrait;
+use super::{stream_parse_options, PrimitiveTrait, StreamParseStatus};
 use crate::components::grammar::{BooleanGrammar, Grammar};
 use crate::workflows::reason::ReasonTrait;
 use anyhow::Result;
@@ -49,6 +49,14 @@ impl PrimitiveTrait for BooleanPrimitive {
         let parsed: Self::PrimitiveResult = self.grammar_inner().grammar_parse(content)?;
         Ok(parsed)
     }
+
+    fn stream_parse(&self, content: &str) -> StreamParseStatus<Self::PrimitiveResult> {
+        match stream_parse_options(content, &["true", "false"]) {
+            StreamParseStatus::Incomplete => StreamParseStatus::Incomplete,
+            StreamParseStatus::Complete(option) => StreamParseStatus::Complete(option == "true"),
+            StreamParseStatus::Invalid => StreamParseStatus::Invalid,
+        }
+    }
 }
 
 impl ReasonTrait for BooleanPrimitive {
@@ -0,0 +1,138 @@
+use super::PrimitiveTrait;
+use crate::components::grammar::{
+    date::{ISO_8601_DATE, ISO_8601_DATE_TIME},
+    DateGrammar, Grammar,
+};
+use anyhow::Result;
+
+/// A calendar date. Impossible dates like `2024-02-30` fail to parse.
+pub struct DatePrimitive {
+    pub format: String,
+}
+
+impl Default for DatePrimitive {
+    fn default() -> Self {
+        DatePrimitive {
+            format: ISO_8601_DATE.to_string(),
+        }
+    }
+}
+
+impl DatePrimitive {
+    /// Set the format of the date. Default is `YYYY-MM-DD`. See [DateGrammar] for the format syntax.
+    pub fn format<T: Into<String>>(&mut self, format: T) -> &mut Self {
+        self.format = format.into();
+        self
+    }
+
+    fn grammar_inner(&self) -> DateGrammar {
+        Grammar::date().format(&self.format)
+    }
+}
+
+impl PrimitiveTrait for DatePrimitive {
+    type PrimitiveResult = chrono::NaiveDate;
+
+    fn clear_primitive(&mut self) {}
+
+    fn type_description(&self, result_can_be_none: bool) -> &str {
+        if result_can_be_none {
+            "date or 'Unknown.'"
+        } else {
+            "date"
+        }
+    }
+
+    fn solution_description(&self, result_can_be_none: bool) -> String {
+        if result_can_be_none {
+            format!(
+                "a date in the format {} or, if the date is unknown, 'Unknown.'",
+                self.format
+            )
+        } else {
+            format!("a date in the format {}", self.format)
+        }
+    }
+
+    fn stop_word_result_is_none(&self, result_can_be_none: bool) -> Option<String> {
+        if result_can_be_none {
+            Some("Unknown.".to_string())
+        } else {
+            None
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.grammar_inner().wrap()
+    }
+
+    fn parse_to_primitive(&self, content: &str) -> Result<Self::PrimitiveResult> {
+        Ok(self.grammar_inner().grammar_parse(content)?.date())
+    }
+}
+
+/// A date and time without a time zone. Dates and times that don't exist fail to parse.
+pub struct DateTimePrimitive {
+    pub format: String,
+}
+
+impl Default for DateTimePrimitive {
+    fn default() -> Self {
+        DateTimePrimitive {
+            format: ISO_8601_DATE_TIME.to_string(),
+        }
+    }
+}
+
+impl DateTimePrimitive {
+    /// Set the format of the date and time. Default is `YYYY-MM-DDThh:mm:ss`. See [DateGrammar] for the format syntax.
+    pub fn format<T: Into<String>>(&mut self, format: T) -> &mut Self {
+        self.format = format.into();
+        self
+    }
+
+    fn grammar_inner(&self) -> DateGrammar {
+        Grammar::date().format(&self.format)
+    }
+}
+
+impl PrimitiveTrait for DateTimePrimitive {
+    type PrimitiveResult = chrono::NaiveDateTime;
+
+    fn clear_primitive(&mut self) {}
+
+    fn type_description(&self, result_can_be_none: bool) -> &str {
+        if result_can_be_none {
+            "timestamp or 'Unknown.'"
+        } else {
+            "timestamp"
+        }
+    }
+
+    fn solution_description(&self, result_can_be_none: bool) -> String {
+        if result_can_be_none {
+            format!(
+                "a timestamp in the format {} or, if the timestamp is unknown, 'Unknown.'",
+                self.format
+            )
+        } else {
+            format!("a timestamp in the format {}", self.format)
+        }
+    }
+
+    fn stop_word_result_is_none(&self, result_can_be_none: bool) -> Option<String> {
+        if result_can_be_none {
+            Some("Unknown.".to_string())
+        } else {
+            None
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.grammar_inner().wrap()
+    }
+
+    fn parse_to_primitive(&self, content: &str) -> Result<Self::PrimitiveResult> {
+        Ok(self.grammar_inner().grammar_parse(content)?)
+    }
+}
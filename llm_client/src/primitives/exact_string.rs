@@ -1,4 +1,4 @@
-use super::PrimitiveTrait;
+use super::{stream_parse_options, PrimitiveTrait, StreamParseStatus};
 use crate::workflows::reason::ReasonTrait;
 use anyhow::Result;
 use crate::components::grammar::{ExactStringGrammar, Grammar};
@@ -84,6 +84,15 @@ impl PrimitiveTrait for ExactStringPrimitive {
         let parsed: Self::PrimitiveResult = self.grammar_inner().grammar_parse(content)?;
         Ok(parsed)
     }
+
+    fn stream_parse(&self, content: &str) -> StreamParseStatus<Self::PrimitiveResult> {
+        let options: Vec<&str> = self.allowed_strings.iter().map(|s| s.as_str()).collect();
+        match stream_parse_options(content, &options) {
+            StreamParseStatus::Incomplete => StreamParseStatus::Incomplete,
+            StreamParseStatus::Complete(option) => StreamParseStatus::Complete(option.to_string()),
+            StreamParseStatus::Invalid => StreamParseStatus::Invalid,
+        }
+    }
 }
 
 impl ReasonTrait for ExactStringPrimitive {
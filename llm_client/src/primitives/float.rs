@@ -0,0 +1,250 @@
+use super::PrimitiveTrait;
+use crate::{
+    components::grammar::{FloatGrammar, Grammar},
+    workflows::reason::ReasonTrait,
+};
+use anyhow::Result;
+
+/// A decimal number, e.g. a price or a measurement.
+pub struct FloatPrimitive {
+    pub lower_bound: Option<f64>,
+    pub upper_bound: Option<f64>,
+    pub decimal_places: Option<u32>,
+    pub decimal_separator: char,
+    pub thousands_separator: Option<char>,
+    pub allow_scientific: bool,
+    /// The distinct results seen while reasoning, indexed by their reason result index.
+    results: std::sync::Mutex<Vec<f64>>,
+}
+
+impl Default for FloatPrimitive {
+    fn default() -> Self {
+        FloatPrimitive {
+            lower_bound: None,
+            upper_bound: None,
+            decimal_places: None,
+            decimal_separator: '.',
+            thousands_separator: None,
+            allow_scientific: false,
+            results: Default::default(),
+        }
+    }
+}
+
+impl FloatPrimitive {
+    /// Set the lower bound of the number. Default is None.
+    pub fn lower_bound(&mut self, lower_bound: f64) -> &mut Self {
+        self.lower_bound = Some(lower_bound);
+        self
+    }
+
+    /// Set the upper bound of the number. Default is None.
+    pub fn upper_bound(&mut self, upper_bound: f64) -> &mut Self {
+        self.upper_bound = Some(upper_bound);
+        self
+    }
+
+    /// Set the maximum number of digits after the decimal separator. Default is None (unlimited).
+    pub fn decimal_places(&mut self, decimal_places: u32) -> &mut Self {
+        self.decimal_places = Some(decimal_places);
+        self
+    }
+
+    /// Set the decimal separator used by the model's locale, e.g. ',' for "12,5". Default is '.'.
+    pub fn decimal_separator(&mut self, decimal_separator: char) -> &mut Self {
+        self.decimal_separator = decimal_separator;
+        self
+    }
+
+    /// Set the thousands separator used by the model's locale, e.g. '.' for "1.000,5". Default is None.
+    ///
+    /// Grouped numbers are only accepted if every group after the first has three digits.
+    pub fn thousands_separator(&mut self, thousands_separator: char) -> &mut Self {
+        self.thousands_separator = Some(thousands_separator);
+        self
+    }
+
+    /// Allow scientific notation, e.g. "1.5e-3". Default is false.
+    pub fn allow_scientific(&mut self, allow_scientific: bool) -> &mut Self {
+        self.allow_scientific = allow_scientific;
+        self
+    }
+
+    fn grammar_inner(&self) -> FloatGrammar {
+        let mut grammar = Grammar::float()
+            .decimal_separator(self.decimal_separator)
+            .allow_scientific(self.allow_scientific);
+        if let Some(lower_bound) = self.lower_bound {
+            grammar = grammar.lower_bound(lower_bound);
+        }
+        if let Some(upper_bound) = self.upper_bound {
+            grammar = grammar.upper_bound(upper_bound);
+        }
+        if let Some(decimal_places) = self.decimal_places {
+            grammar = grammar.decimal_places(decimal_places);
+        }
+        match self.thousands_separator {
+            Some(thousands_separator) => grammar.thousands_separator(thousands_separator),
+            None => grammar,
+        }
+    }
+
+    fn results(&self) -> std::sync::MutexGuard<'_, Vec<f64>> {
+        self.results.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn range_description(&self) -> String {
+        match (self.lower_bound, self.upper_bound) {
+            (Some(lower_bound), Some(upper_bound)) => {
+                format!(" between {lower_bound} and {upper_bound}")
+            }
+            (Some(lower_bound), None) => format!(" of at least {lower_bound}"),
+            (None, Some(upper_bound)) => format!(" of at most {upper_bound}"),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+impl PrimitiveTrait for FloatPrimitive {
+    type PrimitiveResult = f64;
+
+    fn clear_primitive(&mut self) {
+        self.results().clear();
+    }
+
+    fn type_description(&self, result_can_be_none: bool) -> &str {
+        if result_can_be_none {
+            "decimal number or 'Unknown.'"
+        } else {
+            "decimal number"
+        }
+    }
+
+    fn solution_description(&self, result_can_be_none: bool) -> String {
+        if result_can_be_none {
+            format!(
+                "a decimal number{} or, if the solution is unknown or not in range, 'Unknown.'",
+                self.range_description()
+            )
+        } else {
+            format!("a decimal number{}", self.range_description())
+        }
+    }
+
+    fn stop_word_result_is_none(&self, result_can_be_none: bool) -> Option<String> {
+        if result_can_be_none {
+            Some("Unknown.".to_string())
+        } else {
+            None
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.grammar_inner().wrap()
+    }
+
+    fn parse_to_primitive(&self, content: &str) -> Result<Self::PrimitiveResult> {
+        Ok(self.grammar_inner().grammar_parse(content)?)
+    }
+}
+
+/// Reason results are indexed by their position in the distinct results seen so far, so only answers that parse to the
+/// same f64 count as the same vote, and the result is returned exactly.
+impl ReasonTrait for FloatPrimitive {
+    fn primitive_to_result_index(&self, content: &str) -> u32 {
+        let output = self.parse_to_primitive(content).unwrap();
+        let mut results = self.results();
+        let index = match results
+            .iter()
+            .position(|result| result.to_bits() == output.to_bits())
+        {
+            Some(index) => index,
+            None => {
+                results.push(output);
+                results.len() - 1
+            }
+        };
+        index as u32
+    }
+
+    fn result_index_to_primitive(&self, result_index: Option<u32>) -> Result<Option<f64>> {
+        match result_index {
+            Some(result_index) => match self.results().get(result_index as usize) {
+                Some(result) => Ok(Some(*result)),
+                None => Err(anyhow::anyhow!("No float result at index {result_index}")),
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_float_descriptions() {
+        let mut primitive = FloatPrimitive::default();
+        assert_eq!(primitive.type_description(false), "decimal number");
+        assert_eq!(
+            primitive.type_description(true),
+            "decimal number or 'Unknown.'"
+        );
+        assert_eq!(primitive.solution_description(false), "a decimal number");
+
+        primitive.lower_bound(0.5);
+        assert_eq!(
+            primitive.solution_description(false),
+            "a decimal number of at least 0.5"
+        );
+        primitive.upper_bound(10.0);
+        assert_eq!(
+            primitive.solution_description(true),
+            "a decimal number between 0.5 and 10 or, if the solution is unknown or not in range, 'Unknown.'"
+        );
+        assert_eq!(
+            primitive.stop_word_result_is_none(true).as_deref(),
+            Some("Unknown.")
+        );
+        assert_eq!(primitive.stop_word_result_is_none(false), None);
+    }
+
+    #[test]
+    fn test_float_parse_to_primitive() {
+        let mut primitive = FloatPrimitive::default();
+        assert_eq!(primitive.parse_to_primitive("12.5").unwrap(), 12.5);
+        assert_eq!(primitive.parse_to_primitive("-0.25").unwrap(), -0.25);
+        assert!(primitive.parse_to_primitive("twelve").is_err());
+
+        primitive.decimal_separator(',').thousands_separator('.');
+        assert_eq!(primitive.parse_to_primitive("1.000,5").unwrap(), 1000.5);
+
+        let mut primitive = FloatPrimitive::default();
+        primitive.lower_bound(0.0).upper_bound(1.0);
+        assert_eq!(primitive.parse_to_primitive("0.75").unwrap(), 0.75);
+        assert!(primitive.parse_to_primitive("1.5").is_err());
+    }
+
+    #[test]
+    fn test_float_result_index() {
+        let mut primitive = FloatPrimitive::default();
+        let index = primitive.primitive_to_result_index("0.1");
+        assert_eq!(
+            primitive.result_index_to_primitive(Some(index)).unwrap(),
+            Some(0.1)
+        );
+        assert_eq!(index, primitive.primitive_to_result_index("0.10"));
+        assert_ne!(index, primitive.primitive_to_result_index("0.100000001"));
+
+        let index = primitive.primitive_to_result_index("1234567.89");
+        assert_ne!(index, primitive.primitive_to_result_index("1234567.88"));
+        assert_eq!(
+            primitive.result_index_to_primitive(Some(index)).unwrap(),
+            Some(1234567.89)
+        );
+        assert_eq!(primitive.result_index_to_primitive(None).unwrap(), None);
+
+        primitive.clear_primitive();
+        assert!(primitive.result_index_to_primitive(Some(index)).is_err());
+    }
+}
@@ -6,6 +6,7 @@ use crate::components::grammar::{Grammar, IntegerGrammar};
 pub struct IntegerPrimitive {
     pub lower_bound: u32,
     pub upper_bound: u32,
+    pub thousands_separator: Option<char>,
 }
 
 impl Default for IntegerPrimitive {
@@ -13,6 +14,7 @@ impl Default for IntegerPrimitive {
         IntegerPrimitive {
             lower_bound: 0,
             upper_bound: 9999,
+            thousands_separator: None,
         }
     }
 }
@@ -34,10 +36,22 @@ impl IntegerPrimitive {
         self
     }
 
+    /// Set the thousands separator used by the model's locale, e.g. '.' for "1.000". Default is None.
+    ///
+    /// Grouped numbers are only accepted if every group after the first has three digits.
+    pub fn thousands_separator(&mut self, thousands_separator: char) -> &mut Self {
+        self.thousands_separator = Some(thousands_separator);
+        self
+    }
+
     fn grammar_inner(&self) -> IntegerGrammar {
-        Grammar::integer()
+        let grammar = Grammar::integer()
             .lower_bound(self.lower_bound)
-            .upper_bound(self.upper_bound)
+            .upper_bound(self.upper_bound);
+        match self.thousands_separator {
+            Some(thousands_separator) => grammar.thousands_separator(thousands_separator),
+            None => grammar,
+        }
     }
 }
 
@@ -0,0 +1,157 @@
+use super::PrimitiveTrait;
+use crate::components::grammar::{Grammar, ListGrammar};
+use anyhow::Result;
+use std::slice::{Iter, IterMut};
+use std::vec::IntoIter;
+
+/// A list of `min_count` to `max_count` items, each constrained and parsed by the item primitive.
+///
+/// The model writes the items as `[item, item, ...]`. Item primitives whose output can contain a comma are not
+/// supported.
+pub struct ListPrimitive<P: PrimitiveTrait> {
+    pub item: P,
+    pub min_count: u8,
+    pub max_count: u8,
+}
+
+impl<P: PrimitiveTrait> Default for ListPrimitive<P> {
+    fn default() -> Self {
+        ListPrimitive {
+            item: P::default(),
+            min_count: 1,
+            max_count: 5,
+        }
+    }
+}
+
+impl<P: PrimitiveTrait> ListPrimitive<P> {
+    /// The item primitive, to set its constraints.
+    pub fn item(&mut self) -> &mut P {
+        &mut self.item
+    }
+
+    /// Set the minimum number of items. Default is 1.
+    pub fn min_count(&mut self, min_count: u8) -> &mut Self {
+        self.min_count = min_count;
+        self
+    }
+
+    /// Set the maximum number of items. Default is 5.
+    pub fn max_count(&mut self, max_count: u8) -> &mut Self {
+        self.max_count = max_count;
+        self
+    }
+
+    fn grammar_inner(&self) -> ListGrammar {
+        Grammar::list()
+            .item(self.item.grammar())
+            .min_count(self.min_count)
+            .max_count(self.max_count)
+    }
+}
+
+pub struct ListType<T>(pub Vec<T>);
+
+impl<T: std::fmt::Display> std::fmt::Display for ListType<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let items: Vec<String> = self.0.iter().map(|item| item.to_string()).collect();
+        write!(f, "[{}]", items.join(", "))
+    }
+}
+
+impl<T> IntoIterator for ListType<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a ListType<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut ListType<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter_mut()
+    }
+}
+
+impl<P: PrimitiveTrait> PrimitiveTrait for ListPrimitive<P> {
+    type PrimitiveResult = ListType<P::PrimitiveResult>;
+
+    fn clear_primitive(&mut self) {
+        self.item.clear_primitive();
+    }
+
+    fn type_description(&self, result_can_be_none: bool) -> &str {
+        if result_can_be_none {
+            "list or 'None.'"
+        } else {
+            "list"
+        }
+    }
+
+    fn solution_description(&self, result_can_be_none: bool) -> String {
+        let description = format!(
+            "a list of between {}-{} items in brackets and separated by commas, where each item is {}",
+            self.min_count,
+            self.max_count,
+            self.item.solution_description(false)
+        );
+        if result_can_be_none {
+            format!("{description}, or, possibly, 'None.'")
+        } else {
+            description
+        }
+    }
+
+    fn stop_word_result_is_none(&self, result_can_be_none: bool) -> Option<String> {
+        if result_can_be_none {
+            Some("None.".to_string())
+        } else {
+            None
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.grammar_inner().wrap()
+    }
+
+    fn parse_to_primitive(&self, content: &str) -> Result<Self::PrimitiveResult> {
+        let items = self
+            .grammar_inner()
+            .split_items(content)?
+            .into_iter()
+            .map(|item| self.item.parse_to_primitive(item))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(ListType(items))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::IntegerPrimitive;
+
+    #[test]
+    fn test_parse_to_primitive() {
+        let mut primitive = ListPrimitive::<IntegerPrimitive>::default();
+        primitive.item().lower_bound(1).upper_bound(10);
+        primitive.max_count(3);
+        let parsed = primitive.parse_to_primitive(" [3, 10, 7]").unwrap();
+        assert_eq!(parsed.0, vec![3, 10, 7]);
+        assert_eq!(parsed.to_string(), "[3, 10, 7]");
+        assert!(primitive.parse_to_primitive("[1, 2, 3, 4]").is_err());
+        assert!(primitive.parse_to_primitive("1, 2").is_err());
+    }
+}
@@ -1,6 +1,9 @@
 pub mod boolean;
+pub mod date;
 pub mod exact_string;
+pub mod float;
 pub mod integer;
+pub mod list;
 pub mod sentences;
 pub mod text;
 pub mod text_list;
@@ -9,8 +12,15 @@ pub mod words;
 use crate::components::grammar::Grammar;
 use anyhow::Result;
 pub use boolean::BooleanPrimitive;
+pub use date::{DatePrimitive, DateTimePrimitive};
 pub use exact_string::ExactStringPrimitive;
+pub use float::FloatPrimitive;
 pub use integer::IntegerPrimitive;
+pub use list::ListPrimitive;
+use llm_interface::requests::{
+    completion::{CompletionResponse, ResponseDiff},
+    res_components::InferenceProbabilities,
+};
 pub use sentences::SentencesPrimitive;
 pub use text::TextPrimitive;
 pub use text_list::TextListPrimitive;
@@ -30,4 +40,237 @@ pub trait PrimitiveTrait: Default {
     fn grammar(&self) -> Grammar;
 
     fn parse_to_primitive(&self, content: &str) -> Result<Self::PrimitiveResult>;
+
+    /// Parses the content and returns the model's confidence in it, derived from the logprobs of the tokens that
+    /// generated the parsed result. Tokens around it, like the stop word, are left out. The confidence is `None` when
+    /// logprobs weren't requested (see `top_logprobs`) or don't cover the result.
+    fn parse_with_confidence(
+        &self,
+        content: &str,
+        logprobs: Option<&[InferenceProbabilities]>,
+    ) -> (Result<Self::PrimitiveResult>, Option<f32>) {
+        let result = self.parse_to_primitive(content);
+        let confidence = logprobs.and_then(|logprobs| match &result {
+            Ok(parsed) => result_confidence(&parsed.to_string(), logprobs)
+                .or_else(|| result_confidence(content, logprobs)),
+            Err(_) => result_confidence(content, logprobs),
+        });
+        (result, confidence)
+    }
+
+    /// Parses the partial content of a streamed response. Primitives that can tell when a result can no longer
+    /// change override this so streaming callers can stop before EOS. The default waits for the full response.
+    fn stream_parse(&self, _content: &str) -> StreamParseStatus<Self::PrimitiveResult> {
+        StreamParseStatus::Incomplete
+    }
+
+    /// Compares two responses, e.g. of two model versions, including whether both parse to the same result. The
+    /// results are compared by their displayed value. See [CompletionResponse::compare_parsed].
+    fn compare_responses(
+        &self,
+        response: &CompletionResponse,
+        other: &CompletionResponse,
+    ) -> ResponseDiff {
+        response.compare_parsed(other, |content| {
+            self.parse_to_primitive(content)
+                .map(|result| result.to_string())
+        })
+    }
+}
+
+/// The state of a primitive parsed from the partial content of a streamed response.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamParseStatus<T> {
+    /// More tokens are needed to decide the result.
+    Incomplete,
+    /// The result is complete and further tokens can't change it.
+    Complete(T),
+    /// The content can no longer become a valid result, e.g. the model generated the no result stop word.
+    Invalid,
+}
+
+/// Accumulates token deltas and parses them with [PrimitiveTrait::stream_parse] as they arrive.
+pub struct PrimitiveStreamParser<'a, P: PrimitiveTrait> {
+    primitive: &'a P,
+    content: String,
+}
+
+impl<'a, P: PrimitiveTrait> PrimitiveStreamParser<'a, P> {
+    pub fn new(primitive: &'a P) -> Self {
+        Self {
+            primitive,
+            content: String::new(),
+        }
+    }
+
+    /// Adds a token delta and returns the status of the content so far.
+    pub fn push_delta(&mut self, delta: &str) -> StreamParseStatus<P::PrimitiveResult> {
+        self.content.push_str(delta);
+        self.primitive.stream_parse(&self.content)
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// Parses the accumulated content as a full response. Use when the stream ends before a result is complete.
+    pub fn finish(&self) -> Result<P::PrimitiveResult> {
+        self.primitive.parse_to_primitive(&self.content)
+    }
+}
+
+/// The joint probability, from 0.0 to 1.0, of the generated tokens that cover `result`. Returns `None` if the tokens
+/// don't contain `result`, or if one of them is missing from its top probabilities.
+pub fn result_confidence(result: &str, logprobs: &[InferenceProbabilities]) -> Option<f32> {
+    total_token_logprob(result_tokens(result, logprobs)?).map(f32::exp)
+}
+
+/// The run of generated tokens whose text contains the first occurrence of `result`, ignoring its surrounding
+/// whitespace.
+pub(crate) fn result_tokens<'a>(
+    result: &str,
+    logprobs: &'a [InferenceProbabilities],
+) -> Option<&'a [InferenceProbabilities]> {
+    let result = result.trim();
+    if result.is_empty() {
+        return None;
+    }
+    let mut text = String::new();
+    let mut token_ends = Vec::with_capacity(logprobs.len());
+    for probabilities in logprobs {
+        text.push_str(probabilities.content.as_deref()?);
+        token_ends.push(text.len());
+    }
+    let start = text.find(result)?;
+    let end = start + result.len();
+    let first = token_ends.iter().position(|token_end| *token_end > start)?;
+    let last = token_ends.iter().position(|token_end| *token_end >= end)?;
+    Some(&logprobs[first..=last])
+}
+
+/// The sum of the logprobs of the generated tokens.
+pub(crate) fn total_token_logprob(logprobs: &[InferenceProbabilities]) -> Option<f32> {
+    if logprobs.is_empty() {
+        return None;
+    }
+    let mut total_logprob = 0.0;
+    for probabilities in logprobs {
+        let content = probabilities.content.as_deref()?;
+        total_logprob += probabilities
+            .top_probs
+            .iter()
+            .find(|p| p.token == content)?
+            .prob;
+    }
+    Some(total_logprob)
+}
+
+/// Resolves a streamed `content` against a fixed set of `options`. Complete once `content` starts with an option
+/// and no longer option could still match.
+fn stream_parse_options<'o>(content: &str, options: &[&'o str]) -> StreamParseStatus<&'o str> {
+    let content = content.trim_start();
+    if options
+        .iter()
+        .any(|o| o.len() > content.len() && o.starts_with(content))
+    {
+        return StreamParseStatus::Incomplete;
+    }
+    match options
+        .iter()
+        .filter(|o| content.starts_with(**o))
+        .max_by_key(|o| o.len())
+    {
+        Some(option) => StreamParseStatus::Complete(option),
+        None => StreamParseStatus::Invalid,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use llm_interface::requests::res_components::TopProbabilities;
+
+    fn token_probabilities(content: &str, probs: &[(&str, f32)]) -> InferenceProbabilities {
+        InferenceProbabilities {
+            content: Some(content.to_string()),
+            top_probs: probs
+                .iter()
+                .map(|(token, prob)| TopProbabilities {
+                    token: token.to_string(),
+                    prob: prob.ln(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_with_confidence() {
+        let primitive = BooleanPrimitive::default();
+        let logprobs = vec![
+            token_probabilities(" true", &[(" true", 0.8), (" false", 0.2)]),
+            token_probabilities(" Done", &[(" Done", 0.5)]),
+        ];
+        let (result, confidence) = primitive.parse_with_confidence(" true", Some(&logprobs));
+        assert!(result.unwrap());
+        assert!((confidence.unwrap() - 0.8).abs() < 1e-5);
+
+        let (result, confidence) = primitive.parse_with_confidence(" false", None);
+        assert!(!result.unwrap());
+        assert_eq!(confidence, None);
+
+        let logprobs = vec![token_probabilities(" true", &[(" false", 0.9)])];
+        assert_eq!(result_confidence("true", &logprobs), None);
+        assert_eq!(result_confidence("true", &[]), None);
+    }
+
+    #[test]
+    fn test_confidence_ignores_stop_word() {
+        let primitive = IntegerPrimitive::default();
+        let logprobs = vec![
+            token_probabilities(" 4", &[(" 4", 0.9)]),
+            token_probabilities("2", &[("2", 0.5)]),
+            token_probabilities(" Done", &[(" Done", 0.01), (".", 0.9)]),
+            token_probabilities(".", &[(".", 0.1)]),
+        ];
+        let (result, confidence) = primitive.parse_with_confidence(" 42", Some(&logprobs));
+        assert_eq!(result.unwrap(), 42);
+        assert!((confidence.unwrap() - 0.45).abs() < 1e-5);
+
+        // A result that starts and ends inside tokens includes both of them.
+        assert_eq!(result_tokens("4", &logprobs).unwrap().len(), 1);
+        assert_eq!(result_tokens("42 Do", &logprobs).unwrap().len(), 3);
+        assert!(result_tokens("43", &logprobs).is_none());
+    }
+
+    #[test]
+    fn test_stream_parse_boolean() {
+        let primitive = BooleanPrimitive::default();
+        let mut parser = PrimitiveStreamParser::new(&primitive);
+        assert_eq!(parser.push_delta(" t"), StreamParseStatus::Incomplete);
+        assert_eq!(parser.push_delta("ru"), StreamParseStatus::Incomplete);
+        assert_eq!(parser.push_delta("e"), StreamParseStatus::Complete(true));
+
+        let mut parser = PrimitiveStreamParser::new(&primitive);
+        assert_eq!(parser.push_delta(" Neither"), StreamParseStatus::Invalid);
+    }
+
+    #[test]
+    fn test_stream_parse_exact_string() {
+        let mut primitive = ExactStringPrimitive::default();
+        primitive.add_strings_to_allowed(&["cat", "category", "dog"]);
+        let mut parser = PrimitiveStreamParser::new(&primitive);
+        assert_eq!(parser.push_delta("cat"), StreamParseStatus::Incomplete);
+        assert_eq!(
+            parser.push_delta(" Done."),
+            StreamParseStatus::Complete("cat".to_string())
+        );
+
+        let mut parser = PrimitiveStreamParser::new(&primitive);
+        assert_eq!(parser.push_delta(" do"), StreamParseStatus::Incomplete);
+        assert_eq!(
+            parser.push_delta("g"),
+            StreamParseStatus::Complete("dog".to_string())
+        );
+        assert_eq!(parser.finish().unwrap(), "dog");
+    }
 }
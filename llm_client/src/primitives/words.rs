@@ -1,11 +1,12 @@
 use super::PrimitiveTrait;
+use crate::components::grammar::{text::words::split_words, Grammar, WordsGrammar};
 use anyhow::Result;
-use crate::components::grammar::{Grammar, WordsGrammar};
 pub struct WordsPrimitive {
     pub min_count: u8,
     pub max_count: u8,
     pub word_char_length: u8,
     pub concatenator: String,
+    pub strip_punctuation: bool,
 }
 
 impl Default for WordsPrimitive {
@@ -15,6 +16,7 @@ impl Default for WordsPrimitive {
             max_count: 3,
             word_char_length: 12,
             concatenator: " ".to_string(),
+            strip_punctuation: false,
         }
     }
 }
@@ -46,6 +48,39 @@ impl WordsPrimitive {
         self
     }
 
+    /// Strip leading and trailing punctuation from each word in [WordsPrimitive::parse_to_words]. Default is false.
+    pub fn strip_punctuation(&mut self, strip_punctuation: bool) -> &mut Self {
+        self.strip_punctuation = strip_punctuation;
+        self
+    }
+
+    /// Parse the content into individual words, split on the whole concatenator string and then on whitespace.
+    /// Empty words from repeated separators or surrounding whitespace are dropped, as are stray copies of the
+    /// concatenator itself.
+    pub fn parse_to_words(&self, content: &str) -> Result<Vec<String>> {
+        let parsed = self.parse_to_primitive(content)?;
+        let concatenator = self.concatenator.trim();
+        let pieces: Vec<&str> = if concatenator.is_empty() {
+            vec![parsed.as_str()]
+        } else {
+            parsed.split(self.concatenator.as_str()).collect()
+        };
+        Ok(pieces
+            .into_iter()
+            .flat_map(|piece| piece.split_whitespace())
+            .filter(|word| *word != concatenator)
+            .map(|word| {
+                if self.strip_punctuation {
+                    word.trim_matches(|c: char| c.is_ascii_punctuation())
+                } else {
+                    word
+                }
+            })
+            .filter(|word| !word.is_empty())
+            .map(|word| word.to_string())
+            .collect())
+    }
+
     fn grammar_inner(&self) -> WordsGrammar {
         Grammar::words()
             .min_count(self.min_count)
@@ -91,8 +126,99 @@ impl PrimitiveTrait for WordsPrimitive {
         self.grammar_inner().wrap()
     }
 
+    /// Errors if the number of words, counted with [split_words], is outside the min and max count.
     fn parse_to_primitive(&self, content: &str) -> Result<Self::PrimitiveResult> {
         let parsed: Self::PrimitiveResult = self.grammar_inner().grammar_parse(content)?;
+        let count = split_words(&parsed).len();
+        if count < self.min_count as usize || count > self.max_count as usize {
+            return Err(anyhow::anyhow!(
+                "Expected between {}-{} words, found {count} in: {parsed}",
+                self.min_count,
+                self.max_count
+            ));
+        }
         Ok(parsed)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_to_words() {
+        let mut primitive = WordsPrimitive::default();
+        assert_eq!(
+            primitive.parse_to_words("  red   green blue ").unwrap(),
+            vec!["red", "green", "blue"]
+        );
+
+        primitive.concatenator(", ");
+        assert_eq!(
+            primitive.parse_to_words("red, green, blue.").unwrap(),
+            vec!["red", "green", "blue."]
+        );
+
+        primitive.strip_punctuation(true);
+        assert_eq!(
+            primitive.parse_to_words("red, green, blue.").unwrap(),
+            vec!["red", "green", "blue"]
+        );
+    }
+
+    #[test]
+    fn test_parse_to_words_multi_char_concatenator() {
+        let mut primitive = WordsPrimitive::default();
+        primitive.concatenator(" - ");
+        assert_eq!(
+            primitive.parse_to_words("well-known - fact").unwrap(),
+            vec!["well-known", "fact"]
+        );
+
+        primitive.concatenator(" and ");
+        assert_eq!(
+            primitive.parse_to_words("sand and bread").unwrap(),
+            vec!["sand", "bread"]
+        );
+    }
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(
+            split_words(" well-known  mother-in-law "),
+            vec!["well-known", "mother-in-law"]
+        );
+        assert_eq!(
+            split_words("don't stop, it's 3 am"),
+            vec!["don't", "stop", "it's", "3", "am"]
+        );
+        assert_eq!(
+            split_words("- red -- 'blue' 10-4"),
+            vec!["red", "blue", "10-4"]
+        );
+        assert_eq!(split_words("3.5"), vec!["3", "5"]);
+        assert!(split_words(" - ' ").is_empty());
+    }
+
+    #[test]
+    fn test_parse_to_primitive_word_count() {
+        let mut primitive = WordsPrimitive::default();
+        primitive.min_count(2).max_count(2);
+        assert!(primitive.parse_to_primitive(" well-known fact ").is_ok());
+        assert!(primitive.parse_to_primitive(" it's 42 ").is_ok());
+        assert!(primitive.parse_to_primitive(" well known fact ").is_err());
+        assert!(primitive.parse_to_primitive(" fact ").is_err());
+    }
+
+    #[test]
+    fn test_words_grammar() {
+        let mut primitive = WordsPrimitive::default();
+        primitive.word_char_length(8);
+        let grammar = primitive.grammar().grammar_string();
+        assert!(grammar.contains("word ::= [a-z0-9]{1,8} ( [-'] [a-z0-9]{1,8} ){0,2}"));
+        assert_eq!(
+            llm_interface::requests::completion::validate_gbnf(&grammar),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{config_file::LlmClientConfig, LlmClient};
+use serde::Deserialize;
+use std::{collections::BTreeMap, path::Path};
+
+/// Maps friendly aliases such as `fast` or `smart` to client configurations, so application code doesn't depend on
+/// model ids that change as providers deprecate models.
+///
+/// ```toml
+/// [models.fast]
+/// backend = "openai"
+/// model_id = "gpt-4o-mini"
+///
+/// [models.smart]
+/// backend = "anthropic"
+/// model_id = "claude-3-5-sonnet"
+/// ```
+///
+/// ```ignore
+/// let registry = ModelRegistry::from_file("models.toml")?;
+/// let client = registry.resolve("fast").await?;
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelRegistry {
+    #[serde(default)]
+    models: BTreeMap<String, LlmClientConfig>,
+}
+
+impl ModelRegistry {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Loads and validates a registry from a TOML or JSON file with a `models` table keyed by alias.
+    /// See [LlmClientConfig] for the fields of each model.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let registry: Self = crate::config_file::parse_file(path)?;
+        for (alias, config) in &registry.models {
+            config.validate().map_err(|e| {
+                crate::anyhow!("Invalid model `{alias}` in {}: {e}", path.display())
+            })?;
+        }
+        Ok(registry)
+    }
+
+    /// Registers `config` under `alias`, replacing any previous configuration for the alias.
+    pub fn register<S: Into<String>>(
+        &mut self,
+        alias: S,
+        config: LlmClientConfig,
+    ) -> crate::Result<&mut Self> {
+        let alias = alias.into();
+        config
+            .validate()
+            .map_err(|e| crate::anyhow!("Invalid model `{alias}`: {e}"))?;
+        self.models.insert(alias, config);
+        Ok(self)
+    }
+
+    pub fn aliases(&self) -> Vec<&str> {
+        self.models.keys().map(String::as_str).collect()
+    }
+
+    pub fn contains(&self, alias: &str) -> bool {
+        self.models.contains_key(alias)
+    }
+
+    /// The configuration registered under `alias`.
+    pub fn config(&self, alias: &str) -> crate::Result<&LlmClientConfig> {
+        match self.models.get(alias) {
+            Some(config) => Ok(config),
+            None => crate::bail!(
+                "Unknown model alias `{alias}`. Registered aliases: {:?}",
+                self.aliases()
+            ),
+        }
+    }
+
+    /// Builds a new client for `alias`. Local backends start a new server on every call, so keep the client
+    /// rather than resolving the alias for each request.
+    pub async fn resolve(&self, alias: &str) -> crate::Result<LlmClient> {
+        self.config(alias)?.build().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_aliases() {
+        let mut registry: ModelRegistry = toml::from_str(
+            r#"
+            [models.fast]
+            backend = "openai"
+            model_id = "gpt-4o-mini"
+
+            [models.smart]
+            backend = "anthropic"
+            model_id = "claude-3-5-sonnet"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(registry.aliases(), vec!["fast", "smart"]);
+        assert_eq!(
+            registry.config("fast").unwrap().model_id.as_deref(),
+            Some("gpt-4o-mini")
+        );
+
+        let err = registry.config("cheap").unwrap_err().to_string();
+        assert!(err.contains("`cheap`"), "{err}");
+
+        let config: LlmClientConfig =
+            toml::from_str("backend = \"perplexity\"\nmodel_id = \"sonar\"").unwrap();
+        registry.register("cheap", config).unwrap();
+        assert!(registry.contains("cheap"));
+
+        let invalid: LlmClientConfig = toml::from_str("backend = \"openai\"").unwrap();
+        assert!(registry.register("broken", invalid).is_err());
+        assert!(!registry.contains("broken"));
+    }
+}
@@ -0,0 +1,207 @@
+use crate::LlmClient;
+use std::collections::BTreeMap;
+
+/// Routes requests to local models pinned to specific GPUs.
+///
+/// llama.cpp can't move a loaded model between devices per request, so each device gets its own
+/// [llm_interface::llms::local::llama_cpp::server::LlamaCppServer] and requests are routed by choosing the client
+/// for a device.
+///
+/// ```ignore
+/// let mut router = LlmClientRouter::new();
+/// router.add_llama_cpp(0, LlmClient::llama_cpp().llama3_1_8b_instruct()).await?;
+/// router.add_llama_cpp(1, LlmClient::llama_cpp().mistral7b_instruct_v0_3()).await?;
+/// let res = router.client(1)?.basic_completion().run().await?;
+/// ```
+#[derive(Default)]
+pub struct LlmClientRouter {
+    clients: BTreeMap<u32, RoutedClient>,
+}
+
+struct RoutedClient {
+    client: LlmClient,
+    address: String,
+}
+
+impl LlmClientRouter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Starts a llama.cpp server that only uses the CUDA device `device` and registers it under that device.
+    ///
+    /// If the builder still uses the default port, the server listens on the default port plus the device ordinal,
+    /// so servers on different devices don't replace each other.
+    #[cfg(feature = "llama_cpp_backend")]
+    pub async fn add_llama_cpp(
+        &mut self,
+        device: u32,
+        mut builder: crate::backend_builders::llama_cpp::LlamaCppBackendBuilder,
+    ) -> crate::Result<&LlmClient> {
+        use llm_interface::llms::local::llama_cpp::LLAMA_CPP_API_PORT;
+
+        self.check_device_free(device)?;
+        let address =
+            self.device_address(device, &mut builder.config.api_config, LLAMA_CPP_API_PORT)?;
+        #[cfg(any(target_os = "linux", target_os = "windows"))]
+        {
+            use llm_devices::devices::CudaConfig;
+            use llm_interface::llms::local::LlmLocalTrait;
+            builder = builder.cuda_config(CudaConfig::new_with_main_device(vec![device], device));
+        }
+
+        let client = builder.init().await?;
+        Ok(&self
+            .clients
+            .entry(device)
+            .or_insert(RoutedClient { client, address })
+            .client)
+    }
+
+    /// Registers an already built client under `device`.
+    pub fn insert(&mut self, device: u32, client: LlmClient) -> crate::Result<()> {
+        self.check_device_free(device)?;
+        self.clients.insert(
+            device,
+            RoutedClient {
+                client,
+                address: format!("device:{device}"),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn get(&self, device: u32) -> Option<&LlmClient> {
+        self.clients.get(&device).map(|c| &c.client)
+    }
+
+    /// The client pinned to `device`.
+    pub fn client(&self, device: u32) -> crate::Result<&LlmClient> {
+        match self.get(device) {
+            Some(client) => Ok(client),
+            None => crate::bail!(
+                "No client registered for device {device}. Registered devices: {:?}",
+                self.devices()
+            ),
+        }
+    }
+
+    pub fn devices(&self) -> Vec<u32> {
+        self.clients.keys().copied().collect()
+    }
+
+    /// Shuts down the client for `device` and stops routing to it. Returns false if no client was registered.
+    pub fn remove(&mut self, device: u32) -> bool {
+        match self.clients.remove(&device) {
+            Some(routed) => {
+                routed.client.shutdown();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn shutdown(&self) {
+        for routed in self.clients.values() {
+            routed.client.shutdown();
+        }
+    }
+
+    /// Moves a server still on `default_port` to the default port plus `device`, and returns its address.
+    /// Errors if another device already uses the address.
+    fn device_address(
+        &self,
+        device: u32,
+        api_config: &mut llm_interface::llms::api::config::ApiConfig,
+        default_port: &str,
+    ) -> crate::Result<String> {
+        if api_config.port.as_deref() == Some(default_port) {
+            let port = default_port.parse::<u32>()? + device;
+            api_config.port = Some(port.to_string());
+        }
+        let address = match &api_config.port {
+            Some(port) => format!("{}:{}", api_config.host, port),
+            None => api_config.host.clone(),
+        };
+        if let Some((other_device, _)) = self.clients.iter().find(|(_, c)| c.address == address) {
+            crate::bail!(
+                "The server for device {device} would use {address}, which is already used by device {other_device}. Set a different port with `with_api_port`."
+            );
+        }
+        Ok(address)
+    }
+
+    fn check_device_free(&self, device: u32) -> crate::Result<()> {
+        if self.clients.contains_key(&device) {
+            crate::bail!("A client is already registered for device {device}");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use llm_interface::llms::api::{config::LlmApiConfigTrait, openai::OpenAiConfig};
+
+    fn client() -> LlmClient {
+        LlmClient::openai().with_api_key("sk-test").init().unwrap()
+    }
+
+    #[test]
+    fn test_router_routes_by_device() {
+        let mut router = LlmClientRouter::new();
+        router.insert(1, client()).unwrap();
+        router.insert(0, client()).unwrap();
+        assert_eq!(router.devices(), vec![0, 1]);
+        assert!(router.client(1).is_ok());
+        assert!(router.insert(1, client()).is_err());
+
+        let err = router.client(2).err().unwrap().to_string();
+        assert!(err.contains("Registered devices: [0, 1]"), "{err}");
+
+        assert!(router.remove(1));
+        assert!(!router.remove(1));
+        assert!(router.get(1).is_none());
+        assert_eq!(router.devices(), vec![0]);
+    }
+
+    #[test]
+    fn test_router_device_address() {
+        let mut router = LlmClientRouter::new();
+        let api_config = |port: &str| {
+            let mut api_config = OpenAiConfig::default().api_config;
+            api_config.host = "localhost".to_string();
+            api_config.port = Some(port.to_string());
+            api_config
+        };
+
+        // Servers left on the default port fall back to the default port plus the device ordinal.
+        let mut config = api_config("8080");
+        assert_eq!(
+            router.device_address(2, &mut config, "8080").unwrap(),
+            "localhost:8082"
+        );
+        assert_eq!(config.port.as_deref(), Some("8082"));
+
+        // An explicit port is kept.
+        let mut config = api_config("9000");
+        assert_eq!(
+            router.device_address(1, &mut config, "8080").unwrap(),
+            "localhost:9000"
+        );
+
+        router.clients.insert(
+            1,
+            RoutedClient {
+                client: client(),
+                address: "localhost:9000".to_string(),
+            },
+        );
+        let err = router
+            .device_address(3, &mut api_config("9000"), "8080")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("already used by device 1"), "{err}");
+    }
+}
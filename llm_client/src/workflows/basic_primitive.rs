@@ -1,6 +1,9 @@
 use crate::{
     components::{
-        cascade::{step::StepConfig, CascadeFlow},
+        cascade::{
+            step::{fallback_instructions, StepConfig},
+            CascadeFlow,
+        },
         instruct_prompt::InstructPrompt,
         InstructPromptTrait,
     },
@@ -11,6 +14,7 @@ use llm_interface::{
     requests::{
         completion::CompletionRequest,
         req_components::{RequestConfig, RequestConfigTrait},
+        res_components::InferenceProbabilities,
     },
 };
 
@@ -41,6 +45,23 @@ impl<P: PrimitiveTrait> BasicPrimitiveWorkflow<P> {
         }
     }
 
+    /// Like [Self::return_primitive], but also returns the model's confidence in the result. The confidence is only
+    /// available when `top_logprobs` is set on the request. See [PrimitiveTrait::parse_with_confidence].
+    pub async fn return_primitive_with_confidence(
+        &mut self,
+    ) -> crate::Result<(P::PrimitiveResult, Option<f32>)> {
+        self.result_can_be_none = false;
+        let res = self.return_result().await?;
+        if let Some(primitive_result) = &res.primitive_result {
+            let (primitive_result, confidence) = self
+                .primitive
+                .parse_with_confidence(primitive_result, res.completion_probabilities.as_deref());
+            Ok((primitive_result?, confidence))
+        } else {
+            Err(anyhow::format_err!("No result returned."))
+        }
+    }
+
     pub async fn return_optional_primitive(&mut self) -> crate::Result<Option<P::PrimitiveResult>> {
         self.result_can_be_none = true;
         let res = self.return_result().await?;
@@ -78,6 +99,10 @@ impl<P: PrimitiveTrait> BasicPrimitiveWorkflow<P> {
                 .primitive
                 .stop_word_result_is_none(self.result_can_be_none),
             grammar: self.primitive.grammar(),
+            fallback_instructions: Some(fallback_instructions(
+                &self.primitive,
+                self.result_can_be_none,
+            )),
             ..StepConfig::default()
         };
 
@@ -115,6 +140,11 @@ impl BasicPrimitiveWorkflowBuilder {
         }
     }
 
+    /// A list whose items are each constrained by the primitive `P`, e.g. `list::<IntegerPrimitive>()`.
+    pub fn list<P: PrimitiveTrait>(self) -> BasicPrimitiveWorkflow<ListPrimitive<P>> {
+        self.build()
+    }
+
     fn build<P: PrimitiveTrait>(self) -> BasicPrimitiveWorkflow<P> {
         BasicPrimitiveWorkflow {
             primitive: P::default(),
@@ -140,15 +170,20 @@ macro_rules! basic_primitive_workflow_primitive_impl {
 basic_primitive_workflow_primitive_impl! {
     boolean => BooleanPrimitive,
     integer => IntegerPrimitive,
+    float => FloatPrimitive,
     sentences => SentencesPrimitive,
     words => WordsPrimitive,
     exact_string => ExactStringPrimitive,
-    text_list => TextListPrimitive
+    text_list => TextListPrimitive,
+    date => DatePrimitive,
+    date_time => DateTimePrimitive
 }
 
 #[derive(Clone)]
 pub struct BasicPrimitiveResult {
     pub primitive_result: Option<String>,
+    /// The logprobs of the tokens generated for the result, if `top_logprobs` was set on the request.
+    pub completion_probabilities: Option<Vec<InferenceProbabilities>>,
     pub duration: std::time::Duration,
     pub workflow: CascadeFlow,
 }
@@ -157,6 +192,7 @@ impl BasicPrimitiveResult {
     pub fn new(flow: CascadeFlow) -> crate::Result<Self> {
         let reason_result = BasicPrimitiveResult {
             primitive_result: flow.primitive_result(),
+            completion_probabilities: flow.primitive_probabilities(),
             duration: flow.duration,
             workflow: flow,
         };
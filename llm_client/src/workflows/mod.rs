@@ -2,3 +2,4 @@ pub mod basic_primitive;
 
 pub mod nlp;
 pub mod reason;
+pub mod summarize;
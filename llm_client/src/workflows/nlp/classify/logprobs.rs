@@ -0,0 +1,195 @@
+use llm_interface::requests::{completion::CompletionRequest, res_components::TopProbabilities};
+
+/// Single-label classification from the log probabilities of the first generated token.
+///
+/// The model generates a single token, and the probabilities of the tokens that begin each label are normalized
+/// into a distribution over the labels. This is cheaper and more reliable than parsing a free generation, but
+/// requires a backend that returns log probabilities, see [llm_interface::requests::req_components::RequestConfig::top_logprobs].
+///
+/// Labels must begin with different tokens, e.g. `positive` and `negative` rather than `very good` and `very bad`.
+pub struct ClassifyByLogprobs {
+    pub base_req: CompletionRequest,
+    pub content: String,
+    pub labels: Vec<String>,
+    /// Number of candidate tokens requested. Labels whose first token isn't among them get a probability of 0.
+    pub top_logprobs: u8,
+}
+
+impl ClassifyByLogprobs {
+    /// The maximum number of top log probabilities returned by OpenAI.
+    pub const DEFAULT_TOP_LOGPROBS: u8 = 20;
+
+    pub fn new<S: AsRef<str>>(base_req: CompletionRequest, content: &str, labels: &[S]) -> Self {
+        Self {
+            base_req,
+            content: content.to_owned(),
+            labels: labels.iter().map(|l| l.as_ref().to_owned()).collect(),
+            top_logprobs: Self::DEFAULT_TOP_LOGPROBS,
+        }
+    }
+
+    pub fn top_logprobs(mut self, top_logprobs: u8) -> Self {
+        self.top_logprobs = top_logprobs;
+        self
+    }
+
+    pub async fn run(mut self) -> crate::Result<LabelProbabilities> {
+        if self.labels.len() < 2 {
+            crate::bail!("At least two labels are required, got {:?}", self.labels);
+        }
+        self.check_first_tokens_differ()?;
+
+        let task = indoc::formatdoc! {"
+            Classify the text with exactly one of these labels: {}
+            Respond with only the label.
+
+            Text:
+            {}",
+            self.labels.join(", "),
+            self.content
+        };
+        self.base_req.prompt.add_user_message()?.set_content(task);
+        let config = &mut self.base_req.config;
+        config.requested_response_tokens = Some(1);
+        config.temperature = 0.0;
+        config.top_logprobs = Some(self.top_logprobs);
+        let res = self.base_req.request().await?;
+        let first_token = res
+            .completion_probabilities
+            .and_then(|probs| probs.into_iter().next())
+            .ok_or_else(|| {
+                crate::anyhow!(
+                    "The {} backend didn't return log probabilities",
+                    self.base_req.backend.model_id()
+                )
+            })?;
+        label_probabilities(&self.labels, &first_token.top_probs)
+    }
+
+    /// Labels that start with the same token share its probability and can't be told apart.
+    fn check_first_tokens_differ(&self) -> crate::Result<()> {
+        let tokenizer = self.base_req.backend.tokenizer();
+        if tokenizer.is_approximate() {
+            return Ok(());
+        }
+        let mut first_tokens: Vec<(String, &str)> = Vec::new();
+        for label in &self.labels {
+            let Some(token) = tokenizer.tokenize(label).first().copied() else {
+                crate::bail!("Labels can't be empty");
+            };
+            let token = normalize(&tokenizer.detokenize_one(token)?);
+            if let Some((_, other)) = first_tokens.iter().find(|(t, _)| *t == token) {
+                crate::bail!(
+                    "The labels `{other}` and `{label}` both start with the token `{token}`"
+                );
+            }
+            first_tokens.push((token, label));
+        }
+        Ok(())
+    }
+}
+
+/// The probability of each label, normalized to sum to 1 and sorted from most to least likely.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelProbabilities {
+    pub probabilities: Vec<(String, f32)>,
+}
+
+impl LabelProbabilities {
+    pub fn best(&self) -> Option<&str> {
+        self.probabilities.first().map(|(label, _)| label.as_str())
+    }
+
+    pub fn probability(&self, label: &str) -> Option<f32> {
+        self.probabilities
+            .iter()
+            .find(|(l, _)| l == label)
+            .map(|(_, p)| *p)
+    }
+}
+
+/// Sums the probabilities of the candidate tokens that begin each label. A token that begins several labels is
+/// split between them.
+fn label_probabilities(
+    labels: &[String],
+    top_probs: &[TopProbabilities],
+) -> crate::Result<LabelProbabilities> {
+    let normalized_labels: Vec<String> = labels.iter().map(|l| normalize(l)).collect();
+    let mut totals = vec![0.0_f32; labels.len()];
+    for top_prob in top_probs {
+        let token = normalize(&top_prob.token);
+        if token.is_empty() {
+            continue;
+        }
+        let matches: Vec<usize> = normalized_labels
+            .iter()
+            .enumerate()
+            .filter(|(_, label)| label.starts_with(&token))
+            .map(|(i, _)| i)
+            .collect();
+        for i in &matches {
+            totals[*i] += top_prob.prob.exp() / matches.len() as f32;
+        }
+    }
+    let sum: f32 = totals.iter().sum();
+    if sum <= 0.0 {
+        let tokens: Vec<&str> = top_probs.iter().map(|t| t.token.as_str()).collect();
+        crate::bail!("None of the candidate tokens {tokens:?} begin one of the labels {labels:?}");
+    }
+    let mut probabilities: Vec<(String, f32)> = labels
+        .iter()
+        .cloned()
+        .zip(totals.into_iter().map(|t| t / sum))
+        .collect();
+    probabilities.sort_by(|a, b| b.1.total_cmp(&a.1));
+    Ok(LabelProbabilities { probabilities })
+}
+
+fn normalize(token: &str) -> String {
+    token.trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_label_probabilities() {
+        let labels = vec![
+            "Positive".to_string(),
+            "Negative".to_string(),
+            "Neutral".to_string(),
+        ];
+        let top_probs: Vec<TopProbabilities> = [
+            ("Positive", 0.6_f32),
+            (" pos", 0.1),
+            ("Ne", 0.2),
+            ("The", 0.1),
+        ]
+        .into_iter()
+        .map(|(token, p)| TopProbabilities {
+            token: token.to_string(),
+            prob: p.ln(),
+        })
+        .collect();
+        let res = label_probabilities(&labels, &top_probs).unwrap();
+        assert_eq!(res.best(), Some("Positive"));
+        let expected = [
+            ("Positive", 0.7 / 0.9),
+            ("Negative", 0.1 / 0.9),
+            ("Neutral", 0.1 / 0.9),
+        ];
+        for (label, p) in expected {
+            assert!(
+                (res.probability(label).unwrap() - p).abs() < 1e-5,
+                "{label}"
+            );
+        }
+
+        let unrelated = vec![TopProbabilities {
+            token: "The".to_string(),
+            prob: 0.0,
+        }];
+        assert!(label_probabilities(&labels, &unrelated).is_err());
+    }
+}
@@ -2,6 +2,7 @@ use llm_interface::requests::completion::CompletionRequest;
 pub mod hierarchical_tag_system;
 // pub mod hierarchy;
 pub mod label;
+pub mod logprobs;
 pub mod subject_of_text;
 
 pub struct Classify {
@@ -16,4 +17,14 @@ impl Classify {
     pub fn entity(self, content: &str) -> subject_of_text::ClassifySubjectOfText {
         subject_of_text::ClassifySubjectOfText::new(self.base_req, content)
     }
+
+    /// Classifies `content` as one of `labels` from the log probabilities of a single generated token.
+    /// See [logprobs::ClassifyByLogprobs].
+    pub fn by_logprobs<S: AsRef<str>>(
+        self,
+        content: &str,
+        labels: &[S],
+    ) -> logprobs::ClassifyByLogprobs {
+        logprobs::ClassifyByLogprobs::new(self.base_req, content, labels)
+    }
 }
@@ -66,6 +66,7 @@ macro_rules! reason_workflow_primitive_impl {
 reason_workflow_primitive_impl! {
     boolean => BooleanPrimitive,
     integer => IntegerPrimitive,
+    float => FloatPrimitive,
     exact_string => ExactStringPrimitive
 }
 
@@ -0,0 +1,292 @@
+use crate::{
+    components::cascade::{step::StepConfig, CascadeFlow},
+    primitives::*,
+    LlmClient,
+};
+use llm_interface::requests::{
+    completion::CompletionRequest,
+    req_components::{RequestConfig, RequestConfigTrait},
+};
+
+/// Summarizes `text` with [MapReduceSummarize] and its default settings.
+pub async fn map_reduce_summarize(
+    text: &str,
+    client: &LlmClient,
+) -> crate::Result<MapReduceSummary> {
+    client.map_reduce_summarize().run(text).await
+}
+
+/// Summarizes text too long for a single prompt. The text is split into chunks, each chunk is summarized on its own
+/// (map), and the chunk summaries are combined into one summary (reduce). The summaries are combined in batches that
+/// fit [MapReduceSummarize::chunk_tokens], and the combined summaries again, until one summary remains.
+///
+/// Every chunk and every batch is a round of the cascade, so the chunks and their summaries are shown in the cascade
+/// output. The rounds of a level run concurrently, each in its own prompt.
+#[derive(Clone)]
+pub struct MapReduceSummarize {
+    pub base_req: CompletionRequest,
+    /// The maximum number of tokens in a chunk, and in a batch of summaries to combine. Defaults to 1500.
+    pub chunk_tokens: u32,
+    /// The maximum number of tokens in each chunk summary and in the combined summaries. Defaults to 200.
+    pub summary_tokens: u32,
+}
+
+impl MapReduceSummarize {
+    pub fn new(base_req: CompletionRequest) -> Self {
+        Self {
+            base_req,
+            chunk_tokens: 1500,
+            summary_tokens: 200,
+        }
+    }
+
+    pub fn chunk_tokens(mut self, chunk_tokens: u32) -> Self {
+        self.chunk_tokens = chunk_tokens;
+        self
+    }
+
+    pub fn summary_tokens(mut self, summary_tokens: u32) -> Self {
+        self.summary_tokens = summary_tokens;
+        self
+    }
+
+    pub async fn run(&mut self, text: &str) -> crate::Result<MapReduceSummary> {
+        let tokenizer = self.base_req.backend.tokenizer().clone();
+        let count_tokens = |text: &str| tokenizer.count_tokens(text);
+        let chunks = chunk_text(text, self.chunk_tokens, count_tokens);
+        if chunks.is_empty() {
+            return Err(anyhow::anyhow!("No text to summarize"));
+        }
+        let chunk_count = chunks.len();
+        let mut flow = CascadeFlow::new("MapReduceSummarize");
+        flow.open_cascade();
+
+        let tasks = chunks
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| format!(
+                "Summarize part {} of {chunk_count} of a text. Keep the key facts, names and numbers.\nText:\n{chunk}",
+                i + 1
+            ))
+            .collect();
+        let chunk_summaries = self.run_level(&mut flow, "map", tasks).await?;
+
+        let mut summaries = chunk_summaries.clone();
+        let mut level = 1;
+        while summaries.len() > 1 {
+            let tasks = batch_summaries(&summaries, self.chunk_tokens, count_tokens)
+                .into_iter()
+                .map(|batch| format!(
+                    "Combine the summaries of consecutive parts of a text into a single summary of those parts.\nSummaries:\n{batch}"
+                ))
+                .collect();
+            summaries = self
+                .run_level(&mut flow, &format!("reduce {level}"), tasks)
+                .await?;
+            level += 1;
+        }
+        flow.close_cascade()?;
+
+        Ok(MapReduceSummary {
+            summary: summaries.remove(0),
+            chunk_summaries,
+            duration: flow.duration,
+            workflow: flow,
+        })
+    }
+
+    /// Runs a summary round for each task through a cascade of independent rounds, and moves the rounds to `flow`.
+    async fn run_level(
+        &self,
+        flow: &mut CascadeFlow,
+        name: &str,
+        tasks: Vec<String>,
+    ) -> crate::Result<Vec<String>> {
+        let config = StepConfig {
+            step_prefix: Some("Summary: ".to_owned()),
+            grammar: TextPrimitive::default()
+                .text_token_length(self.summary_tokens)
+                .grammar(),
+            ..StepConfig::default()
+        };
+        let mut level = CascadeFlow::new(format!("{} {name}", flow.cascade_name));
+        level.independent_rounds(true);
+        for task in tasks {
+            level.new_round(task).add_inference_step(&config);
+        }
+        level.run_all_rounds(&mut self.base_req.clone()).await?;
+        let summaries = level
+            .rounds
+            .iter()
+            .map(|round| round.primitive_result().unwrap_or_default())
+            .collect();
+        flow.rounds.append(&mut level.rounds);
+        Ok(summaries)
+    }
+}
+
+impl RequestConfigTrait for MapReduceSummarize {
+    fn config(&mut self) -> &mut RequestConfig {
+        &mut self.base_req.config
+    }
+
+    fn reset_request(&mut self) {
+        self.base_req.reset_completion_request();
+    }
+}
+
+#[derive(Clone)]
+pub struct MapReduceSummary {
+    pub summary: String,
+    /// The summary of each chunk, in the order of the text.
+    pub chunk_summaries: Vec<String>,
+    pub duration: std::time::Duration,
+    pub workflow: CascadeFlow,
+}
+
+impl std::fmt::Display for MapReduceSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.workflow)?;
+        writeln!(f, "chunk count: {}", self.chunk_summaries.len())?;
+        writeln!(f, "summary: {}", self.summary)?;
+        writeln!(f, "duration: {:?}", self.duration)
+    }
+}
+
+/// Splits text into chunks of at most `max_tokens`, counted with `count_tokens`. Chunks end at paragraph breaks
+/// where possible, then at line breaks, then between words. A single word longer than `max_tokens` is its own chunk.
+pub fn chunk_text<F>(text: &str, max_tokens: u32, count_tokens: F) -> Vec<String>
+where
+    F: Fn(&str) -> u32,
+{
+    let mut chunks = Vec::new();
+    split_into(
+        text.trim(),
+        max_tokens,
+        &count_tokens,
+        &["\n\n", "\n", " "],
+        &mut chunks,
+    );
+    chunks
+}
+
+fn split_into<F>(
+    text: &str,
+    max_tokens: u32,
+    count_tokens: &F,
+    separators: &[&str],
+    chunks: &mut Vec<String>,
+) where
+    F: Fn(&str) -> u32,
+{
+    if text.is_empty() {
+        return;
+    }
+    if count_tokens(text) <= max_tokens {
+        chunks.push(text.to_owned());
+        return;
+    }
+    let Some((separator, finer)) = separators.split_first() else {
+        chunks.push(text.to_owned());
+        return;
+    };
+    let mut current = String::new();
+    for part in text
+        .split(separator)
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+    {
+        let candidate = if current.is_empty() {
+            part.to_owned()
+        } else {
+            format!("{current}{separator}{part}")
+        };
+        if count_tokens(&candidate) <= max_tokens {
+            current = candidate;
+            continue;
+        }
+        if !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if count_tokens(part) <= max_tokens {
+            current = part.to_owned();
+        } else {
+            split_into(part, max_tokens, count_tokens, finer, chunks);
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+}
+
+/// Groups the summaries, numbered in order, into batches of at most `max_tokens`, counted with `count_tokens`. A batch
+/// has at least two summaries, even if they don't fit, so every reduce level shrinks the number of summaries.
+fn batch_summaries<F>(summaries: &[String], max_tokens: u32, count_tokens: F) -> Vec<String>
+where
+    F: Fn(&str) -> u32,
+{
+    let mut batches = Vec::new();
+    let mut current = String::new();
+    let mut current_count = 0;
+    for (i, summary) in summaries.iter().enumerate() {
+        let part = format!("Part {}: {summary}", i + 1);
+        if current_count >= 2 && count_tokens(&format!("{current}\n{part}")) > max_tokens {
+            batches.push(std::mem::take(&mut current));
+            current_count = 0;
+        }
+        if current_count > 0 {
+            current.push('\n');
+        }
+        current.push_str(&part);
+        current_count += 1;
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_text() {
+        let count_words = |text: &str| text.split_whitespace().count() as u32;
+        let text = "one two three\n\nfour five\n\nsix seven eight nine ten eleven";
+        assert_eq!(
+            chunk_text(text, 5, count_words),
+            vec![
+                "one two three\n\nfour five",
+                "six seven eight nine ten",
+                "eleven"
+            ]
+        );
+        assert_eq!(chunk_text(text, 100, count_words), vec![text]);
+        assert!(chunk_text("  \n\n ", 5, count_words).is_empty());
+    }
+
+    #[test]
+    fn test_batch_summaries() {
+        let count_words = |text: &str| text.split_whitespace().count() as u32;
+        let summaries: Vec<String> = ["a b", "c d", "e f", "g h", "i j"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        // Each part is 4 words, so two fit in 8.
+        assert_eq!(
+            batch_summaries(&summaries, 8, count_words),
+            vec![
+                "Part 1: a b\nPart 2: c d",
+                "Part 3: e f\nPart 4: g h",
+                "Part 5: i j"
+            ]
+        );
+        // Batches have at least two summaries, so the reduce always makes progress.
+        assert_eq!(
+            batch_summaries(&summaries[..3], 1, count_words),
+            vec!["Part 1: a b\nPart 2: c d", "Part 3: e f"]
+        );
+        assert_eq!(batch_summaries(&summaries, 100, count_words).len(), 1);
+    }
+}
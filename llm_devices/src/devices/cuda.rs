@@ -1,5 +1,6 @@
 use super::gpu::GpuDevice;
 use nvml_wrapper::Nvml;
+use std::collections::HashMap;
 
 // See https://gist.github.com/jrruethe/8974d2c8b4ece242a071d1a1526aa763#file-vram-rb-L64
 pub const CUDA_OVERHEAD: u64 = 500 * 1024 * 1024;
@@ -10,6 +11,12 @@ pub struct CudaConfig {
     pub main_gpu: Option<u32>,
     /// Ordinals of the devices to use.
     pub use_cuda_devices: Vec<u32>,
+    /// VRAM reserved on each device for the CUDA context and anything else outside the model, e.g. a display. Defaults to [CUDA_OVERHEAD].
+    pub overhead_bytes: u64,
+    /// Per-device overrides of `overhead_bytes`, keyed by device ordinal.
+    pub device_overhead_bytes: HashMap<u32, u64>,
+    /// Size allocations from total VRAM instead of currently free VRAM. Only set this for dedicated machines where nothing else uses the GPUs. Defaults to false.
+    pub assume_exclusive_access: bool,
     pub(crate) cuda_devices: Vec<CudaDevice>,
     pub(crate) total_vram_bytes: u64,
 }
@@ -19,6 +26,9 @@ impl Default for CudaConfig {
         Self {
             main_gpu: None,
             use_cuda_devices: Vec::new(),
+            overhead_bytes: CUDA_OVERHEAD,
+            device_overhead_bytes: HashMap::new(),
+            assume_exclusive_access: false,
             cuda_devices: Vec::new(),
             total_vram_bytes: 0,
         }
@@ -41,12 +51,61 @@ impl CudaConfig {
         }
     }
 
+    /// Set the VRAM overhead reserved on every device.
+    pub fn with_overhead_bytes(mut self, overhead_bytes: u64) -> Self {
+        self.overhead_bytes = overhead_bytes;
+        self
+    }
+
+    /// Set the VRAM overhead reserved on a single device, overriding `overhead_bytes`.
+    pub fn with_device_overhead_bytes(mut self, ordinal: u32, overhead_bytes: u64) -> Self {
+        self.device_overhead_bytes.insert(ordinal, overhead_bytes);
+        self
+    }
+
+    /// Size allocations from total VRAM instead of currently free VRAM.
+    pub fn with_exclusive_access(mut self, assume_exclusive_access: bool) -> Self {
+        self.assume_exclusive_access = assume_exclusive_access;
+        self
+    }
+
+    fn overhead_for(&self, ordinal: u32) -> u64 {
+        self.device_overhead_bytes
+            .get(&ordinal)
+            .copied()
+            .unwrap_or(self.overhead_bytes)
+    }
+
     pub(crate) fn initialize(&mut self, error_on_config_issue: bool) -> crate::Result<()> {
         let nvml: Nvml = init_nvml_wrapper()?;
+        let driver_device_count = match probe_cuda_driver() {
+            Ok(device_count) => device_count,
+            Err(e) => crate::bail!(
+                "NVML found the GPUs, but llama.cpp won't be able to use them: {}. This is usually a mismatch between \
+                the NVIDIA driver and the CUDA version, or a driver update that needs a reboot.",
+                e
+            ),
+        };
         if self.use_cuda_devices.is_empty() {
             self.cuda_devices = get_all_cuda_devices(Some(&nvml))?;
         } else {
             for ordinal in &self.use_cuda_devices {
+                if *ordinal >= driver_device_count {
+                    if error_on_config_issue {
+                        crate::bail!(
+                            "Device {} specified in cuda_devices is not available to the CUDA driver, which found {} devices",
+                            ordinal,
+                            driver_device_count
+                        );
+                    } else {
+                        crate::warn!(
+                            "Device {} specified in cuda_devices is not available to the CUDA driver, which found {} devices",
+                            ordinal,
+                            driver_device_count
+                        );
+                        continue;
+                    }
+                }
                 match CudaDevice::new(*ordinal, Some(&nvml)) {
                     Ok(cuda_device) => self.cuda_devices.push(cuda_device),
                     Err(e) => {
@@ -70,6 +129,11 @@ impl CudaConfig {
         if self.cuda_devices.is_empty() {
             crate::bail!("No CUDA devices found");
         }
+        for i in 0..self.cuda_devices.len() {
+            let overhead_bytes = self.overhead_for(self.cuda_devices[i].ordinal);
+            self.cuda_devices[i].set_exclusive_access(self.assume_exclusive_access);
+            self.cuda_devices[i].set_overhead_bytes(overhead_bytes);
+        }
 
         self.main_gpu = Some(self.main_gpu(error_on_config_issue)?);
 
@@ -166,6 +230,12 @@ pub fn get_all_cuda_devices(nvml: Option<&Nvml>) -> crate::Result<Vec<CudaDevice
 #[derive(Debug, Clone)]
 pub struct CudaDevice {
     pub ordinal: u32,
+    pub total_vram_bytes: u64,
+    /// VRAM not in use by any process when the device was queried.
+    pub free_vram_bytes: u64,
+    pub overhead_bytes: u64,
+    /// If true, `available_vram_bytes` is based on total VRAM rather than free VRAM.
+    pub exclusive_access: bool,
     pub available_vram_bytes: u64,
     pub name: Option<String>,
     pub power_limit: Option<u32>,
@@ -204,7 +274,11 @@ impl CudaDevice {
                     };
                     let cuda_device = CudaDevice {
                         ordinal: ordinal,
-                        available_vram_bytes: memory_info.total - CUDA_OVERHEAD,
+                        total_vram_bytes: memory_info.total,
+                        free_vram_bytes: memory_info.free,
+                        overhead_bytes: CUDA_OVERHEAD,
+                        exclusive_access: false,
+                        available_vram_bytes: memory_info.free.saturating_sub(CUDA_OVERHEAD),
                         name,
                         power_limit,
                         driver_major,
@@ -223,15 +297,111 @@ impl CudaDevice {
         }
     }
 
+    /// Reserve `overhead_bytes` of VRAM and recalculate `available_vram_bytes`.
+    pub fn set_overhead_bytes(&mut self, overhead_bytes: u64) {
+        self.overhead_bytes = overhead_bytes;
+        self.update_available_vram();
+    }
+
+    /// Base `available_vram_bytes` on total VRAM (exclusive access) or free VRAM (shared GPU).
+    pub fn set_exclusive_access(&mut self, exclusive_access: bool) {
+        self.exclusive_access = exclusive_access;
+        self.update_available_vram();
+    }
+
+    fn update_available_vram(&mut self) {
+        let usable_vram_bytes = if self.exclusive_access {
+            self.total_vram_bytes
+        } else {
+            self.free_vram_bytes
+        };
+        self.available_vram_bytes = usable_vram_bytes.saturating_sub(self.overhead_bytes);
+    }
+
     pub fn to_generic_gpu(&self) -> GpuDevice {
         GpuDevice {
             ordinal: self.ordinal,
+            name: self.name.clone(),
             available_vram_bytes: self.available_vram_bytes,
             ..Default::default()
         }
     }
 }
 
+/// The used and total VRAM bytes of the device, as currently reported by NVML.
+pub fn vram_usage(ordinal: u32) -> crate::Result<(u64, u64)> {
+    let nvml = init_nvml_wrapper()?;
+    let memory_info = nvml.device_by_index(ordinal)?.memory_info()?;
+    Ok((memory_info.used, memory_info.total))
+}
+
+/// Initializes the CUDA driver the way llama.cpp does on start up, and returns the number of devices it can use.
+///
+/// NVML is loaded separately from the CUDA driver, so it reports devices even when the driver can't be initialized,
+/// e.g. when the kernel module and the driver library have different versions.
+pub fn probe_cuda_driver() -> crate::Result<u32> {
+    let library = match cuda_driver_library() {
+        Some(library) => library,
+        None => crate::bail!("Failed to load the CUDA driver library"),
+    };
+    // SAFETY: The symbols are declared with their signatures from cuda.h.
+    let (cu_init, cu_device_get_count) = unsafe {
+        (
+            library.get::<unsafe extern "C" fn(u32) -> i32>(b"cuInit\0")?,
+            library.get::<unsafe extern "C" fn(*mut i32) -> i32>(b"cuDeviceGetCount\0")?,
+        )
+    };
+    let result = unsafe { cu_init(0) };
+    if result != 0 {
+        crate::bail!("cuInit failed with {}", cuda_error_name(result));
+    }
+    let mut device_count = 0;
+    let result = unsafe { cu_device_get_count(&mut device_count) };
+    if result != 0 {
+        crate::bail!("cuDeviceGetCount failed with {}", cuda_error_name(result));
+    }
+    if device_count <= 0 {
+        crate::bail!("the CUDA driver found no devices");
+    }
+    Ok(device_count as u32)
+}
+
+/// The CUDA driver library, loaded once and kept loaded for the rest of the process. Unloading the driver after
+/// `cuInit` leaves its threads and handlers pointing at unmapped code.
+fn cuda_driver_library() -> Option<&'static libloading::Library> {
+    static CUDA_DRIVER: std::sync::OnceLock<Option<libloading::Library>> =
+        std::sync::OnceLock::new();
+    CUDA_DRIVER
+        .get_or_init(|| {
+            let library_names = [
+                "libcuda.so.1", // For Linux and WSL
+                "libcuda.so",   // For Linux
+                "nvcuda.dll",   // For Windows
+            ];
+            library_names.into_iter().find_map(|library_name| {
+                // SAFETY: The CUDA driver library has no initialization routines with preconditions.
+                unsafe { libloading::Library::new(library_name) }.ok()
+            })
+        })
+        .as_ref()
+}
+
+/// The name of a CUresult from cuda.h, for the errors a broken installation returns.
+fn cuda_error_name(result: i32) -> String {
+    let name = match result {
+        2 => "CUDA_ERROR_OUT_OF_MEMORY",
+        3 => "CUDA_ERROR_NOT_INITIALIZED",
+        34 => "CUDA_ERROR_STUB_LIBRARY",
+        100 => "CUDA_ERROR_NO_DEVICE",
+        101 => "CUDA_ERROR_INVALID_DEVICE",
+        803 => "CUDA_ERROR_SYSTEM_DRIVER_MISMATCH",
+        804 => "CUDA_ERROR_COMPAT_NOT_SUPPORTED_ON_DEVICE",
+        999 => "CUDA_ERROR_UNKNOWN",
+        _ => return format!("CUresult {result}"),
+    };
+    format!("{name} ({result})")
+}
+
 pub fn init_nvml_wrapper() -> crate::Result<Nvml> {
     let library_names = vec![
         "libnvidia-ml.so",   // For Linux
@@ -257,6 +427,11 @@ impl std::fmt::Display for CudaConfig {
             f,
             &[
                 format_args!("Main GPU: {:?}", self.main_gpu),
+                format_args!(
+                    "Overhead per device: {:.2} GB",
+                    (self.overhead_bytes as f64) / 1_073_741_824.0
+                ),
+                format_args!("Assume exclusive access: {}", self.assume_exclusive_access),
                 format_args!(
                     "Total vram size: {:.2} GB",
                     (self.total_vram_bytes as f64) / 1_073_741_824.0
@@ -277,6 +452,18 @@ impl std::fmt::Display for CudaDevice {
             f,
             &[
                 format_args!("Ordinal: {:?}", self.ordinal),
+                format_args!(
+                    "Total VRAM: {:.2} GB",
+                    (self.total_vram_bytes as f64) / 1_073_741_824.0
+                ),
+                format_args!(
+                    "Free VRAM: {:.2} GB",
+                    (self.free_vram_bytes as f64) / 1_073_741_824.0
+                ),
+                format_args!(
+                    "Overhead: {:.2} GB",
+                    (self.overhead_bytes as f64) / 1_073_741_824.0
+                ),
                 format_args!(
                     "Available VRAM: {:.2} GB",
                     (self.available_vram_bytes as f64) / 1_073_741_824.0
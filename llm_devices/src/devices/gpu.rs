@@ -1,14 +1,42 @@
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct GpuDevice {
     pub ordinal: u32,
+    pub name: Option<String>,
     pub available_vram_bytes: u64,
     pub allocated_layer_bytes: u64,
     pub allocated_buffer_bytes: u64,
     pub allocated_layers: u64,
     pub is_main_gpu: bool,
+    /// VRAM in use on the device by any process. `None` until measured with [GpuDevice::refresh_usage].
+    pub used_vram_bytes: Option<u64>,
+    /// Total VRAM of the device. `None` until measured with [GpuDevice::refresh_usage].
+    pub total_vram_bytes: Option<u64>,
 }
 
 impl GpuDevice {
+    /// Measures the VRAM currently in use on the device. Only supported for CUDA devices.
+    pub fn refresh_usage(&mut self) -> crate::Result<()> {
+        #[cfg(any(target_os = "linux", target_os = "windows"))]
+        {
+            let (used_vram_bytes, total_vram_bytes) = super::cuda::vram_usage(self.ordinal)?;
+            self.used_vram_bytes = Some(used_vram_bytes);
+            self.total_vram_bytes = Some(total_vram_bytes);
+            Ok(())
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+        {
+            crate::bail!("VRAM usage can only be measured for CUDA devices");
+        }
+    }
+
+    /// The fraction of the device's VRAM in use, from the last [GpuDevice::refresh_usage].
+    pub fn utilization(&self) -> Option<f64> {
+        match (self.used_vram_bytes, self.total_vram_bytes) {
+            (Some(used), Some(total)) if total > 0 => Some(used as f64 / total as f64),
+            _ => None,
+        }
+    }
+
     fn can_allocate(&self, layer_size: u64) -> bool {
         self.available_vram_bytes >= self.allocated_layer_bytes + layer_size
     }
@@ -151,6 +179,7 @@ impl std::fmt::Display for GpuDevice {
             f,
             &[
                 format_args!("Ordinal: {}", self.ordinal),
+                format_args!("Name: {:?}", self.name),
                 format_args!("Main GPU: {}", self.is_main_gpu),
                 format_args!("Allocated Layers: {}", self.allocated_layers),
                 format_args!(
@@ -171,6 +200,17 @@ impl std::fmt::Display for GpuDevice {
                     self.available_vram_bytes as f64 / 1_073_741_824.0
                 ),
             ],
-        )
+        )?;
+        if let (Some(used), Some(total)) = (self.used_vram_bytes, self.total_vram_bytes) {
+            crate::i_ln(
+                f,
+                format_args!(
+                    "Used VRAM: {:.2} GB of {:.2} GB",
+                    used as f64 / 1_073_741_824.0,
+                    total as f64 / 1_073_741_824.0
+                ),
+            )?;
+        }
+        Ok(())
     }
 }
@@ -110,6 +110,7 @@ impl MetalConfig {
     pub(crate) fn to_generic_gpu_device(&self) -> GpuDevice {
         GpuDevice {
             ordinal: 0,
+            name: None,
             available_vram_bytes: self.use_ram_bytes,
             allocated_layer_bytes: 0,
             allocated_buffer_bytes: 0,
@@ -13,6 +13,29 @@ pub mod gpu;
 pub mod metal;
 pub mod ram;
 
+/// Lists the local GPU devices with their names and available VRAM, without starting a backend.
+///
+/// Uses CUDA on Linux and Windows, and Metal on macOS.
+pub fn enumerate_gpus() -> crate::Result<Vec<gpu::GpuDevice>> {
+    #[cfg(any(target_os = "linux", target_os = "windows"))]
+    {
+        Ok(cuda::get_all_cuda_devices(None)?
+            .iter()
+            .map(|d| d.to_generic_gpu())
+            .collect())
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let mut metal_config = MetalConfig::default();
+        metal_config.initialize(false)?;
+        Ok(vec![metal_config.to_generic_gpu_device()])
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+    {
+        crate::bail!("Unsupported OS");
+    }
+}
+
 /// Configuration for device-specific settings in LLM inference.
 #[derive(Debug, Clone)]
 pub struct DeviceConfig {
@@ -16,6 +16,8 @@ pub struct LoggingConfig {
     pub log_path: Option<PathBuf>,
     pub _tracing_guard: Option<std::sync::Arc<tracing::subscriber::DefaultGuard>>,
     pub build_log: bool,
+    /// Emit events to the host application's global subscriber instead of installing a thread local one.
+    pub use_global_subscriber: bool,
 }
 
 impl Default for LoggingConfig {
@@ -27,6 +29,7 @@ impl Default for LoggingConfig {
             log_path: None,
             _tracing_guard: None,
             build_log: false,
+            use_global_subscriber: false,
         }
     }
 }
@@ -37,6 +40,11 @@ impl LoggingConfig {
     }
 
     pub fn load_logger(&mut self) -> crate::Result<()> {
+        if self.use_global_subscriber {
+            // Events go to whatever global subscriber the host application installed.
+            self._tracing_guard = None;
+            return Ok(());
+        }
         self._tracing_guard = if self.logging_enabled {
             Some(std::sync::Arc::new(self.create_logger()?))
         } else {
@@ -125,6 +133,28 @@ pub trait LoggingConfigTrait {
         self
     }
 
+    /// Uses the host application's global subscriber instead of installing this crate's own logger.
+    ///
+    /// By default the crate sets a thread local subscriber with `tracing::subscriber::set_default`, which
+    /// takes precedence over a global subscriber already installed by the application. When enabled, no
+    /// subscriber or log files are created and events are emitted to the global subscriber, so the
+    /// application's filters and outputs apply. `logging_enabled` and the log level are ignored in this mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - A boolean value where `true` uses the global subscriber.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Self` to allow for method chaining.
+    fn use_global_subscriber(mut self, enabled: bool) -> Self
+    where
+        Self: Sized,
+    {
+        self.logging_config_mut().use_global_subscriber = enabled;
+        self
+    }
+
     /// Sets the name of the logger.
     ///
     /// This method allows you to specify a custom name for the logger, which can be useful
@@ -4,6 +4,7 @@ pub(crate) use anyhow::{Error, anyhow, bail, Result};
 pub(crate) use tracing::{debug, error, info, span, trace, warn, Level};
 
 pub mod llms;
+pub mod otel;
 pub mod requests;
 
 pub struct LlmInterface {}
@@ -31,4 +32,60 @@ impl LlmInterface {
     pub fn perplexity() -> llms::api::perplexity::builder::PerplexityBackendBuilder {
         llms::api::perplexity::builder::PerplexityBackendBuilder::default()
     }
+
+    pub fn ollama() -> llms::api::ollama::builder::OllamaBackendBuilder {
+        llms::api::ollama::builder::OllamaBackendBuilder::default()
+    }
+
+    /// Build a backend from a single URI of the form `<scheme>://<model>`.
+    ///
+    /// Supported schemes:
+    /// - `openai://gpt-4o`
+    /// - `anthropic://claude-3-5-sonnet`
+    /// - `perplexity://llama-3.1-sonar-large`
+    /// - `ollama://localhost:11434/llama3` (host and port are optional, e.g. `ollama://llama3`)
+    /// - `llamacpp:///path/to/model.gguf` (requires the `llama_cpp_backend` feature)
+    pub async fn from_uri(uri: &str) -> crate::Result<std::sync::Arc<llms::LlmBackend>> {
+        use llm_models::api_model::{
+            anthropic::AnthropicModelTrait, perplexity::PerplexityModelTrait,
+        };
+        use llms::api::config::LlmApiConfigTrait;
+
+        let (scheme, rest) = match uri.split_once("://") {
+            Some((scheme, rest)) if !rest.is_empty() => (scheme, rest),
+            _ => crate::bail!("Invalid LLM URI '{uri}'. Expected '<scheme>://<model>'"),
+        };
+
+        match scheme {
+            "openai" => llms::api::openai::builder::OpenAiBackendBuilder::with_model_id(rest).init(),
+            "anthropic" => Self::anthropic().model_id_str(rest).init(),
+            "perplexity" => Self::perplexity().model_id_str(rest).init(),
+            "ollama" => {
+                let mut builder = Self::ollama();
+                let model_id = match rest.rsplit_once('/') {
+                    Some((address, model_id)) => {
+                        match address.split_once(':') {
+                            Some((host, port)) => {
+                                builder = builder.with_api_host(host).with_api_port(port)
+                            }
+                            None => builder = builder.with_api_host(address),
+                        }
+                        model_id
+                    }
+                    None => rest,
+                };
+                builder.model_id_str(model_id).init()
+            }
+            #[cfg(feature = "llama_cpp_backend")]
+            "llamacpp" | "llama_cpp" => {
+                use llm_models::local_model::GgufLoaderTrait;
+                let mut builder = Self::llama_cpp();
+                builder.local_quant_file_path(rest);
+                builder.init().await
+            }
+            _ => crate::bail!(
+                "Unknown LLM URI scheme '{scheme}' in '{uri}'. Supported schemes: openai, anthropic, perplexity, ollama, llamacpp"
+            ),
+        }
+    }
 }
@@ -1,4 +1,7 @@
 mod req;
 mod res;
-pub use req::AnthropicCompletionRequest;
-pub use res::AnthropicCompletionResponse;
+pub use req::{
+    AnthropicCompletionRequest, AnthropicCountTokensRequest, AnthropicCountTokensResponse,
+    AnthropicMetadata,
+};
+pub use res::{AnthropicCompletionResponse, StopReason};
@@ -1,4 +1,7 @@
-use crate::requests::completion::{error::CompletionError, request::CompletionRequest};
+use crate::requests::{
+    completion::{error::CompletionError, request::CompletionRequest},
+    tool::{Tool, ToolTurn},
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Serialize, Default, Debug, Deserialize, PartialEq)]
@@ -48,60 +51,173 @@ pub struct AnthropicCompletionRequest {
     /// min: 0.0, max: 1.0, default: None
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>,
+
+    /// Configuration for enabling Claude's extended thinking.
+    ///
+    /// When enabled, responses include thinking content blocks showing Claude's thinking process before the final answer. Requires a minimum budget of 1,024 tokens and counts towards your max_tokens limit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<ThinkingConfig>,
+
+    /// Definitions of tools that the model may use.
+    ///
+    /// If you include tools in your API request, the model may return tool_use content blocks that represent the model's use of those tools. You can then run those tools using the tool input generated by the model and then optionally return results back to the model using tool_result content blocks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<AnthropicTool>>,
+
+    /// An object describing metadata about the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<AnthropicMetadata>,
 }
 
-impl AnthropicCompletionRequest {
-    pub fn new(req: &CompletionRequest) -> crate::Result<Self, CompletionError> {
-        let mut messages = Vec::new();
-        let mut system_prompt = None;
-        match req.prompt.get_built_prompt_hashmap() {
-            Ok(prompt_message) => {
-                for m in prompt_message {
-                    let role = m.get("role").ok_or_else(|| {
-                        CompletionError::RequestBuilderError("Role not found".to_string())
-                    })?;
-                    let content = m.get("content").ok_or_else(|| {
-                        CompletionError::RequestBuilderError("Content not found".to_string())
-                    })?;
-
-                    match role.as_str() {
-                        "user" | "assistant" => messages.push(CompletionRequestMessage {
-                            role: role.to_string(),
-                            content: content.to_string(),
-                        }),
-                        "system" => system_prompt = Some(content.to_string()),
-                        _ => {
-                            return Err(CompletionError::RequestBuilderError(format!(
-                                "Role {} not supported",
-                                role
-                            )))
+#[derive(Clone, Serialize, Default, Debug, Deserialize, PartialEq)]
+pub struct AnthropicMetadata {
+    /// An external identifier for the user who is associated with the request.
+    ///
+    /// This should be a uuid, hash value, or other opaque identifier. Anthropic may use this id to help detect abuse. Do not include any identifying information such as name, email address, or phone number.
+    pub user_id: String,
+}
+
+/// The `system` field and the user and assistant turns of the prompt, followed by any tool turns.
+fn prompt_messages(
+    req: &CompletionRequest,
+) -> crate::Result<(Vec<CompletionRequestMessage>, Option<String>), CompletionError> {
+    let mut messages = Vec::new();
+    let mut system_prompt: Option<String> = None;
+    match req.prompt.get_built_prompt_hashmap() {
+        Ok(prompt_message) => {
+            for m in prompt_message {
+                let role = m.get("role").ok_or_else(|| {
+                    CompletionError::RequestBuilderError("Role not found".to_string())
+                })?;
+                let content = m.get("content").ok_or_else(|| {
+                    CompletionError::RequestBuilderError("Content not found".to_string())
+                })?;
+
+                match role.as_str() {
+                    "user" | "assistant" => messages.push(CompletionRequestMessage {
+                        role: role.to_string(),
+                        content: CompletionRequestContent::Text(content.to_string()),
+                    }),
+                    // The Messages API has no system role, so every system message goes into the
+                    // top-level `system` field.
+                    "system" => match &mut system_prompt {
+                        Some(system) => {
+                            system.push_str("\n\n");
+                            system.push_str(content);
                         }
+                        None => system_prompt = Some(content.to_string()),
+                    },
+                    _ => {
+                        return Err(CompletionError::RequestBuilderError(format!(
+                            "Role {} not supported",
+                            role
+                        )))
                     }
                 }
             }
-            Err(e) => {
-                return Err(CompletionError::RequestBuilderError(format!(
-                    "Error building prompt: {}",
-                    e
-                )))
-            }
         }
+        Err(e) => {
+            return Err(CompletionError::RequestBuilderError(format!(
+                "Error building prompt: {}",
+                e
+            )))
+        }
+    }
+
+    if messages.is_empty() {
+        return Err(CompletionError::RequestBuilderError(
+            "Anthropic requires at least one user message in addition to the system prompt"
+                .to_string(),
+        ));
+    }
+    for tool_turn in &req.tool_turns {
+        messages.extend(CompletionRequestMessage::from_tool_turn(tool_turn));
+    }
+    Ok((messages, system_prompt))
+}
+
+impl AnthropicCompletionRequest {
+    pub fn new(req: &CompletionRequest) -> crate::Result<Self, CompletionError> {
+        let (messages, system_prompt) = prompt_messages(req)?;
+        let tools = req
+            .tools
+            .as_ref()
+            .map(|tools| tools.iter().map(AnthropicTool::from_tool).collect());
 
         let stop = req.stop_sequences.to_vec();
         let stop_sequences = if stop.is_empty() { None } else { Some(stop) };
 
-        Ok(AnthropicCompletionRequest {
+        let max_tokens = req.config.actual_request_tokens.unwrap();
+        let metadata = req
+            .config
+            .end_user_id
+            .as_ref()
+            .map(|user_id| AnthropicMetadata {
+                user_id: user_id.clone(),
+            });
+
+        match thinking(req.config.reasoning_budget)? {
+            // Thinking isn't compatible with temperature or top_p modifications
+            Some(thinking) => Ok(AnthropicCompletionRequest {
+                model: req.backend.model_id().to_owned(),
+                messages,
+                max_tokens: max_tokens + thinking.budget_tokens as u64,
+                stop_sequences,
+                system: system_prompt,
+                temperature: 1.0,
+                top_p: None,
+                thinking: Some(thinking),
+                tools,
+                metadata,
+            }),
+            None => Ok(AnthropicCompletionRequest {
+                model: req.backend.model_id().to_owned(),
+                messages,
+                max_tokens,
+                stop_sequences,
+                system: system_prompt,
+                temperature: temperature(req.config.temperature)?,
+                top_p: top_p(req.config.top_p)?,
+                thinking: None,
+                tools,
+                metadata,
+            }),
+        }
+    }
+}
+
+/// Request body for the `/messages/count_tokens` endpoint. Counts the tokens of the prompt without generating.
+#[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
+pub struct AnthropicCountTokensRequest {
+    pub model: String,
+    pub messages: Vec<CompletionRequestMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<AnthropicTool>>,
+}
+
+impl AnthropicCountTokensRequest {
+    pub fn new(req: &CompletionRequest) -> crate::Result<Self, CompletionError> {
+        let (messages, system) = prompt_messages(req)?;
+        Ok(Self {
             model: req.backend.model_id().to_owned(),
             messages,
-            max_tokens: req.config.actual_request_tokens.unwrap(),
-            stop_sequences,
-            system: system_prompt,
-            temperature: temperature(req.config.temperature)?,
-            top_p: top_p(req.config.top_p)?,
+            system,
+            tools: req
+                .tools
+                .as_ref()
+                .map(|tools| tools.iter().map(AnthropicTool::from_tool).collect()),
         })
     }
 }
 
+#[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
+pub struct AnthropicCountTokensResponse {
+    /// The total number of tokens across the messages, system prompt and tools.
+    pub input_tokens: u32,
+}
+
 /// Convert the native temperature from 0.0 to 2.0 to 0.0 to 1.0
 fn temperature(value: f32) -> crate::Result<f32, CompletionError> {
     if (0.0..=2.0).contains(&value) {
@@ -128,8 +244,115 @@ fn top_p(value: Option<f32>) -> crate::Result<Option<f32>, CompletionError> {
     }
 }
 
+fn thinking(value: Option<u32>) -> crate::Result<Option<ThinkingConfig>, CompletionError> {
+    match value {
+        Some(v) => {
+            if v >= 1024 {
+                Ok(Some(ThinkingConfig {
+                    r#type: "enabled".to_string(),
+                    budget_tokens: v,
+                }))
+            } else {
+                Err(CompletionError::RequestBuilderError(
+                    "Reasoning budget must be at least 1024 tokens".to_string(),
+                ))
+            }
+        }
+        None => Ok(None),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ThinkingConfig {
+    /// Must be "enabled".
+    pub r#type: String,
+    /// Determines how many tokens Claude can use for its internal reasoning process.
+    pub budget_tokens: u32,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct CompletionRequestMessage {
     pub role: String,
-    pub content: String,
+    pub content: CompletionRequestContent,
+}
+
+impl CompletionRequestMessage {
+    /// The assistant message with the model's `tool_use` blocks followed by a user message with the `tool_result` blocks.
+    pub fn from_tool_turn(tool_turn: &ToolTurn) -> Vec<Self> {
+        let tool_use = tool_turn
+            .tool_calls
+            .iter()
+            .map(|c| RequestContentBlock::ToolUse {
+                id: c.id.clone(),
+                name: c.name.clone(),
+                input: c.arguments.clone(),
+            })
+            .collect();
+        let tool_results = tool_turn
+            .tool_results
+            .iter()
+            .map(|r| RequestContentBlock::ToolResult {
+                tool_use_id: r.tool_call_id.clone(),
+                content: r.content.clone(),
+            })
+            .collect();
+        vec![
+            CompletionRequestMessage {
+                role: "assistant".to_string(),
+                content: CompletionRequestContent::Blocks(tool_use),
+            },
+            CompletionRequestMessage {
+                role: "user".to_string(),
+                content: CompletionRequestContent::Blocks(tool_results),
+            },
+        ]
+    }
+}
+
+/// The content of a message. Either a single string or an array of content blocks.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum CompletionRequestContent {
+    Text(String),
+    Blocks(Vec<RequestContentBlock>),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RequestContentBlock {
+    Text {
+        text: String,
+    },
+    /// A tool call previously made by the model.
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    /// The result of a tool call, sent in a user message.
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AnthropicTool {
+    /// Name of the tool.
+    pub name: String,
+    /// Description of what this tool does.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// JSON schema for this tool's input.
+    pub input_schema: serde_json::Value,
+}
+
+impl AnthropicTool {
+    pub fn from_tool(tool: &Tool) -> Self {
+        Self {
+            name: tool.name.clone(),
+            description: tool.description.clone(),
+            input_schema: tool.parameters.clone(),
+        }
+    }
 }
@@ -1,4 +1,4 @@
-use crate::requests::completion::*;
+use crate::requests::{completion::*, tool::ToolCall};
 use serde::{Deserialize, Serialize};
 
 impl CompletionResponse {
@@ -24,37 +24,86 @@ impl CompletionResponse {
                 }
             }
             StopReason::MaxTokens => CompletionFinishReason::StopLimit,
-            StopReason::ToolUse => {
-                return Err(CompletionError::StopReasonUnsupported(
-                    "StopReason::ToolUse is not supported".to_owned(),
-                ))
+            StopReason::ToolUse => CompletionFinishReason::ToolCalls,
+            StopReason::Refusal => {
+                let refusal = res
+                    .content
+                    .iter()
+                    .filter_map(|block| match block {
+                        CompletionContent::Text { text } => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                return Err(CompletionError::Refused(refusal));
+            }
+            StopReason::PauseTurn | StopReason::Unknown => {
+                return Err(CompletionError::StopReasonUnsupported(format!(
+                    "AnthropicBackend stop_reason {:?} is not supported",
+                    res.stop_reason
+                )))
             }
         };
 
-        if res.content.is_empty() {
-            return Err(CompletionError::ReponseContentEmpty);
+        let mut text_blocks = Vec::new();
+        let mut thinking_blocks = Vec::new();
+        let mut tool_calls = Vec::new();
+        for block in &res.content {
+            match block {
+                CompletionContent::Text { text } => text_blocks.push(text),
+                CompletionContent::Thinking { thinking, .. } => thinking_blocks.push(thinking),
+                CompletionContent::RedactedThinking { .. } => (),
+                CompletionContent::ToolUse { id, name, input } => tool_calls.push(ToolCall {
+                    id: id.clone(),
+                    name: name.clone(),
+                    arguments: input.clone(),
+                }),
+            }
         }
 
-        if res.content.len() > 1 {
+        // Tool use responses may have any number of text blocks, including none, before the tool_use blocks.
+        let content = if !tool_calls.is_empty() {
+            text_blocks
+                .iter()
+                .map(|t| t.as_str())
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else if text_blocks.len() == 1 {
+            text_blocks[0].to_string()
+        } else {
             return Err(CompletionError::ReponseContentEmpty);
-        }
-        let content = res
-            .content
-            .first()
-            .ok_or_else(|| CompletionError::ReponseContentEmpty)?
-            .text
-            .to_owned();
+        };
+
+        let reasoning = if thinking_blocks.is_empty() {
+            None
+        } else {
+            Some(
+                thinking_blocks
+                    .iter()
+                    .map(|t| t.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            )
+        };
 
         Ok(Self {
             id: res.id.to_owned(),
             index: None,
             content,
+            reasoning,
+            tool_calls: if tool_calls.is_empty() {
+                None
+            } else {
+                Some(tool_calls)
+            },
             finish_reason,
             completion_probabilities: None,
             truncated: false,
             generation_settings: GenerationSettings::new_from_anthropic(req, &res),
             timing_usage: TimingUsage::new_from_generic(req.start_time),
             token_usage: TokenUsage::new_from_anthropic(&res),
+            raw_response: None,
+            special_tokens: req.stop_sequences.to_vec(),
         })
     }
 }
@@ -68,7 +117,7 @@ pub struct AnthropicCompletionResponse {
     pub id: String,
     /// Content generated by the model.
     ///
-    /// This is an array of content blocks, each of which has a type that determines its shape. Currently, the only type in responses is "text".
+    /// This is an array of content blocks, each of which has a type that determines its shape. With extended thinking enabled, "thinking" and "redacted_thinking" blocks precede the "text" block.
     pub content: Vec<CompletionContent>,
     /// The model that handled the request.
     pub model: String,
@@ -79,6 +128,9 @@ pub struct AnthropicCompletionResponse {
     /// "end_turn": the model reached a natural stopping point
     /// "max_tokens": we exceeded the requested max_tokens or the model's maximum
     /// "stop_sequence": one of your provided custom stop_sequences was generated
+    /// "tool_use": the model invoked one or more tools
+    /// "pause_turn": a long running turn was paused
+    /// "refusal": the model declined to respond for safety reasons
     pub stop_reason: StopReason,
     /// Which custom stop sequence was generated, if any.
     ///
@@ -95,9 +147,20 @@ pub struct AnthropicCompletionResponse {
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
-pub struct CompletionContent {
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CompletionContent {
     /// The single text content.
-    pub text: String,
+    Text { text: String },
+    /// The model's reasoning when extended thinking is enabled.
+    Thinking { thinking: String, signature: String },
+    /// Reasoning that was flagged by the safety systems and returned encrypted.
+    RedactedThinking { data: String },
+    /// A request by the model to call one of the provided tools.
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
 }
 
 /// Usage statistics for the completion request.
@@ -120,4 +183,11 @@ pub enum StopReason {
     StopSequence,
     /// Claude wants to use an external tool.
     ToolUse,
+    /// A long running turn was paused, to be continued by sending the response back.
+    PauseTurn,
+    /// Claude declined to respond for safety reasons.
+    Refusal,
+    /// A stop reason added to the API after this client.
+    #[serde(other)]
+    Unknown,
 }
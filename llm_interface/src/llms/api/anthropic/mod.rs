@@ -3,12 +3,14 @@ pub mod completion;
 
 use super::{
     client::ApiClient,
-    config::{ApiConfig, ApiConfigTrait},
+    config::{ApiConfig, ApiConfigTrait, RetryConfig, DEFAULT_MAX_RESPONSE_BYTES},
 };
 use crate::requests::completion::{
     error::CompletionError, request::CompletionRequest, response::CompletionResponse,
 };
-use completion::AnthropicCompletionRequest;
+use completion::{
+    AnthropicCompletionRequest, AnthropicCountTokensRequest, AnthropicCountTokensResponse,
+};
 use llm_devices::logging::LoggingConfig;
 use llm_models::api_model::ApiLlmModel;
 use reqwest::header::HeaderMap;
@@ -31,7 +33,7 @@ impl AnthropicBackend {
         config.logging_config.load_logger()?;
         config.api_config.api_key = Some(config.api_config.load_api_key()?);
         Ok(Self {
-            client: ApiClient::new(config),
+            client: ApiClient::new(config)?,
             model,
         })
     }
@@ -41,13 +43,42 @@ impl AnthropicBackend {
     ) -> crate::Result<CompletionResponse, CompletionError> {
         match self
             .client
-            .post("/messages", AnthropicCompletionRequest::new(request)?)
+            .post_with_raw(
+                "/messages",
+                AnthropicCompletionRequest::new(request)?,
+                request.config.capture_raw,
+            )
             .await
         {
             Err(e) => Err(CompletionError::ClientError(e)),
-            Ok(res) => Ok(CompletionResponse::new_from_anthropic(request, res)?),
+            Ok((res, raw)) => {
+                let mut response = CompletionResponse::new_from_anthropic(request, res)?;
+                response.raw_response = raw;
+                Ok(response)
+            }
         }
     }
+
+    /// Counts the input tokens of `request` with the `/messages/count_tokens` endpoint.
+    ///
+    /// Anthropic's tokenizer isn't public, so the local count from [ApiLlmModel::model_tokenizer] is only an
+    /// estimate. Use this when an exact count is needed.
+    pub async fn count_tokens(
+        &self,
+        request: &CompletionRequest,
+    ) -> crate::Result<u32, CompletionError> {
+        let res: AnthropicCountTokensResponse = self
+            .client
+            .post_with_raw(
+                "/messages/count_tokens",
+                AnthropicCountTokensRequest::new(request)?,
+                false,
+            )
+            .await
+            .map_err(CompletionError::ClientError)?
+            .0;
+        Ok(res.input_tokens)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -66,6 +97,10 @@ impl Default for AnthropicConfig {
                 port: None,
                 api_key: None,
                 api_key_env_var: "ANTHROPIC_API_KEY".to_string(),
+                max_response_bytes: Some(DEFAULT_MAX_RESPONSE_BYTES),
+                retry: Default::default(),
+                moderation: None,
+                user_agent: None,
             },
             logging_config: LoggingConfig {
                 logger_name: "anthropic".to_string(),
@@ -125,4 +160,16 @@ impl ApiConfigTrait for AnthropicConfig {
     fn api_key(&self) -> &Option<Secret<String>> {
         &self.api_config.api_key
     }
+
+    fn max_response_bytes(&self) -> Option<usize> {
+        self.api_config.max_response_bytes
+    }
+
+    fn retry_config(&self) -> &RetryConfig {
+        &self.api_config.retry
+    }
+
+    fn user_agent(&self) -> Option<&str> {
+        self.api_config.user_agent.as_deref()
+    }
 }
@@ -1,3 +1,5 @@
+pub mod sse;
+
 use super::error::map_serialization_error;
 use super::{
     config::ApiConfigTrait,
@@ -14,18 +16,30 @@ pub(crate) struct ApiClient<C: ApiConfigTrait> {
 }
 
 impl<C: ApiConfigTrait> ApiClient<C> {
-    pub fn new(config: C) -> Self {
-        Self {
-            http_client: reqwest::Client::new(),
+    /// Errors if the configured user agent isn't a valid header value.
+    pub fn new(config: C) -> crate::Result<Self> {
+        let http_client = match config.user_agent() {
+            Some(user_agent) => reqwest::Client::builder()
+                .user_agent(user_agent)
+                .build()
+                .map_err(|e| crate::anyhow!("Invalid user agent {user_agent:?}: {e}"))?,
+            None => reqwest::Client::new(),
+        };
+        Ok(Self {
+            http_client,
+            backoff: config.retry_config().backoff(),
             config,
-            backoff: backoff::ExponentialBackoffBuilder::new()
-                .with_max_elapsed_time(Some(std::time::Duration::from_secs(60)))
-                .build(),
-        }
+        })
     }
 
-    /// Make a POST request to {path} and deserialize the response body
-    pub(crate) async fn post<I, O>(&self, path: &str, request: I) -> Result<O, ClientError>
+    /// Make a POST request to {path} and deserialize the response body.
+    /// If capture_raw is true, the untouched response JSON is returned alongside it.
+    pub(crate) async fn post_with_raw<I, O>(
+        &self,
+        path: &str,
+        request: I,
+        capture_raw: bool,
+    ) -> Result<(O, Option<serde_json::Value>), ClientError>
     where
         I: Serialize + std::fmt::Debug,
         O: DeserializeOwned,
@@ -45,7 +59,44 @@ impl<C: ApiConfigTrait> ApiClient<C> {
             // crate::trace!("Serialized post request: {:?}", request_builder); // This will log API keys!
             Ok(request_builder.build()?)
         };
-        self.execute(request_maker).await
+        self.execute(request_maker, capture_raw).await
+    }
+
+    /// Make a streaming POST request to {path}. Each server-sent event is deserialized as `O`.
+    pub(crate) async fn post_stream<I, O>(
+        &self,
+        path: &str,
+        request: I,
+    ) -> Result<sse::SseEventStream<O>, ClientError>
+    where
+        I: Serialize + std::fmt::Debug,
+        O: DeserializeOwned,
+    {
+        let serialized_request =
+            serde_json::to_string(&request).map_err(map_serialization_error)?;
+        crate::trace!("Serialized streaming post request: {}", serialized_request);
+        let response = self
+            .http_client
+            .post(self.config.url(path))
+            .headers(self.config.headers())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(serialized_request)
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let bytes = read_body(response, self.config.max_response_bytes()).await?;
+            let wrapped_error: WrappedError = serde_json::from_slice(bytes.as_ref())
+                .map_err(|e| map_deserialization_error(e, bytes.as_ref()))?;
+            return Err(if status.as_u16() == 503 {
+                ClientError::ServiceUnavailable {
+                    message: wrapped_error.error.message,
+                }
+            } else {
+                ClientError::ApiError(wrapped_error.error)
+            });
+        }
+        Ok(sse::SseEventStream::new(response))
     }
 
     /// Make a GET request to {path} and deserialize the response body
@@ -63,7 +114,8 @@ impl<C: ApiConfigTrait> ApiClient<C> {
             // crate::trace!("Serialized post request: {:?}", request_builder); // This will log API keys!
             Ok(request_builder.build()?)
         };
-        self.execute(request_maker).await
+        let (response, _) = self.execute(request_maker, false).await?;
+        Ok(response)
     }
 
     /// Execute a HTTP request and retry on rate limit
@@ -87,10 +139,8 @@ impl<C: ApiConfigTrait> ApiClient<C> {
                 .map_err(backoff::Error::Permanent)?;
 
             let status = response.status();
-            let bytes = response
-                .bytes()
+            let bytes = read_body(response, self.config.max_response_bytes())
                 .await
-                .map_err(ClientError::Reqwest)
                 .map_err(backoff::Error::Permanent)?;
 
             // Deserialize response body from either error object or actual response object
@@ -134,7 +184,11 @@ impl<C: ApiConfigTrait> ApiClient<C> {
     /// request_maker serves one purpose: to be able to create request again
     /// to retry API call after getting rate limited. request_maker is async because
     /// reqwest::multipart::Form is created by async calls to read files for uploads.
-    async fn execute<O, M, Fut>(&self, request_maker: M) -> Result<O, ClientError>
+    async fn execute<O, M, Fut>(
+        &self,
+        request_maker: M,
+        capture_raw: bool,
+    ) -> Result<(O, Option<serde_json::Value>), ClientError>
     where
         O: DeserializeOwned,
         M: Fn() -> Fut,
@@ -150,10 +204,42 @@ impl<C: ApiConfigTrait> ApiClient<C> {
         let pretty_json = serde_json::to_string_pretty(&value).map_err(map_serialization_error)?;
         crate::trace!("Serialized response: {}", pretty_json);
 
+        // Keep a copy of the Value only if the caller asked for it
+        let raw = if capture_raw {
+            Some(value.clone())
+        } else {
+            None
+        };
+
         // Convert the Value into the target type
         let response: O =
             serde_json::from_value(value).map_err(|e| map_deserialization_error(e, &bytes))?;
 
-        Ok(response)
+        Ok((response, raw))
+    }
+}
+
+/// Reads the response body, failing as soon as it grows past `max_bytes` so an oversized response is never fully
+/// buffered.
+async fn read_body(
+    mut response: reqwest::Response,
+    max_bytes: Option<usize>,
+) -> Result<Bytes, ClientError> {
+    let Some(limit) = max_bytes else {
+        return response.bytes().await.map_err(ClientError::Reqwest);
+    };
+    if response
+        .content_length()
+        .is_some_and(|len| len > limit as u64)
+    {
+        return Err(ClientError::ResponseTooLarge { limit });
+    }
+    let mut body = bytes::BytesMut::new();
+    while let Some(chunk) = response.chunk().await.map_err(ClientError::Reqwest)? {
+        if body.len() + chunk.len() > limit {
+            return Err(ClientError::ResponseTooLarge { limit });
+        }
+        body.extend_from_slice(&chunk);
     }
+    Ok(body.freeze())
 }
@@ -0,0 +1,203 @@
+use super::super::error::{map_deserialization_error, ClientError};
+use serde::de::DeserializeOwned;
+use std::collections::VecDeque;
+
+const DONE_DATA: &str = "[DONE]";
+
+/// A dispatched server-sent event.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SseMessage {
+    /// The `data` lines of the event, joined with a newline.
+    Data(String),
+    /// The `data: [DONE]` terminator sent by OpenAI compatible servers.
+    Done,
+}
+
+/// Incremental UTF-8 decoder for byte streams.
+///
+/// A multi-byte character can be split across chunks, for example when a token ends partway through a Japanese or
+/// Arabic character. The trailing incomplete sequence is held back until the rest of its bytes arrive, so only
+/// complete characters are emitted. Invalid sequences are replaced with U+FFFD.
+#[derive(Debug, Default)]
+pub struct Utf8StreamDecoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8StreamDecoder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a chunk of bytes and returns the characters completed by it.
+    pub fn push(&mut self, chunk: &[u8]) -> String {
+        self.pending.extend_from_slice(chunk);
+        let mut decoded = String::new();
+        loop {
+            match std::str::from_utf8(&self.pending) {
+                Ok(valid) => {
+                    decoded.push_str(valid);
+                    self.pending.clear();
+                    return decoded;
+                }
+                Err(e) => {
+                    let valid_up_to = e.valid_up_to();
+                    // Safe to unwrap: the bytes up to `valid_up_to` were just validated.
+                    decoded.push_str(std::str::from_utf8(&self.pending[..valid_up_to]).unwrap());
+                    match e.error_len() {
+                        Some(invalid_len) => {
+                            decoded.push(char::REPLACEMENT_CHARACTER);
+                            self.pending.drain(..valid_up_to + invalid_len);
+                        }
+                        None => {
+                            // The remaining bytes are the start of a character that may be completed by the next chunk.
+                            self.pending.drain(..valid_up_to);
+                            return decoded;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether bytes of an incomplete character are buffered.
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Flushes the buffer when the stream ends. An incomplete trailing character is replaced with U+FFFD.
+    pub fn finish(&mut self) -> String {
+        String::from_utf8_lossy(&std::mem::take(&mut self.pending)).into_owned()
+    }
+}
+
+/// Incremental parser for a `text/event-stream` body.
+///
+/// Chunks can end anywhere, including in the middle of a line or a multi-byte character. Bytes are decoded with
+/// [Utf8StreamDecoder] and buffered until a full line is available. Comment lines (keep-alives starting with `:`)
+/// and the `event`, `id` and `retry` fields are skipped.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    decoder: Utf8StreamDecoder,
+    buffer: String,
+    data_lines: Vec<String>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a chunk of the body and returns the events completed by it.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseMessage> {
+        let decoded = self.decoder.push(chunk);
+        self.buffer.push_str(&decoded);
+        let mut messages = Vec::new();
+        while let Some(pos) = self.buffer.find('\n') {
+            let line: String = self.buffer.drain(..=pos).collect();
+            let line = line.trim_end_matches('\n').trim_end_matches('\r');
+            if let Some(message) = self.process_line(line) {
+                messages.push(message);
+            }
+        }
+        messages
+    }
+
+    /// Dispatches any event left when the stream closes without a trailing blank line.
+    pub fn finish(&mut self) -> Option<SseMessage> {
+        let remaining = self.decoder.finish();
+        self.buffer.push_str(&remaining);
+        if !self.buffer.is_empty() {
+            let line = std::mem::take(&mut self.buffer);
+            if let Some(message) = self.process_line(line.trim_end_matches('\r')) {
+                return Some(message);
+            }
+        }
+        self.dispatch()
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseMessage> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        if field == "data" {
+            self.data_lines.push(value.to_string());
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseMessage> {
+        if self.data_lines.is_empty() {
+            return None;
+        }
+        let data = std::mem::take(&mut self.data_lines).join("\n");
+        if data.trim() == DONE_DATA {
+            Some(SseMessage::Done)
+        } else {
+            Some(SseMessage::Data(data))
+        }
+    }
+}
+
+/// Reads a streaming response and yields each event's data deserialized as `T`.
+pub struct SseEventStream<T> {
+    response: reqwest::Response,
+    parser: SseParser,
+    pending: VecDeque<SseMessage>,
+    done: bool,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: DeserializeOwned> SseEventStream<T> {
+    pub fn new(response: reqwest::Response) -> Self {
+        Self {
+            response,
+            parser: SseParser::new(),
+            pending: VecDeque::new(),
+            done: false,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// The next event, or `None` once `[DONE]` is received or the stream closes.
+    pub async fn next_event(&mut self) -> Option<Result<T, ClientError>> {
+        loop {
+            if self.done {
+                return None;
+            }
+            if let Some(message) = self.pending.pop_front() {
+                match message {
+                    SseMessage::Done => {
+                        self.done = true;
+                        return None;
+                    }
+                    SseMessage::Data(data) => {
+                        return Some(
+                            serde_json::from_str(&data)
+                                .map_err(|e| map_deserialization_error(e, data.as_bytes())),
+                        );
+                    }
+                }
+            }
+            match self.response.chunk().await {
+                Ok(Some(chunk)) => self.pending.extend(self.parser.push(&chunk)),
+                Ok(None) => {
+                    match self.parser.finish() {
+                        Some(message) => self.pending.push_back(message),
+                        None => self.done = true,
+                    };
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(ClientError::Reqwest(e)));
+                }
+            }
+        }
+    }
+}
@@ -1,12 +1,62 @@
+use super::moderation::ModerationPolicy;
 use reqwest::header::HeaderMap;
 use secrecy::Secret;
 
+/// Default for [ApiConfig::max_response_bytes].
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 64 * 1024 * 1024;
+
 #[derive(Clone, Debug)]
 pub struct ApiConfig {
     pub host: String,
     pub port: Option<String>,
     pub api_key: Option<Secret<String>>,
     pub api_key_env_var: String,
+    /// Reading a response body larger than this fails with [super::error::ClientError::ResponseTooLarge] instead of
+    /// buffering it. `None` disables the limit. Streamed responses are not limited.
+    pub max_response_bytes: Option<usize>,
+    /// How rate limited and unavailable responses are retried.
+    pub retry: RetryConfig,
+    /// Checks the user messages before each request. Only used by the API backends.
+    pub moderation: Option<ModerationPolicy>,
+    /// Sent as the `User-Agent` header of every request. `None` sends no `User-Agent`.
+    pub user_agent: Option<String>,
+}
+
+/// The exponential backoff used to retry rate limited (429) and unavailable (503) responses.
+#[derive(Clone, Debug)]
+pub struct RetryConfig {
+    pub initial_interval: std::time::Duration,
+    pub multiplier: f64,
+    pub max_interval: std::time::Duration,
+    /// Stop retrying after this long. `None` retries forever.
+    pub max_elapsed_time: Option<std::time::Duration>,
+    /// Each wait is picked at random from `interval * (1 ± randomization_factor)`, so clients that were rate limited
+    /// together don't retry in lockstep. Between 0.0, which disables the jitter, and 1.0.
+    pub randomization_factor: f64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            initial_interval: std::time::Duration::from_millis(500),
+            multiplier: 1.5,
+            max_interval: std::time::Duration::from_secs(60),
+            max_elapsed_time: Some(std::time::Duration::from_secs(60)),
+            randomization_factor: 0.5,
+        }
+    }
+}
+
+impl RetryConfig {
+    pub(crate) fn backoff(&self) -> backoff::ExponentialBackoff {
+        backoff::ExponentialBackoffBuilder::new()
+            .with_initial_interval(self.initial_interval)
+            .with_multiplier(self.multiplier)
+            .with_max_interval(self.max_interval)
+            .with_max_elapsed_time(self.max_elapsed_time)
+            .with_randomization_factor(self.randomization_factor.clamp(0.0, 1.0))
+            .build()
+    }
 }
 
 impl ApiConfig {
@@ -63,6 +113,54 @@ pub trait LlmApiConfigTrait {
         self
     }
 
+    /// Set the maximum size of a response body. Default is [DEFAULT_MAX_RESPONSE_BYTES].
+    fn with_max_response_bytes(mut self, max_response_bytes: Option<usize>) -> Self
+    where
+        Self: Sized,
+    {
+        self.api_base_config_mut().max_response_bytes = max_response_bytes;
+        self
+    }
+
+    /// Set how rate limited and unavailable responses are retried. See [RetryConfig].
+    fn with_retry_config(mut self, retry: RetryConfig) -> Self
+    where
+        Self: Sized,
+    {
+        self.api_base_config_mut().retry = retry;
+        self
+    }
+
+    /// Set the randomization factor of the retry backoff, between 0.0 and 1.0. Default is 0.5. Raise it to spread
+    /// out the retries of many concurrent clients. See [RetryConfig::randomization_factor].
+    fn with_retry_jitter(mut self, randomization_factor: f64) -> Self
+    where
+        Self: Sized,
+    {
+        self.api_base_config_mut().retry.randomization_factor = randomization_factor;
+        self
+    }
+
+    /// Run every request through a moderation check first, and fail flagged requests with
+    /// [crate::requests::completion::CompletionError::ModerationBlocked]. Only used by the API backends.
+    fn with_moderation(mut self, policy: ModerationPolicy) -> Self
+    where
+        Self: Sized,
+    {
+        self.api_base_config_mut().moderation = Some(policy);
+        self
+    }
+
+    /// Set the `User-Agent` header sent with every request, e.g. to tell your traffic apart in the provider's logs.
+    /// Initializing the backend fails if it isn't a valid header value.
+    fn with_user_agent<S: Into<String>>(mut self, user_agent: S) -> Self
+    where
+        Self: Sized,
+    {
+        self.api_base_config_mut().user_agent = Some(user_agent.into());
+        self
+    }
+
     /// Set the environment variable name for the API key. Default is set from the backend.
     fn with_api_key_env_var<S: Into<String>>(mut self, api_key_env_var: S) -> Self
     where
@@ -79,4 +177,10 @@ pub(crate) trait ApiConfigTrait {
     fn url(&self, path: &str) -> String;
 
     fn api_key(&self) -> &Option<Secret<String>>;
+
+    fn max_response_bytes(&self) -> Option<usize>;
+
+    fn retry_config(&self) -> &RetryConfig;
+
+    fn user_agent(&self) -> Option<&str>;
 }
@@ -20,6 +20,9 @@ pub enum ClientError {
     /// Error when a response cannot be deserialized into a Rust type
     #[error("failed to deserialize api response: {0}")]
     JSONDeserialize(serde_json::Error),
+    /// The response body is larger than [super::config::ApiConfig::max_response_bytes]
+    #[error("response body exceeds the limit of {limit} bytes")]
+    ResponseTooLarge { limit: usize },
     /// Error from client side validation
     /// or when builder fails to build request before making API call
     #[error("invalid args: {0}")]
@@ -50,5 +53,20 @@ pub struct ApiError {
     pub message: String,
     pub r#type: Option<String>,
     pub param: Option<String>,
+    /// A string for OpenAI compatible APIs, and the HTTP status code for llama.cpp.
+    #[serde(default, deserialize_with = "deserialize_code")]
     pub code: Option<String>,
 }
+
+fn deserialize_code<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(
+        match Option::<serde_json::Value>::deserialize(deserializer)? {
+            Some(serde_json::Value::String(code)) => Some(code),
+            Some(serde_json::Value::Null) | None => None,
+            Some(code) => Some(code.to_string()),
+        },
+    )
+}
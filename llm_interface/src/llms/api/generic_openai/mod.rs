@@ -1,6 +1,6 @@
 use super::{
     client::ApiClient,
-    config::{ApiConfig, ApiConfigTrait},
+    config::{ApiConfig, ApiConfigTrait, RetryConfig, DEFAULT_MAX_RESPONSE_BYTES},
     openai::completion::OpenAiCompletionRequest,
 };
 use crate::requests::completion::{
@@ -8,7 +8,7 @@ use crate::requests::completion::{
 };
 use llm_devices::logging::LoggingConfig;
 use llm_models::api_model::ApiLlmModel;
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
 use secrecy::{ExposeSecret, Secret};
 
 pub struct GenericApiBackend {
@@ -23,7 +23,7 @@ impl GenericApiBackend {
             config.api_config.api_key = Some(api_key);
         }
         Ok(Self {
-            client: ApiClient::new(config),
+            client: ApiClient::new(config)?,
             model,
         })
     }
@@ -33,14 +33,19 @@ impl GenericApiBackend {
     ) -> crate::Result<CompletionResponse, CompletionError> {
         match self
             .client
-            .post(
+            .post_with_raw(
                 &self.client.config.completion_path,
                 OpenAiCompletionRequest::new(request)?,
+                request.config.capture_raw,
             )
             .await
         {
             Err(e) => Err(CompletionError::ClientError(e)),
-            Ok(res) => Ok(CompletionResponse::new_from_openai(request, res)?),
+            Ok((res, raw)) => {
+                let mut response = CompletionResponse::new_from_openai(request, res)?;
+                response.raw_response = raw;
+                Ok(response)
+            }
         }
     }
 }
@@ -50,6 +55,10 @@ pub struct GenericApiConfig {
     pub api_config: ApiConfig,
     pub logging_config: LoggingConfig,
     pub completion_path: String,
+    /// The URL scheme used to reach the API. Defaults to "https".
+    pub scheme: String,
+    /// Extra headers sent with every request. Applied after the API key, so a custom `Authorization` header replaces the default bearer token.
+    pub custom_headers: Vec<(String, String)>,
 }
 
 impl Default for GenericApiConfig {
@@ -60,12 +69,18 @@ impl Default for GenericApiConfig {
                 port: None,
                 api_key: None,
                 api_key_env_var: Default::default(),
+                max_response_bytes: Some(DEFAULT_MAX_RESPONSE_BYTES),
+                retry: Default::default(),
+                moderation: None,
+                user_agent: None,
             },
             logging_config: LoggingConfig {
                 logger_name: "generic".to_string(),
                 ..Default::default()
             },
             completion_path: "/chat/completions".to_string(),
+            scheme: "https".to_string(),
+            custom_headers: Vec::new(),
         }
     }
 }
@@ -79,6 +94,21 @@ impl GenericApiConfig {
         self.completion_path = path.into();
         self
     }
+
+    pub fn scheme<S: Into<String>>(mut self, scheme: S) -> Self {
+        self.scheme = scheme.into();
+        self
+    }
+
+    /// Adds a header to every request, e.g. an `X-Api-Key` or routing header required by a gateway.
+    /// Setting the same key again replaces the previous value.
+    pub fn with_header<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        let key = key.into();
+        self.custom_headers
+            .retain(|(k, _)| !k.eq_ignore_ascii_case(&key));
+        self.custom_headers.push((key, value.into()));
+        self
+    }
 }
 
 impl ApiConfigTrait for GenericApiConfig {
@@ -93,19 +123,42 @@ impl ApiConfigTrait for GenericApiConfig {
                 crate::error!("Failed to create header value from authorization value");
             }
         }
+        for (key, value) in &self.custom_headers {
+            match (
+                HeaderName::from_bytes(key.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                (Ok(header_name), Ok(header_value)) => {
+                    headers.insert(header_name, header_value);
+                }
+                _ => crate::error!("Failed to create custom header {}", key),
+            }
+        }
 
         headers
     }
 
     fn url(&self, path: &str) -> String {
         if let Some(port) = &self.api_config.port {
-            format!("https://{}:{}{}", self.api_config.host, port, path)
+            format!("{}://{}:{}{}", self.scheme, self.api_config.host, port, path)
         } else {
-            format!("https://{}:{}", self.api_config.host, path)
+            format!("{}://{}{}", self.scheme, self.api_config.host, path)
         }
     }
 
     fn api_key(&self) -> &Option<Secret<String>> {
         &self.api_config.api_key
     }
+
+    fn max_response_bytes(&self) -> Option<usize> {
+        self.api_config.max_response_bytes
+    }
+
+    fn retry_config(&self) -> &RetryConfig {
+        &self.api_config.retry
+    }
+
+    fn user_agent(&self) -> Option<&str> {
+        self.api_config.user_agent.as_deref()
+    }
 }
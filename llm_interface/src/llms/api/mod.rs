@@ -3,5 +3,7 @@ pub mod client;
 pub mod config;
 pub mod error;
 pub mod generic_openai;
+pub mod moderation;
+pub mod ollama;
 pub mod openai;
 pub mod perplexity;
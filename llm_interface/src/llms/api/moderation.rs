@@ -0,0 +1,185 @@
+use super::{client::ApiClient, openai::OpenAiConfig};
+use crate::requests::completion::error::CompletionError;
+use llm_prompt::LlmPrompt;
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+};
+
+/// The default model of the OpenAI moderation endpoint.
+pub const OPENAI_MODERATION_MODEL: &str = "omni-moderation-latest";
+
+/// A local moderation classifier. See [ModerationPolicy::Classifier].
+pub type ModerationClassifier = Arc<dyn Fn(&str) -> Vec<String> + Send + Sync>;
+
+/// Checks the user messages of a request before it is sent to an API backend. Flagged content fails the request with
+/// [CompletionError::ModerationBlocked] without calling the model. Set it with
+/// [super::config::LlmApiConfigTrait::with_moderation].
+#[derive(Clone)]
+pub enum ModerationPolicy {
+    /// OpenAI's moderation endpoint. It has its own API key, so it can gate any API backend.
+    OpenAi(OpenAiModeration),
+    /// A local classifier. It returns the flagged categories of the text, and an empty list if the text is allowed.
+    Classifier(ModerationClassifier),
+}
+
+impl ModerationPolicy {
+    /// OpenAI's moderation endpoint with [OPENAI_MODERATION_MODEL], blocking everything it flags.
+    pub fn openai() -> Self {
+        Self::OpenAi(OpenAiModeration::default())
+    }
+
+    pub fn classifier<F>(classifier: F) -> Self
+    where
+        F: Fn(&str) -> Vec<String> + Send + Sync + 'static,
+    {
+        Self::Classifier(Arc::new(classifier))
+    }
+
+    /// Checks the user messages of the prompt.
+    pub(crate) async fn check_prompt(&self, prompt: &LlmPrompt) -> Result<(), CompletionError> {
+        let messages = prompt
+            .get_built_prompt_hashmap()
+            .map_err(|e| CompletionError::RequestBuilderError(e.to_string()))?;
+        let input = messages
+            .iter()
+            .filter(|message| message.get("role").is_some_and(|role| role == "user"))
+            .filter_map(|message| message.get("content").map(String::as_str))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        self.check(&input).await
+    }
+
+    /// Returns [CompletionError::ModerationBlocked] if `input` is flagged. Empty input is always allowed.
+    pub async fn check(&self, input: &str) -> Result<(), CompletionError> {
+        if input.trim().is_empty() {
+            return Ok(());
+        }
+        let categories = match self {
+            ModerationPolicy::OpenAi(moderation) => moderation.flagged_categories(input).await?,
+            ModerationPolicy::Classifier(classifier) => classifier(input),
+        };
+        if categories.is_empty() {
+            Ok(())
+        } else {
+            crate::info!("Request blocked by moderation: {categories:?}");
+            Err(CompletionError::ModerationBlocked { categories })
+        }
+    }
+}
+
+impl std::fmt::Debug for ModerationPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModerationPolicy::OpenAi(moderation) => {
+                f.debug_tuple("OpenAi").field(moderation).finish()
+            }
+            ModerationPolicy::Classifier(_) => f.write_str("Classifier"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct OpenAiModeration {
+    pub model: String,
+    /// Loaded from the `OPENAI_API_KEY` environment variable if `None`.
+    pub api_key: Option<Secret<String>>,
+    /// Only these categories block a request, e.g. `"violence"`. `None` blocks everything the endpoint flags.
+    pub blocked_categories: Option<Vec<String>>,
+    /// Created on the first check and shared by the clones of the policy.
+    client: Arc<OnceLock<ApiClient<OpenAiConfig>>>,
+}
+
+impl Default for OpenAiModeration {
+    fn default() -> Self {
+        Self {
+            model: OPENAI_MODERATION_MODEL.to_string(),
+            api_key: None,
+            blocked_categories: None,
+            client: Arc::new(OnceLock::new()),
+        }
+    }
+}
+
+impl OpenAiModeration {
+    fn client(&self) -> Result<&ApiClient<OpenAiConfig>, CompletionError> {
+        if let Some(client) = self.client.get() {
+            return Ok(client);
+        }
+        let mut config = OpenAiConfig::default();
+        config.api_config.api_key = Some(match &self.api_key {
+            Some(api_key) => api_key.clone(),
+            None => config
+                .api_config
+                .load_api_key()
+                .map_err(|e| CompletionError::RequestBuilderError(e.to_string()))?,
+        });
+        let client = ApiClient::new(config)
+            .map_err(|e| CompletionError::RequestBuilderError(e.to_string()))?;
+        Ok(self.client.get_or_init(|| client))
+    }
+
+    async fn flagged_categories(&self, input: &str) -> Result<Vec<String>, CompletionError> {
+        let (res, _): (OpenAiModerationResponse, _) = self
+            .client()?
+            .post_with_raw(
+                "/moderations",
+                OpenAiModerationRequest {
+                    model: &self.model,
+                    input,
+                },
+                false,
+            )
+            .await?;
+        Ok(res.flagged_categories(self.blocked_categories.as_deref()))
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct OpenAiModerationRequest<'a> {
+    model: &'a str,
+    input: &'a str,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct OpenAiModerationResponse {
+    pub id: String,
+    pub model: String,
+    pub results: Vec<OpenAiModerationResult>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct OpenAiModerationResult {
+    pub flagged: bool,
+    pub categories: HashMap<String, bool>,
+    #[serde(default)]
+    pub category_scores: HashMap<String, f32>,
+}
+
+impl OpenAiModerationResponse {
+    /// The sorted categories that block the request. With `blocked_categories`, only those categories count.
+    /// Without them, a flagged result blocks even if none of its categories are set.
+    pub fn flagged_categories(&self, blocked_categories: Option<&[String]>) -> Vec<String> {
+        let mut flagged: Vec<String> = Vec::new();
+        for result in &self.results {
+            let categories = result
+                .categories
+                .iter()
+                .filter(|(_, flagged)| **flagged)
+                .map(|(category, _)| category)
+                .filter(|category| blocked_categories.is_none_or(|b| b.contains(category)));
+            for category in categories {
+                if !flagged.contains(category) {
+                    flagged.push(category.clone());
+                }
+            }
+            if result.flagged && blocked_categories.is_none() && flagged.is_empty() {
+                flagged.push("flagged".to_string());
+            }
+        }
+        flagged.sort();
+        flagged
+    }
+}
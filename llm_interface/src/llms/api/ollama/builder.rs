@@ -0,0 +1,84 @@
+use crate::llms::{
+    api::{
+        config::{ApiConfig, LlmApiConfigTrait},
+        generic_openai::{GenericApiBackend, GenericApiConfig},
+    },
+    LlmBackend,
+};
+use llm_devices::logging::{LoggingConfig, LoggingConfigTrait};
+use llm_models::{
+    api_model::{perplexity::model_tokenizer, ApiLlmModel},
+    LlmModelBase,
+};
+// Everything here can be implemented for any struct.
+pub struct OllamaBackendBuilder {
+    pub config: GenericApiConfig,
+    pub model: ApiLlmModel,
+}
+
+impl Default for OllamaBackendBuilder {
+    fn default() -> Self {
+        let mut config = GenericApiConfig::default()
+            .scheme("http")
+            .completion_path("/v1/chat/completions");
+        config.api_config.host = "localhost".to_string();
+        config.api_config.port = Some("11434".to_string());
+        config.api_config.api_key_env_var = "OLLAMA_API_KEY".to_string();
+        config.logging_config.logger_name = "ollama".to_string();
+        Self {
+            config,
+            model: ollama_model("llama3"),
+        }
+    }
+}
+
+impl OllamaBackendBuilder {
+    pub fn init(self) -> crate::Result<std::sync::Arc<LlmBackend>> {
+        Ok(std::sync::Arc::new(LlmBackend::GenericApi(
+            GenericApiBackend::new(self.config, self.model)?,
+        )))
+    }
+
+    /// Set the model using the model tag served by Ollama, e.g. "llama3".
+    pub fn model_id_str(mut self, model_id: &str) -> Self {
+        self.model = ollama_model(model_id);
+        self
+    }
+
+    /// Adds a header to every request. See [GenericApiConfig::with_header].
+    pub fn with_header<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.config = self.config.with_header(key, value);
+        self
+    }
+}
+
+fn ollama_model(model_id: &str) -> ApiLlmModel {
+    ApiLlmModel {
+        model_base: LlmModelBase {
+            model_id: model_id.to_string(),
+            model_ctx_size: 8192,
+            inference_ctx_size: 4096,
+            tokenizer: model_tokenizer(model_id),
+        },
+        cost_per_m_in_tokens: 0.0,
+        cost_per_m_out_tokens: 0.0,
+        tokens_per_message: 3,
+        tokens_per_name: None,
+    }
+}
+
+impl LlmApiConfigTrait for OllamaBackendBuilder {
+    fn api_base_config_mut(&mut self) -> &mut ApiConfig {
+        &mut self.config.api_config
+    }
+
+    fn api_config(&self) -> &ApiConfig {
+        &self.config.api_config
+    }
+}
+
+impl LoggingConfigTrait for OllamaBackendBuilder {
+    fn logging_config_mut(&mut self) -> &mut LoggingConfig {
+        &mut self.config.logging_config
+    }
+}
@@ -21,6 +21,15 @@ impl Default for OpenAiBackendBuilder {
 }
 
 impl OpenAiBackendBuilder {
+    /// A builder for `model_id`. Unlike `default().model_id_str(model_id)`, this doesn't load the tokenizer of the
+    /// default model first.
+    pub fn with_model_id(model_id: &str) -> Self {
+        Self {
+            config: Default::default(),
+            model: ApiLlmModel::openai_model_from_model_id(model_id),
+        }
+    }
+
     pub fn init(self) -> crate::Result<std::sync::Arc<LlmBackend>> {
         Ok(std::sync::Arc::new(LlmBackend::OpenAi(OpenAiBackend::new(
             self.config,
@@ -1,4 +1,8 @@
 mod req;
 mod res;
-pub use req::OpenAiCompletionRequest;
-pub use res::OpenAiCompletionResponse;
+pub use req::{
+    OpenAiCompletionRequest, OpenAiFunction, OpenAiFunctionCall, OpenAiTool, OpenAiToolCall,
+    StreamOptions,
+};
+pub(crate) use res::finish_reason_from_openai;
+pub use res::{ChatChunkToolCall, OpenAiCompletionChunk, OpenAiCompletionResponse};
@@ -1,4 +1,8 @@
-use crate::requests::{completion::*, stop_sequence::StopSequences};
+use crate::requests::{
+    completion::*,
+    stop_sequence::StopSequences,
+    tool::{Tool, ToolCall, ToolTurn},
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -57,6 +61,38 @@ pub struct OpenAiCompletionRequest {
     /// min: 0.0, max: 1.0, default: None
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>,
+
+    /// A list of tools the model may call. Currently, only functions are supported as a tool.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<OpenAiTool>>,
+
+    /// Whether to enable parallel function calling during tool use. Only set when `tools` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parallel_tool_calls: Option<bool>,
+
+    /// If set, the completion is sent as server-sent events as it is generated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+
+    /// Options for streamed responses. Only set when `stream` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+
+    /// If set, the system makes a best effort to sample deterministically, so repeated requests with the same seed
+    /// and parameters return the same result.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+
+    /// A unique identifier representing your end-user, which can help OpenAI to monitor and detect abuse.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+#[derive(Clone, Serialize, Default, Debug, Deserialize, PartialEq)]
+pub struct StreamOptions {
+    /// If set, an extra chunk with the token usage of the whole request is streamed before the stream ends. Its
+    /// `choices` is empty.
+    pub include_usage: bool,
 }
 
 impl OpenAiCompletionRequest {
@@ -65,32 +101,67 @@ impl OpenAiCompletionRequest {
         match &req.prompt.get_built_prompt_hashmap() {
             Ok(prompt_message) => {
                 for m in prompt_message {
-                    messages.push(CompletionRequestMessage::new(m).unwrap());
+                    messages.push(CompletionRequestMessage::new(m)?);
                 }
             }
             Err(e) => return Err(CompletionError::RequestBuilderError(e.to_string())),
         }
+        for tool_turn in &req.tool_turns {
+            messages.extend(CompletionRequestMessage::from_tool_turn(tool_turn)?);
+        }
 
         Ok(OpenAiCompletionRequest {
             messages,
             model: req.backend.model_id().to_owned(),
             logit_bias: req.logit_bias.as_ref().and_then(|lb| lb.get_openai()),
             frequency_penalty: req.config.frequency_penalty,
-            logprobs: None,
-            top_logprobs: None,
+            logprobs: req.config.top_logprobs.map(|_| true),
+            top_logprobs: req.config.top_logprobs,
             max_tokens: req.config.actual_request_tokens,
             presence_penalty: Some(req.config.presence_penalty),
             stop: Stop::new(&req.stop_sequences)?,
             temperature: Some(req.config.temperature),
             top_p: req.config.top_p,
+            tools: req
+                .tools
+                .as_ref()
+                .map(|tools| tools.iter().map(OpenAiTool::from_tool).collect()),
+            parallel_tool_calls: None,
+            stream: None,
+            stream_options: None,
+            seed: req.config.seed,
+            user: req.config.end_user_id.clone(),
         })
     }
+
+    /// Creates a function calling request. The model may respond with any number of calls to the provided tools.
+    pub fn new_with_tools(
+        req: &CompletionRequest,
+        tools: Vec<OpenAiTool>,
+        parallel_tool_calls: bool,
+    ) -> crate::Result<Self, CompletionError> {
+        if tools.is_empty() {
+            return Err(CompletionError::RequestBuilderError(
+                "At least one tool is required for a function calling request".to_string(),
+            ));
+        }
+        let mut request = Self::new(req)?;
+        request.tools.get_or_insert_with(Vec::new).extend(tools);
+        request.parallel_tool_calls = Some(parallel_tool_calls);
+        Ok(request)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct CompletionRequestMessage {
     pub role: String,
-    pub content: String,
+    /// Optional for assistant messages that only contain `tool_calls`.
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<OpenAiToolCall>>,
+    /// Tool call that this message is responding to. Only used with the `tool` role.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
 }
 
 impl CompletionRequestMessage {
@@ -107,7 +178,9 @@ impl CompletionRequestMessage {
         match role.as_str() {
             "system" | "user" | "assistant" => Ok(CompletionRequestMessage {
                 role: role.to_string(),
-                content: content.to_string(),
+                content: Some(content.to_string()),
+                tool_calls: None,
+                tool_call_id: None,
             }),
             _ => Err(CompletionError::RequestBuilderError(format!(
                 "Role {} not supported",
@@ -115,6 +188,41 @@ impl CompletionRequestMessage {
             ))),
         }
     }
+
+    /// The assistant message with the model's tool calls followed by a `tool` message for each result.
+    pub fn from_tool_turn(tool_turn: &ToolTurn) -> crate::Result<Vec<Self>, CompletionError> {
+        let mut messages = vec![CompletionRequestMessage {
+            role: "assistant".to_string(),
+            content: None,
+            tool_calls: Some(
+                tool_turn
+                    .tool_calls
+                    .iter()
+                    .map(OpenAiToolCall::from_tool_call)
+                    .collect(),
+            ),
+            tool_call_id: None,
+        }];
+        for tool_result in &tool_turn.tool_results {
+            if !tool_turn
+                .tool_calls
+                .iter()
+                .any(|c| c.id == tool_result.tool_call_id)
+            {
+                return Err(CompletionError::RequestBuilderError(format!(
+                    "Tool result for unknown tool call id {}",
+                    tool_result.tool_call_id
+                )));
+            }
+            messages.push(CompletionRequestMessage {
+                role: "tool".to_string(),
+                content: Some(tool_result.content.clone()),
+                tool_calls: None,
+                tool_call_id: Some(tool_result.tool_call_id.clone()),
+            });
+        }
+        Ok(messages)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -135,3 +243,99 @@ impl Stop {
         }
     }
 }
+
+/// A tool the model may call.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OpenAiTool {
+    /// The type of the tool. Currently, only `function` is supported.
+    pub r#type: String,
+    pub function: OpenAiFunction,
+}
+
+impl OpenAiTool {
+    /// `parameters` is a JSON Schema object describing the arguments of the function.
+    pub fn function<S: Into<String>>(
+        name: S,
+        description: Option<String>,
+        parameters: serde_json::Value,
+    ) -> Self {
+        Self {
+            r#type: "function".to_string(),
+            function: OpenAiFunction {
+                name: name.into(),
+                description,
+                parameters: Some(parameters),
+            },
+        }
+    }
+
+    pub fn from_tool(tool: &Tool) -> Self {
+        Self::function(
+            tool.name.clone(),
+            tool.description.clone(),
+            tool.parameters.clone(),
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OpenAiFunction {
+    /// The name of the function to be called. Must be a-z, A-Z, 0-9, or contain underscores and dashes, with a maximum length of 64.
+    pub name: String,
+    /// A description of what the function does, used by the model to choose when and how to call the function.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The parameters the functions accepts, described as a JSON Schema object. Omitting `parameters` defines a function with an empty parameter list.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<serde_json::Value>,
+}
+
+/// A tool call as sent and received by the API.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OpenAiToolCall {
+    /// The ID of the tool call.
+    pub id: String,
+    /// The type of the tool. Currently, only `function` is supported.
+    pub r#type: String,
+    pub function: OpenAiFunctionCall,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OpenAiFunctionCall {
+    /// The name of the function to call.
+    pub name: String,
+    /// The arguments to call the function with, as generated by the model in JSON format.
+    /// Note that the model does not always generate valid JSON.
+    pub arguments: String,
+}
+
+impl OpenAiToolCall {
+    pub fn from_tool_call(tool_call: &ToolCall) -> Self {
+        Self {
+            id: tool_call.id.clone(),
+            r#type: "function".to_string(),
+            function: OpenAiFunctionCall {
+                name: tool_call.name.clone(),
+                arguments: tool_call.arguments.to_string(),
+            },
+        }
+    }
+
+    pub fn to_tool_call(&self) -> crate::Result<ToolCall, CompletionError> {
+        let arguments = if self.function.arguments.trim().is_empty() {
+            serde_json::Value::Object(Default::default())
+        } else {
+            serde_json::from_str(&self.function.arguments).map_err(|e| {
+                CompletionError::ToolCallParseError(format!(
+                    "Invalid arguments for tool call {}: {}",
+                    self.id, e
+                ))
+            })?
+        };
+        Ok(ToolCall {
+            id: self.id.clone(),
+            name: self.function.name.clone(),
+            arguments,
+        })
+    }
+}
@@ -1,4 +1,6 @@
+use super::req::{OpenAiFunctionCall, OpenAiToolCall};
 use crate::requests::completion::*;
+use crate::requests::res_components::{InferenceProbabilities, TopProbabilities};
 use serde::{Deserialize, Serialize};
 
 impl CompletionResponse {
@@ -6,45 +8,89 @@ impl CompletionResponse {
         req: &CompletionRequest,
         res: OpenAiCompletionResponse,
     ) -> Result<Self, CompletionError> {
-        let choice = if res.choices.is_empty() || res.choices[0].message.content.is_none() {
-            return Err(CompletionError::ReponseContentEmpty);
-        } else {
-            &res.choices[0]
-        };
-        let finish_reason = match choice.finish_reason {
-            Some(FinishReason::Stop) => CompletionFinishReason::Eos,
-            Some(FinishReason::Length) => CompletionFinishReason::StopLimit,
-            Some(FinishReason::ToolCalls) => {
-                return Err(CompletionError::StopReasonUnsupported(
-                    "FinishReason::ToolCalls is not supported".to_owned(),
-                ))
-            }
-            Some(FinishReason::ContentFilter) => {
-                return Err(CompletionError::StopReasonUnsupported(
-                    "FinishReason::ContentFilter is not supported".to_owned(),
-                ))
+        if let Some(refusal) = res
+            .choices
+            .first()
+            .and_then(|choice| choice.message.refusal.as_ref())
+        {
+            return Err(CompletionError::Refused(refusal.clone()));
+        }
+        let choice = match res.choices.first() {
+            Some(choice)
+                if choice.message.content.is_some() || choice.message.tool_calls.is_some() =>
+            {
+                choice
             }
-            Some(FinishReason::FunctionCall) => {
-                return Err(CompletionError::StopReasonUnsupported(
-                    "FinishReason::FunctionCall is not supported".to_owned(),
-                ))
-            }
-            None => CompletionFinishReason::Eos,
+            _ => return Err(CompletionError::ReponseContentEmpty),
         };
+        let tool_calls = match &choice.message.tool_calls {
+            Some(tool_calls) => Some(
+                tool_calls
+                    .iter()
+                    .map(|c| c.to_tool_call())
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            None => None,
+        };
+        let finish_reason = finish_reason_from_openai(
+            req,
+            choice.finish_reason,
+            choice.message.content.as_deref().unwrap_or_default(),
+        )?;
         Ok(Self {
             id: res.id.to_owned(),
             index: None,
-            content: choice.message.content.as_ref().unwrap().to_owned(),
+            content: choice.message.content.clone().unwrap_or_default(),
+            reasoning: None,
+            tool_calls,
             finish_reason,
-            completion_probabilities: None,
+            completion_probabilities: choice
+                .logprobs
+                .as_ref()
+                .and_then(|logprobs| logprobs.content.as_ref())
+                .map(|content| {
+                    content
+                        .iter()
+                        .map(|t| t.to_inference_probabilities())
+                        .collect()
+                }),
             truncated: false,
             generation_settings: GenerationSettings::new_from_openai(req, &res),
             timing_usage: TimingUsage::new_from_generic(req.start_time),
             token_usage: TokenUsage::new_from_generic(&res),
+            raw_response: None,
+            special_tokens: req.stop_sequences.to_vec(),
         })
     }
 }
 
+/// Maps OpenAI's finish reason, inferring a matched stop sequence from the content since OpenAI doesn't report it.
+pub(crate) fn finish_reason_from_openai(
+    req: &CompletionRequest,
+    finish_reason: Option<FinishReason>,
+    content: &str,
+) -> Result<CompletionFinishReason, CompletionError> {
+    Ok(match finish_reason {
+        Some(FinishReason::Stop) => match req.stop_sequences.parse_api_stop(content) {
+            Some(stop_sequence) => CompletionFinishReason::MatchingStoppingSequence(stop_sequence),
+            None => CompletionFinishReason::Eos,
+        },
+        Some(FinishReason::Length) => CompletionFinishReason::StopLimit,
+        Some(FinishReason::ToolCalls) => CompletionFinishReason::ToolCalls,
+        Some(FinishReason::ContentFilter) => {
+            return Err(CompletionError::StopReasonUnsupported(
+                "FinishReason::ContentFilter is not supported".to_owned(),
+            ))
+        }
+        Some(FinishReason::FunctionCall) => {
+            return Err(CompletionError::StopReasonUnsupported(
+                "FinishReason::FunctionCall is not supported".to_owned(),
+            ))
+        }
+        None => CompletionFinishReason::Eos,
+    })
+}
+
 /// Represents a chat completion response returned by model, based on the provided input.
 #[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
 pub struct OpenAiCompletionResponse {
@@ -73,6 +119,83 @@ pub struct ChatChoice {
     pub logprobs: Option<ChatChoiceLogprobs>,
 }
 
+/// A streamed chunk of a chat completion, sent when `stream` is set on the request.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct OpenAiCompletionChunk {
+    pub id: String,
+    pub choices: Vec<ChatChunkChoice>,
+    /// Only set on the last chunk, and only if `stream_options.include_usage` is set on the request.
+    #[serde(default)]
+    pub usage: Option<CompletionUsage>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct ChatChunkChoice {
+    pub delta: ChatChunkDelta,
+    /// Only set on the last chunk of the choice.
+    pub finish_reason: Option<FinishReason>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct ChatChunkDelta {
+    #[serde(default)]
+    pub content: Option<String>,
+    /// Part of the refusal message, streamed instead of the content.
+    #[serde(default)]
+    pub refusal: Option<String>,
+    /// Parts of the tool calls, see [ChatChunkToolCall::merge_into].
+    #[serde(default)]
+    pub tool_calls: Option<Vec<ChatChunkToolCall>>,
+}
+
+/// A part of a streamed tool call. The first part of a call has its id and name, and the arguments are streamed as
+/// fragments of their JSON in the following parts.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct ChatChunkToolCall {
+    /// The position of the call in the response's tool calls.
+    pub index: usize,
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub function: Option<ChatChunkFunctionCall>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct ChatChunkFunctionCall {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub arguments: Option<String>,
+}
+
+impl ChatChunkToolCall {
+    /// Adds this part to the call at its index in `tool_calls`.
+    pub fn merge_into(&self, tool_calls: &mut Vec<OpenAiToolCall>) {
+        while tool_calls.len() <= self.index {
+            tool_calls.push(OpenAiToolCall {
+                id: String::new(),
+                r#type: "function".to_string(),
+                function: OpenAiFunctionCall {
+                    name: String::new(),
+                    arguments: String::new(),
+                },
+            });
+        }
+        let tool_call = &mut tool_calls[self.index];
+        if let Some(id) = &self.id {
+            tool_call.id.push_str(id);
+        }
+        if let Some(function) = &self.function {
+            if let Some(name) = &function.name {
+                tool_call.function.name.push_str(name);
+            }
+            if let Some(arguments) = &function.arguments {
+                tool_call.function.arguments.push_str(arguments);
+            }
+        }
+    }
+}
+
 /// Usage statistics for the completion request.
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct CompletionUsage {
@@ -90,6 +213,13 @@ pub struct ChatCompletionResponseMessage {
     /// The contents of the message.
     pub content: Option<String>,
 
+    /// The tool calls generated by the model, such as function calls.
+    pub tool_calls: Option<Vec<OpenAiToolCall>>,
+
+    /// The refusal message of the model, sent instead of the content when it declines the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refusal: Option<String>,
+
     /// The role of the author of this message.
     pub role: Role,
 }
@@ -122,6 +252,22 @@ pub struct ChatCompletionTokenLogprob {
     pub top_logprobs: Vec<TopLogprobs>,
 }
 
+impl ChatCompletionTokenLogprob {
+    pub fn to_inference_probabilities(&self) -> InferenceProbabilities {
+        InferenceProbabilities {
+            content: Some(self.token.clone()),
+            top_probs: self
+                .top_logprobs
+                .iter()
+                .map(|t| TopProbabilities {
+                    token: t.token.clone(),
+                    prob: t.logprob,
+                })
+                .collect(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct TopLogprobs {
     /// The token.
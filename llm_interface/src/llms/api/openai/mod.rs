@@ -3,12 +3,19 @@ pub mod completion;
 
 use super::{
     client::ApiClient,
-    config::{ApiConfig, ApiConfigTrait},
+    config::{ApiConfig, ApiConfigTrait, RetryConfig, DEFAULT_MAX_RESPONSE_BYTES},
 };
 use crate::requests::completion::{
-    error::CompletionError, request::CompletionRequest, response::CompletionResponse,
+    error::CompletionError,
+    request::CompletionRequest,
+    response::{CompletionFinishReason, CompletionResponse},
+    stream::StreamControl,
+};
+use crate::requests::tool::ToolCall;
+use completion::{
+    finish_reason_from_openai, OpenAiCompletionChunk, OpenAiCompletionRequest, OpenAiTool,
+    OpenAiToolCall, StreamOptions,
 };
-use completion::OpenAiCompletionRequest;
 use llm_devices::logging::LoggingConfig;
 use llm_models::api_model::ApiLlmModel;
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
@@ -31,26 +38,166 @@ impl OpenAiBackend {
         config.logging_config.load_logger()?;
         config.api_config.api_key = Some(config.api_config.load_api_key()?);
         Ok(Self {
-            client: ApiClient::new(config),
+            client: ApiClient::new(config)?,
             model,
         })
     }
 
+    /// Creates the backend for `model_id` without first building a default model, so only the tokenizer of the
+    /// requested model is loaded.
+    pub fn with_model_id(config: OpenAiConfig, model_id: &str) -> crate::Result<Self> {
+        Self::new(config, ApiLlmModel::openai_model_from_model_id(model_id))
+    }
+
     pub(crate) async fn completion_request(
         &self,
         request: &CompletionRequest,
     ) -> crate::Result<CompletionResponse, CompletionError> {
         match self
             .client
-            .post("/chat/completions", OpenAiCompletionRequest::new(request)?)
+            .post_with_raw(
+                "/chat/completions",
+                OpenAiCompletionRequest::new(request)?,
+                request.config.capture_raw,
+            )
+            .await
+        {
+            Err(e) => Err(CompletionError::ClientError(e)),
+            Ok((res, raw)) => {
+                let mut response = CompletionResponse::new_from_openai(request, res)?;
+                response.raw_response = raw;
+                Ok(response)
+            }
+        }
+    }
+
+    /// Streams the completion to `on_delta`. Matches [crate::llms::local::llama_cpp::LlamaCppBackend::completion_request_streaming].
+    /// Tool calls aren't streamed to `on_delta`, they are assembled into [CompletionResponse::tool_calls].
+    pub(crate) async fn completion_request_streaming(
+        &self,
+        request: &CompletionRequest,
+        on_delta: &mut (dyn FnMut(&str) -> StreamControl + Send),
+    ) -> crate::Result<CompletionResponse, CompletionError> {
+        let mut openai_request = OpenAiCompletionRequest::new(request)?;
+        openai_request.stream = Some(true);
+        openai_request.stream_options = Some(StreamOptions {
+            include_usage: true,
+        });
+        let mut events = self
+            .client
+            .post_stream::<_, OpenAiCompletionChunk>("/chat/completions", openai_request)
+            .await?;
+        let mut content = String::new();
+        let mut refusal = String::new();
+        let mut tool_calls: Vec<OpenAiToolCall> = Vec::new();
+        let mut finished: Option<(String, CompletionFinishReason)> = None;
+        while let Some(chunk) = events.next_event().await {
+            let chunk = chunk?;
+            // The usage chunk follows the chunk with the finish reason, and is the last one.
+            if let Some(usage) = chunk.usage {
+                let Some((id, finish_reason)) = finished else {
+                    break;
+                };
+                let mut response =
+                    CompletionResponse::new_from_stream(request, &id, content, finish_reason);
+                response.tool_calls = stream_tool_calls(&tool_calls)?;
+                response.token_usage.prompt_tokens = usage.prompt_tokens;
+                response.token_usage.completion_tokens = usage.completion_tokens;
+                response.token_usage.total_tokens = usage.total_tokens;
+                return Ok(response);
+            }
+            let Some(choice) = chunk.choices.first() else {
+                continue;
+            };
+            if let Some(delta) = choice.delta.refusal.as_deref() {
+                refusal.push_str(delta);
+            }
+            for delta in choice.delta.tool_calls.iter().flatten() {
+                delta.merge_into(&mut tool_calls);
+            }
+            if let Some(delta) = choice.delta.content.as_deref().filter(|d| !d.is_empty()) {
+                content.push_str(delta);
+                if on_delta(delta) == StreamControl::Stop {
+                    // Dropping the stream closes the connection, which stops generation.
+                    return Ok(CompletionResponse::new_from_stream(
+                        request,
+                        &chunk.id,
+                        content,
+                        CompletionFinishReason::StreamStopped,
+                    ));
+                }
+            }
+            if choice.finish_reason.is_some() {
+                if !refusal.is_empty() {
+                    return Err(CompletionError::Refused(refusal));
+                }
+                let finish_reason =
+                    finish_reason_from_openai(request, choice.finish_reason, &content)?;
+                finished = Some((chunk.id, finish_reason));
+            }
+        }
+        // Servers that ignore `stream_options` end the stream without a usage chunk, so the usage is estimated.
+        if let Some((id, finish_reason)) = finished {
+            let mut response =
+                CompletionResponse::new_from_stream(request, &id, content, finish_reason);
+            response.tool_calls = stream_tool_calls(&tool_calls)?;
+            return Ok(response);
+        }
+        Err(CompletionError::ClientError(
+            crate::llms::api::error::ClientError::GenericError {
+                message: "OpenAI stream ended before the completion finished".to_string(),
+            },
+        ))
+    }
+
+    /// Sends the request with the given tool definitions. If the model decides to call tools, the response has
+    /// [crate::requests::completion::CompletionFinishReason::ToolCalls] and the parsed calls in
+    /// [CompletionResponse::tool_calls]. Run the tools, then pass the calls and their outputs to
+    /// [CompletionRequest::submit_tool_results] and call this again for the follow-up turn.
+    pub async fn function_calling_request(
+        &self,
+        request: &CompletionRequest,
+        tools: Vec<OpenAiTool>,
+        parallel_tool_calls: bool,
+    ) -> crate::Result<CompletionResponse, CompletionError> {
+        request.backend.validate_prompt(&request.prompt)?;
+        if let Some(moderation) = &self.client.config.api_config.moderation {
+            moderation.check_prompt(&request.prompt).await?;
+        }
+        match self
+            .client
+            .post_with_raw(
+                "/chat/completions",
+                OpenAiCompletionRequest::new_with_tools(request, tools, parallel_tool_calls)?,
+                request.config.capture_raw,
+            )
             .await
         {
             Err(e) => Err(CompletionError::ClientError(e)),
-            Ok(res) => Ok(CompletionResponse::new_from_openai(request, res)?),
+            Ok((res, raw)) => {
+                let mut response = CompletionResponse::new_from_openai(request, res)?;
+                response.raw_response = raw;
+                Ok(response)
+            }
         }
     }
 }
 
+/// The tool calls assembled from a stream, or `None` if the model didn't call any tools.
+fn stream_tool_calls(
+    tool_calls: &[OpenAiToolCall],
+) -> crate::Result<Option<Vec<ToolCall>>, CompletionError> {
+    if tool_calls.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(
+        tool_calls
+            .iter()
+            .map(|c| c.to_tool_call())
+            .collect::<Result<Vec<_>, _>>()?,
+    ))
+}
+
 #[derive(Clone, Debug)]
 pub struct OpenAiConfig {
     pub api_config: ApiConfig,
@@ -67,6 +214,10 @@ impl Default for OpenAiConfig {
                 port: None,
                 api_key: None,
                 api_key_env_var: "OPENAI_API_KEY".to_string(),
+                max_response_bytes: Some(DEFAULT_MAX_RESPONSE_BYTES),
+                retry: Default::default(),
+                moderation: None,
+                user_agent: None,
             },
             logging_config: LoggingConfig {
                 logger_name: "openai".to_string(),
@@ -134,4 +285,16 @@ impl ApiConfigTrait for OpenAiConfig {
     fn api_key(&self) -> &Option<Secret<String>> {
         &self.api_config.api_key
     }
+
+    fn max_response_bytes(&self) -> Option<usize> {
+        self.api_config.max_response_bytes
+    }
+
+    fn retry_config(&self) -> &RetryConfig {
+        &self.api_config.retry
+    }
+
+    fn user_agent(&self) -> Option<&str> {
+        self.api_config.user_agent.as_deref()
+    }
 }
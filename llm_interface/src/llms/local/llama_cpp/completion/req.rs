@@ -46,27 +46,46 @@ pub struct LlamaCppCompletionRequest {
     /// min: 0.0, max: 1.0, default: None
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>,
+    /// Limits sampling to the `top_k` most likely tokens. 0 disables the limit. Default: 40
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<i32>,
+    /// Removes tokens less likely than `min_p` times the most likely token. 0.0 disables it. Default: 0.05
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_p: Option<f32>,
+    /// Control the repetition of token sequences in the generated text. 1.0 is disabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f32>,
+    /// Last n tokens to consider for penalizing repetition. 0 is disabled and -1 is ctx-size.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_last_n: Option<i32>,
+    /// If greater than 0, the response includes the probabilities of the top n tokens for each generated token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n_probs: Option<u32>,
+    /// The random number generator seed. The server picks a random seed if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
 }
 
 impl LlamaCppCompletionRequest {
     pub fn new(req: &CompletionRequest) -> crate::Result<Self, CompletionError> {
-        let cache_prompt = if req.config.cache_prompt {
-            Some(true)
-        } else {
-            None
-        };
-        Ok(Self {
-            prompt: req
-                .prompt
-                .get_built_prompt_as_tokens()
-                .map_err(|e| CompletionError::RequestBuilderError(e.to_string()))?,
-            prompt_string: Some(
+        let (prompt, prompt_string) = match &req.prompt_tokens {
+            Some(tokens) => (tokens.clone(), None),
+            None => (
                 req.prompt
-                    .get_built_prompt_string()
+                    .get_built_prompt_as_tokens()
                     .map_err(|e| CompletionError::RequestBuilderError(e.to_string()))?,
+                Some(
+                    req.prompt
+                        .get_built_prompt_string()
+                        .map_err(|e| CompletionError::RequestBuilderError(e.to_string()))?,
+                ),
             ),
+        };
+        Ok(Self {
+            prompt,
+            prompt_string,
             grammar: req.grammar_string.clone(),
-            cache_prompt,
+            cache_prompt: req.config.cache_prompt,
             logit_bias: req.logit_bias.as_ref().and_then(|lb| lb.get_llama_cpp()),
             frequency_penalty: req.config.frequency_penalty,
             stream: None,
@@ -75,6 +94,12 @@ impl LlamaCppCompletionRequest {
             stop: Some(req.stop_sequences.to_vec()),
             temperature: Some(req.config.temperature),
             top_p: req.config.top_p,
+            top_k: None,
+            min_p: None,
+            repeat_penalty: req.config.repeat_penalty,
+            repeat_last_n: req.config.repeat_last_n,
+            n_probs: req.config.top_logprobs.map(u32::from),
+            seed: req.config.seed,
         })
     }
 }
@@ -1,4 +1,7 @@
-use crate::requests::completion::*;
+use crate::requests::{
+    completion::*,
+    res_components::{InferenceProbabilities, TopProbabilities},
+};
 use serde::{Deserialize, Serialize};
 
 impl CompletionResponse {
@@ -30,12 +33,25 @@ impl CompletionResponse {
             id: "llama_cpp".to_owned(),
             index: None,
             content: res.content.to_owned(),
+            reasoning: None,
+            tool_calls: None,
             finish_reason,
-            completion_probabilities: None,
+            completion_probabilities: res.completion_probabilities.as_ref().map(|probabilities| {
+                probabilities
+                    .iter()
+                    .map(LlamaCompletionProbabilities::to_inference_probabilities)
+                    .collect()
+            }),
             truncated: res.truncated,
             generation_settings: GenerationSettings::new_from_llama(&res),
             timing_usage: TimingUsage::new_from_llama(&res, req.start_time),
             token_usage: TokenUsage::new_from_llama(&res),
+            raw_response: None,
+            special_tokens: [req.backend.bos_token(), req.backend.eos_token()]
+                .into_iter()
+                .map(str::to_owned)
+                .chain(req.stop_sequences.to_vec())
+                .collect(),
         })
     }
 }
@@ -55,6 +71,40 @@ pub struct LlamaCppCompletionResponse {
     pub tokens_cached: u16,
     pub tokens_evaluated: u16,
     pub truncated: bool,
+    /// Only present if `n_probs` was set in the request.
+    #[serde(default)]
+    pub completion_probabilities: Option<Vec<LlamaCompletionProbabilities>>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
+pub struct LlamaCompletionProbabilities {
+    /// The generated token.
+    pub content: String,
+    /// The most likely tokens at this position, most likely first.
+    pub probs: Vec<LlamaTokenProbability>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
+pub struct LlamaTokenProbability {
+    pub tok_str: String,
+    /// The probability, not the log probability.
+    pub prob: f32,
+}
+
+impl LlamaCompletionProbabilities {
+    pub fn to_inference_probabilities(&self) -> InferenceProbabilities {
+        InferenceProbabilities {
+            content: Some(self.content.clone()),
+            top_probs: self
+                .probs
+                .iter()
+                .map(|p| TopProbabilities {
+                    token: p.tok_str.clone(),
+                    prob: p.prob.ln(),
+                })
+                .collect(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
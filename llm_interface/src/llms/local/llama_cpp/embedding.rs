@@ -0,0 +1,68 @@
+use crate::requests::{completion::CompletionError, embedding::normalize_embedding};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Default, Debug, PartialEq)]
+pub struct LlamaCppEmbeddingRequest {
+    /// The texts to embed.
+    pub content: Vec<String>,
+    /// The normalization the server applies: -1 for none, 2 for euclidean. Servers older than this parameter always
+    /// normalize with the euclidean norm.
+    pub embd_normalize: i32,
+}
+
+/// The `/embedding` response. Its shape changed between llama.cpp versions, so every known one is accepted.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum LlamaCppEmbeddingResponse {
+    Results { results: Vec<LlamaCppEmbedding> },
+    List(Vec<LlamaCppEmbedding>),
+    Single(LlamaCppEmbedding),
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct LlamaCppEmbedding {
+    #[serde(default)]
+    pub index: usize,
+    pub embedding: LlamaCppEmbeddingValue,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum LlamaCppEmbeddingValue {
+    Pooled(Vec<f32>),
+    /// Newer servers wrap a pooled embedding in a list, which holds one vector per token without pooling.
+    Nested(Vec<Vec<f32>>),
+}
+
+impl LlamaCppEmbeddingResponse {
+    /// The pooled embedding of each input, in the order of the request, normalized if `normalize` is set.
+    pub fn into_embeddings(self, normalize: bool) -> Result<Vec<Vec<f32>>, CompletionError> {
+        let mut results = match self {
+            LlamaCppEmbeddingResponse::Results { results } => results,
+            LlamaCppEmbeddingResponse::List(results) => results,
+            LlamaCppEmbeddingResponse::Single(result) => vec![result],
+        };
+        results.sort_by_key(|result| result.index);
+        results
+            .into_iter()
+            .map(|result| {
+                let mut embedding = match result.embedding {
+                    LlamaCppEmbeddingValue::Pooled(embedding) => embedding,
+                    LlamaCppEmbeddingValue::Nested(mut embeddings) if embeddings.len() == 1 => {
+                        embeddings.remove(0)
+                    }
+                    LlamaCppEmbeddingValue::Nested(_) => {
+                        return Err(CompletionError::LocalClientError(
+                            "LlamaCppServer returned token embeddings instead of a pooled embedding"
+                                .to_string(),
+                        ))
+                    }
+                };
+                if normalize {
+                    normalize_embedding(&mut embedding);
+                }
+                Ok(embedding)
+            })
+            .collect()
+    }
+}
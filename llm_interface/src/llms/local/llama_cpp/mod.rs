@@ -1,15 +1,25 @@
 pub mod builder;
 pub mod completion;
+pub mod embedding;
 pub mod server;
+pub mod supervisor;
 
-use super::LocalLlmConfig;
+use super::{request_queue::RequestQueue, LocalLlmConfig};
 use crate::{
     llms::api::{
         client::ApiClient,
-        config::{ApiConfig, ApiConfigTrait},
+        config::{ApiConfig, ApiConfigTrait, RetryConfig, DEFAULT_MAX_RESPONSE_BYTES},
+        error::ClientError,
     },
-    requests::completion::{
-        error::CompletionError, request::CompletionRequest, response::CompletionResponse,
+    requests::{
+        completion::{
+            error::CompletionError,
+            request::CompletionRequest,
+            response::{CompletionFinishReason, CompletionResponse},
+            stream::StreamControl,
+        },
+        embedding::EmbeddingPooling,
+        score::TextScore,
     },
 };
 use completion::LlamaCppCompletionRequest;
@@ -17,15 +27,22 @@ use llm_devices::logging::LoggingConfig;
 use llm_models::local_model::{gguf::GgufLoader, LocalLlmModel};
 use reqwest::header::{HeaderMap, AUTHORIZATION};
 use secrecy::{ExposeSecret, Secret};
-use server::LlamaCppServer;
+use server::{DraftModelConfig, LlamaCppServer};
+use supervisor::{CircuitState, ServerSupervisor, SupervisorConfig};
 
 pub const LLAMA_CPP_API_HOST: &str = "localhost";
 pub const LLAMA_CPP_API_PORT: &str = "8080";
+/// The number of most likely tokens requested per position when scoring text.
+const SCORE_TOP_PROBS: u32 = 100;
 
 pub struct LlamaCppBackend {
     pub model: LocalLlmModel,
-    pub server: LlamaCppServer,
+    /// Shared with other backends if [LlamaCppConfig::shared_server] is set.
+    pub server: std::sync::Arc<LlamaCppServer>,
     pub(crate) client: ApiClient<LlamaCppConfig>,
+    /// Shared with the other backends of a shared server, like [Self::server].
+    pub(crate) queue: std::sync::Arc<RequestQueue>,
+    pub(crate) supervisor: Option<std::sync::Arc<ServerSupervisor>>,
 }
 
 impl LlamaCppBackend {
@@ -42,13 +59,31 @@ impl LlamaCppBackend {
         let model = local_config.load_model(llm_loader)?;
 
         let mut server = LlamaCppServer::new(
-            local_config.device_config,
+            &model.model_base.model_id,
+            local_config,
             &config.api_config.host,
             &config.api_config.port,
-            local_config.inference_ctx_size,
+            config.startup_timeout,
+            config.draft_model.clone(),
+            config.parallel_slots,
         )?;
-        let client: ApiClient<LlamaCppConfig> = ApiClient::new(config);
-        server.start_server(&client).await?;
+        server.status_check_timeout = config.status_check_timeout;
+        server.status_check_interval = config.status_check_interval;
+        server.embeddings = config.embeddings;
+        let supervisor = config.supervisor.clone().map(ServerSupervisor::new);
+        let queue = RequestQueue::new(config.max_concurrent_requests, config.max_queue_depth);
+        let (shared, client) = if config.shared_server {
+            server::shared::shared_server(server, queue, supervisor, config).await?
+        } else {
+            let client: ApiClient<LlamaCppConfig> = ApiClient::new(config)?;
+            server.start_server(&client).await?;
+            let shared = server::shared::SharedServer {
+                server: std::sync::Arc::new(server),
+                queue: std::sync::Arc::new(queue),
+                supervisor: supervisor.map(std::sync::Arc::new),
+            };
+            (shared, client)
+        };
         println!(
             "{} with model: {}",
             colorful::Colorful::bold(colorful::Colorful::color(
@@ -59,37 +94,381 @@ impl LlamaCppBackend {
         );
         Ok(Self {
             client,
-            server,
+            server: shared.server,
             model,
+            queue: shared.queue,
+            supervisor: shared.supervisor,
         })
     }
 
+    /// llama.cpp serves either embeddings or completions, depending on whether the server was started with
+    /// `--embeddings`. Fails early with the fix, instead of with the server's error.
+    fn check_server_mode(&self, embeddings: bool) -> crate::Result<(), CompletionError> {
+        let model_id = &self.model.model_base.model_id;
+        match (self.server.embeddings.is_some(), embeddings) {
+            (false, true) => Err(CompletionError::RequestBuilderError(format!(
+                "The LlamaCppServer for model {model_id} was started for text generation, so it can't return \
+                embeddings. Start the backend with `LlamaCppConfig::with_embeddings` or the `embeddings` builder \
+                method. To generate text with the same model, use a second backend on another port."
+            ))),
+            (true, false) => Err(CompletionError::RequestBuilderError(format!(
+                "The LlamaCppServer for model {model_id} was started for embeddings, so it can't generate text. \
+                Start a second backend without `embeddings` on another port for text generation."
+            ))),
+            _ => Ok(()),
+        }
+    }
+
     pub(crate) async fn completion_request(
         &self,
         request: &CompletionRequest,
     ) -> crate::Result<CompletionResponse, CompletionError> {
-        match self
+        self.check_server_mode(false)?;
+        if let Some(supervisor) = &self.supervisor {
+            supervisor
+                .check()
+                .map_err(CompletionError::LocalClientError)?;
+        }
+        let result = {
+            let _permit = self.queue.acquire().await?;
+            self.client
+                .post_with_raw(
+                    "/completion",
+                    LlamaCppCompletionRequest::new(request)?,
+                    request.config.capture_raw,
+                )
+                .await
+        };
+        // The permit is released first, so a restart doesn't hold a slot while waiting for its backoff.
+        if let Some(supervisor) = &self.supervisor {
+            match &result {
+                Ok(_) => supervisor.record_success(),
+                Err(e) => {
+                    supervisor
+                        .record_failure(e, &self.server, &self.client)
+                        .await
+                }
+            }
+        }
+        match result {
+            Err(e) => Err(CompletionError::ClientError(e)),
+            Ok((res, raw)) => {
+                let mut response = CompletionResponse::new_from_llama(request, res)?;
+                response.raw_response = raw;
+                Ok(response)
+            }
+        }
+    }
+
+    /// Like [Self::completion_request], but calls `on_delta` with each piece of generated text as the server
+    /// streams it. The returned response has the full content, or the content streamed before
+    /// [crate::requests::req_components::RequestConfig::stream_timeout] passed.
+    pub(crate) async fn completion_request_streaming(
+        &self,
+        request: &CompletionRequest,
+        on_delta: &mut (dyn FnMut(&str) -> StreamControl + Send),
+    ) -> crate::Result<CompletionResponse, CompletionError> {
+        self.check_server_mode(false)?;
+        let _permit = self.queue.acquire().await?;
+        let mut llama_request = LlamaCppCompletionRequest::new(request)?;
+        llama_request.stream = Some(true);
+        let mut events = self
             .client
-            .post("/completion", LlamaCppCompletionRequest::new(request)?)
-            .await
+            .post_stream::<_, serde_json::Value>("/completion", llama_request)
+            .await?;
+        let deadline = request
+            .config
+            .stream_timeout
+            .map(|timeout| tokio::time::Instant::now() + timeout);
+        let mut content = String::new();
+        loop {
+            let next_event = match deadline {
+                Some(deadline) => {
+                    match tokio::time::timeout_at(deadline, events.next_event()).await {
+                        Ok(next_event) => next_event,
+                        // Dropping the stream closes the connection, which stops the server's generation.
+                        Err(_) => {
+                            return Ok(CompletionResponse::new_from_stream(
+                                request,
+                                "llama_cpp",
+                                content,
+                                CompletionFinishReason::TimedOut,
+                            ))
+                        }
+                    }
+                }
+                None => events.next_event().await,
+            };
+            let Some(event) = next_event else {
+                break;
+            };
+            let event = event?;
+            if let Some(delta) = event.get("content").and_then(|c| c.as_str()) {
+                if !delta.is_empty() {
+                    content.push_str(delta);
+                    if on_delta(delta) == StreamControl::Stop {
+                        // Dropping the stream closes the connection, which stops the server's generation.
+                        return Ok(CompletionResponse::new_from_stream(
+                            request,
+                            "llama_cpp",
+                            content,
+                            CompletionFinishReason::StreamStopped,
+                        ));
+                    }
+                }
+            }
+            // The final event carries the stop reason, timings and usage of the whole completion.
+            if event.get("stop").and_then(|s| s.as_bool()) == Some(true) {
+                let mut res: completion::LlamaCppCompletionResponse = serde_json::from_value(event)
+                    .map_err(|e| CompletionError::LocalClientError(e.to_string()))?;
+                res.content = content;
+                return CompletionResponse::new_from_llama(request, res);
+            }
+        }
+        Err(CompletionError::LocalClientError(
+            "LlamaCppBackend stream ended before the completion stopped".to_string(),
+        ))
+    }
+
+    /// Scores how likely the model finds `continuation` as the text following `prompt`. The texts are scored as raw
+    /// text, without the chat template.
+    ///
+    /// llama.cpp doesn't echo the prompt's log probabilities, so each continuation token is scored with a one token
+    /// completion of the text before it. The server's prompt cache keeps this close to a single pass over the text.
+    /// The prompt and continuation are tokenized together, so the continuation is scored as the tokens the model
+    /// would see after the prompt.
+    pub async fn score_text(
+        &self,
+        prompt: &str,
+        continuation: &str,
+    ) -> crate::Result<TextScore, CompletionError> {
+        self.check_server_mode(false)?;
+        let tokenizer = &self.model.model_base.tokenizer;
+        let text_tokens = tokenizer.tokenize(format!("{prompt}{continuation}"));
+        // A token spanning the end of the prompt and the start of the continuation is scored with the continuation.
+        let prompt_len = tokenizer
+            .tokenize(prompt)
+            .iter()
+            .zip(&text_tokens)
+            .take_while(|(prompt_token, text_token)| prompt_token == text_token)
+            .count();
+        let continuation_tokens = text_tokens[prompt_len..].to_vec();
+        if continuation_tokens.is_empty() {
+            return Err(CompletionError::RequestBuilderError(
+                "Continuation to score is empty.".to_string(),
+            ));
+        }
+        let mut tokens = Vec::new();
+        if let Ok(bos_token) = tokenizer.try_into_single_token(&self.model.chat_template.bos_token)
         {
-            Err(e) => Err(CompletionError::ClientError(e)),
-            Ok(res) => Ok(CompletionResponse::new_from_llama(request, res)?),
+            tokens.push(bos_token);
         }
+        tokens.extend_from_slice(&text_tokens[..prompt_len]);
+
+        let _permit = self.queue.acquire().await?;
+        let mut score = TextScore {
+            token_logprobs: Vec::with_capacity(continuation_tokens.len()),
+            tokens_outside_top_probs: 0,
+        };
+        for token in continuation_tokens {
+            let request = LlamaCppCompletionRequest {
+                prompt: tokens.clone(),
+                cache_prompt: Some(true),
+                n_predict: Some(1),
+                // Without truncation the returned candidates carry the probabilities of the full softmax. Greedy
+                // sampling would return 1.0 for the most likely token and 0.0 for the others.
+                temperature: Some(1.0),
+                top_k: Some(0),
+                top_p: Some(1.0),
+                min_p: Some(0.0),
+                n_probs: Some(SCORE_TOP_PROBS),
+                ..Default::default()
+            };
+            let (res, _) = self
+                .client
+                .post_with_raw::<_, completion::LlamaCppCompletionResponse>(
+                    "/completion",
+                    request,
+                    false,
+                )
+                .await
+                .map_err(CompletionError::ClientError)?;
+            let probs = res
+                .completion_probabilities
+                .and_then(|p| p.into_iter().next())
+                .map(|p| p.probs)
+                .unwrap_or_default();
+            // The server only returns the text of the candidates, so they are matched to the token by id.
+            let top_probs: Vec<(Option<u32>, f32)> = probs
+                .iter()
+                .map(|p| (tokenizer.token_to_id(&p.tok_str), p.prob))
+                .collect();
+            score.push_token(token, &top_probs)?;
+            tokens.push(token);
+        }
+        Ok(score)
+    }
+
+    /// The pooled embedding of each input, in order. The server must be started with
+    /// [LlamaCppConfig::with_embeddings], which sets the pooling. Otherwise this fails with an error explaining how
+    /// to start it.
+    ///
+    /// With `normalize`, each embedding has a euclidean length of 1. Otherwise the server's raw embeddings are
+    /// returned, if it supports turning off normalization.
+    pub async fn embeddings(
+        &self,
+        inputs: &[&str],
+        normalize: bool,
+    ) -> crate::Result<Vec<Vec<f32>>, CompletionError> {
+        self.check_server_mode(true)?;
+        if inputs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let request = embedding::LlamaCppEmbeddingRequest {
+            content: inputs.iter().map(|input| input.to_string()).collect(),
+            embd_normalize: if normalize { 2 } else { -1 },
+        };
+        let _permit = self.queue.acquire().await?;
+        let (res, _) = self
+            .client
+            .post_with_raw::<_, embedding::LlamaCppEmbeddingResponse>("/embedding", request, false)
+            .await
+            .map_err(|e| match e {
+                // A server started outside this backend, e.g. by another process, may run in the other mode.
+                ClientError::ApiError(e) if e.r#type.as_deref() == Some("not_supported_error") => {
+                    CompletionError::RequestBuilderError(format!(
+                        "The LlamaCppServer at {} doesn't serve embeddings: {}. It was started without \
+                        `--embeddings` outside this backend. Stop it, so the backend can start its own.",
+                        self.server.server_http_path, e.message
+                    ))
+                }
+                e => CompletionError::ClientError(e),
+            })?;
+        let embeddings = res.into_embeddings(normalize)?;
+        if embeddings.len() != inputs.len() {
+            return Err(CompletionError::LocalClientError(format!(
+                "LlamaCppServer returned {} embeddings for {} inputs",
+                embeddings.len(),
+                inputs.len()
+            )));
+        }
+        Ok(embeddings)
+    }
+
+    /// The context size, chat template and build info the running server reports.
+    pub async fn server_props(&self) -> crate::Result<server::props::LlamaCppProps> {
+        self.server.server_props(&self.client).await
+    }
+
+    /// The server's slots and whether each is running a request.
+    pub async fn slots(&self) -> crate::Result<Vec<server::slots::LlamaCppSlot>> {
+        Ok(server::slots::slots_request(&self.client).await?)
+    }
+
+    /// The number of slots not running a request.
+    pub async fn available_slots(&self) -> crate::Result<usize> {
+        Ok(server::slots::available_slots(&self.slots().await?))
+    }
+
+    /// Lines written by the llama.cpp server from now on, tagged with the model. The lines are also logged with `tracing` at debug level.
+    pub fn server_logs(&self) -> std::sync::mpsc::Receiver<server::output::ServerLogLine> {
+        self.server.output.subscribe()
+    }
+
+    /// The number of requests waiting in the client for a free server slot. See [LlamaCppConfig::max_concurrent_requests].
+    pub fn queue_depth(&self) -> usize {
+        self.queue.queue_depth()
+    }
+
+    /// The supervisor's circuit breaker state, or `None` if the server is not supervised. See [SupervisorConfig].
+    pub fn circuit_state(&self) -> Option<CircuitState> {
+        self.supervisor.as_deref().map(ServerSupervisor::state)
+    }
+
+    /// The number of times the supervisor restarted the server.
+    pub fn restart_count(&self) -> u32 {
+        self.supervisor
+            .as_deref()
+            .map_or(0, ServerSupervisor::restarts)
+    }
+
+    /// See [LlamaCppServer::gpu_utilization].
+    pub fn gpu_utilization(&self) -> Vec<llm_devices::devices::gpu::GpuDevice> {
+        self.server.gpu_utilization()
+    }
+
+    pub(crate) async fn wait_until_ready(&self) -> crate::Result<()> {
+        self.server.wait_until_ready(&self.client).await
     }
 
     pub(crate) fn shutdown(&self) {
+        // The server shuts down once the last backend sharing it is dropped.
+        let users = std::sync::Arc::strong_count(&self.server);
+        if users > 1 {
+            crate::info!(
+                "Not shutting down LlamaCppServer, it is shared with {} other backends",
+                users - 1
+            );
+            return;
+        }
         match self.server.shutdown() {
             Ok(_) => (),
             Err(e) => crate::error!("Failed to shutdown server: {}", e),
         }
     }
+
+    /// Stops accepting requests, waits up to `timeout` for the queued and in-flight requests to finish, and then
+    /// shuts the server down. Requests made after this is called fail with [CompletionError::ShuttingDown].
+    pub(crate) async fn shutdown_graceful(&self, timeout: std::time::Duration) {
+        if !self.queue.drain(timeout).await {
+            crate::warn!(
+                "Shutting down LlamaCppServer with {} requests still in flight after {:?}",
+                self.queue.in_flight(),
+                timeout
+            );
+        }
+        self.shutdown();
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct LlamaCppConfig {
     pub api_config: ApiConfig,
     pub logging_config: LoggingConfig,
+    /// How long to wait for the server to load the model on start up. Defaults to 30 seconds.
+    pub startup_timeout: std::time::Duration,
+    /// Enables speculative decoding with a draft model. See [DraftModelConfig].
+    pub draft_model: Option<DraftModelConfig>,
+    /// --parallel
+    /// The number of slots the server is started with, each running one request at a time. The context size is
+    /// split between the slots, and rounded down to a multiple of the slot count. Defaults to `None` (llama.cpp's
+    /// default).
+    pub parallel_slots: Option<u32>,
+    /// The number of requests sent to the server at once. Further requests wait in the client in FIFO order.
+    /// Defaults to 1, matching a server started without `--parallel`.
+    pub max_concurrent_requests: usize,
+    /// The number of requests allowed to wait for a slot. Requests beyond this fail with
+    /// [CompletionError::QueueFull]. Defaults to `None` (unbounded).
+    pub max_queue_depth: Option<usize>,
+    /// Restarts the server after repeated request failures. Defaults to `None` (no restarts).
+    pub supervisor: Option<SupervisorConfig>,
+    /// Reuses a server started by another backend in this process for the same model and device config, instead of
+    /// spawning a second one. The reused server keeps its own address, request queue and supervisor, so the
+    /// concurrency and supervisor settings of the reusing backend are ignored. Both backends must set this. Defaults
+    /// to false.
+    pub shared_server: bool,
+    /// How long to keep checking whether a server is already running before starting one. Defaults to 650
+    /// milliseconds.
+    ///
+    /// A running server that doesn't answer within the timeout is treated as stopped and replaced, so raise it on a
+    /// heavily loaded machine.
+    pub status_check_timeout: std::time::Duration,
+    /// The wait between the status checks. Defaults to 200 milliseconds.
+    pub status_check_interval: std::time::Duration,
+    /// --embeddings, --pooling
+    /// Starts the server for [LlamaCppBackend::embeddings] with the given pooling. Defaults to `None`
+    /// (text generation).
+    pub embeddings: Option<EmbeddingPooling>,
 }
 
 impl Default for LlamaCppConfig {
@@ -100,11 +479,27 @@ impl Default for LlamaCppConfig {
                 port: Some(LLAMA_CPP_API_PORT.to_string()),
                 api_key: None,
                 api_key_env_var: "LLAMA_API_KEY".to_string(),
+                max_response_bytes: Some(DEFAULT_MAX_RESPONSE_BYTES),
+                retry: Default::default(),
+                moderation: None,
+                user_agent: None,
             },
             logging_config: LoggingConfig {
                 logger_name: "llama_cpp".to_string(),
                 ..Default::default()
             },
+            startup_timeout: std::time::Duration::from_secs(server::START_UP_CHECK_TIME_S),
+            draft_model: None,
+            parallel_slots: None,
+            max_concurrent_requests: 1,
+            max_queue_depth: None,
+            supervisor: None,
+            shared_server: false,
+            status_check_timeout: std::time::Duration::from_millis(server::STATUS_CHECK_TIME_MS),
+            status_check_interval: std::time::Duration::from_millis(
+                server::STATUS_RETRY_TIMEOUT_MS,
+            ),
+            embeddings: None,
         }
     }
 }
@@ -113,6 +508,59 @@ impl LlamaCppConfig {
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Large models on slow disks can take longer than the default to load.
+    pub fn with_startup_timeout(mut self, startup_timeout: std::time::Duration) -> Self {
+        self.startup_timeout = startup_timeout;
+        self
+    }
+
+    pub fn with_draft_model(mut self, draft_model: DraftModelConfig) -> Self {
+        self.draft_model = Some(draft_model);
+        self
+    }
+
+    /// Starts the server with `parallel_slots` slots, and sends as many requests to it at once.
+    pub fn with_parallel_slots(mut self, parallel_slots: u32) -> Self {
+        self.parallel_slots = Some(parallel_slots);
+        self.max_concurrent_requests = parallel_slots as usize;
+        self
+    }
+
+    pub fn with_max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = max_concurrent_requests;
+        self
+    }
+
+    pub fn with_max_queue_depth(mut self, max_queue_depth: usize) -> Self {
+        self.max_queue_depth = Some(max_queue_depth);
+        self
+    }
+
+    pub fn with_supervisor(mut self, supervisor: SupervisorConfig) -> Self {
+        self.supervisor = Some(supervisor);
+        self
+    }
+
+    pub fn with_shared_server(mut self) -> Self {
+        self.shared_server = true;
+        self
+    }
+
+    pub fn with_status_check(
+        mut self,
+        timeout: std::time::Duration,
+        interval: std::time::Duration,
+    ) -> Self {
+        self.status_check_timeout = timeout;
+        self.status_check_interval = interval;
+        self
+    }
+
+    pub fn with_embeddings(mut self, pooling: EmbeddingPooling) -> Self {
+        self.embeddings = Some(pooling);
+        self
+    }
 }
 
 impl ApiConfigTrait for LlamaCppConfig {
@@ -143,4 +591,16 @@ impl ApiConfigTrait for LlamaCppConfig {
     fn api_key(&self) -> &Option<Secret<String>> {
         &self.api_config.api_key
     }
+
+    fn max_response_bytes(&self) -> Option<usize> {
+        self.api_config.max_response_bytes
+    }
+
+    fn retry_config(&self) -> &RetryConfig {
+        &self.api_config.retry
+    }
+
+    fn user_agent(&self) -> Option<&str> {
+        self.api_config.user_agent.as_deref()
+    }
 }
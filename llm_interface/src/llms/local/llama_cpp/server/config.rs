@@ -1,5 +1,6 @@
+use crate::llms::local::{KvCacheType, LocalLlmConfig, RopeScaling, RopeScalingType};
 use llm_devices::devices::cpu::CpuConfig;
-use llm_devices::devices::DeviceConfig;
+use llm_devices::devices::{gpu::GpuDevice, DeviceConfig};
 
 pub struct LlamaCppServerConfig {
     /// -t, --threads
@@ -32,6 +33,26 @@ pub struct LlamaCppServerConfig {
     /// -nkvo, --no-kv-offload 	disable KV offload
     /// Used when no GPUs are available
     no_kv_offload: Option<NoKvOffload>,
+    /// -fa, --flash-attn
+    /// Enable Flash Attention (default: disabled)
+    flash_attn: Option<FlashAttn>,
+    /// -ctk, --cache-type-k
+    /// KV cache data type for K (default: f16)
+    cache_type_k: Option<CacheTypeK>,
+    /// -ctv, --cache-type-v
+    /// KV cache data type for V (default: f16)
+    cache_type_v: Option<CacheTypeV>,
+    /// --no-mmap
+    /// Do not memory-map the model (slower load but may reduce pageouts if not using mlock)
+    no_mmap: Option<NoMmap>,
+    /// --mlock
+    /// Force system to keep model in RAM rather than swapping or compressing
+    mlock: Option<Mlock>,
+    /// --rope-scaling, --rope-freq-scale, --yarn-orig-ctx
+    /// Extends the context beyond the length the model was trained for
+    rope_scaling: Option<RopeScalingArgs>,
+    /// The layers allocated to each GPU when the config was built.
+    pub(crate) gpu_devices: Vec<GpuDevice>,
 }
 
 impl Default for LlamaCppServerConfig {
@@ -44,17 +65,54 @@ impl Default for LlamaCppServerConfig {
             tensor_split: None,
             main_gpu: None,
             no_kv_offload: None,
+            flash_attn: None,
+            cache_type_k: None,
+            cache_type_v: None,
+            no_mmap: None,
+            mlock: None,
+            rope_scaling: None,
+            gpu_devices: Vec::new(),
         }
     }
 }
 
 impl LlamaCppServerConfig {
-    pub fn new(device_config: &DeviceConfig) -> crate::Result<Self> {
-        match device_config.gpu_count() {
-            0 => Self::new_only_cpu(device_config),
-            1 => Self::new_single_gpu(device_config),
-            _ => Self::new_multiple_gpu(device_config),
+    pub fn new(local_config: &LocalLlmConfig) -> crate::Result<Self> {
+        let device_config = &local_config.device_config;
+        let mut server_config = match device_config.gpu_count() {
+            0 => Self::new_only_cpu(device_config)?,
+            1 => Self::new_single_gpu(device_config)?,
+            _ => Self::new_multiple_gpu(device_config)?,
+        };
+        let mut flash_attention = local_config.flash_attention;
+        if let Some(cache_type_v) = local_config.cache_type_v {
+            if cache_type_v.is_quantized() && !flash_attention {
+                if device_config.error_on_config_issue {
+                    crate::bail!(
+                        "A quantized V cache ({}) requires flash attention",
+                        cache_type_v.as_str()
+                    );
+                }
+                crate::warn!(
+                    "A quantized V cache ({}) requires flash attention. Enabling flash attention.",
+                    cache_type_v.as_str()
+                );
+                flash_attention = true;
+            }
+        }
+        if flash_attention {
+            server_config.flash_attn = Some(FlashAttn);
+        }
+        server_config.cache_type_k = local_config.cache_type_k.map(CacheTypeK);
+        server_config.cache_type_v = local_config.cache_type_v.map(CacheTypeV);
+        if !local_config.use_mmap {
+            server_config.no_mmap = Some(NoMmap);
+        }
+        if local_config.use_mlock {
+            server_config.mlock = Some(Mlock);
         }
+        server_config.rope_scaling = local_config.rope_scaling.map(RopeScalingArgs);
+        Ok(server_config)
     }
 
     fn new_only_cpu(device_config: &DeviceConfig) -> crate::Result<Self> {
@@ -71,10 +129,12 @@ impl LlamaCppServerConfig {
         let gpu_devices = device_config.allocate_layers_to_gpus(1, 1)?;
         let layer_count = gpu_devices.iter().map(|d| d.allocated_layers).sum();
         Ok(Self {
+            threads: Threads::new_if_set(&device_config.cpu_config),
             threads_batch: Some(ThreadsBatch::new_from_cpu_config(&device_config.cpu_config)),
             split_mode: Some(SplitMode::None),
             n_gpu_layers: Some(NGpuLayers(layer_count)),
             main_gpu: Some(MainGpu(device_config.main_gpu()?)),
+            gpu_devices,
             ..Default::default()
         })
     }
@@ -83,10 +143,12 @@ impl LlamaCppServerConfig {
         let gpu_devices = device_config.allocate_layers_to_gpus(1, 1)?;
         let layer_count = gpu_devices.iter().map(|d| d.allocated_layers).sum();
         Ok(Self {
+            threads: Threads::new_if_set(&device_config.cpu_config),
             threads_batch: Some(ThreadsBatch::new_from_cpu_config(&device_config.cpu_config)),
             split_mode: Some(SplitMode::Layer),
             main_gpu: Some(MainGpu(device_config.main_gpu()?)),
             n_gpu_layers: Some(NGpuLayers(layer_count)),
+            gpu_devices,
             ..Default::default()
         })
     }
@@ -115,6 +177,93 @@ impl LlamaCppServerConfig {
         if let Some(no_kv_offload) = &self.no_kv_offload {
             command.arg(no_kv_offload.as_arg());
         }
+        if let Some(flash_attn) = &self.flash_attn {
+            command.arg(flash_attn.as_arg());
+        }
+        if let Some(cache_type_k) = &self.cache_type_k {
+            command.args(cache_type_k.as_arg());
+        }
+        if let Some(cache_type_v) = &self.cache_type_v {
+            command.args(cache_type_v.as_arg());
+        }
+        if let Some(no_mmap) = &self.no_mmap {
+            command.arg(no_mmap.as_arg());
+        }
+        if let Some(mlock) = &self.mlock {
+            command.arg(mlock.as_arg());
+        }
+        if let Some(rope_scaling) = &self.rope_scaling {
+            command.args(rope_scaling.as_arg());
+        }
+    }
+}
+
+/// A smaller model llama.cpp uses to draft tokens for speculative decoding. The main model verifies the drafted
+/// tokens in a single pass, which lowers latency when the output is predictable.
+///
+/// The draft model must share the main model's vocabulary. Its VRAM is not accounted for when layers of the main
+/// model are allocated to the GPUs, so leave room for it.
+#[derive(Clone, Debug, Default)]
+pub struct DraftModelConfig {
+    /// --model-draft
+    /// The local path to the draft model's GGUF file.
+    pub local_model_path: String,
+    /// --draft
+    /// The number of tokens to draft per step. Defaults to llama.cpp's default of 5.
+    pub draft_tokens: Option<u32>,
+    /// -ngld, --gpu-layers-draft
+    /// The number of the draft model's layers to store in VRAM. `Some(0)` keeps the draft model on the CPU.
+    /// Defaults to all layers when the main model uses the GPU, and none otherwise.
+    pub gpu_layers: Option<u64>,
+}
+
+impl DraftModelConfig {
+    /// llama.cpp clamps the layer count to the model's, so this offloads every layer.
+    const ALL_LAYERS: u64 = 999;
+
+    pub fn new<S: Into<String>>(local_model_path: S) -> Self {
+        Self {
+            local_model_path: local_model_path.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_draft_tokens(mut self, draft_tokens: u32) -> Self {
+        self.draft_tokens = Some(draft_tokens);
+        self
+    }
+
+    pub fn with_gpu_layers(mut self, gpu_layers: u64) -> Self {
+        self.gpu_layers = Some(gpu_layers);
+        self
+    }
+
+    /// Keeps the draft model on the CPU, leaving the VRAM to the main model.
+    pub fn on_cpu(self) -> Self {
+        self.with_gpu_layers(0)
+    }
+
+    pub(crate) fn validate(&self) -> crate::Result<()> {
+        super::check_model_file(&self.local_model_path)?;
+        if self.draft_tokens == Some(0) {
+            crate::bail!("The number of draft tokens must be greater than 0");
+        }
+        Ok(())
+    }
+
+    pub(crate) fn populate_args(&self, command: &mut std::process::Command, use_gpu: bool) {
+        command.arg("--model-draft").arg(&self.local_model_path);
+        if let Some(draft_tokens) = self.draft_tokens {
+            command.arg("--draft").arg(draft_tokens.to_string());
+        }
+        let gpu_layers = match self.gpu_layers {
+            Some(gpu_layers) => gpu_layers,
+            None if use_gpu => Self::ALL_LAYERS,
+            None => 0,
+        };
+        command
+            .arg("--gpu-layers-draft")
+            .arg(gpu_layers.to_string());
     }
 }
 
@@ -123,6 +272,10 @@ impl Threads {
     fn new_from_cpu_config(cpu_config: &CpuConfig) -> Self {
         Self(cpu_config.thread_count_or_default())
     }
+    /// Only an explicit thread count, so with GPU offload the server's default is kept unless it was overridden.
+    fn new_if_set(cpu_config: &CpuConfig) -> Option<Self> {
+        cpu_config.threads.map(Self)
+    }
     fn as_arg(&self) -> [String; 2] {
         ["--threads".to_string(), self.0.to_string()]
     }
@@ -192,3 +345,63 @@ impl NoKvOffload {
         format!("--no-kv-offload")
     }
 }
+
+pub(crate) struct FlashAttn;
+
+impl FlashAttn {
+    fn as_arg(&self) -> String {
+        "--flash-attn".to_string()
+    }
+}
+
+pub(crate) struct CacheTypeK(pub KvCacheType);
+
+impl CacheTypeK {
+    fn as_arg(&self) -> [String; 2] {
+        ["--cache-type-k".to_string(), self.0.as_str().to_string()]
+    }
+}
+
+pub(crate) struct CacheTypeV(pub KvCacheType);
+
+impl CacheTypeV {
+    fn as_arg(&self) -> [String; 2] {
+        ["--cache-type-v".to_string(), self.0.as_str().to_string()]
+    }
+}
+
+pub(crate) struct NoMmap;
+
+impl NoMmap {
+    fn as_arg(&self) -> String {
+        "--no-mmap".to_string()
+    }
+}
+
+pub(crate) struct Mlock;
+
+impl Mlock {
+    fn as_arg(&self) -> String {
+        "--mlock".to_string()
+    }
+}
+
+pub(crate) struct RopeScalingArgs(pub RopeScaling);
+
+impl RopeScalingArgs {
+    fn as_arg(&self) -> Vec<String> {
+        let mut args = vec![
+            "--rope-scaling".to_string(),
+            self.0.scaling_type.as_str().to_string(),
+            "--rope-freq-scale".to_string(),
+            (1.0 / self.0.factor).to_string(),
+        ];
+        if let (RopeScalingType::Yarn, Some(original_ctx_size)) =
+            (self.0.scaling_type, self.0.original_ctx_size)
+        {
+            args.push("--yarn-orig-ctx".to_string());
+            args.push(original_ctx_size.to_string());
+        }
+        args
+    }
+}
@@ -1,39 +1,113 @@
 pub mod config;
 pub mod health;
 pub mod models;
+pub mod output;
+pub mod props;
+pub(crate) mod shared;
+pub mod slots;
 pub mod status;
 
 use std::process::Command;
 
-use llm_devices::{build::get_target_directory, devices::DeviceConfig};
+use llm_devices::{
+    build::get_target_directory,
+    devices::{gpu::GpuDevice, DeviceConfig},
+};
 
-use crate::llms::{api::client::ApiClient, local::llama_cpp::LlamaCppConfig};
+use crate::{
+    llms::{
+        api::client::ApiClient,
+        local::{llama_cpp::LlamaCppConfig, LocalLlmConfig},
+    },
+    requests::embedding::EmbeddingPooling,
+};
 
+pub use config::DraftModelConfig;
 use config::LlamaCppServerConfig;
-use status::{server_status, ServerStatus};
+use health::{health_request, HealthStatus};
+use output::{ServerLogStream, ServerOutput};
+use props::{props_request, LlamaCppProps};
+use shared::SharedServerKey;
+use status::{server_status, ConnectionFailure, ServerStatus};
 
-const STATUS_CHECK_TIME_MS: u64 = 650;
-const STATUS_RETRY_TIMEOUT_MS: u64 = 200;
-const START_UP_CHECK_TIME_S: u64 = 30;
+pub(crate) const STATUS_CHECK_TIME_MS: u64 = 650;
+pub(crate) const STATUS_RETRY_TIMEOUT_MS: u64 = 200;
+pub(crate) const START_UP_CHECK_TIME_S: u64 = 30;
 const START_UP_RETRY_TIME_S: u64 = 5;
 
+/// Why [LlamaCppServer] failed to start. Returned inside the [crate::Error] from
+/// [crate::llms::local::llama_cpp::LlamaCppBackend::new], so callers can `downcast_ref` it to react to a failed
+/// model load, e.g. by retrying with a smaller model.
+#[derive(thiserror::Error, Debug)]
+pub enum ServerStartupError {
+    #[error("Model file not found or not readable: {0}")]
+    ModelNotFound(String),
+    #[error("Failed to spawn llama-server: {0}")]
+    Spawn(String),
+    #[error("llama-server exited during startup with {status}.\n{diagnostics}")]
+    Exited { status: String, diagnostics: String },
+    #[error("Failed to start LlamaCppServer: not reachable at {address} after {timeout:?}: {reason}.\n{diagnostics}")]
+    Timeout {
+        address: String,
+        timeout: std::time::Duration,
+        reason: ConnectionFailure,
+        diagnostics: String,
+    },
+    #[error("Failed to start LlamaCppServer with the requested model. Found model: {found}")]
+    WrongModel { found: String },
+    #[error(
+        "LlamaCppServer is running with a context size of {actual}, but {requested} was requested"
+    )]
+    ContextSizeMismatch { requested: u64, actual: u64 },
+    #[error("Failed to start LlamaCppServer: {message}\n{diagnostics}")]
+    Failed {
+        message: String,
+        diagnostics: String,
+    },
+}
+
 pub struct LlamaCppServer {
     pub device_config: DeviceConfig,
     pub(crate) server_config: LlamaCppServerConfig,
-    pub server_process: Option<std::process::Child>,
+    pub server_process: std::sync::Mutex<Option<std::process::Child>>,
     pub host: String,
     pub server_http_path: String,
     pub port: Option<String>,
+    /// The context size the server is started with, summed over its slots. Rounded down to a multiple of
+    /// [Self::parallel_slots] so every slot gets the same whole number of tokens.
     pub inference_ctx_size: u64,
+    /// How long to wait for the server to load the model and report healthy.
+    pub startup_timeout: std::time::Duration,
+    /// The server's stdout and stderr.
+    pub output: ServerOutput,
+    /// Enables speculative decoding with a draft model.
+    pub draft_model: Option<DraftModelConfig>,
+    /// The number of requests the server runs at once. `None` starts the server with llama.cpp's default.
+    pub parallel_slots: Option<u32>,
+    /// How long to keep checking whether a server is already running before starting one. A busy server that doesn't
+    /// answer in time is restarted.
+    pub status_check_timeout: std::time::Duration,
+    /// The wait between the checks within [Self::status_check_timeout].
+    pub status_check_interval: std::time::Duration,
+    /// Serves embeddings with this pooling instead of generating text.
+    pub embeddings: Option<EmbeddingPooling>,
+    /// Appended to the server command. See [LocalLlmConfig::extra_server_args].
+    pub extra_server_args: Vec<String>,
 }
 
 impl LlamaCppServer {
     pub fn new(
-        device_config: DeviceConfig,
+        model_id: &str,
+        local_config: LocalLlmConfig,
         host: &str,
         port: &Option<String>,
-        inference_ctx_size: u64,
+        startup_timeout: std::time::Duration,
+        draft_model: Option<DraftModelConfig>,
+        parallel_slots: Option<u32>,
     ) -> crate::Result<Self> {
+        if let Some(draft_model) = &draft_model {
+            draft_model.validate()?;
+        }
         let server_http_path = if let Some(port) = port {
             format!("{}:{}", &host, port)
         } else {
@@ -41,31 +115,58 @@ impl LlamaCppServer {
         };
 
         Ok(Self {
-            server_process: None,
-            server_config: LlamaCppServerConfig::new(&device_config)?,
+            server_process: std::sync::Mutex::new(None),
+            server_config: LlamaCppServerConfig::new(&local_config)?,
             server_http_path,
             host: host.to_owned(),
             port: port.as_deref().map(|p| p.to_owned()),
-            inference_ctx_size,
-            device_config,
+            inference_ctx_size: match parallel_slots {
+                Some(slots) if slots > 1 => {
+                    local_config.inference_ctx_size / u64::from(slots) * u64::from(slots)
+                }
+                _ => local_config.inference_ctx_size,
+            },
+            startup_timeout,
+            output: ServerOutput::new(model_id),
+            draft_model,
+            parallel_slots,
+            status_check_timeout: std::time::Duration::from_millis(STATUS_CHECK_TIME_MS),
+            status_check_interval: std::time::Duration::from_millis(STATUS_RETRY_TIMEOUT_MS),
+            embeddings: None,
+            extra_server_args: local_config.extra_server_args,
+            device_config: local_config.device_config,
         })
     }
 
     pub(crate) async fn start_server(
-        &mut self,
+        &self,
         client: &ApiClient<LlamaCppConfig>,
     ) -> crate::Result<ServerStatus> {
         match server_status(
             &self.device_config.local_model_path,
             &self.server_http_path,
-            std::time::Duration::from_millis(STATUS_CHECK_TIME_MS),
-            std::time::Duration::from_millis(STATUS_RETRY_TIMEOUT_MS),
+            self.status_check_timeout,
+            self.status_check_interval,
             client,
         )
         .await?
         {
-            ServerStatus::RunningRequested => return Ok(ServerStatus::RunningRequested),
-            ServerStatus::Offline => (),
+            ServerStatus::RunningRequested => match self.verify_props(client).await {
+                Ok(()) => return Ok(ServerStatus::RunningRequested),
+                Err(e) => {
+                    crate::info!("Restarting the running LlamaCppServer: {}", e);
+                    let model_path = &self.device_config.local_model_path;
+                    if let Err(e) = kill_server_from_model(model_path) {
+                        crate::error!(
+                            "Failed to kill LlamaCppServer with model ID: {} {}",
+                            model_path,
+                            e
+                        );
+                        kill_all_servers()?;
+                    }
+                }
+            },
+            ServerStatus::Offline(_) => (),
             ServerStatus::RunningModel(model_id) => match kill_server_from_model(&model_id) {
                 Ok(_) => (),
                 Err(e) => {
@@ -79,46 +180,48 @@ impl LlamaCppServer {
             },
         };
 
-        let original = if !self.device_config.use_gpu {
-            let original = std::env::var("CUDA_VISIBLE_DEVICES").ok();
-            std::env::set_var("CUDA_VISIBLE_DEVICES", "");
-            original
-        } else {
-            None
-        };
-
-        self.server_process = Some(self.start_server_backend()?);
+        let mut process = self.start_server_backend()?;
 
-        match server_status(
-            &self.device_config.local_model_path,
-            &self.server_http_path,
-            std::time::Duration::from_secs(START_UP_CHECK_TIME_S),
-            std::time::Duration::from_secs(START_UP_RETRY_TIME_S),
-            client,
-        )
-        .await?
-        {
-            ServerStatus::RunningRequested => {
-                if !self.device_config.use_gpu {
-                    match original {
-                        Some(value) => std::env::set_var("CUDA_VISIBLE_DEVICES", value),
-                        None => std::env::remove_var("CUDA_VISIBLE_DEVICES"),
-                    }
+        // Stop waiting as soon as the process exits instead of polling until the timeout.
+        let status = tokio::select! {
+            status = server_status(
+                &self.device_config.local_model_path,
+                &self.server_http_path,
+                self.startup_timeout,
+                std::time::Duration::from_secs(START_UP_RETRY_TIME_S),
+                client,
+            ) => status,
+            exit_status = wait_for_exit(&mut process) => {
+                *self.server_process() = Some(process);
+                return Err(ServerStartupError::Exited {
+                    status: exit_status,
+                    diagnostics: self.output.diagnostics(),
                 }
-                crate::trace!(
-                    "Started LlamaCppServer with process PID: {}",
-                    self.server_process
-                        .as_ref()
-                        .expect("LlamaCppServer process not created")
-                        .id()
-                );
-                Ok(ServerStatus::RunningRequested)
+                .into());
             }
-            ServerStatus::Offline => {
+        };
+        crate::trace!("Started LlamaCppServer with process PID: {}", process.id());
+        *self.server_process() = Some(process);
+        match status {
+            Ok(ServerStatus::RunningRequested) => match self.verify_props(client).await {
+                Ok(()) => Ok(ServerStatus::RunningRequested),
+                Err(e) => {
+                    self.shutdown()?;
+                    Err(e.into())
+                }
+            },
+            Ok(ServerStatus::Offline(reason)) => {
+                let diagnostics = self.startup_diagnostics();
                 self.shutdown()?;
-                crate::bail!("Failed to start LlamaCppServer");
+                Err(ServerStartupError::Timeout {
+                    address: self.server_http_path.clone(),
+                    timeout: self.startup_timeout,
+                    reason,
+                    diagnostics,
+                }
+                .into())
             }
-            ServerStatus::RunningModel(model_id) => {
+            Ok(ServerStatus::RunningModel(model_id)) => {
                 match kill_server_from_model(&model_id) {
                     Ok(_) => (),
                     Err(e) => {
@@ -130,46 +233,198 @@ impl LlamaCppServer {
                         kill_all_servers()?;
                     }
                 };
-                crate::bail!("Failed to start LlamaCppServer with correct model.");
+                Err(ServerStartupError::WrongModel { found: model_id }.into())
+            }
+            Err(e) => {
+                let diagnostics = self.startup_diagnostics();
+                self.shutdown()?;
+                match e.downcast::<ConnectionFailure>() {
+                    Ok(reason) => Err(ServerStartupError::Timeout {
+                        address: self.server_http_path.clone(),
+                        timeout: self.startup_timeout,
+                        reason,
+                        diagnostics,
+                    }
+                    .into()),
+                    Err(e) => Err(ServerStartupError::Failed {
+                        message: e.to_string(),
+                        diagnostics,
+                    }
+                    .into()),
+                }
+            }
+        }
+    }
+
+    /// The settings reported by the running server's `/props` endpoint.
+    pub(crate) async fn server_props(
+        &self,
+        client: &ApiClient<LlamaCppConfig>,
+    ) -> crate::Result<LlamaCppProps> {
+        props_request(client).await.map_err(|e| {
+            crate::anyhow!("Props request for {} failed: {}", self.server_http_path, e)
+        })
+    }
+
+    /// Checks that the running server uses at least the requested context size. Servers without the `/props` endpoint, or
+    /// that don't report a context size, are not checked.
+    async fn verify_props(
+        &self,
+        client: &ApiClient<LlamaCppConfig>,
+    ) -> Result<(), ServerStartupError> {
+        let props = match self.server_props(client).await {
+            Ok(props) => props,
+            Err(e) => {
+                crate::warn!("Skipping the LlamaCppServer settings check: {}", e);
+                return Ok(());
+            }
+        };
+        crate::trace!("LlamaCppServer props: {:?}", props);
+        props.check_context_size(self.inference_ctx_size)
+    }
+
+    /// The exit status, if the process already exited, and the tail of its output.
+    fn startup_diagnostics(&self) -> String {
+        let exit_status = match self.server_process().as_mut().map(|p| p.try_wait()) {
+            Some(Ok(Some(status))) => format!("llama-server exited with {}.\n", status),
+            _ => String::new(),
+        };
+        format!("{}{}", exit_status, self.output.diagnostics())
+    }
+
+    /// Polls the health endpoint until the server reports the model is loaded.
+    pub(crate) async fn wait_until_ready(
+        &self,
+        client: &ApiClient<LlamaCppConfig>,
+    ) -> crate::Result<()> {
+        let timeout = self.startup_timeout;
+        let start_time = std::time::Instant::now();
+        loop {
+            let last_status = match health_request(client).await {
+                HealthStatus::Alive => return Ok(()),
+                HealthStatus::Loading => "Loading".to_string(),
+                HealthStatus::ErrorOrOffline(e) => e,
+            };
+            if start_time.elapsed() >= timeout {
+                crate::bail!(
+                    "LlamaCppServer at {} not ready after {:?}: {}",
+                    self.server_http_path,
+                    timeout,
+                    last_status
+                );
             }
+            tokio::time::sleep(std::time::Duration::from_millis(STATUS_RETRY_TIMEOUT_MS)).await;
+        }
+    }
+
+    /// The arguments that decide what the server runs and on which devices, without the address it listens on.
+    fn populate_model_args(&self, command: &mut std::process::Command) {
+        self.server_config.populate_args(command);
+        command
+            .arg("--model")
+            .arg(&self.device_config.local_model_path)
+            .arg("--ctx-size")
+            .arg(self.inference_ctx_size.to_string());
+        if let Some(parallel_slots) = self.parallel_slots {
+            command.arg("--parallel").arg(parallel_slots.to_string());
+        }
+        if let Some(draft_model) = &self.draft_model {
+            draft_model.populate_args(command, self.device_config.use_gpu);
+        }
+        if let Some(pooling) = self.embeddings {
+            command
+                .arg("--embeddings")
+                .arg("--pooling")
+                .arg(pooling.as_str());
+        }
+    }
+
+    pub(crate) fn shared_key(&self) -> SharedServerKey {
+        let mut command = std::process::Command::new("llama-server");
+        self.populate_model_args(&mut command);
+        command.args(&self.extra_server_args);
+        SharedServerKey {
+            model_path: self.device_config.local_model_path.clone(),
+            use_gpu: self.device_config.use_gpu,
+            server_args: command.get_args().map(|arg| arg.to_owned()).collect(),
         }
     }
 
     fn start_server_backend(&self) -> crate::Result<std::process::Child> {
+        check_model_file(&self.device_config.local_model_path)?;
         let path = get_target_directory()?.join("llama_cpp");
         let mut command = std::process::Command::new("./llama-server");
         command.current_dir(path);
-        self.server_config.populate_args(&mut command);
+        self.populate_model_args(&mut command);
         command
-            .arg("--model")
-            .arg(&self.device_config.local_model_path)
-            .arg("--ctx-size")
-            .arg(self.inference_ctx_size.to_string())
             .arg("--timeout")
             .arg("600")
             .arg("--host")
             .arg(&self.host)
-            .arg("--log-disable")
-            .arg("--verbose");
+            .arg("--verbose")
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped());
 
         if let Some(port) = &self.port {
             command.arg("--port").arg(port);
         }
+        command.args(&self.extra_server_args);
+        // Set on the spawned command only, so concurrent startups don't race on the process-wide environment.
+        if !self.device_config.use_gpu {
+            command.env("CUDA_VISIBLE_DEVICES", "");
+        }
         crate::info!("Starting LlamaCppServer with command: {:?}", command);
-        let process = command.spawn().expect("Failed to start LlamaCppServer");
+        let mut process = command
+            .spawn()
+            .map_err(|e| ServerStartupError::Spawn(e.to_string()))?;
+        if let Some(stdout) = process.stdout.take() {
+            self.output.capture(stdout, ServerLogStream::Stdout);
+        }
+        if let Some(stderr) = process.stderr.take() {
+            self.output.capture(stderr, ServerLogStream::Stderr);
+        }
 
         Ok(process)
     }
 
+    /// The layers allocated to each GPU, with the VRAM currently in use measured by NVML.
+    ///
+    /// Devices whose usage can't be measured are returned with `used_vram_bytes` set to `None`.
+    pub fn gpu_utilization(&self) -> Vec<GpuDevice> {
+        let mut gpu_devices = self.server_config.gpu_devices.clone();
+        for gpu in &mut gpu_devices {
+            if let Err(e) = gpu.refresh_usage() {
+                crate::warn!("Failed to measure VRAM usage of GPU {}: {}", gpu.ordinal, e);
+            }
+        }
+        gpu_devices
+    }
+
+    /// Kills the server process and starts a new one with the same arguments.
+    pub(crate) async fn restart(
+        &self,
+        client: &ApiClient<LlamaCppConfig>,
+    ) -> crate::Result<ServerStatus> {
+        if let Err(e) = self.shutdown() {
+            crate::error!("Failed to shutdown LlamaCppServer before restart: {}", e);
+        }
+        self.start_server(client).await
+    }
+
+    fn server_process(&self) -> std::sync::MutexGuard<'_, Option<std::process::Child>> {
+        self.server_process
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
     pub fn shutdown(&self) -> crate::Result<()> {
-        let process = if let Some(server_process) = &self.server_process {
-            server_process
+        let pid = if let Some(server_process) = &*self.server_process() {
+            server_process.id()
         } else {
             crate::error!("LlamaCppServer process not started. No need to shutdown.");
             return Ok(());
         };
 
-        let pid = process.id();
         match kill_server_from_pid(pid) {
             Ok(_) => {
                 crate::info!("LlamaCppServer process with PID: {} killed", pid);
@@ -182,6 +437,28 @@ impl LlamaCppServer {
     }
 }
 
+/// Checked before spawning, because llama-server fails on a missing model with a generic load error.
+pub(crate) fn check_model_file(path: &str) -> Result<(), ServerStartupError> {
+    let readable = std::path::Path::new(path).is_file() && std::fs::File::open(path).is_ok();
+    if readable {
+        Ok(())
+    } else {
+        Err(ServerStartupError::ModelNotFound(path.to_owned()))
+    }
+}
+
+/// Resolves once the process exits, with its exit status.
+async fn wait_for_exit(process: &mut std::process::Child) -> String {
+    loop {
+        match process.try_wait() {
+            Ok(Some(status)) => return status.to_string(),
+            Ok(None) => (),
+            Err(e) => return format!("unknown status ({e})"),
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(STATUS_RETRY_TIMEOUT_MS)).await;
+    }
+}
+
 pub fn kill_server_from_model(model_id: &str) -> crate::Result<()> {
     let pid = if let Some(pid) = get_server_pid_by_model(model_id)? {
         pid
@@ -235,10 +512,9 @@ pub fn kill_all_servers() -> crate::Result<()> {
     };
     crate::info!("Killing LlamaCppServer processes with PIDs: {:?}", pids);
     for pid in pids {
-        std::process::Command::new("kill")
-            .arg(pid)
-            .status()
-            .expect("Failed to kill process");
+        if let Err(e) = std::process::Command::new("kill").arg(&pid).status() {
+            crate::error!("Failed to kill LlamaCppServer process with PID: {pid}: {e}");
+        }
     }
     std::thread::sleep(std::time::Duration::from_millis(250));
     let pids = match get_all_server_pids() {
@@ -346,6 +622,10 @@ pub fn get_all_server_pids() -> crate::Result<Vec<String>> {
 
 impl Drop for LlamaCppServer {
     fn drop(&mut self) {
+        // Nothing to stop if this server was never spawned, e.g. because a shared server was reused instead.
+        if self.server_process().is_none() {
+            return;
+        }
         match self.shutdown() {
             Ok(_) => (),
             Err(e) => crate::error!("Failed to shutdown LlamaCppServer: {}", e),
@@ -0,0 +1,121 @@
+use std::{
+    collections::VecDeque,
+    io::{BufRead, BufReader, Read},
+    sync::{mpsc, Arc, Mutex},
+};
+
+const OUTPUT_TAIL_LINES: usize = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ServerLogStream {
+    Stdout,
+    Stderr,
+}
+
+impl std::fmt::Display for ServerLogStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServerLogStream::Stdout => write!(f, "stdout"),
+            ServerLogStream::Stderr => write!(f, "stderr"),
+        }
+    }
+}
+
+/// A line written by the server process.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerLogLine {
+    /// The model the server was started with.
+    pub model_id: String,
+    pub stream: ServerLogStream,
+    pub line: String,
+}
+
+/// Captures the server process's stdout and stderr. Each line is forwarded to `tracing` at debug level, sent to
+/// any subscribers, and the last lines are kept so a failed start up can report why.
+#[derive(Clone)]
+pub struct ServerOutput {
+    model_id: String,
+    lines: Arc<Mutex<VecDeque<String>>>,
+    subscribers: Arc<Mutex<Vec<mpsc::Sender<ServerLogLine>>>>,
+}
+
+impl ServerOutput {
+    pub fn new<S: Into<String>>(model_id: S) -> Self {
+        Self {
+            model_id: model_id.into(),
+            lines: Default::default(),
+            subscribers: Default::default(),
+        }
+    }
+
+    /// Reads the stream on a background thread until the process closes it.
+    pub(crate) fn capture<R: Read + Send + 'static>(
+        &self,
+        stream: R,
+        stream_kind: ServerLogStream,
+    ) {
+        let output = self.clone();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stream).lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                output.push_line(stream_kind, line);
+            }
+        });
+    }
+
+    fn push_line(&self, stream: ServerLogStream, line: String) {
+        crate::debug!(model_id = %self.model_id, %stream, "llama-server: {}", line);
+        {
+            let mut lines = self.lines.lock().unwrap_or_else(|e| e.into_inner());
+            if lines.len() == OUTPUT_TAIL_LINES {
+                lines.pop_front();
+            }
+            lines.push_back(line.clone());
+        }
+        let log_line = ServerLogLine {
+            model_id: self.model_id.clone(),
+            stream,
+            line,
+        };
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|s| s.send(log_line.clone()).is_ok());
+    }
+
+    /// Returns a receiver for every line the server writes from now on. Dropping the receiver unsubscribes.
+    pub fn subscribe(&self) -> mpsc::Receiver<ServerLogLine> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(sender);
+        receiver
+    }
+
+    pub fn tail(&self) -> Vec<String> {
+        self.lines
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// The captured lines formatted for an error message.
+    pub fn diagnostics(&self) -> String {
+        let tail = self.tail();
+        if tail.is_empty() {
+            "No output captured from llama-server.".to_string()
+        } else {
+            format!(
+                "Last {} lines of llama-server output:\n{}",
+                tail.len(),
+                tail.join("\n")
+            )
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+use super::ServerStartupError;
+use crate::llms::{
+    api::{client::ApiClient, error::ClientError},
+    local::llama_cpp::LlamaCppConfig,
+};
+
+/// The settings the running server was started with, from the `/props` endpoint.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LlamaCppProps {
+    #[serde(default)]
+    pub default_generation_settings: LlamaCppPropsGenerationSettings,
+    #[serde(default)]
+    pub total_slots: Option<u32>,
+    #[serde(default)]
+    pub model_path: Option<String>,
+    #[serde(default)]
+    pub chat_template: Option<String>,
+    #[serde(default)]
+    pub build_info: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LlamaCppPropsGenerationSettings {
+    /// The context size of each slot.
+    #[serde(default)]
+    pub n_ctx: Option<u64>,
+}
+
+impl LlamaCppProps {
+    /// The context size of the server, summed over its slots.
+    pub fn context_size(&self) -> Option<u64> {
+        self.default_generation_settings
+            .n_ctx
+            .map(|n_ctx| n_ctx * u64::from(self.slots()))
+    }
+
+    /// Checks that the server's context size isn't smaller than `requested`. llama.cpp may pad the context size up,
+    /// and splits it evenly between the slots, flooring the per-slot size, so up to one token per slot besides the
+    /// first is allowed to be missing. Servers that don't report a context size aren't checked.
+    pub fn check_context_size(&self, requested: u64) -> Result<(), ServerStartupError> {
+        let tolerance = u64::from(self.slots() - 1);
+        match self.context_size() {
+            Some(actual) if actual + tolerance < requested => {
+                Err(ServerStartupError::ContextSizeMismatch { requested, actual })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn slots(&self) -> u32 {
+        self.total_slots.unwrap_or(1).max(1)
+    }
+}
+
+pub(crate) async fn props_request(
+    client: &ApiClient<LlamaCppConfig>,
+) -> crate::Result<LlamaCppProps, ClientError> {
+    client.get::<LlamaCppProps>("/props").await
+}
@@ -0,0 +1,92 @@
+use super::LlamaCppServer;
+use crate::llms::{
+    api::client::ApiClient,
+    local::{
+        llama_cpp::{supervisor::ServerSupervisor, LlamaCppConfig},
+        request_queue::RequestQueue,
+    },
+};
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    sync::{Arc, LazyLock, Weak},
+};
+
+/// Identifies servers that run the same model on the same devices. The device config is compared by the server
+/// arguments derived from it, so servers listening on different addresses still match.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct SharedServerKey {
+    pub(crate) model_path: String,
+    pub(crate) use_gpu: bool,
+    pub(crate) server_args: Vec<OsString>,
+}
+
+/// A server and the request queue and supervisor of the backend that started it. Every backend reusing the server
+/// shares them, so the server's slots are counted and its failures tracked across the backends.
+#[derive(Clone)]
+pub(crate) struct SharedServer {
+    pub(crate) server: Arc<LlamaCppServer>,
+    pub(crate) queue: Arc<RequestQueue>,
+    pub(crate) supervisor: Option<Arc<ServerSupervisor>>,
+}
+
+struct SharedServerEntry {
+    server: Weak<LlamaCppServer>,
+    queue: Arc<RequestQueue>,
+    supervisor: Option<Arc<ServerSupervisor>>,
+}
+
+/// The servers started with [LlamaCppConfig::shared_server]. Only weak references to the servers are kept, so a
+/// server is shut down when the last backend using it is dropped. The lock is held while a server starts, so
+/// concurrent backends for the same model wait for the first one instead of spawning a duplicate.
+static SHARED_SERVERS: LazyLock<tokio::sync::Mutex<HashMap<SharedServerKey, SharedServerEntry>>> =
+    LazyLock::new(Default::default);
+
+/// Returns the running server with the same key as `shared.server`, with its queue and supervisor, or starts
+/// `shared.server` and registers it.
+///
+/// A reused server keeps the address it was started with, so `config` is pointed at it before the client is built.
+/// The queue and supervisor settings of a backend reusing a server are ignored.
+pub(crate) async fn shared_server(
+    server: LlamaCppServer,
+    queue: RequestQueue,
+    supervisor: Option<ServerSupervisor>,
+    mut config: LlamaCppConfig,
+) -> crate::Result<(SharedServer, ApiClient<LlamaCppConfig>)> {
+    let key = server.shared_key();
+    let mut servers = SHARED_SERVERS.lock().await;
+    servers.retain(|_, entry| entry.server.strong_count() > 0);
+    if let Some(entry) = servers.get(&key) {
+        if let Some(running) = entry.server.upgrade() {
+            crate::info!(
+                "Reusing the shared LlamaCppServer at {} for model: {}",
+                running.server_http_path,
+                key.model_path
+            );
+            config.api_config.host = running.host.clone();
+            config.api_config.port = running.port.clone();
+            let shared = SharedServer {
+                server: running,
+                queue: entry.queue.clone(),
+                supervisor: entry.supervisor.clone(),
+            };
+            return Ok((shared, ApiClient::new(config)?));
+        }
+    }
+    let client = ApiClient::new(config)?;
+    server.start_server(&client).await?;
+    let shared = SharedServer {
+        server: Arc::new(server),
+        queue: Arc::new(queue),
+        supervisor: supervisor.map(Arc::new),
+    };
+    servers.insert(
+        key,
+        SharedServerEntry {
+            server: Arc::downgrade(&shared.server),
+            queue: shared.queue.clone(),
+            supervisor: shared.supervisor.clone(),
+        },
+    );
+    Ok((shared, client))
+}
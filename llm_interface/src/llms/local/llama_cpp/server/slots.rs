@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+use crate::llms::{
+    api::{client::ApiClient, error::ClientError},
+    local::llama_cpp::LlamaCppConfig,
+};
+
+/// A server slot, from the `/slots` endpoint. Each slot runs one request at a time.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LlamaCppSlot {
+    pub id: u32,
+    /// The context size of the slot.
+    #[serde(default)]
+    pub n_ctx: Option<u64>,
+    /// Reported by newer servers.
+    #[serde(default)]
+    pub is_processing: Option<bool>,
+    /// Reported by older servers: 0 when idle, 1 when processing.
+    #[serde(default)]
+    pub state: Option<u32>,
+}
+
+impl LlamaCppSlot {
+    pub fn is_busy(&self) -> bool {
+        match (self.is_processing, self.state) {
+            (Some(is_processing), _) => is_processing,
+            (None, Some(state)) => state != 0,
+            (None, None) => false,
+        }
+    }
+}
+
+/// The number of slots not running a request.
+pub fn available_slots(slots: &[LlamaCppSlot]) -> usize {
+    slots.iter().filter(|slot| !slot.is_busy()).count()
+}
+
+pub(crate) async fn slots_request(
+    client: &ApiClient<LlamaCppConfig>,
+) -> crate::Result<Vec<LlamaCppSlot>, ClientError> {
+    client.get::<Vec<LlamaCppSlot>>("/slots").await
+}
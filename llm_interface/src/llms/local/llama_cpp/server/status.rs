@@ -11,7 +11,22 @@ use super::{
 pub enum ServerStatus {
     RunningModel(String),
     RunningRequested,
-    Offline,
+    Offline(ConnectionFailure),
+}
+
+/// Why a server could not be reached. A server process that exited is reported separately, see
+/// [super::ServerStartupError::Exited].
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum ConnectionFailure {
+    /// Nothing accepted a TCP connection, e.g. the server hasn't bound the port yet or failed to bind it.
+    #[error("port {address} is not open ({last_error})")]
+    PortNotOpen { address: String, last_error: String },
+    /// The port accepted connections, but `/health` didn't report the server as ready, e.g. the model is still loading.
+    #[error("port {address} is open, but /health is not responding ({last_status})")]
+    NotHealthy {
+        address: String,
+        last_status: String,
+    },
 }
 
 pub(crate) async fn server_status(
@@ -23,35 +38,39 @@ pub(crate) async fn server_status(
 ) -> crate::Result<ServerStatus> {
     let start_time = Instant::now();
     // First, test the TCP connection
-    if let Err(e) = timeout(test_time, test_connection(server_http_path, retry_time)).await {
+    if let Err(last_error) = test_connection(server_http_path, test_time, retry_time).await {
         crate::trace!(
             "TCP connection to {} failed after {:?}: {}",
             server_http_path,
             test_time,
-            e
+            last_error
         );
-        return Ok(ServerStatus::Offline);
+        return Ok(ServerStatus::Offline(ConnectionFailure::PortNotOpen {
+            address: server_http_path.to_owned(),
+            last_error,
+        }));
     }
 
     // Then, repeatedly check the health status
     loop {
+        let last_status = match health_request(client).await {
+            HealthStatus::Alive => break,
+            HealthStatus::Loading => "Loading".to_string(),
+            HealthStatus::ErrorOrOffline(e) => e,
+        };
         if Instant::now().duration_since(start_time) >= test_time {
-            crate::bail!(
+            crate::trace!(
                 "Health check for {} failed after {:?}",
                 server_http_path,
                 test_time
             );
-        }
-
-        match health_request(client).await {
-            HealthStatus::Alive => break,
-            HealthStatus::Loading => {
-                sleep(retry_time).await;
-            }
-            HealthStatus::ErrorOrOffline(_) => {
-                sleep(retry_time).await;
+            return Err(ConnectionFailure::NotHealthy {
+                address: server_http_path.to_owned(),
+                last_status,
             }
+            .into());
         }
+        sleep(retry_time).await;
     }
 
     match model_request(client).await {
@@ -67,8 +86,15 @@ pub(crate) async fn server_status(
                 return Ok(ServerStatus::RunningModel(model_id));
             }
         }
-        Ok(ModelStatus::LoadedModels(_model_ids)) => {
-            todo!()
+        Ok(ModelStatus::LoadedModels(model_ids)) => {
+            if model_ids.iter().any(|id| id == requested_model_path) {
+                Ok(ServerStatus::RunningRequested)
+            } else {
+                match model_ids.into_iter().next() {
+                    Some(model_id) => Ok(ServerStatus::RunningModel(model_id)),
+                    None => crate::bail!("Model check for {} returned no models", server_http_path),
+                }
+            }
         }
         Err(e) => {
             crate::bail!("Model check for {} failed: {}", server_http_path, e);
@@ -76,16 +102,26 @@ pub(crate) async fn server_status(
     }
 }
 
+/// Retries a TCP connection until it succeeds or `test_time` elapses. Returns the last connection error.
 pub(crate) async fn test_connection(
     server_http_path: &str,
+    test_time: Duration,
     retry_time: Duration,
-) -> crate::Result<()> {
-    loop {
-        match tokio::net::TcpStream::connect(server_http_path).await {
-            Ok(_) => return Ok(()),
-            Err(_) => {
-                sleep(retry_time).await;
+) -> Result<(), String> {
+    let mut last_error = "timed out".to_string();
+    let attempts = async {
+        loop {
+            match tokio::net::TcpStream::connect(server_http_path).await {
+                Ok(_) => return,
+                Err(e) => {
+                    last_error = e.to_string();
+                    sleep(retry_time).await;
+                }
             }
         }
+    };
+    match timeout(test_time, attempts).await {
+        Ok(()) => Ok(()),
+        Err(_) => Err(last_error),
     }
 }
@@ -0,0 +1,192 @@
+use super::{server::LlamaCppServer, LlamaCppConfig};
+use crate::llms::api::{client::ApiClient, error::ClientError};
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Restarts the llama.cpp server after repeated request failures, e.g. after a CUDA error leaves it unhealthy.
+#[derive(Clone, Debug)]
+pub struct SupervisorConfig {
+    /// Consecutive failed requests before the server is restarted. Defaults to 3.
+    pub failure_threshold: u32,
+    /// Restart attempts before the circuit opens. Defaults to 3.
+    pub max_restart_attempts: u32,
+    /// Wait before the first restart attempt. Doubles after each failed attempt. Defaults to 1 second.
+    pub initial_backoff: Duration,
+    /// The longest wait between restart attempts, and how long the circuit stays open before the next request
+    /// tries again. Defaults to 30 seconds.
+    pub max_backoff: Duration,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            max_restart_attempts: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl SupervisorConfig {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_failure_threshold(mut self, failure_threshold: u32) -> Self {
+        self.failure_threshold = failure_threshold.max(1);
+        self
+    }
+
+    pub fn with_max_restart_attempts(mut self, max_restart_attempts: u32) -> Self {
+        self.max_restart_attempts = max_restart_attempts.max(1);
+        self
+    }
+
+    pub fn with_backoff(mut self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self.max_backoff = max_backoff.max(initial_backoff);
+        self
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff)
+    }
+}
+
+/// The circuit breaker state of the supervised server.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CircuitState {
+    /// Requests are sent to the server.
+    Closed,
+    /// The server is being restarted. Requests fail immediately.
+    Restarting,
+    /// Restarting failed. Requests fail immediately until the cooldown ends, then the next request retries the restart.
+    Open { until: Instant },
+}
+
+pub(crate) struct ServerSupervisor {
+    config: SupervisorConfig,
+    consecutive_failures: AtomicU32,
+    restarts: AtomicU32,
+    state: Mutex<CircuitState>,
+    restart_lock: tokio::sync::Mutex<()>,
+}
+
+impl ServerSupervisor {
+    pub(crate) fn new(config: SupervisorConfig) -> Self {
+        Self {
+            config,
+            consecutive_failures: AtomicU32::new(0),
+            restarts: AtomicU32::new(0),
+            state: Mutex::new(CircuitState::Closed),
+            restart_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub(crate) fn state(&self) -> CircuitState {
+        *self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn set_state(&self, state: CircuitState) {
+        *self.state.lock().unwrap_or_else(|e| e.into_inner()) = state;
+    }
+
+    /// The number of successful restarts.
+    pub(crate) fn restarts(&self) -> u32 {
+        self.restarts.load(Ordering::SeqCst)
+    }
+
+    /// Errors if the circuit is open or a restart is in progress, so the request fails fast.
+    pub(crate) fn check(&self) -> Result<(), String> {
+        match self.state() {
+            CircuitState::Closed => Ok(()),
+            CircuitState::Restarting => Err("LlamaCppServer is restarting".to_string()),
+            CircuitState::Open { until } if Instant::now() < until => Err(format!(
+                "LlamaCppServer circuit is open after failed restarts. Retrying in {:?}",
+                until - Instant::now()
+            )),
+            // The cooldown ended, so let this request trigger another restart attempt.
+            CircuitState::Open { .. } => {
+                self.set_state(CircuitState::Closed);
+                self.consecutive_failures
+                    .store(self.config.failure_threshold, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+    }
+
+    pub(crate) fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::SeqCst);
+    }
+
+    /// Counts the failure if it indicates an unhealthy server, and restarts the server once the threshold is reached.
+    pub(crate) async fn record_failure(
+        &self,
+        error: &ClientError,
+        server: &LlamaCppServer,
+        client: &ApiClient<LlamaCppConfig>,
+    ) {
+        if !is_server_failure(error) {
+            return;
+        }
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::SeqCst) + 1;
+        if failures < self.config.failure_threshold {
+            return;
+        }
+        // Concurrent failures wait here instead of starting a second restart.
+        let Ok(_restarting) = self.restart_lock.try_lock() else {
+            return;
+        };
+        self.set_state(CircuitState::Restarting);
+        for attempt in 0..self.config.max_restart_attempts {
+            let backoff = self.config.backoff(attempt);
+            crate::warn!(
+                "LlamaCppServer failed {} consecutive requests. Restarting in {:?} (attempt {}/{})",
+                failures,
+                backoff,
+                attempt + 1,
+                self.config.max_restart_attempts
+            );
+            tokio::time::sleep(backoff).await;
+            match server.restart(client).await {
+                Ok(_) => {
+                    self.restarts.fetch_add(1, Ordering::SeqCst);
+                    self.consecutive_failures.store(0, Ordering::SeqCst);
+                    self.set_state(CircuitState::Closed);
+                    crate::info!("LlamaCppServer restarted");
+                    return;
+                }
+                Err(e) => crate::error!("Failed to restart LlamaCppServer: {}", e),
+            }
+        }
+        self.set_state(CircuitState::Open {
+            until: Instant::now() + self.config.max_backoff,
+        });
+    }
+}
+
+/// Whether `error` indicates an unhealthy server: the connection failed or timed out, or the server answered with a
+/// 5xx status. Errors caused by the request itself, like an invalid grammar or a prompt that's too long, don't count.
+pub fn is_server_failure(error: &ClientError) -> bool {
+    match error {
+        ClientError::Reqwest(e) => e.is_connect() || e.is_timeout(),
+        ClientError::ServiceUnavailable { .. } => true,
+        // llama.cpp reports the HTTP status as the error code.
+        ClientError::ApiError(e) => {
+            e.r#type.as_deref() == Some("server_error")
+                || e.code
+                    .as_deref()
+                    .and_then(|code| code.parse::<u16>().ok())
+                    .is_some_and(|status| status >= 500)
+        }
+        _ => false,
+    }
+}
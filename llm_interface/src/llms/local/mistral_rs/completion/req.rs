@@ -8,6 +8,7 @@ pub fn new(
     request: &CompletionRequest,
     tx: tokio::sync::mpsc::Sender<Response>,
     id: usize,
+    is_streaming: bool,
 ) -> crate::Result<MistralCompletionRequest, CompletionError> {
     let sampling_params = SamplingParams {
         temperature: Some(request.config.temperature.into()),
@@ -44,7 +45,7 @@ pub fn new(
         sampling_params,
         response: tx,
         return_logprobs: false,
-        is_streaming: false,
+        is_streaming,
         id,
         constraint,
         suffix: None,
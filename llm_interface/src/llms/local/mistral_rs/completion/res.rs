@@ -26,12 +26,85 @@ impl CompletionResponse {
             id: "mistral_rs".to_owned(),
             index: None,
             content: choice.text.to_owned(),
+            reasoning: None,
+            tool_calls: None,
             finish_reason,
             completion_probabilities: None,
             truncated: false,
             generation_settings: GenerationSettings::new_from_mistral(req, &res),
             timing_usage: TimingUsage::new_from_mistral(&res, req.start_time),
             token_usage: TokenUsage::new_from_mistral(&res),
+            raw_response: None,
+            special_tokens: [req.backend.bos_token(), req.backend.eos_token()]
+                .into_iter()
+                .map(str::to_owned)
+                .chain(req.stop_sequences.to_vec())
+                .collect(),
+        })
+    }
+
+    /// Builds the response from a streamed completion. The stream carries no timings, so only the token counts
+    /// are reported.
+    #[cfg(feature = "mistral_rs_backend")]
+    pub fn new_from_mistral_stream(
+        req: &CompletionRequest,
+        model: &str,
+        content: String,
+        finish_reason: &str,
+        completion_tokens: u32,
+    ) -> Result<Self, CompletionError> {
+        if content.is_empty() {
+            return Err(CompletionError::ReponseContentEmpty);
+        }
+        let finish_reason = match finish_reason {
+            "stop" => CompletionFinishReason::Eos,
+            "length" => CompletionFinishReason::StopLimit,
+            _ => {
+                return Err(CompletionError::StopReasonUnsupported(format!(
+                    "Unsupported stop reason: {finish_reason}"
+                )))
+            }
+        };
+        let prompt_tokens = req.prompt.get_total_prompt_tokens().unwrap_or_default() as u32;
+
+        Ok(Self {
+            id: "mistral_rs".to_owned(),
+            index: None,
+            content,
+            reasoning: None,
+            tool_calls: None,
+            finish_reason,
+            completion_probabilities: None,
+            truncated: false,
+            generation_settings: GenerationSettings {
+                model: model.to_owned(),
+                frequency_penalty: req.config.frequency_penalty,
+                presence_penalty: req.config.presence_penalty,
+                temperature: req.config.temperature,
+                top_p: req.config.top_p,
+                n_choices: 1,
+                n_predict: req.config.actual_request_tokens.map(|x| x as i32),
+                n_ctx: req.config.inference_ctx_size,
+                logit_bias: None,
+                grammar: None,
+                stop_sequences: req.stop_sequences.to_vec(),
+            },
+            timing_usage: TimingUsage::new_from_generic(req.start_time),
+            token_usage: TokenUsage {
+                tokens_cached: None,
+                cached_prompt_tokens: None,
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+                dollar_cost: None,
+                cents_cost: None,
+            },
+            raw_response: None,
+            special_tokens: [req.backend.bos_token(), req.backend.eos_token()]
+                .into_iter()
+                .map(str::to_owned)
+                .chain(req.stop_sequences.to_vec())
+                .collect(),
         })
     }
 }
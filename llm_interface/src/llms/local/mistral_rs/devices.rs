@@ -23,39 +23,51 @@ fn new_only_cpu(generic_device_map: &DeviceConfig) -> crate::Result<(Device, Dev
 }
 
 fn new_single_gpu(generic_device_map: &DeviceConfig) -> crate::Result<(Device, DeviceMapMetadata)> {
-    let _gpu_devices = generic_device_map.allocate_layers_to_gpus(0, 0)?;
+    let gpu_devices = generic_device_map.allocate_layers_to_gpus(1, 1)?;
     let main_gpu = generic_device_map.main_gpu()?;
-    // let layer_count = gpu_devices
-    //     .iter()
-    //     .map(|d| d.allocated_layers as usize)
-    //     .sum();
+    let layer_count = gpu_devices
+        .iter()
+        .map(|d| d.allocated_layers as usize)
+        .sum();
 
+    // Layers that don't fit in VRAM stay on the CPU.
     Ok((
         Device::cuda_if_available(main_gpu as usize)?,
-        DeviceMapMetadata::dummy(),
+        DeviceMapMetadata::from_num_device_layers(vec![DeviceLayerMapMetadata {
+            ordinal: main_gpu as usize,
+            layers: layer_count,
+        }]),
     ))
-    // Ok((
-    //     Device::cuda_if_available(main_gpu as usize)?,
-    //     DeviceMapMetadata::from_num_device_layers(vec![DeviceLayerMapMetadata {
-    //         ordinal: main_gpu as usize,
-    //         layers: layer_count,
-    //     }]),
-    // ))
 }
 
+/// Splits the layers across the GPUs in proportion to their available VRAM, the same allocation the llama.cpp
+/// backend uses for `--tensor-split`. The main GPU is listed first, so it holds the first layers.
 fn new_multiple_gpu(
     generic_device_map: &DeviceConfig,
 ) -> crate::Result<(Device, DeviceMapMetadata)> {
-    let gpu_devices = generic_device_map.allocate_layers_to_gpus(0, 0)?;
+    let mut gpu_devices = generic_device_map.allocate_layers_to_gpus(1, 1)?;
     let main_gpu = generic_device_map.main_gpu()?;
+    gpu_devices.sort_by_key(|gpu| gpu.ordinal != main_gpu);
 
-    let mut device_map_metadata: Vec<DeviceLayerMapMetadata> = Vec::new();
-    for gpu in &gpu_devices {
-        device_map_metadata.push(DeviceLayerMapMetadata {
+    let device_map_metadata: Vec<DeviceLayerMapMetadata> = gpu_devices
+        .iter()
+        .filter(|gpu| gpu.allocated_layers > 0)
+        .map(|gpu| DeviceLayerMapMetadata {
             ordinal: gpu.ordinal as usize,
             layers: gpu.allocated_layers as usize,
-        });
+        })
+        .collect();
+    if device_map_metadata.is_empty() {
+        crate::bail!("No layers could be allocated to the GPUs");
     }
+    crate::info!(
+        "mistral_rs device map: {}",
+        device_map_metadata
+            .iter()
+            .map(|d| format!("GPU {}: {} layers", d.ordinal, d.layers))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
     Ok((
         Device::cuda_if_available(main_gpu as usize)?,
         DeviceMapMetadata::from_num_device_layers(device_map_metadata),
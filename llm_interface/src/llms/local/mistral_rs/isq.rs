@@ -0,0 +1,37 @@
+use mistralrs::IsqType;
+
+/// The quantization applied by mistral.rs in-situ quantization (ISQ) while loading an unquantized model.
+///
+/// The `K` variants are the k-quants, which keep more quality than the legacy types at the same size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MistralRsIsq {
+    Q4_0,
+    Q4_1,
+    Q5_0,
+    Q5_1,
+    Q8_0,
+    Q2K,
+    Q3K,
+    Q4K,
+    Q5K,
+    Q6K,
+    Q8K,
+}
+
+impl From<MistralRsIsq> for IsqType {
+    fn from(isq: MistralRsIsq) -> Self {
+        match isq {
+            MistralRsIsq::Q4_0 => IsqType::Q4_0,
+            MistralRsIsq::Q4_1 => IsqType::Q4_1,
+            MistralRsIsq::Q5_0 => IsqType::Q5_0,
+            MistralRsIsq::Q5_1 => IsqType::Q5_1,
+            MistralRsIsq::Q8_0 => IsqType::Q8_0,
+            MistralRsIsq::Q2K => IsqType::Q2K,
+            MistralRsIsq::Q3K => IsqType::Q3K,
+            MistralRsIsq::Q4K => IsqType::Q4K,
+            MistralRsIsq::Q5K => IsqType::Q5K,
+            MistralRsIsq::Q6K => IsqType::Q6K,
+            MistralRsIsq::Q8K => IsqType::Q8K,
+        }
+    }
+}
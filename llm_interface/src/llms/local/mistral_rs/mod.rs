@@ -1,19 +1,26 @@
 use std::num::NonZeroUsize;
 
 use super::LocalLlmConfig;
-use crate::requests::completion::{CompletionError, CompletionRequest, CompletionResponse};
+use crate::requests::completion::{
+    CompletionError, CompletionFinishReason, CompletionRequest, CompletionResponse, StreamControl,
+};
 
 use devices::mistral_rs_device_map;
+use isq::MistralRsIsq;
 use llm_devices::logging::LoggingConfig;
 use llm_models::local_model::{gguf::GgufLoader, LocalLlmModel};
 use mistralrs::{
-    DefaultSchedulerMethod,  GGUFLoaderBuilder, GGUFSpecificConfig, MemoryGpuConfig, MistralRs, MistralRsBuilder, ModelDType,  PagedAttentionConfig,  Response, SchedulerConfig, TokenSource
+    DefaultSchedulerMethod,  GGUFLoaderBuilder, GGUFSpecificConfig, Loader, MemoryGpuConfig, MistralRs, MistralRsBuilder, ModelDType, NormalLoaderBuilder, NormalSpecificConfig, PagedAttentionConfig,  Response, SchedulerConfig, TokenSource
 };
 
 
+/// Chunks buffered between the mistral.rs engine and the caller.
+const STREAM_CHANNEL_SIZE: usize = 64;
+
 pub mod builder;
 pub mod completion;
 pub mod devices;
+pub mod isq;
 
 pub struct MistralRsBackend {
     pub model: LocalLlmModel,
@@ -26,7 +33,7 @@ impl MistralRsBackend {
         config.logging_config.load_logger()?;
         config.local_config.device_config.initialize()?;
         let model = config.local_config.load_model(llm_loader)?;
-        let client = Self::init_from_gguf_local(&model, &config.local_config).await?;
+        let client = Self::init_pipeline(&model, &config).await?;
         Ok(Self {
             client,
             config,
@@ -34,10 +41,11 @@ impl MistralRsBackend {
         })
     }
 
-    async fn init_from_gguf_local(
+    async fn init_pipeline(
         model: &LocalLlmModel,
-        local_config: &LocalLlmConfig,
+        config: &MistralRsConfig,
     ) -> crate::Result<std::sync::Arc<MistralRs>> {
+        let local_config = &config.local_config;
         std::env::set_var("MISTRALRS_DEBUG", "1");
 
         let prompt_batchsize = if local_config.batch_size > 0 {
@@ -58,26 +66,55 @@ impl MistralRsBackend {
         let directory = model.local_model_path.parent().and_then(|p| p.to_str()).expect("Model path must have a parent directory");
         let filename = model.local_model_path.file_name().and_then(|s| s.to_str()).expect("Model path must have a filename");
 
-        let loader = GGUFLoaderBuilder::new(
-            None,
-            None,
-            directory.to_string(),
-            vec![filename.to_string()],
-            GGUFSpecificConfig {
-                prompt_batchsize,
-                topology: None,
-            },
-        )
-        .build();
+        // GGUF files are already quantized, so ISQ loads the unquantized weights instead. The GGUF model still
+        // provides the tokenizer, chat template and metadata.
+        let (loader, token_source): (Box<dyn Loader>, TokenSource) = match &config.isq {
+            Some(_) => {
+                let repo_id = config.unquantized_repo_id.clone().ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "ISQ requires an unquantized model. Set it with `unquantized_repo_id`."
+                    )
+                })?;
+                let loader = NormalLoaderBuilder::new(
+                    NormalSpecificConfig {
+                        use_flash_attn: false,
+                        prompt_batchsize,
+                        topology: None,
+                        organization: Default::default(),
+                        write_uqff: None,
+                        from_uqff: None,
+                    },
+                    None,
+                    None,
+                    Some(repo_id),
+                )
+                .build(None)?;
+                (loader, TokenSource::CacheToken)
+            }
+            None => {
+                let loader = GGUFLoaderBuilder::new(
+                    None,
+                    None,
+                    directory.to_string(),
+                    vec![filename.to_string()],
+                    GGUFSpecificConfig {
+                        prompt_batchsize,
+                        topology: None,
+                    },
+                )
+                .build();
+                (loader, TokenSource::None)
+            }
+        };
 
         let pipeline = loader.load_model_from_hf(
             None,
-            TokenSource::None,
+            token_source,
             &ModelDType::Auto,
             &device,
             false,
             mapper,
-            None,
+            config.isq.map(Into::into),
             cache_config,
         )?;
 
@@ -121,7 +158,7 @@ impl MistralRsBackend {
 
         let (tx, mut rx) = tokio::sync::mpsc::channel(1);
         let id = 0;
-        let mistral_request = completion::new(request, tx, id)?;
+        let mistral_request = completion::new(request, tx, id, false)?;
 
        let sender = self.client
             .get_sender()
@@ -158,12 +195,114 @@ impl MistralRsBackend {
         
     }
 
+    /// Like [Self::completion_request], but calls `on_delta` with each piece of generated text as mistral.rs
+    /// streams it. Matches [crate::llms::local::llama_cpp::LlamaCppBackend::completion_request_streaming].
+    pub async fn completion_request_streaming(
+        &self,
+        request: &CompletionRequest,
+        on_delta: &mut (dyn FnMut(&str) -> StreamControl + Send),
+    ) -> crate::Result<CompletionResponse, CompletionError> {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(STREAM_CHANNEL_SIZE);
+        let id = 0;
+        let mistral_request = completion::new(request, tx, id, true)?;
+
+        let sender = self
+            .client
+            .get_sender()
+            .map_err(|e| CompletionError::RequestBuilderError(e.to_string()))?;
+        sender
+            .send(mistral_request)
+            .await
+            .map_err(|e| CompletionError::RequestBuilderError(e.to_string()))?;
+
+        let deadline = request
+            .config
+            .stream_timeout
+            .map(|timeout| tokio::time::Instant::now() + timeout);
+        let mut content = String::new();
+        let mut completion_tokens = 0;
+        loop {
+            let response = match deadline {
+                Some(deadline) => match tokio::time::timeout_at(deadline, rx.recv()).await {
+                    Ok(response) => response,
+                    // Dropping the receiver makes the engine stop generating for this request.
+                    Err(_) => {
+                        return Ok(CompletionResponse::new_from_stream(
+                            request,
+                            "mistral_rs",
+                            content,
+                            CompletionFinishReason::TimedOut,
+                        ))
+                    }
+                },
+                None => rx.recv().await,
+            };
+            let Some(response) = response else {
+                break;
+            };
+            match response {
+                Response::CompletionChunk(chunk) => {
+                    let Some(choice) = chunk.choices.first() else {
+                        continue;
+                    };
+                    if !choice.text.is_empty() {
+                        content.push_str(&choice.text);
+                        completion_tokens += 1;
+                        if on_delta(&choice.text) == StreamControl::Stop {
+                            // Dropping the receiver makes the engine stop generating for this request.
+                            return Ok(CompletionResponse::new_from_stream(
+                                request,
+                                "mistral_rs",
+                                content,
+                                CompletionFinishReason::StreamStopped,
+                            ));
+                        }
+                    }
+                    if let Some(finish_reason) = &choice.finish_reason {
+                        return CompletionResponse::new_from_mistral_stream(
+                            request,
+                            &chunk.model,
+                            content,
+                            finish_reason,
+                            completion_tokens,
+                        );
+                    }
+                }
+                Response::CompletionDone(completion) => {
+                    let mut res = CompletionResponse::new_from_mistral(request, completion)?;
+                    res.content = content;
+                    return Ok(res);
+                }
+                Response::InternalError(e) | Response::ValidationError(e) => {
+                    return Err(CompletionError::LocalClientError(e.to_string()))
+                }
+                Response::ModelError(e, _) | Response::CompletionModelError(e, _) => {
+                    return Err(CompletionError::LocalClientError(e.to_string()))
+                }
+                Response::Chunk(_) | Response::Done(_) | Response::ImageGeneration(_) => {
+                    return Err(CompletionError::LocalClientError(
+                        "MistralRsBackend stream error: unexpected chat or image response"
+                            .to_string(),
+                    ))
+                }
+            }
+        }
+        Err(CompletionError::LocalClientError(
+            "MistralRsBackend stream ended before the completion finished".to_string(),
+        ))
+    }
+
 }
 
 #[derive(Clone, Debug)]
 pub struct MistralRsConfig {
     pub logging_config: LoggingConfig,
     pub local_config: LocalLlmConfig,
+    /// Quantizes the unquantized model while loading it. Requires `unquantized_repo_id`.
+    pub isq: Option<MistralRsIsq>,
+    /// The Hugging Face repo of the model's unquantized safetensors weights, e.g.
+    /// `meta-llama/Llama-3.2-3B-Instruct`. Only used with `isq`.
+    pub unquantized_repo_id: Option<String>,
 }
 
 impl Default for MistralRsConfig {
@@ -174,6 +313,8 @@ impl Default for MistralRsConfig {
                 ..Default::default()
             },
             local_config: LocalLlmConfig::default(),
+            isq: None,
+            unquantized_repo_id: None,
         }
     }
 }
@@ -182,4 +323,11 @@ impl MistralRsConfig {
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Loads the unquantized model from `unquantized_repo_id` and quantizes it to `isq` on the fly.
+    pub fn with_isq<T: Into<String>>(mut self, isq: MistralRsIsq, unquantized_repo_id: T) -> Self {
+        self.isq = Some(isq);
+        self.unquantized_repo_id = Some(unquantized_repo_id.into());
+        self
+    }
 }
@@ -11,12 +11,28 @@ use llm_models::local_model::{
 pub mod llama_cpp;
 #[cfg(feature = "mistral_rs_backend")]
 pub mod mistral_rs;
+pub mod request_queue;
 
 #[derive(Clone, Debug)]
 pub struct LocalLlmConfig {
     pub batch_size: u64,
     pub inference_ctx_size: u64,
     pub device_config: DeviceConfig,
+    /// Enables flash attention, which lowers the memory used by the KV cache and speeds up long contexts.
+    /// Defaults to false.
+    pub flash_attention: bool,
+    /// The data type of the K cache. Defaults to `None` (f16).
+    pub cache_type_k: Option<KvCacheType>,
+    /// The data type of the V cache. A quantized V cache requires flash attention. Defaults to `None` (f16).
+    pub cache_type_v: Option<KvCacheType>,
+    /// Memory-maps the model file instead of reading it into memory. Defaults to true.
+    pub use_mmap: bool,
+    /// Locks the model in RAM so the OS can't swap it out. Defaults to false.
+    pub use_mlock: bool,
+    /// Arguments appended to the llama.cpp server command, for flags this crate doesn't set. Defaults to none.
+    pub extra_server_args: Vec<String>,
+    /// Extends the model's context beyond the length it was trained for. Defaults to `None`.
+    pub rope_scaling: Option<RopeScaling>,
 }
 
 impl Default for LocalLlmConfig {
@@ -25,13 +41,118 @@ impl Default for LocalLlmConfig {
             batch_size: 512,
             inference_ctx_size: DEFAULT_CONTEXT_LENGTH,
             device_config: DeviceConfig::default(),
+            flash_attention: false,
+            cache_type_k: None,
+            cache_type_v: None,
+            use_mmap: true,
+            use_mlock: false,
+            extra_server_args: Vec::new(),
+            rope_scaling: None,
+        }
+    }
+}
+
+/// The data type of the KV cache. Quantized types fit longer contexts in the same memory at a small cost in quality.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KvCacheType {
+    F32,
+    F16,
+    BF16,
+    Q8_0,
+    Q4_0,
+    Q4_1,
+    Iq4Nl,
+    Q5_0,
+    Q5_1,
+}
+
+impl KvCacheType {
+    /// The name llama.cpp uses for the type.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::F32 => "f32",
+            Self::F16 => "f16",
+            Self::BF16 => "bf16",
+            Self::Q8_0 => "q8_0",
+            Self::Q4_0 => "q4_0",
+            Self::Q4_1 => "q4_1",
+            Self::Iq4Nl => "iq4_nl",
+            Self::Q5_0 => "q5_0",
+            Self::Q5_1 => "q5_1",
         }
     }
+
+    pub fn is_quantized(&self) -> bool {
+        !matches!(self, Self::F32 | Self::F16 | Self::BF16)
+    }
+}
+
+/// How the RoPE frequencies are scaled to run a model beyond the context length it was trained for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RopeScalingType {
+    /// Linear interpolation of the positions. Works without fine-tuning for small factors.
+    Linear,
+    /// YaRN, which keeps quality better at large factors, especially for models fine-tuned with it.
+    Yarn,
+}
+
+impl RopeScalingType {
+    /// The name llama.cpp uses for the type.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Linear => "linear",
+            Self::Yarn => "yarn",
+        }
+    }
+}
+
+/// Extends a model's context by `factor`, e.g. a factor of 4.0 runs an 8k model at 32k.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RopeScaling {
+    /// --rope-scaling
+    pub scaling_type: RopeScalingType,
+    /// The context length multiplier, applied to `original_ctx_size`. llama.cpp's `--rope-freq-scale` is its inverse.
+    pub factor: f32,
+    /// --yarn-orig-ctx
+    /// The context length the model was trained for. Defaults to `rope.scaling.original_context_length` from the
+    /// model's metadata, so the factor doesn't stack on a GGUF whose context is already extended, or else to the
+    /// model's context length.
+    pub original_ctx_size: Option<u64>,
+}
+
+impl RopeScaling {
+    pub fn linear(factor: f32) -> Self {
+        Self {
+            scaling_type: RopeScalingType::Linear,
+            factor,
+            original_ctx_size: None,
+        }
+    }
+
+    pub fn yarn(factor: f32) -> Self {
+        Self {
+            scaling_type: RopeScalingType::Yarn,
+            factor,
+            original_ctx_size: None,
+        }
+    }
+
+    /// Set the context length the model was trained for, if its metadata doesn't report it correctly.
+    pub fn with_original_ctx_size(mut self, original_ctx_size: u64) -> Self {
+        self.original_ctx_size = Some(original_ctx_size);
+        self
+    }
+
+    /// The context length of a model with `model_ctx_size` after scaling.
+    pub fn extended_ctx_size(&self, model_ctx_size: u64) -> u64 {
+        let original_ctx_size = self.original_ctx_size.unwrap_or(model_ctx_size);
+        (original_ctx_size as f64 * self.factor as f64) as u64
+    }
 }
 
 impl LocalLlmConfig {
     pub fn load_model(&mut self, mut llm_loader: GgufLoader) -> crate::Result<LocalLlmModel> {
-        let model = if llm_loader.gguf_local_loader.local_quant_file_path.is_none()
+        let mut model = if llm_loader.gguf_local_loader.local_quant_file_path.is_none()
             || llm_loader.gguf_hf_loader.hf_quant_file_url.is_none()
         {
             self.load_preset_model(llm_loader)?
@@ -39,12 +160,31 @@ impl LocalLlmConfig {
             llm_loader.load()?
         };
 
-        if self.inference_ctx_size > model.model_metadata.context_length() {
+        if let Some(rope_scaling) = &mut self.rope_scaling {
+            if rope_scaling.factor.is_nan() || rope_scaling.factor < 1.0 {
+                crate::bail!(
+                    "The RoPE scaling factor must be at least 1.0, got {}",
+                    rope_scaling.factor
+                );
+            }
+            let original_ctx_size = *rope_scaling.original_ctx_size.get_or_insert(
+                model
+                    .model_metadata
+                    .original_context_length()
+                    .unwrap_or(model.model_metadata.context_length()),
+            );
+            // The token budget of requests is based on the extended context.
+            model.model_base.model_ctx_size = rope_scaling.extended_ctx_size(original_ctx_size);
+            if self.inference_ctx_size > model.model_base.model_ctx_size {
+                crate::warn!("Given value for ctx_size {} is greater than the model's max {} with RoPE scaling. Using the models max.", self.inference_ctx_size, model.model_base.model_ctx_size);
+                self.inference_ctx_size = model.model_base.model_ctx_size;
+            };
+        } else if self.inference_ctx_size > model.model_metadata.context_length() {
             eprintln!("Given value for ctx_size {} is greater than the model's max {}. Using the models max.", self.inference_ctx_size, model.model_metadata.context_length());
             self.inference_ctx_size = model.model_metadata.context_length();
         };
 
-        self.device_config.layer_count = Some(model.model_metadata.layers.count_blocks());
+        self.device_config.layer_count = Some(model.model_metadata.layers.count_blocks() as u64);
         self.device_config.average_layer_size_bytes = Some(
             model
                 .model_metadata
@@ -167,7 +307,7 @@ pub trait LlmLocalTrait {
         self
     }
 
-    /// Sets the number of CPU threads to use for inference.
+    /// Sets the number of CPU threads to use for inference. Passed to llama.cpp as `--threads`.
     ///
     /// # Arguments
     ///
@@ -175,7 +315,11 @@ pub trait LlmLocalTrait {
     ///
     /// # Notes
     ///
-    /// If loading purely in VRAM, this defaults to 1.
+    /// With GPUs, the threads run the layers that don't fit in VRAM. If not set, llama.cpp's default is used.
+    ///
+    /// # Default
+    ///
+    /// Without GPUs, defaults to a percentage of the physical CPU cores.
     fn threads(mut self, threads: i16) -> Self
     where
         Self: Sized,
@@ -184,7 +328,8 @@ pub trait LlmLocalTrait {
         self
     }
 
-    /// Sets the number of CPU threads to use for batching and prompt processing.
+    /// Sets the number of CPU threads to use for batching and prompt processing. Passed to llama.cpp as
+    /// `--threads-batch`.
     ///
     /// # Arguments
     ///
@@ -236,6 +381,122 @@ pub trait LlmLocalTrait {
         self
     }
 
+    /// Enables flash attention (llama.cpp's `--flash-attn`). It lowers the memory used by the KV cache and speeds up
+    /// long contexts, but isn't supported by every model and GPU.
+    ///
+    /// # Default
+    ///
+    /// Defaults to false.
+    fn flash_attention(mut self, flash_attention: bool) -> Self
+    where
+        Self: Sized,
+    {
+        self.config().flash_attention = flash_attention;
+        self
+    }
+
+    /// Sets the data type of the K and V caches (llama.cpp's `--cache-type-k` and `--cache-type-v`). A quantized
+    /// cache such as [KvCacheType::Q8_0] roughly halves the memory used by the context.
+    ///
+    /// # Notes
+    ///
+    /// A quantized V cache requires flash attention. If it isn't enabled with `flash_attention`, it is enabled
+    /// automatically, or the server fails to start if `error_on_config_issue` is set.
+    ///
+    /// # Default
+    ///
+    /// Defaults to f16 for both.
+    fn kv_cache_type(mut self, cache_type_k: KvCacheType, cache_type_v: KvCacheType) -> Self
+    where
+        Self: Sized,
+    {
+        self.config().cache_type_k = Some(cache_type_k);
+        self.config().cache_type_v = Some(cache_type_v);
+        self
+    }
+
+    /// Memory-maps the model file (llama.cpp's default). Pass `false` for `--no-mmap`, which reads the whole model into
+    /// memory at startup.
+    ///
+    /// # Notes
+    ///
+    /// Memory-mapping loads quickly from a local disk and lets the OS share and evict the model's pages. On network
+    /// storage such as NFS, paging the model in on demand is slow, and reading it once with `--no-mmap` is faster.
+    ///
+    /// # Default
+    ///
+    /// Defaults to true.
+    fn use_mmap(mut self, use_mmap: bool) -> Self
+    where
+        Self: Sized,
+    {
+        self.config().use_mmap = use_mmap;
+        self
+    }
+
+    /// Locks the model in RAM (llama.cpp's `--mlock`), so it is never swapped out or compressed and responses don't
+    /// stall on page faults.
+    ///
+    /// # Notes
+    ///
+    /// The locked memory stays in use for as long as the server runs, and isn't available to other processes even
+    /// when the server is idle. The model's layers that stay on the CPU must fit in physical RAM, and the user's
+    /// locked memory limit (`ulimit -l` on Linux) must allow it, or the lock fails and llama.cpp logs a warning.
+    ///
+    /// # Default
+    ///
+    /// Defaults to false.
+    fn use_mlock(mut self, use_mlock: bool) -> Self
+    where
+        Self: Sized,
+    {
+        self.config().use_mlock = use_mlock;
+        self
+    }
+
+    /// Appends arguments to the llama.cpp server command, e.g. `["--override-kv", "tokenizer.ggml.add_bos_token=bool:false"]`,
+    /// for flags this crate doesn't set yet.
+    ///
+    /// # Notes
+    ///
+    /// The arguments are passed as given, after the ones this crate sets, so llama.cpp uses them for flags set twice.
+    /// Arguments the server doesn't know make it fail to start. Only used by the llama.cpp backend.
+    ///
+    /// # Default
+    ///
+    /// Defaults to none.
+    fn extra_server_args<I, S>(mut self, extra_server_args: I) -> Self
+    where
+        Self: Sized,
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config()
+            .extra_server_args
+            .extend(extra_server_args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Runs the model beyond the context length it was trained for (llama.cpp's `--rope-scaling`,
+    /// `--rope-freq-scale` and `--yarn-orig-ctx`), e.g. `RopeScaling::yarn(4.0)` to run an 8k model at 32k.
+    ///
+    /// # Notes
+    ///
+    /// The model's context size becomes the trained context length times the factor, so set `inference_ctx_size`
+    /// to use it. Quality drops the further the context is extended, unless the model was fine-tuned for it. Only
+    /// used by the llama.cpp backend.
+    ///
+    /// # Default
+    ///
+    /// Defaults to no scaling.
+    fn rope_scaling(mut self, rope_scaling: RopeScaling) -> Self
+    where
+        Self: Sized,
+    {
+        self.config().rope_scaling = Some(rope_scaling);
+        self
+    }
+
     /// Sets the amount of RAM to use for inference.
     ///
     /// # Arguments
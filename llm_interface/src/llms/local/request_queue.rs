@@ -0,0 +1,89 @@
+use crate::requests::completion::error::CompletionError;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Queues requests in the client, so a local server with a single slot isn't sent more requests than it can run.
+///
+/// Waiting requests acquire a permit in FIFO order. If `max_queue_depth` is set, requests arriving at a full queue
+/// fail immediately with [CompletionError::QueueFull] instead of waiting.
+#[derive(Debug)]
+pub struct RequestQueue {
+    semaphore: Semaphore,
+    max_concurrent_requests: usize,
+    max_queue_depth: Option<usize>,
+    waiting: AtomicUsize,
+    closed: AtomicBool,
+}
+
+impl RequestQueue {
+    pub fn new(max_concurrent_requests: usize, max_queue_depth: Option<usize>) -> Self {
+        let max_concurrent_requests = max_concurrent_requests.max(1);
+        Self {
+            semaphore: Semaphore::new(max_concurrent_requests),
+            max_concurrent_requests,
+            max_queue_depth,
+            waiting: AtomicUsize::new(0),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    /// Waits for a free slot. The slot is released when the permit is dropped.
+    pub async fn acquire(&self) -> crate::Result<SemaphorePermit<'_>, CompletionError> {
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(CompletionError::ShuttingDown);
+        }
+        if let Ok(permit) = self.semaphore.try_acquire() {
+            return Ok(permit);
+        }
+        let depth = self.waiting.fetch_add(1, Ordering::SeqCst);
+        let _waiting = WaitingGuard(&self.waiting);
+        if let Some(max_queue_depth) = self.max_queue_depth {
+            if depth >= max_queue_depth {
+                return Err(CompletionError::QueueFull(format!(
+                    "{depth} requests are already waiting (max_queue_depth: {max_queue_depth})."
+                )));
+            }
+        }
+        self.semaphore
+            .acquire()
+            .await
+            .map_err(|e| CompletionError::LocalClientError(e.to_string()))
+    }
+
+    /// Stops accepting requests and waits up to `timeout` for the queued and in-flight requests to finish. Returns
+    /// false if requests were still running at the timeout.
+    pub async fn drain(&self, timeout: std::time::Duration) -> bool {
+        self.closed.store(true, Ordering::SeqCst);
+        // Permits are handed out in FIFO order, so this waits for every request queued before it.
+        let drained = matches!(
+            tokio::time::timeout(
+                timeout,
+                self.semaphore
+                    .acquire_many(self.max_concurrent_requests as u32)
+            )
+            .await,
+            Ok(Ok(_))
+        );
+        self.semaphore.close();
+        drained
+    }
+
+    /// The number of requests waiting for a slot.
+    pub fn queue_depth(&self) -> usize {
+        self.waiting.load(Ordering::SeqCst)
+    }
+
+    /// The number of requests currently holding a slot.
+    pub fn in_flight(&self) -> usize {
+        self.max_concurrent_requests - self.semaphore.available_permits()
+    }
+}
+
+/// Removes a request from the waiting count when it gets a slot, fails, or is cancelled.
+struct WaitingGuard<'a>(&'a AtomicUsize);
+
+impl Drop for WaitingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
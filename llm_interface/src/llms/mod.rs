@@ -1,11 +1,14 @@
 use crate::requests::{
     completion::{
         error::CompletionError, request::CompletionRequest, response::CompletionResponse,
+        stream::StreamControl,
     },
     logit_bias::LogitBias,
+    score::TextScore,
 };
 use llm_models::tokenizer::LlmTokenizer;
 use llm_prompt::{LlmPrompt, PromptTokenizer};
+use tracing::Instrument;
 pub mod api;
 #[cfg(any(feature = "llama_cpp_backend", feature = "mistral_rs_backend"))]
 pub mod local;
@@ -24,6 +27,19 @@ impl LlmBackend {
     pub(crate) async fn completion_request(
         &self,
         request: &CompletionRequest,
+    ) -> crate::Result<CompletionResponse, CompletionError> {
+        let span = crate::otel::completion_span(self.model_id());
+        let result = self
+            .backend_completion_request(request)
+            .instrument(span.clone())
+            .await;
+        crate::otel::record_completion(&span, &result);
+        result
+    }
+
+    async fn backend_completion_request(
+        &self,
+        request: &CompletionRequest,
     ) -> crate::Result<CompletionResponse, CompletionError> {
         match self {
             #[cfg(feature = "llama_cpp_backend")]
@@ -36,11 +52,45 @@ impl LlmBackend {
         }
     }
 
+    /// Streams the completion to `on_delta` as it is generated, until it returns [StreamControl::Stop]. Supported by
+    /// the local and OpenAI backends, which yield the same deltas and final [CompletionResponse] so they can be
+    /// swapped for each other.
+    pub(crate) async fn completion_request_streaming(
+        &self,
+        request: &CompletionRequest,
+        on_delta: &mut (dyn FnMut(&str) -> StreamControl + Send),
+    ) -> crate::Result<CompletionResponse, CompletionError> {
+        match self {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(b) => b.completion_request_streaming(request, on_delta).await,
+            #[cfg(feature = "mistral_rs_backend")]
+            LlmBackend::MistralRs(b) => b.completion_request_streaming(request, on_delta).await,
+            LlmBackend::OpenAi(b) => b.completion_request_streaming(request, on_delta).await,
+            _ => Err(CompletionError::RequestBuilderError(format!(
+                "Streaming is not supported by the backend for model {}.",
+                self.model_id()
+            ))),
+        }
+    }
+
+    /// The moderation policy of an API backend. See [api::config::LlmApiConfigTrait::with_moderation].
+    pub(crate) fn moderation(&self) -> Option<&api::moderation::ModerationPolicy> {
+        match self {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(_) => None,
+            #[cfg(feature = "mistral_rs_backend")]
+            LlmBackend::MistralRs(_) => None,
+            LlmBackend::OpenAi(b) => b.client.config.api_config.moderation.as_ref(),
+            LlmBackend::Anthropic(b) => b.client.config.api_config.moderation.as_ref(),
+            LlmBackend::GenericApi(b) => b.client.config.api_config.moderation.as_ref(),
+        }
+    }
+
     pub async fn clear_cache(
         self: &std::sync::Arc<Self>,
     ) -> crate::Result<CompletionResponse, CompletionError> {
         let mut request = CompletionRequest::new(std::sync::Arc::clone(self));
-        request.config.cache_prompt = false;
+        request.config.cache_prompt = Some(false);
         request.config.requested_response_tokens = Some(0);
         request.request().await
     }
@@ -50,12 +100,186 @@ impl LlmBackend {
         prompt: &LlmPrompt,
     ) -> crate::Result<CompletionResponse, CompletionError> {
         let mut request = CompletionRequest::new(std::sync::Arc::clone(self));
-        request.config.cache_prompt = true;
+        request.config.cache_prompt = Some(true);
         request.prompt = prompt.clone();
         request.config.requested_response_tokens = Some(0);
         request.request().await
     }
 
+    /// Preloads the model so the first real request doesn't pay the model load and graph compile costs.
+    /// Local backends wait until the server reports ready and then run a zero token completion, so load errors
+    /// surface here. API backends have nothing to preload and return immediately.
+    pub async fn warm_up(self: &std::sync::Arc<Self>) -> crate::Result<(), CompletionError> {
+        let is_local = match self.as_ref() {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(b) => {
+                b.wait_until_ready()
+                    .await
+                    .map_err(|e| CompletionError::LocalClientError(e.to_string()))?;
+                true
+            }
+            #[cfg(feature = "mistral_rs_backend")]
+            LlmBackend::MistralRs(_) => true,
+            LlmBackend::OpenAi(_) | LlmBackend::Anthropic(_) | LlmBackend::GenericApi(_) => false,
+        };
+        if is_local {
+            self.clear_cache().await?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the prompt is the type this backend expects: a chat template prompt for local backends
+    /// and an OpenAI-style prompt for API backends. Use [LlmBackend::new_prompt] to create a matching prompt.
+    pub fn validate_prompt(&self, prompt: &LlmPrompt) -> crate::Result<(), CompletionError> {
+        let (expected, is_match) = match self {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(_) => (
+                "ChatTemplatePrompt",
+                matches!(prompt, LlmPrompt::ChatTemplatePrompt(_)),
+            ),
+            #[cfg(feature = "mistral_rs_backend")]
+            LlmBackend::MistralRs(_) => (
+                "ChatTemplatePrompt",
+                matches!(prompt, LlmPrompt::ChatTemplatePrompt(_)),
+            ),
+            LlmBackend::OpenAi(_) | LlmBackend::Anthropic(_) | LlmBackend::GenericApi(_) => {
+                ("OpenAiPrompt", matches!(prompt, LlmPrompt::OpenAiPrompt(_)))
+            }
+        };
+        if is_match {
+            return Ok(());
+        }
+        let found = match prompt {
+            LlmPrompt::ChatTemplatePrompt(_) => "ChatTemplatePrompt",
+            LlmPrompt::OpenAiPrompt(_) => "OpenAiPrompt",
+        };
+        Err(CompletionError::RequestBuilderError(format!(
+            "Prompt type mismatch: backend for model {} expects a {expected}, but the request has a {found}. Create prompts with LlmBackend::new_prompt.",
+            self.model_id()
+        )))
+    }
+
+    /// Whether the backend can translate [crate::requests::tool::Tool] definitions and tool results to its native format.
+    pub fn supports_tools(&self) -> bool {
+        match self {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(_) => false,
+            #[cfg(feature = "mistral_rs_backend")]
+            LlmBackend::MistralRs(_) => false,
+            LlmBackend::OpenAi(_) | LlmBackend::Anthropic(_) | LlmBackend::GenericApi(_) => true,
+        }
+    }
+
+    /// Whether the backend accepts token IDs as the prompt. See
+    /// [crate::requests::completion::CompletionRequest::prompt_tokens].
+    pub fn supports_token_prompt(&self) -> bool {
+        match self {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(_) => true,
+            #[cfg(feature = "mistral_rs_backend")]
+            LlmBackend::MistralRs(_) => false,
+            LlmBackend::OpenAi(_) | LlmBackend::Anthropic(_) | LlmBackend::GenericApi(_) => false,
+        }
+    }
+
+    /// Whether the backend passes [crate::requests::req_components::RequestConfig::seed] to the model. Other backends
+    /// ignore the seed.
+    pub fn supports_seed(&self) -> bool {
+        match self {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(_) => true,
+            #[cfg(feature = "mistral_rs_backend")]
+            LlmBackend::MistralRs(_) => false,
+            LlmBackend::OpenAi(_) | LlmBackend::GenericApi(_) => true,
+            LlmBackend::Anthropic(_) => false,
+        }
+    }
+
+    /// Whether the backend constrains generation with [crate::requests::completion::CompletionRequest::grammar_string].
+    /// Other backends ignore the grammar.
+    pub fn supports_grammar(&self) -> bool {
+        match self {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(_) => true,
+            #[cfg(feature = "mistral_rs_backend")]
+            LlmBackend::MistralRs(_) => false,
+            LlmBackend::OpenAi(_) | LlmBackend::Anthropic(_) | LlmBackend::GenericApi(_) => false,
+        }
+    }
+
+    /// The layers allocated to each GPU and the VRAM currently in use. Empty for backends that don't run on local GPUs.
+    pub fn gpu_utilization(&self) -> Vec<llm_devices::devices::gpu::GpuDevice> {
+        match self {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(b) => b.gpu_utilization(),
+            #[cfg(feature = "mistral_rs_backend")]
+            LlmBackend::MistralRs(_) => Vec::new(),
+            LlmBackend::OpenAi(_) | LlmBackend::Anthropic(_) | LlmBackend::GenericApi(_) => {
+                Vec::new()
+            }
+        }
+    }
+
+    /// The number of slots on the llama.cpp server not running a request, from its `/slots` endpoint.
+    pub async fn available_slots(&self) -> crate::Result<usize, CompletionError> {
+        match self {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(b) => b
+                .available_slots()
+                .await
+                .map_err(|e| CompletionError::LocalClientError(e.to_string())),
+            _ => Err(CompletionError::RequestBuilderError(format!(
+                "Querying slots is not supported by the backend for model {}.",
+                self.model_id()
+            ))),
+        }
+    }
+
+    /// The number of requests waiting in the client for a free slot on a local server. Always 0 for API backends.
+    pub fn queue_depth(&self) -> usize {
+        match self {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(b) => b.queue_depth(),
+            #[cfg(feature = "mistral_rs_backend")]
+            LlmBackend::MistralRs(_) => 0,
+            LlmBackend::OpenAi(_) | LlmBackend::Anthropic(_) | LlmBackend::GenericApi(_) => 0,
+        }
+    }
+
+    /// Scores how likely the model finds `continuation` as the text following `prompt`. Only supported by the
+    /// llama_cpp backend. See [local::llama_cpp::LlamaCppBackend::score_text].
+    pub async fn score_text(
+        &self,
+        prompt: &str,
+        continuation: &str,
+    ) -> crate::Result<TextScore, CompletionError> {
+        match self {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(b) => b.score_text(prompt, continuation).await,
+            _ => Err(CompletionError::RequestBuilderError(format!(
+                "Scoring text is not supported by the backend for model {}.",
+                self.model_id()
+            ))),
+        }
+    }
+
+    /// The pooled embedding of each input. Only supported by the llama_cpp backend started for embeddings. See
+    /// [local::llama_cpp::LlamaCppBackend::embeddings].
+    pub async fn embeddings(
+        &self,
+        inputs: &[&str],
+        normalize: bool,
+    ) -> crate::Result<Vec<Vec<f32>>, CompletionError> {
+        match self {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(b) => b.embeddings(inputs, normalize).await,
+            _ => Err(CompletionError::RequestBuilderError(format!(
+                "Embeddings are not supported by the backend for model {}.",
+                self.model_id()
+            ))),
+        }
+    }
+
     pub fn new_prompt(&self) -> LlmPrompt {
         match self {
             #[cfg(feature = "llama_cpp_backend")]
@@ -118,6 +342,28 @@ impl LlmBackend {
         }
     }
 
+    /// The number of tokens a single request can use for its prompt and response together. For llama.cpp this is the
+    /// running server's context size per slot, for mistral.rs the configured context size, and for the API backends
+    /// the model's context size.
+    pub fn request_ctx_size(&self) -> u64 {
+        match self {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(b) => {
+                let slots = u64::from(b.server.parallel_slots.unwrap_or(1).max(1));
+                std::cmp::min(
+                    b.model.model_base.model_ctx_size,
+                    b.server.inference_ctx_size / slots,
+                )
+            }
+            #[cfg(feature = "mistral_rs_backend")]
+            LlmBackend::MistralRs(b) => std::cmp::min(
+                b.model.model_base.model_ctx_size,
+                b.config.local_config.inference_ctx_size,
+            ),
+            _ => self.model_ctx_size(),
+        }
+    }
+
     pub fn inference_ctx_size(&self) -> u64 {
         match self {
             #[cfg(feature = "llama_cpp_backend")]
@@ -184,6 +430,36 @@ impl LlmBackend {
         }
     }
 
+    /// The model's Jinja chat template, or `None` for API backends, which apply their own template.
+    pub fn chat_template(&self) -> Option<&str> {
+        match self {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(b) => Some(&b.model.chat_template.chat_template),
+            #[cfg(feature = "mistral_rs_backend")]
+            LlmBackend::MistralRs(b) => Some(&b.model.chat_template.chat_template),
+            _ => None,
+        }
+    }
+
+    /// Renders messages through the model's chat template, ending with the assistant generation prefix, as they
+    /// would be sent to the model. Each message is a map with a `role` and `content`. Errors for API backends, which
+    /// don't expose their chat template.
+    pub fn render_chat(
+        &self,
+        messages: &[std::collections::HashMap<String, String>],
+    ) -> crate::Result<String> {
+        match self {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(b) => Ok(b.model.chat_template.render(messages, true)),
+            #[cfg(feature = "mistral_rs_backend")]
+            LlmBackend::MistralRs(b) => Ok(b.model.chat_template.render(messages, true)),
+            _ => crate::bail!(
+                "Chat template not available for the backend of model {}",
+                self.model_id()
+            ),
+        }
+    }
+
     pub fn eos_token(&self) -> &str {
         match self {
             #[cfg(feature = "llama_cpp_backend")]
@@ -231,6 +507,36 @@ impl LlmBackend {
         }
     }
 
+    /// Returns a copy of this API backend that uses a different model. The HTTP client, its connection pool and
+    /// config are shared, only the model and its tokenizer change. Local backends must be rebuilt to load a new model.
+    pub fn with_model_id(&self, model_id: &str) -> crate::Result<Self> {
+        use api::{
+            anthropic::AnthropicBackend, generic_openai::GenericApiBackend, openai::OpenAiBackend,
+        };
+        use llm_models::api_model::ApiLlmModel;
+        match self {
+            LlmBackend::OpenAi(b) => Ok(LlmBackend::OpenAi(OpenAiBackend {
+                client: b.client.clone(),
+                model: ApiLlmModel::openai_model_from_model_id(model_id),
+            })),
+            LlmBackend::Anthropic(b) => Ok(LlmBackend::Anthropic(AnthropicBackend {
+                client: b.client.clone(),
+                model: ApiLlmModel::anthropic_model_from_model_id(model_id),
+            })),
+            LlmBackend::GenericApi(b) => {
+                // The served models are unknown, so keep the current limits and tokenizer.
+                let mut model = b.model.clone();
+                model.model_base.model_id = model_id.to_string();
+                Ok(LlmBackend::GenericApi(GenericApiBackend {
+                    client: b.client.clone(),
+                    model,
+                }))
+            }
+            #[allow(unreachable_patterns)]
+            _ => crate::bail!("Changing the model is only supported for API backends"),
+        }
+    }
+
     pub fn shutdown(&self) {
         match self {
             #[cfg(feature = "llama_cpp_backend")]
@@ -242,4 +548,18 @@ impl LlmBackend {
             LlmBackend::GenericApi(_) => (),
         }
     }
+
+    /// Like [Self::shutdown], but local backends first stop accepting requests and wait up to `timeout` for the
+    /// in-flight requests to finish.
+    pub async fn shutdown_graceful(&self, timeout: std::time::Duration) {
+        match self {
+            #[cfg(feature = "llama_cpp_backend")]
+            LlmBackend::LlamaCpp(b) => b.shutdown_graceful(timeout).await,
+            #[cfg(feature = "mistral_rs_backend")]
+            LlmBackend::MistralRs(_) => (),
+            LlmBackend::OpenAi(_) => (),
+            LlmBackend::Anthropic(_) => (),
+            LlmBackend::GenericApi(_) => (),
+        }
+    }
 }
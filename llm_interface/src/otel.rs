@@ -0,0 +1,61 @@
+//! Spans for OpenTelemetry export, enabled with the `otel` feature.
+//!
+//! The spans are regular `tracing` spans with attributes named after the OpenTelemetry GenAI semantic conventions.
+//! To export them, install a global subscriber with a `tracing-opentelemetry` layer and enable
+//! [llm_devices::logging::LoggingConfigTrait::use_global_subscriber]. Without the feature the functions return
+//! disabled spans, so the call sites cost nothing.
+
+use crate::requests::completion::{CompletionError, CompletionResponse};
+
+/// A span around a single call to a backend. Record the outcome with [record_completion].
+pub fn completion_span(model_id: &str) -> tracing::Span {
+    #[cfg(feature = "otel")]
+    {
+        tracing::info_span!(
+            "completion_request",
+            otel.name = format!("completion {model_id}"),
+            otel.kind = "client",
+            otel.status_code = tracing::field::Empty,
+            gen_ai.operation.name = "completion",
+            gen_ai.request.model = model_id,
+            gen_ai.response.finish_reasons = tracing::field::Empty,
+            gen_ai.usage.input_tokens = tracing::field::Empty,
+            gen_ai.usage.output_tokens = tracing::field::Empty,
+            error.type = tracing::field::Empty,
+        )
+    }
+    #[cfg(not(feature = "otel"))]
+    {
+        let _ = model_id;
+        tracing::Span::none()
+    }
+}
+
+/// Adds the token counts and finish reason, or the error, to a span from [completion_span]. Nothing is formatted
+/// for a disabled span.
+pub fn record_completion(
+    span: &tracing::Span,
+    result: &Result<CompletionResponse, CompletionError>,
+) {
+    if span.is_disabled() {
+        return;
+    }
+    match result {
+        Ok(res) => {
+            span.record("otel.status_code", "OK");
+            span.record(
+                "gen_ai.response.finish_reasons",
+                res.finish_reason.to_string(),
+            );
+            span.record("gen_ai.usage.input_tokens", res.token_usage.prompt_tokens);
+            span.record(
+                "gen_ai.usage.output_tokens",
+                res.token_usage.completion_tokens,
+            );
+        }
+        Err(e) => {
+            span.record("otel.status_code", "ERROR");
+            span.record("error.type", e.to_string());
+        }
+    }
+}
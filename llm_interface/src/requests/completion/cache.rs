@@ -0,0 +1,328 @@
+use super::{request::CompletionRequest, response::CompletionResponse};
+use llm_prompt::LlmPrompt;
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// An opt-in cache of responses for deterministic requests, so an identical request doesn't call the model again.
+///
+/// Only requests with a temperature of `0.0` and without logit bias or tools are cached. The key is a [CacheKey] made
+/// of the model, the built prompt, the grammar, the stop sequences and the sampling settings. Entries are evicted least
+/// recently used first once `capacity` entries or the optional byte budget is reached, and expire after the optional
+/// TTL. Hits, misses and evictions are counted, see [ResponseCache::cache_stats].
+///
+/// The cache is shared with [CompletionRequest::with_response_cache], so one cache can serve many requests.
+pub struct ResponseCache {
+    capacity: usize,
+    max_bytes: Option<usize>,
+    ttl: Option<Duration>,
+    inner: Mutex<CacheInner>,
+}
+
+#[derive(Default)]
+struct CacheInner {
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// Keys from least to most recently used.
+    order: VecDeque<CacheKey>,
+    bytes: usize,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+/// Everything that identifies a cached request. The full key is compared on lookup, so requests whose hashes collide
+/// never share a response.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    model_id: String,
+    prompt: CachePrompt,
+    grammar: Option<String>,
+    stop_sequences: Vec<String>,
+    /// The sampling settings, with floats stored as their bits.
+    settings: Vec<Option<u64>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum CachePrompt {
+    Tokens(Vec<u32>),
+    Text(String),
+    Messages(Vec<Vec<(String, String)>>),
+}
+
+impl std::fmt::Display for CacheKey {
+    /// A short hash of the key, for logging.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        write!(f, "{:016x}", hasher.finish())
+    }
+}
+
+struct CacheEntry {
+    response: CompletionResponse,
+    inserted_at: Instant,
+    bytes: usize,
+}
+
+/// A snapshot of the counters of a [ResponseCache].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries removed to stay within the entry or byte budget, or because they expired.
+    pub evictions: u64,
+    pub entries: usize,
+    /// The estimated size of the cached responses.
+    pub bytes: usize,
+}
+
+impl CacheStats {
+    /// The fraction of lookups that were hits, or `None` before the first lookup.
+    pub fn hit_ratio(&self) -> Option<f32> {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            None
+        } else {
+            Some(self.hits as f32 / lookups as f32)
+        }
+    }
+}
+
+impl std::fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "hits: {}, misses: {}, evictions: {}, entries: {}, bytes: {}",
+            self.hits, self.misses, self.evictions, self.entries, self.bytes
+        )?;
+        if let Some(hit_ratio) = self.hit_ratio() {
+            write!(f, ", hit ratio: {:.1}%", hit_ratio * 100.0)?;
+        }
+        Ok(())
+    }
+}
+
+impl ResponseCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            max_bytes: None,
+            ttl: None,
+            inner: Mutex::new(CacheInner::default()),
+        }
+    }
+
+    /// Entries older than `ttl` are treated as missing and dropped.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Evicts entries once the estimated size of the cached responses exceeds `max_bytes`. A response larger than the
+    /// budget is not cached.
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// The cache key for `request`, or `None` if the request is not deterministic and must not be cached.
+    pub fn key(request: &CompletionRequest) -> Option<CacheKey> {
+        if request.config.temperature != 0.0
+            || request.logit_bias.is_some()
+            || request.tools.is_some()
+            || !request.tool_turns.is_empty()
+        {
+            return None;
+        }
+        let prompt = if let Some(tokens) = &request.prompt_tokens {
+            CachePrompt::Tokens(tokens.clone())
+        } else {
+            match &request.prompt {
+                LlmPrompt::ChatTemplatePrompt(_) => {
+                    CachePrompt::Text(request.prompt.get_built_prompt_string().ok()?)
+                }
+                LlmPrompt::OpenAiPrompt(_) => CachePrompt::Messages(
+                    request
+                        .prompt
+                        .get_built_prompt_hashmap()
+                        .ok()?
+                        .into_iter()
+                        .map(|message| {
+                            let mut fields: Vec<_> = message.into_iter().collect();
+                            fields.sort();
+                            fields
+                        })
+                        .collect(),
+                ),
+            }
+        };
+        let config = &request.config;
+        let f32_bits = |value: f32| value.to_bits() as u64;
+        Some(CacheKey {
+            model_id: request.backend.model_id().to_owned(),
+            prompt,
+            grammar: request.grammar_string.clone(),
+            stop_sequences: request.stop_sequences.to_vec(),
+            settings: vec![
+                config.actual_request_tokens,
+                config.top_p.map(f32_bits),
+                config.frequency_penalty.map(f32_bits),
+                Some(f32_bits(config.presence_penalty)),
+                config.repeat_penalty.map(f32_bits),
+                config.repeat_last_n.map(|n| n as u64),
+                config.top_logprobs.map(|n| n as u64),
+                config.reasoning_budget.map(|n| n as u64),
+                config.seed,
+            ],
+        })
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<CompletionResponse> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(entry) = inner.entries.get(key) else {
+            inner.misses += 1;
+            return None;
+        };
+        let expired = self
+            .ttl
+            .is_some_and(|ttl| entry.inserted_at.elapsed() > ttl);
+        if expired {
+            inner.remove(key);
+            inner.evictions += 1;
+            inner.misses += 1;
+            return None;
+        }
+        inner.hits += 1;
+        inner.touch(key);
+        inner.entries.get(key).map(|e| e.response.clone())
+    }
+
+    pub fn insert(&self, key: CacheKey, response: CompletionResponse) {
+        let bytes = estimated_size(&response);
+        if self.capacity == 0 || self.max_bytes.is_some_and(|max_bytes| bytes > max_bytes) {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.remove(&key);
+        while inner.entries.len() >= self.capacity
+            || self
+                .max_bytes
+                .is_some_and(|max_bytes| inner.bytes + bytes > max_bytes)
+        {
+            match inner.order.pop_front() {
+                Some(oldest) => {
+                    if let Some(entry) = inner.entries.remove(&oldest) {
+                        inner.bytes -= entry.bytes;
+                    }
+                    inner.evictions += 1;
+                }
+                None => break,
+            }
+        }
+        inner.order.push_back(key.clone());
+        inner.entries.insert(
+            key,
+            CacheEntry {
+                response,
+                inserted_at: Instant::now(),
+                bytes,
+            },
+        );
+        inner.bytes += bytes;
+    }
+
+    /// Drops the entry for `key`, so the next identical request calls the model again. Returns false if there was
+    /// no entry.
+    pub fn remove(&self, key: &CacheKey) -> bool {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let removed = inner.entries.contains_key(key);
+        inner.remove(key);
+        removed
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        CacheStats {
+            hits: inner.hits,
+            misses: inner.misses,
+            evictions: inner.evictions,
+            entries: inner.entries.len(),
+            bytes: inner.bytes,
+        }
+    }
+
+    /// Sets the hit, miss and eviction counters back to zero without touching the entries.
+    pub fn reset_stats(&self) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.hits = 0;
+        inner.misses = 0;
+        inner.evictions = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entries
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.entries.clear();
+        inner.order.clear();
+        inner.bytes = 0;
+    }
+}
+
+impl CacheInner {
+    fn touch(&mut self, key: &CacheKey) {
+        if let Some(pos) = self.order.iter().position(|k| k == key) {
+            if let Some(key) = self.order.remove(pos) {
+                self.order.push_back(key);
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.bytes -= entry.bytes;
+            if let Some(pos) = self.order.iter().position(|k| k == key) {
+                self.order.remove(pos);
+            }
+        }
+    }
+}
+
+/// The approximate memory used by a cached response: its text, log probabilities and raw body, plus the struct itself.
+fn estimated_size(response: &CompletionResponse) -> usize {
+    let probabilities: usize = response
+        .completion_probabilities
+        .iter()
+        .flatten()
+        .map(|p| {
+            std::mem::size_of_val(p)
+                + p.content.as_ref().map_or(0, String::len)
+                + p.top_probs
+                    .iter()
+                    .map(|t| std::mem::size_of_val(t) + t.token.len())
+                    .sum::<usize>()
+        })
+        .sum();
+    std::mem::size_of::<CompletionResponse>()
+        + response.id.len()
+        + response.content.len()
+        + response.reasoning.as_ref().map_or(0, String::len)
+        + response
+            .raw_response
+            .as_ref()
+            .map_or(0, |raw| raw.to_string().len())
+        + probabilities
+}
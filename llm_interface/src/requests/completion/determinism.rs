@@ -0,0 +1,121 @@
+use super::{error::CompletionError, request::CompletionRequest};
+
+/// The seed used by [verify_determinism] when the request doesn't set one.
+pub const DEFAULT_DETERMINISM_SEED: u64 = 42;
+
+/// Runs `request` `runs` times (at least twice) with the same seed and compares the responses.
+///
+/// The request's seed is used, or [DEFAULT_DETERMINISM_SEED] if it has none. The response cache is bypassed so every
+/// run reaches the model. A backend that ignores the seed, such as Anthropic, usually fails the check unless the
+/// temperature is zero, and [DeterminismReport::seed_supported] is false for it.
+pub async fn verify_determinism(
+    request: &CompletionRequest,
+    runs: usize,
+) -> Result<DeterminismReport, CompletionError> {
+    let seed = request.config.seed.unwrap_or(DEFAULT_DETERMINISM_SEED);
+    let mut outputs = Vec::with_capacity(runs.max(2));
+    for _ in 0..runs.max(2) {
+        let mut req = request.clone();
+        req.response_cache = None;
+        req.config.seed = Some(seed);
+        outputs.push(req.request().await?.content);
+    }
+    Ok(DeterminismReport::from_outputs(
+        seed,
+        request.backend.supports_seed(),
+        outputs,
+    ))
+}
+
+/// The result of [verify_determinism]. Every run is compared to the first one.
+#[derive(Debug, Clone)]
+pub struct DeterminismReport {
+    pub seed: u64,
+    /// Whether the backend passes the seed to the model. See [crate::llms::LlmBackend::supports_seed].
+    pub seed_supported: bool,
+    /// The response content of each run.
+    pub outputs: Vec<String>,
+    /// The runs whose content differs from the first run.
+    pub mismatches: Vec<DeterminismMismatch>,
+}
+
+/// Where a run first differs from the first run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeterminismMismatch {
+    /// The index of the run in [DeterminismReport::outputs].
+    pub run: usize,
+    /// The character offset of the first difference.
+    pub offset: usize,
+    /// The first run's content from the first difference, up to [DeterminismMismatch::EXCERPT_CHARS] characters.
+    pub expected: String,
+    /// This run's content from the first difference, up to [DeterminismMismatch::EXCERPT_CHARS] characters.
+    pub actual: String,
+}
+
+impl DeterminismMismatch {
+    pub const EXCERPT_CHARS: usize = 40;
+}
+
+impl DeterminismReport {
+    pub fn from_outputs(seed: u64, seed_supported: bool, outputs: Vec<String>) -> Self {
+        let mismatches = match outputs.split_first() {
+            Some((first, rest)) => rest
+                .iter()
+                .enumerate()
+                .filter_map(|(i, output)| {
+                    let offset = first
+                        .chars()
+                        .zip(output.chars())
+                        .position(|(a, b)| a != b)
+                        .or_else(|| {
+                            let (a, b) = (first.chars().count(), output.chars().count());
+                            (a != b).then_some(a.min(b))
+                        })?;
+                    let excerpt = |text: &str| {
+                        text.chars()
+                            .skip(offset)
+                            .take(DeterminismMismatch::EXCERPT_CHARS)
+                            .collect()
+                    };
+                    Some(DeterminismMismatch {
+                        run: i + 1,
+                        offset,
+                        expected: excerpt(first),
+                        actual: excerpt(output),
+                    })
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        Self {
+            seed,
+            seed_supported,
+            outputs,
+            mismatches,
+        }
+    }
+
+    /// True if every run returned the same content.
+    pub fn is_deterministic(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl std::fmt::Display for DeterminismReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f)?;
+        writeln!(f, "DeterminismReport:")?;
+        writeln!(f, "    seed: {}", self.seed)?;
+        writeln!(f, "    seed_supported: {}", self.seed_supported)?;
+        writeln!(f, "    runs: {}", self.outputs.len())?;
+        writeln!(f, "    deterministic: {}", self.is_deterministic())?;
+        for mismatch in &self.mismatches {
+            writeln!(
+                f,
+                "    run {} differs at char {}: expected {:?}, got {:?}",
+                mismatch.run, mismatch.offset, mismatch.expected, mismatch.actual
+            )?;
+        }
+        Ok(())
+    }
+}
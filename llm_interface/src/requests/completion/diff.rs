@@ -0,0 +1,96 @@
+use super::response::CompletionResponse;
+use std::collections::HashMap;
+
+/// How two responses differ, e.g. the responses of two model versions to the same request. See
+/// [CompletionResponse::compare].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseDiff {
+    /// True if the trimmed contents are equal.
+    pub identical_content: bool,
+    /// The overlap of the whitespace separated tokens of the contents, from 0.0 to 1.0. See [token_overlap].
+    pub token_overlap: f32,
+    /// The number of characters the other content has more than this one. Negative if it is shorter.
+    pub length_delta: i64,
+    /// The number of completion tokens the other response used more than this one, as reported by the backends.
+    pub completion_tokens_delta: i64,
+    /// True if both responses stopped for the same reason.
+    pub same_finish_reason: bool,
+    /// Whether both contents parse to the same result. Set by [CompletionResponse::compare_parsed].
+    pub same_result: ResultComparison,
+}
+
+/// How the parsed results of two responses compare, see [ResponseDiff::same_result].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultComparison {
+    /// The contents weren't parsed, see [CompletionResponse::compare].
+    NotParsed,
+    /// Both contents parse to the same result.
+    Same,
+    /// Both contents parse, to different results.
+    Different,
+    /// Only one of the contents parses.
+    OneFailed,
+    /// Neither content parses.
+    BothFailed,
+}
+
+impl CompletionResponse {
+    /// Compares this response with `other`.
+    pub fn compare(&self, other: &CompletionResponse) -> ResponseDiff {
+        let (content, other_content) = (self.content_trimmed(), other.content_trimmed());
+        ResponseDiff {
+            identical_content: content == other_content,
+            token_overlap: token_overlap(content, other_content),
+            length_delta: other_content.chars().count() as i64 - content.chars().count() as i64,
+            completion_tokens_delta: other.token_usage.completion_tokens as i64
+                - self.token_usage.completion_tokens as i64,
+            same_finish_reason: self.finish_reason == other.finish_reason,
+            same_result: ResultComparison::NotParsed,
+        }
+    }
+
+    /// Like [Self::compare], but also parses both trimmed contents with `parse` to compare their results.
+    pub fn compare_parsed<T, E, F>(&self, other: &CompletionResponse, parse: F) -> ResponseDiff
+    where
+        T: PartialEq,
+        F: Fn(&str) -> Result<T, E>,
+    {
+        let same_result = match (
+            parse(self.content_trimmed()),
+            parse(other.content_trimmed()),
+        ) {
+            (Ok(result), Ok(other_result)) if result == other_result => ResultComparison::Same,
+            (Ok(_), Ok(_)) => ResultComparison::Different,
+            (Err(_), Err(_)) => ResultComparison::BothFailed,
+            _ => ResultComparison::OneFailed,
+        };
+        ResponseDiff {
+            same_result,
+            ..self.compare(other)
+        }
+    }
+}
+
+/// The share of whitespace separated tokens two texts have in common, counting repeated tokens: twice the number of
+/// shared tokens divided by the total number of tokens. 1.0 for identical token lists, including two empty texts.
+pub fn token_overlap(text: &str, other: &str) -> f32 {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut total = 0;
+    for token in text.split_whitespace() {
+        *counts.entry(token).or_default() += 1;
+        total += 1;
+    }
+    let mut shared = 0;
+    for token in other.split_whitespace() {
+        if let Some(count) = counts.get_mut(token).filter(|count| **count > 0) {
+            *count -= 1;
+            shared += 1;
+        }
+        total += 1;
+    }
+    if total == 0 {
+        1.0
+    } else {
+        2.0 * shared as f32 / total as f32
+    }
+}
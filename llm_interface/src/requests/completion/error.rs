@@ -9,8 +9,23 @@ pub enum CompletionError {
     LocalClientError(String),
     #[error("RequestTokenLimitError: {0}")]
     RequestTokenLimitError(#[from] llm_prompt::RequestTokenLimitError),
+    #[error("ContextLengthExceeded: prompt_tokens ({prompt_tokens}) + requested_response_tokens ({requested_response_tokens}) exceeds the request context size ({ctx_size}) by {overflow} tokens")]
+    ContextLengthExceeded {
+        prompt_tokens: u64,
+        requested_response_tokens: u64,
+        ctx_size: u64,
+        overflow: u64,
+    },
     #[error("StopReasonUnsupported: {0}")]
     StopReasonUnsupported(String),
+    #[error("QueueFull: {0}")]
+    QueueFull(String),
+    #[error("Refused: {0}")]
+    Refused(String),
+    #[error("ModerationBlocked: The request was flagged by moderation for {categories:?}")]
+    ModerationBlocked { categories: Vec<String> },
+    #[error("ShuttingDown: The backend is shutting down and no longer accepts requests")]
+    ShuttingDown,
     #[error("ExceededRetryCount")]
     ExceededRetryCount {
         message: String,
@@ -29,4 +44,6 @@ pub enum CompletionError {
         "NonMatchingStopSequence: One of the sequences is required, but response's stop sequence was: {0}."
     )]
     NonMatchingStopSequence(String),
+    #[error("ToolCallParseError: {0}")]
+    ToolCallParseError(String),
 }
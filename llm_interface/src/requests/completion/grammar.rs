@@ -0,0 +1,147 @@
+use std::collections::HashSet;
+
+#[derive(Debug, PartialEq)]
+enum GbnfToken {
+    Name(String),
+    Define,
+    Terminal,
+    LParen,
+    RParen,
+    Alternate,
+    Repeat,
+}
+
+/// Checks that `gbnf` is a syntactically valid GBNF grammar: every rule is `name ::= ...`, literals, character
+/// classes and groups are closed, repetition operators follow an element, and every referenced rule, including
+/// `root`, is defined.
+///
+/// This catches mistakes before the request is sent. The server's parser remains the final authority.
+pub fn validate_gbnf(gbnf: &str) -> Result<(), String> {
+    let tokens = tokenize_gbnf(gbnf)?;
+    let mut defined = HashSet::new();
+    let mut referenced = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let name = match (&tokens[i], tokens.get(i + 1)) {
+            (GbnfToken::Name(name), Some(GbnfToken::Define)) => name,
+            (token, _) => return Err(format!("Expected a rule definition, found {token:?}.")),
+        };
+        if !defined.insert(name.as_str()) {
+            return Err(format!("Rule '{name}' is defined more than once."));
+        }
+        i += 2;
+        let mut depth = 0usize;
+        let mut after_element = false;
+        while i < tokens.len() {
+            if depth == 0 && tokens.get(i + 1) == Some(&GbnfToken::Define) {
+                break;
+            }
+            match &tokens[i] {
+                GbnfToken::Name(reference) => {
+                    referenced.push(reference.as_str());
+                    after_element = true;
+                }
+                GbnfToken::Terminal => after_element = true,
+                GbnfToken::LParen => {
+                    depth += 1;
+                    after_element = false;
+                }
+                GbnfToken::RParen => {
+                    depth = depth
+                        .checked_sub(1)
+                        .ok_or_else(|| format!("Unmatched ')' in rule '{name}'."))?;
+                    after_element = true;
+                }
+                GbnfToken::Alternate => after_element = false,
+                GbnfToken::Repeat => {
+                    if !after_element {
+                        return Err(format!(
+                            "Repetition operator without an element in rule '{name}'."
+                        ));
+                    }
+                }
+                GbnfToken::Define => return Err(format!("Unexpected '::=' in rule '{name}'.")),
+            }
+            i += 1;
+        }
+        if depth > 0 {
+            return Err(format!("Unclosed '(' in rule '{name}'."));
+        }
+    }
+    if !defined.contains("root") {
+        return Err("Grammar has no 'root' rule.".to_string());
+    }
+    match referenced.into_iter().find(|r| !defined.contains(r)) {
+        Some(undefined) => Err(format!("Rule '{undefined}' is referenced but not defined.")),
+        None => Ok(()),
+    }
+}
+
+fn tokenize_gbnf(gbnf: &str) -> Result<Vec<GbnfToken>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = gbnf.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => (),
+            '#' => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '"' | '[' => {
+                let end = if c == '"' { '"' } else { ']' };
+                loop {
+                    match chars.next() {
+                        Some('\\') => {
+                            chars.next();
+                        }
+                        Some(c) if c == end => break,
+                        Some('\n') | None => return Err(format!("Unclosed '{c}'.")),
+                        Some(_) => (),
+                    }
+                }
+                tokens.push(GbnfToken::Terminal);
+            }
+            '.' => tokens.push(GbnfToken::Terminal),
+            '(' => tokens.push(GbnfToken::LParen),
+            ')' => tokens.push(GbnfToken::RParen),
+            '|' => tokens.push(GbnfToken::Alternate),
+            '*' | '+' | '?' => tokens.push(GbnfToken::Repeat),
+            '{' => {
+                let mut count = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) if c.is_ascii_digit() || c == ',' || c == ' ' => count.push(c),
+                        _ => return Err("Malformed repetition count.".to_string()),
+                    }
+                }
+                if !count.chars().any(|c| c.is_ascii_digit()) {
+                    return Err("Repetition count is missing a number.".to_string());
+                }
+                tokens.push(GbnfToken::Repeat);
+            }
+            ':' => {
+                if chars.next() != Some(':') || chars.next() != Some('=') {
+                    return Err("Expected '::='.".to_string());
+                }
+                tokens.push(GbnfToken::Define);
+            }
+            c if c.is_ascii_alphanumeric() || c == '-' || c == '_' => {
+                let mut name = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                        break;
+                    }
+                    name.push(c);
+                    chars.next();
+                }
+                tokens.push(GbnfToken::Name(name));
+            }
+            c => return Err(format!("Unexpected character '{c}'.")),
+        }
+    }
+    Ok(tokens)
+}
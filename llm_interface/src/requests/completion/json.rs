@@ -0,0 +1,73 @@
+/// Parses `content` as JSON. With `repair`, content that fails to parse is repaired with [repair_json] and parsed
+/// again, and the error of the repaired content is returned if that fails too.
+pub fn parse_json(content: &str, repair: bool) -> Result<serde_json::Value, serde_json::Error> {
+    match serde_json::from_str(content.trim()) {
+        Ok(value) => Ok(value),
+        Err(_) if repair => serde_json::from_str(&repair_json(content)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Fixes the usual ways a model wraps or breaks otherwise valid JSON: the value is taken from inside a markdown code
+/// fence, text before the first bracket and after the last matching bracket is dropped, and trailing commas before a
+/// closing bracket are removed. Commas and brackets inside strings are left alone.
+pub fn repair_json(content: &str) -> String {
+    let content = strip_code_fence(content.trim());
+    let content = match content.find(['{', '[']) {
+        Some(start) => {
+            let close = if content[start..].starts_with('{') {
+                '}'
+            } else {
+                ']'
+            };
+            match content.rfind(close) {
+                Some(end) if end > start => &content[start..=end],
+                _ => &content[start..],
+            }
+        }
+        None => content,
+    };
+    remove_trailing_commas(content)
+}
+
+/// The text inside the first ``` fence, with its language tag removed, or `content` if it has no fence.
+fn strip_code_fence(content: &str) -> &str {
+    let Some(start) = content.find("```") else {
+        return content;
+    };
+    let after = &content[start + 3..];
+    // The language tag, e.g. "json", runs until the end of the opening fence's line.
+    let body = match after.find('\n') {
+        Some(newline) => &after[newline + 1..],
+        None => after,
+    };
+    match body.find("```") {
+        Some(end) => body[..end].trim(),
+        None => body.trim(),
+    }
+}
+
+fn remove_trailing_commas(content: &str) -> String {
+    let mut repaired = String::with_capacity(content.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in content.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => (),
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let next = content[i + 1..].trim_start().chars().next();
+            if matches!(next, Some('}' | ']')) {
+                continue;
+            }
+        }
+        repaired.push(c);
+    }
+    repaired
+}
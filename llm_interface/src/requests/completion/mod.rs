@@ -1,8 +1,20 @@
+pub mod cache;
+pub mod determinism;
+pub mod diff;
 pub mod error;
+pub mod grammar;
+pub mod json;
 pub mod request;
 pub mod response;
+pub mod stream;
 
 pub use super::res_components::{GenerationSettings, TimingUsage, TokenUsage};
+pub use cache::{CacheKey, CacheStats, ResponseCache};
+pub use determinism::{verify_determinism, DeterminismReport};
+pub use diff::{token_overlap, ResponseDiff, ResultComparison};
 pub use error::CompletionError;
+pub use grammar::validate_gbnf;
+pub use json::{parse_json, repair_json};
 pub use request::CompletionRequest;
 pub use response::{CompletionFinishReason, CompletionResponse};
+pub use stream::{JsonStreamTracker, StreamControl};
@@ -1,9 +1,17 @@
-use super::{error::CompletionError, response::CompletionResponse};
+use super::{
+    cache::ResponseCache,
+    error::CompletionError,
+    response::CompletionResponse,
+    stream::{JsonStreamTracker, StreamControl},
+};
 use crate::{
     llms::LlmBackend,
     requests::{
-        completion::response::CompletionFinishReason, logit_bias::LogitBias,
-        req_components::RequestConfig, stop_sequence::StopSequences,
+        completion::response::CompletionFinishReason,
+        logit_bias::LogitBias,
+        req_components::RequestConfig,
+        stop_sequence::StopSequences,
+        tool::{Tool, ToolCall, ToolResult, ToolTurn},
     },
 };
 use llm_prompt::LlmPrompt;
@@ -17,6 +25,15 @@ pub struct CompletionRequest {
     pub config: RequestConfig,
     pub backend: std::sync::Arc<LlmBackend>,
     pub llm_interface_errors: Vec<CompletionError>,
+    /// Tools the model may call. Translated by each backend to its native format.
+    pub tools: Option<Vec<Tool>>,
+    /// Tool calls made by the model and their results, sent after the prompt messages on the follow-up turn.
+    pub tool_turns: Vec<ToolTurn>,
+    /// Responses of deterministic requests are stored here and reused for identical requests. See [ResponseCache].
+    pub response_cache: Option<std::sync::Arc<ResponseCache>>,
+    /// Token IDs sent as the prompt instead of [Self::prompt], e.g. to continue from an exact tokenization. The
+    /// tokens are sent as given, without a chat template or BOS token. Only supported by the llama.cpp backend.
+    pub prompt_tokens: Option<Vec<u32>>,
 }
 
 impl Clone for CompletionRequest {
@@ -30,6 +47,10 @@ impl Clone for CompletionRequest {
             config: self.config.clone(),
             backend: std::sync::Arc::clone(&self.backend),
             llm_interface_errors: Vec::new(),
+            tools: self.tools.clone(),
+            tool_turns: self.tool_turns.clone(),
+            response_cache: self.response_cache.clone(),
+            prompt_tokens: self.prompt_tokens.clone(),
         }
     }
 }
@@ -40,36 +61,212 @@ impl CompletionRequest {
             start_time: std::time::Instant::now(),
             stop_sequences: Default::default(),
             logit_bias: None,
-            config: RequestConfig::new(backend.model_ctx_size(), backend.inference_ctx_size()),
+            config: RequestConfig::new(backend.request_ctx_size(), backend.inference_ctx_size()),
             prompt: backend.new_prompt(),
             grammar_string: None,
             backend: std::sync::Arc::clone(&backend),
             llm_interface_errors: Vec::new(),
+            tools: None,
+            tool_turns: Vec::new(),
+            response_cache: None,
+            prompt_tokens: None,
         }
     }
 
+    /// A request with pre-tokenized input as the prompt. See [Self::prompt_tokens].
+    pub fn from_tokens(backend: std::sync::Arc<LlmBackend>, tokens: Vec<u32>) -> CompletionRequest {
+        let mut request = Self::new(backend);
+        request.prompt_tokens = Some(tokens);
+        request
+    }
+
     pub fn reset_completion_request(&mut self) {
         self.prompt.reset_prompt();
         self.stop_sequences.sequences.clear();
         self.grammar_string = None;
         self.logit_bias = None;
+        self.tools = None;
+        self.tool_turns.clear();
+        self.prompt_tokens = None;
+    }
+
+    /// Sets the tools the model may call. If the model calls any, the response has
+    /// [CompletionFinishReason::ToolCalls] and the calls in [CompletionResponse::tool_calls].
+    pub fn with_tools(&mut self, tools: Vec<Tool>) -> &mut Self {
+        self.tools = if tools.is_empty() { None } else { Some(tools) };
+        self
+    }
+
+    /// Constrains generation with a GBNF grammar written by hand, e.g. loaded from a `.gbnf` file. The grammar is
+    /// checked with [super::validate_gbnf]. Errors if the backend doesn't support grammars.
+    pub fn with_grammar<T: Into<String>>(
+        &mut self,
+        gbnf: T,
+    ) -> crate::Result<&mut Self, CompletionError> {
+        if !self.backend.supports_grammar() {
+            return Err(CompletionError::RequestBuilderError(format!(
+                "Grammars are not supported by the backend for model {}",
+                self.backend.model_id()
+            )));
+        }
+        let gbnf = gbnf.into();
+        super::validate_gbnf(&gbnf)
+            .map_err(|e| CompletionError::RequestBuilderError(format!("Invalid grammar: {e}")))?;
+        self.grammar_string = Some(gbnf);
+        Ok(self)
+    }
+
+    /// Reuses responses from `cache` for identical requests with a temperature of `0.0`.
+    pub fn with_response_cache(&mut self, cache: std::sync::Arc<ResponseCache>) -> &mut Self {
+        self.response_cache = Some(cache);
+        self
+    }
+
+    /// Submits the results of the tool calls from a previous response. The calls and their results are
+    /// sent to the model on the next request so it can continue with the tool output.
+    pub fn submit_tool_results(
+        &mut self,
+        tool_calls: Vec<ToolCall>,
+        tool_results: Vec<ToolResult>,
+    ) {
+        self.tool_turns.push(ToolTurn {
+            tool_calls,
+            tool_results,
+        });
     }
 
     pub async fn request(&mut self) -> crate::Result<CompletionResponse, CompletionError> {
+        let total_prompt_tokens = self.prepare_request()?;
+
+        let cache_key = match &self.response_cache {
+            Some(cache) => ResponseCache::key(self).map(|key| (cache.clone(), key)),
+            None => None,
+        };
+        if let Some((cache, key)) = &cache_key {
+            if let Some(res) = cache.get(key) {
+                tracing::info!("Returning cached response for request with key {key}");
+                return Ok(res);
+            }
+        }
+        self.check_moderation().await?;
+        let res = self.request_with_retries(total_prompt_tokens).await?;
+        if let Some((cache, key)) = cache_key {
+            cache.insert(key, res.clone());
+        }
+        Ok(res)
+    }
+
+    /// Like [Self::request], but calls `on_delta` with each piece of generated text as it is generated.
+    ///
+    /// Supported by the local and OpenAI backends. The request is not retried, since the deltas of a failed attempt
+    /// have already been delivered, and the response cache is not used.
+    pub async fn request_streaming<F: FnMut(&str) + Send>(
+        &mut self,
+        mut on_delta: F,
+    ) -> crate::Result<CompletionResponse, CompletionError> {
+        self.prepare_request()?;
+        self.check_moderation().await?;
+        tracing::info!("{}", self);
+        let res = self
+            .backend
+            .completion_request_streaming(self, &mut |delta: &str| {
+                on_delta(delta);
+                StreamControl::Continue
+            })
+            .await?;
+        tracing::info!("{}", res);
+        Ok(res)
+    }
+
+    /// Like [Self::request_streaming], but stops generating as soon as the first complete and parseable top level
+    /// JSON object or array has been generated. Useful for structured output on backends without grammar support,
+    /// where the model may keep talking after the JSON.
+    ///
+    /// The response content is the JSON value alone, and `on_delta` receives the text up to its end. If the model
+    /// stops before completing a value, the response is returned unchanged.
+    pub async fn request_json_streaming<F: FnMut(&str) + Send>(
+        &mut self,
+        mut on_delta: F,
+    ) -> crate::Result<CompletionResponse, CompletionError> {
+        self.prepare_request()?;
+        self.check_moderation().await?;
+        tracing::info!("{}", self);
+        let mut tracker = JsonStreamTracker::new();
+        let mut res = self
+            .backend
+            .completion_request_streaming(self, &mut |delta: &str| {
+                let consumed = tracker.push_delta(delta);
+                if consumed > 0 {
+                    on_delta(&delta[..consumed]);
+                }
+                match tracker.json() {
+                    Some(_) => StreamControl::Stop,
+                    None => StreamControl::Continue,
+                }
+            })
+            .await?;
+        if let Some(json) = tracker.json() {
+            res.content = json.to_owned();
+        }
+        tracing::info!("{}", res);
+        Ok(res)
+    }
+
+    /// Validates the request and sets the response token limit. Returns the prompt's token count.
+    fn prepare_request(&mut self) -> crate::Result<u64, CompletionError> {
         self.llm_interface_errors.clear();
         self.start_time = std::time::Instant::now();
+        self.config.validate()?;
+        if self.prompt_tokens.is_none() {
+            self.backend.validate_prompt(&self.prompt)?;
+        }
+        if (self.tools.is_some() || !self.tool_turns.is_empty()) && !self.backend.supports_tools() {
+            return Err(CompletionError::RequestBuilderError(format!(
+                "Tool calling is not supported by the {} backend",
+                self.backend.model_id()
+            )));
+        }
         self.backend
             .build_logit_bias(&mut self.logit_bias)
             .map_err(|e| CompletionError::RequestBuilderError(e.to_string()))?;
-        let total_prompt_tokens = self
-            .prompt
-            .get_total_prompt_tokens()
-            .map_err(|e| CompletionError::RequestBuilderError(e.to_string()))?;
+        let total_prompt_tokens = match &self.prompt_tokens {
+            Some(_) if !self.backend.supports_token_prompt() => {
+                return Err(CompletionError::RequestBuilderError(format!(
+                    "Token prompts are not supported by the {} backend",
+                    self.backend.model_id()
+                )))
+            }
+            Some(tokens) if tokens.is_empty() => {
+                return Err(CompletionError::RequestBuilderError(
+                    "Token prompt is empty".to_string(),
+                ))
+            }
+            Some(tokens) => tokens.len() as u64,
+            None => self
+                .prompt
+                .get_total_prompt_tokens()
+                .map_err(|e| CompletionError::RequestBuilderError(e.to_string()))?,
+        };
+        self.config.check_context_length(total_prompt_tokens)?;
 
         self.config
             .set_max_tokens_for_request(total_prompt_tokens)
             .map_err(CompletionError::RequestTokenLimitError)?;
+        Ok(total_prompt_tokens)
+    }
+
+    /// Runs the backend's moderation policy on the prompt, once per request rather than once per attempt.
+    async fn check_moderation(&self) -> crate::Result<(), CompletionError> {
+        match self.backend.moderation() {
+            Some(moderation) => moderation.check_prompt(&self.prompt).await,
+            None => Ok(()),
+        }
+    }
 
+    async fn request_with_retries(
+        &mut self,
+        total_prompt_tokens: u64,
+    ) -> crate::Result<CompletionResponse, CompletionError> {
         let mut retry_count: u8 = 0;
 
         loop {
@@ -90,6 +287,10 @@ impl CompletionRequest {
                     match e {
                         CompletionError::RequestBuilderError { .. }
                         | CompletionError::StopReasonUnsupported { .. }
+                        | CompletionError::QueueFull { .. }
+                        | CompletionError::ShuttingDown
+                        | CompletionError::ModerationBlocked { .. }
+                        | CompletionError::Refused { .. }
                         | CompletionError::ClientError { .. } => {
                             return Err(e);
                         }
@@ -101,6 +302,9 @@ impl CompletionRequest {
                 }
                 Ok(res) => {
                     tracing::info!("{}", res);
+                    if res.finish_reason == CompletionFinishReason::ToolCalls {
+                        return Ok(res);
+                    }
                     if self.stop_sequences.required {
                         if matches!(
                             res.finish_reason,
@@ -143,7 +347,10 @@ impl CompletionRequest {
                             }
                             return Ok(res);
                         }
-                        CompletionFinishReason::Eos => return Ok(res),
+                        CompletionFinishReason::Eos
+                        | CompletionFinishReason::ToolCalls
+                        | CompletionFinishReason::StreamStopped
+                        | CompletionFinishReason::TimedOut => return Ok(res),
                     }
                 }
             };
@@ -181,12 +388,22 @@ impl std::fmt::Display for CompletionRequest {
         writeln!(f)?;
         writeln!(f, "CompletionRequest:")?;
 
-        writeln!(f, "  prompt: {}", self.prompt)?;
+        match &self.prompt_tokens {
+            Some(tokens) => writeln!(f, "  prompt_tokens: {tokens:?}")?,
+            None => writeln!(f, "  prompt: {}", self.prompt)?,
+        }
         writeln!(f, "  stop_sequences: {:?}", self.stop_sequences.to_vec())?;
         if let Some(logit_bias) = &self.logit_bias {
             writeln!(f, "  logit_bias: {}", logit_bias)?;
         }
         writeln!(f, "  grammar_string: {:?}", self.grammar_string)?;
+        if let Some(tools) = &self.tools {
+            let names: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+            writeln!(f, "  tools: {:?}", names)?;
+        }
+        if !self.tool_turns.is_empty() {
+            writeln!(f, "  tool_turns: {:?}", self.tool_turns)?;
+        }
         write!(f, "  config: {}", self.config)
     }
 }
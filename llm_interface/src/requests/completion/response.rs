@@ -1,8 +1,11 @@
+use super::request::CompletionRequest;
 use crate::requests::{
     res_components::{GenerationSettings, InferenceProbabilities, TimingUsage, TokenUsage},
     stop_sequence::StoppingSequence,
+    tool::ToolCall,
 };
 
+#[derive(Clone)]
 pub struct CompletionResponse {
     /// A unique identifier for the chat completion.
     pub id: String,
@@ -10,6 +13,10 @@ pub struct CompletionResponse {
     pub index: Option<u32>,
     /// The generated completion.
     pub content: String,
+    /// The model's reasoning, kept separate from the final answer in `content`. Only set if [crate::requests::req_components::RequestConfig::reasoning_budget] is enabled and the backend returns it.
+    pub reasoning: Option<String>,
+    /// The tools the model requested to call. Set when [CompletionFinishReason::ToolCalls] is the finish reason.
+    pub tool_calls: Option<Vec<ToolCall>>,
     pub finish_reason: CompletionFinishReason,
     pub completion_probabilities: Option<Vec<InferenceProbabilities>>,
     /// True if the context size was exceeded during generation, i.e. the number of tokens provided in the prompt (tokens_evaluated) plus tokens generated (tokens predicted) exceeded the context size (n_ctx)
@@ -17,6 +24,105 @@ pub struct CompletionResponse {
     pub generation_settings: GenerationSettings,
     pub timing_usage: TimingUsage,
     pub token_usage: TokenUsage,
+    /// The untouched response body from the backend. Only set if [crate::requests::req_components::RequestConfig::capture_raw] is enabled.
+    pub raw_response: Option<serde_json::Value>,
+    /// The strings [CompletionResponse::content_trimmed] removes from the ends of the content: the chat template's
+    /// BOS and EOS tokens for local backends, and the request's stop sequences.
+    pub special_tokens: Vec<String>,
+}
+
+impl CompletionResponse {
+    /// The fraction of the prompt reused from the server's prompt cache. See [TokenUsage::cache_hit_ratio].
+    pub fn cache_hit_ratio(&self) -> Option<f32> {
+        self.token_usage.cache_hit_ratio()
+    }
+
+    /// True if generation was cut off before the model finished, see [CompletionFinishReason::TimedOut]. The content
+    /// is the text generated until then.
+    pub fn is_partial(&self) -> bool {
+        self.finish_reason == CompletionFinishReason::TimedOut
+    }
+
+    /// A response assembled from streamed content, for streams whose final event doesn't carry the backend's usage
+    /// and settings. Token counts are measured with the backend's tokenizer.
+    pub fn new_from_stream(
+        req: &CompletionRequest,
+        id: &str,
+        content: String,
+        finish_reason: CompletionFinishReason,
+    ) -> Self {
+        let prompt_tokens = match &req.prompt_tokens {
+            Some(tokens) => tokens.len() as u32,
+            None => req.prompt.get_total_prompt_tokens().unwrap_or_default() as u32,
+        };
+        let completion_tokens = req.backend.tokenizer().count_tokens(&content);
+        Self {
+            id: id.to_owned(),
+            index: None,
+            content,
+            reasoning: None,
+            tool_calls: None,
+            finish_reason,
+            completion_probabilities: None,
+            truncated: false,
+            generation_settings: GenerationSettings {
+                model: req.backend.model_id().to_owned(),
+                frequency_penalty: req.config.frequency_penalty,
+                presence_penalty: req.config.presence_penalty,
+                temperature: req.config.temperature,
+                top_p: req.config.top_p,
+                n_choices: 1,
+                n_predict: req.config.actual_request_tokens.map(|x| x as i32),
+                n_ctx: req.config.inference_ctx_size,
+                logit_bias: None,
+                grammar: req.grammar_string.clone(),
+                stop_sequences: req.stop_sequences.to_vec(),
+            },
+            timing_usage: TimingUsage::new_from_generic(req.start_time),
+            token_usage: TokenUsage {
+                tokens_cached: None,
+                cached_prompt_tokens: None,
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+                dollar_cost: None,
+                cents_cost: None,
+            },
+            raw_response: None,
+            special_tokens: req.stop_sequences.to_vec(),
+        }
+    }
+
+    /// The content with leading and trailing template tokens and echoed stop sequences removed.
+    ///
+    /// Some local models emit their BOS token or repeat a stop word at the end of the completion. The raw content is
+    /// still available in [CompletionResponse::content].
+    pub fn content_trimmed(&self) -> &str {
+        let mut content = self.content.trim();
+        loop {
+            let start = self
+                .special_tokens
+                .iter()
+                .filter(|t| !t.is_empty())
+                .find_map(|t| content.strip_prefix(t.as_str()));
+            match start {
+                Some(rest) => content = rest.trim_start(),
+                None => break,
+            }
+        }
+        loop {
+            let end = self
+                .special_tokens
+                .iter()
+                .filter(|t| !t.is_empty())
+                .find_map(|t| content.strip_suffix(t.as_str()));
+            match end {
+                Some(rest) => content = rest.trim_end(),
+                None => break,
+            }
+        }
+        content
+    }
 }
 
 impl std::fmt::Display for CompletionResponse {
@@ -25,13 +131,26 @@ impl std::fmt::Display for CompletionResponse {
         writeln!(f, "CompletionResponse:")?;
         writeln!(f, "    content: {:?}", self.content)?;
         writeln!(f, "    finish_reason: {}", self.finish_reason)?;
+        if self.is_partial() {
+            writeln!(f, "    partial: true")?;
+        }
         write!(f, "    generation_settings: {}", self.generation_settings)?;
         write!(f, "    timing_usage: {}", self.timing_usage)?;
-        write!(f, "    token_usage: {}", self.token_usage)
+        write!(f, "    token_usage: {}", self.token_usage)?;
+        if let Some(tool_calls) = &self.tool_calls {
+            writeln!(f, "    tool_calls: {:?}", tool_calls)?;
+        }
+        if let Some(reasoning) = &self.reasoning {
+            writeln!(f, "    reasoning: {}", reasoning)?;
+        }
+        if let Some(raw_response) = &self.raw_response {
+            writeln!(f, "    raw_response: {}", raw_response)?;
+        }
+        Ok(())
     }
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq)]
 pub enum CompletionFinishReason {
     /// The completion finished because the model generated the EOS token.
     Eos,
@@ -41,6 +160,14 @@ pub enum CompletionFinishReason {
     NonMatchingStoppingSequence(Option<String>),
     /// The completion finished because the model reached the maximum token limit.
     StopLimit,
+    /// The completion finished because the model requested one or more tool calls, e.g. Anthropic's `tool_use` or
+    /// OpenAI's `tool_calls` stop reason. See [CompletionResponse::tool_calls].
+    ToolCalls,
+    /// The streaming callback stopped the completion before the model finished. See [super::StreamControl].
+    StreamStopped,
+    /// The streamed completion was stopped by [crate::requests::req_components::RequestConfig::stream_timeout]
+    /// before the model finished.
+    TimedOut,
 }
 
 impl std::fmt::Display for CompletionFinishReason {
@@ -54,6 +181,9 @@ impl std::fmt::Display for CompletionFinishReason {
                 write!(f, "NonMatchingStoppingSequence({:?})", seq)
             }
             CompletionFinishReason::StopLimit => write!(f, "StopLimit"),
+            CompletionFinishReason::ToolCalls => write!(f, "ToolCalls"),
+            CompletionFinishReason::StreamStopped => write!(f, "StreamStopped"),
+            CompletionFinishReason::TimedOut => write!(f, "TimedOut"),
         }
     }
 }
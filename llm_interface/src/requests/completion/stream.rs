@@ -0,0 +1,89 @@
+/// Returned by a streaming callback to keep generating or to stop the completion early.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamControl {
+    Continue,
+    /// Stops generating. The response has [super::CompletionFinishReason::StreamStopped] as its finish reason.
+    Stop,
+}
+
+/// Finds the end of the first complete top level JSON object or array in streamed text.
+///
+/// Text before the opening bracket is skipped. Brackets inside strings are ignored, and a balanced value that fails to
+/// parse is discarded so the search continues after it.
+#[derive(Debug, Default, Clone)]
+pub struct JsonStreamTracker {
+    content: String,
+    start: Option<usize>,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    end: Option<usize>,
+}
+
+impl JsonStreamTracker {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a delta and returns how many of its bytes belong to the content up to the end of the JSON value. This is
+    /// the whole delta unless the value completes inside it, and zero once the value is complete.
+    pub fn push_delta(&mut self, delta: &str) -> usize {
+        if self.end.is_some() {
+            return 0;
+        }
+        let offset = self.content.len();
+        self.content.push_str(delta);
+        for (i, c) in delta.char_indices() {
+            let position = offset + i;
+            let Some(start) = self.start else {
+                if c == '{' || c == '[' {
+                    self.start = Some(position);
+                    self.depth = 1;
+                }
+                continue;
+            };
+            if self.in_string {
+                match c {
+                    _ if self.escaped => self.escaped = false,
+                    '\\' => self.escaped = true,
+                    '"' => self.in_string = false,
+                    _ => (),
+                }
+                continue;
+            }
+            match c {
+                '"' => self.in_string = true,
+                '{' | '[' => self.depth += 1,
+                '}' | ']' => {
+                    self.depth -= 1;
+                    if self.depth == 0 {
+                        let end = position + 1;
+                        if serde_json::from_str::<serde_json::Value>(&self.content[start..end])
+                            .is_ok()
+                        {
+                            self.end = Some(end);
+                            self.content.truncate(end);
+                            return end - offset;
+                        }
+                        self.start = None;
+                    }
+                }
+                _ => (),
+            }
+        }
+        delta.len()
+    }
+
+    /// The JSON value, once it is complete.
+    pub fn json(&self) -> Option<&str> {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) => Some(&self.content[start..end]),
+            _ => None,
+        }
+    }
+
+    /// All text pushed up to the end of the JSON value.
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+}
@@ -0,0 +1,37 @@
+/// How llama.cpp combines the token embeddings of an input into a single vector. Set when the server starts with
+/// [crate::llms::local::llama_cpp::LlamaCppConfig::with_embeddings].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EmbeddingPooling {
+    /// The average of the token embeddings. Suits most sentence embedding models.
+    #[default]
+    Mean,
+    /// The embedding of the first token, for BERT style models trained with a CLS token.
+    Cls,
+    /// The embedding of the last token, for decoder models trained for embeddings.
+    Last,
+}
+
+impl EmbeddingPooling {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmbeddingPooling::Mean => "mean",
+            EmbeddingPooling::Cls => "cls",
+            EmbeddingPooling::Last => "last",
+        }
+    }
+}
+
+impl std::fmt::Display for EmbeddingPooling {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Scales `embedding` to a euclidean length of 1, so the dot product of two embeddings is their cosine similarity.
+/// A zero vector is left unchanged.
+pub fn normalize_embedding(embedding: &mut [f32]) {
+    let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        embedding.iter_mut().for_each(|x| *x /= norm);
+    }
+}
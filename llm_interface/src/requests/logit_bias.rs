@@ -13,6 +13,7 @@ pub struct LogitBias {
     from_chars: FromChars,
     from_words: FromWords,
     from_texts: FromTexts,
+    from_banned_words: FromBannedWords,
 }
 
 impl LogitBias {
@@ -50,11 +51,21 @@ impl LogitBias {
         self
     }
 
+    /// Bans each word by setting a -100 bias on every token it is made of, with and without a leading space.
+    ///
+    /// Warns if a word spans multiple tokens, because banning its first subtoken also suppresses allowed words that start with it.
+    pub fn ban_words(&mut self, words: &[&str]) -> &mut Self {
+        self.from_banned_words.add_words(words);
+        self.clear_built();
+        self
+    }
+
     pub fn clear_logit_bias(&mut self) -> &mut Self {
         self.from_token_ids.clear();
         self.from_chars.clear();
         self.from_words.clear();
         self.from_texts.clear();
+        self.from_banned_words.clear();
         self.clear_built();
         self
     }
@@ -98,9 +109,15 @@ impl LogitBias {
             && self.from_chars.is_none()
             && self.from_words.is_none()
             && self.from_texts.is_none()
+            && self.from_banned_words.is_none()
         {
             return Ok(());
         }
+        if tokenizer.is_approximate() {
+            return Err(crate::anyhow!(
+                "logit_bias is unavailable because no tokenizer could be loaded for this model"
+            ));
+        }
         let validated_logit_bias = self.from_token_ids.get(tokenizer)?;
         self.from_token_ids.clear();
 
@@ -122,6 +139,12 @@ impl LogitBias {
         ]);
         self.from_texts.clear();
 
+        let validated_logit_bias = Self::merge_logit_biases(vec![
+            &validated_logit_bias,
+            &self.from_banned_words.get(tokenizer)?,
+        ]);
+        self.from_banned_words.clear();
+
         if !validated_logit_bias.is_empty() {
             Self::validate_logit_bias_values(&validated_logit_bias)?;
             self.base_logit_bias = Some(validated_logit_bias);
@@ -352,6 +375,64 @@ impl FromTexts {
     }
 }
 
+#[derive(Clone, Default)]
+struct FromBannedWords {
+    pub words: Option<Vec<String>>,
+}
+
+impl FromBannedWords {
+    fn is_none(&self) -> bool {
+        self.words.is_none()
+    }
+
+    fn clear(&mut self) {
+        self.words = None;
+    }
+
+    fn get(&self, tokenizer: &Arc<LlmTokenizer>) -> crate::Result<HashMap<u32, f32>> {
+        if let Some(words) = &self.words {
+            let mut token_logit_bias: HashMap<u32, f32> = HashMap::new();
+            for word in words {
+                let word = word.trim();
+                if word.is_empty() {
+                    return Err(crate::anyhow!("banned words contains an empty word"));
+                }
+                // Mid-sentence words are usually tokenized with a leading space.
+                for variant in [word.to_string(), format!(" {word}")] {
+                    let token_ids: Vec<u32> = tokenizer
+                        .tokenize(&variant)
+                        .into_iter()
+                        .filter(|id| *id != tokenizer.white_space_token_id)
+                        .collect();
+                    if token_ids.len() > 1 {
+                        let first_subtoken = tokenizer
+                            .detokenize_one(token_ids[0])
+                            .unwrap_or_else(|_| token_ids[0].to_string());
+                        crate::warn!(
+                            "Banned word {:?} is split into {} tokens. Banning its first subtoken {:?} will also suppress allowed words that start with it.",
+                            variant,
+                            token_ids.len(),
+                            first_subtoken
+                        );
+                    }
+                    for id in token_ids {
+                        token_logit_bias.insert(id, -100.0);
+                    }
+                }
+            }
+            Ok(token_logit_bias)
+        } else {
+            Ok(HashMap::new())
+        }
+    }
+
+    fn add_words(&mut self, words: &[&str]) {
+        self.words
+            .get_or_insert_with(Vec::new)
+            .extend(words.iter().map(|word| word.to_string()));
+    }
+}
+
 #[derive(Clone, Default)]
 pub struct OpenAiLogitBias {
     pub built_logit_bias: Option<HashMap<String, serde_json::Value>>,
@@ -374,6 +455,7 @@ impl OpenAiLogitBias {
                 serde_json::Value::Number(serde_json::Number::from(value.ceil() as i32)),
             );
         }
+        self.built_logit_bias = Some(openai_logit_bias);
     }
 
     fn get(&self) -> Option<HashMap<String, serde_json::Value>> {
@@ -487,6 +569,16 @@ pub trait LogitBiasTrait: RequestConfigTrait {
         self
     }
 
+    /// Bans words from the output by setting a -100 bias on all of their tokens. Supported by local and OpenAI backends.
+    ///
+    /// # Arguments
+    ///
+    /// * `words` - The words to ban.
+    fn logit_bias_ban_words(&mut self, words: &[&str]) -> &mut Self {
+        self.logit_bias().ban_words(words);
+        self
+    }
+
     /// Clearss the logit bias configuration. To reuse the request object for another request. Mostly for testing.
     fn clear_logit_bias(&mut self) -> &mut Self {
         self.logit_bias().clear_logit_bias();
@@ -1,6 +1,9 @@
 pub mod completion;
+pub mod embedding;
 // pub mod constraints;
 pub mod logit_bias;
 pub mod req_components;
 pub mod res_components;
+pub mod score;
 pub mod stop_sequence;
+pub mod tool;
@@ -1,3 +1,4 @@
+use super::completion::CompletionError;
 use llm_prompt::{check_and_get_max_tokens, MaxTokenState, RequestTokenLimitError};
 
 #[derive(Clone)]
@@ -9,7 +10,9 @@ pub struct RequestConfig {
     /// RequestConfig is created and is used to calculate the available token budget
     /// for each request.
     ///
-    /// This limit applies to all LLM types, including both local and API-based models.
+    /// This limit applies to all LLM types, including both local and API-based models. For requests created from a
+    /// backend it's [crate::llms::LlmBackend::request_ctx_size], the context a single request gets on the running
+    /// server.
     pub(crate) model_ctx_size: u64,
     /// Maximum token limit for model output.
     ///
@@ -76,6 +79,8 @@ pub struct RequestConfig {
     /// - Encouraging more diverse vocabulary usage (with positive values)
     /// - Maintaining consistent terminology (with negative values)
     ///
+    /// Values outside of [RequestConfig::PENALTY_RANGE] are rejected when the request is sent.
+    ///
     /// Supported LLMs: openai, llama_cpp
     ///
     /// Defaults to `None` (no frequency penalty applied).
@@ -97,9 +102,11 @@ pub struct RequestConfig {
     /// - Encouraging the model to cover more topics (with positive values)
     /// - Maintaining focus on specific themes (with negative values)
     ///
+    /// Values outside of [RequestConfig::PENALTY_RANGE] are rejected when the request is sent.
+    ///
     /// Supported LLMs: openai, llama_cpp
     ///
-    /// Defaults to `0.0` (no presence penalty applied).
+    /// Defaults to [RequestConfig::DEFAULT_PRESENCE_PENALTY] (no presence penalty applied).
     pub presence_penalty: f32,
     /// Controls diversity via nucleus sampling.
     ///
@@ -119,6 +126,30 @@ pub struct RequestConfig {
     ///
     /// Defaults to `None` (not used, falling back to temperature-based sampling).
     pub top_p: Option<f32>,
+    /// Penalizes tokens that were generated within the last [RequestConfig::repeat_last_n] tokens.
+    ///
+    /// Values above 1.0 make recently generated tokens less likely, which helps small models that loop on long
+    /// outputs. A value of 1.0 disables the penalty.
+    ///
+    /// Supported LLMs: llama_cpp
+    ///
+    /// Defaults to `None` (the server default is used).
+    pub repeat_penalty: Option<f32>,
+    /// Number of most recent tokens considered by [RequestConfig::repeat_penalty].
+    ///
+    /// `0` disables the penalty and `-1` uses the full context size.
+    ///
+    /// Supported LLMs: llama_cpp
+    ///
+    /// Defaults to `None` (the server default is used).
+    pub repeat_last_n: Option<i32>,
+    /// Number of most likely tokens returned with their log probabilities at each position of the response, in
+    /// [crate::requests::completion::CompletionResponse::completion_probabilities].
+    ///
+    /// Supported LLMs: openai (max 20), llama_cpp
+    ///
+    /// Defaults to `None` (no log probabilities are returned).
+    pub top_logprobs: Option<u8>,
     /// Maximum number of retry attempts after a request failure.
     ///
     /// Specifies how many times the system should attempt to retry a failed request before giving up.
@@ -138,18 +169,102 @@ pub struct RequestConfig {
     ///
     /// Defaults to `false`.
     pub increase_limit_on_fail: bool,
-    /// Enable prompt caching for subsequent requests.
+    /// Whether the server reuses the KV cache of a previous request that shares a prefix with this prompt.
     ///
-    /// When set to `true`, the system will cache the prompt and reuse it for the next request.
-    /// This can potentially improve performance for repeated or similar queries.
+    /// - `Some(true)`: the shared prefix isn't processed again, which speeds up repeated or similar prompts.
+    /// - `Some(false)`: the whole prompt is processed. The slot's cache is still replaced by this request's prompt,
+    ///   so with a single slot a prefix cached by earlier requests has to be processed again by the next one.
+    /// - `None`: the server's default, which is enabled on recent llama.cpp versions.
     ///
     /// Supported LLMs: llama_cpp
     ///
+    /// Defaults to `None`.
+    pub cache_prompt: Option<bool>,
+    /// Capture the untouched response body from the backend.
+    ///
+    /// When set to `true`, the full response JSON is stored in [crate::requests::completion::CompletionResponse::raw_response].
+    /// This is useful for inspecting provider-specific fields that are not mapped to the typed response.
+    ///
+    /// Supported LLMs: openai, anthropic, generic_api, llama_cpp
+    ///
     /// Defaults to `false`.
-    pub cache_prompt: bool,
+    pub capture_raw: bool,
+    /// Token budget for extended thinking (reasoning) before the final answer.
+    ///
+    /// When set, the model is allowed to spend up to this many tokens reasoning before it answers.
+    /// The reasoning is returned separately in [crate::requests::completion::CompletionResponse::reasoning]
+    /// and is not included in the response content. The budget is added on top of the requested tokens,
+    /// and the temperature and top_p settings are ignored while thinking is enabled.
+    /// Must be at least 1024.
+    ///
+    /// Supported LLMs: anthropic
+    ///
+    /// Defaults to `None` (extended thinking disabled).
+    pub reasoning_budget: Option<u32>,
+    /// Seed for the sampler, so repeated requests with the same settings return the same response.
+    ///
+    /// OpenAI only makes a best effort to be deterministic. Backends that don't support a seed ignore it, see
+    /// [crate::llms::LlmBackend::supports_seed].
+    ///
+    /// Supported LLMs: openai, generic_api, llama_cpp
+    ///
+    /// Defaults to `None` (a random seed).
+    pub seed: Option<u64>,
+    /// The time limit for a streamed completion. When it passes, generation is stopped and the text streamed so far
+    /// is returned as a partial response, see [crate::requests::completion::CompletionResponse::is_partial].
+    ///
+    /// Supported LLMs: llama_cpp, mistral_rs
+    ///
+    /// Defaults to `None` (no time limit).
+    pub stream_timeout: Option<std::time::Duration>,
+    /// An id of the end user the request is made for, which providers use to monitor abuse. Pass a hash of your
+    /// user id rather than anything that identifies the user.
+    ///
+    /// Sent as `user` to OpenAI and as `metadata.user_id` to Anthropic.
+    ///
+    /// Supported LLMs: openai, anthropic, generic_api
+    ///
+    /// Defaults to `None`.
+    pub end_user_id: Option<String>,
+}
+
+/// Named combinations of sampling settings, so callers don't need to tune each setting.
+///
+/// A preset sets [RequestConfig::temperature], [RequestConfig::top_p], [RequestConfig::frequency_penalty],
+/// [RequestConfig::presence_penalty] and [RequestConfig::repeat_penalty]. Settings applied after the preset override it.
+/// Backends ignore the settings they don't support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplerPreset {
+    /// Always picks the most likely token. Use for extraction and classification. Responses are eligible for
+    /// [crate::requests::completion::ResponseCache].
+    Deterministic,
+    /// Mostly focused output with some variation. Use for chat and general tasks.
+    Balanced,
+    /// Diverse output that avoids repeating itself. Use for brainstorming and creative writing.
+    Creative,
+}
+
+impl SamplerPreset {
+    pub fn apply(&self, config: &mut RequestConfig) {
+        let (temperature, top_p, frequency_penalty, presence_penalty, repeat_penalty) = match self {
+            SamplerPreset::Deterministic => (0.0, None, None, 0.0, None),
+            SamplerPreset::Balanced => (0.7, Some(0.9), None, 0.0, Some(1.1)),
+            SamplerPreset::Creative => (1.1, Some(0.95), Some(0.3), 0.3, Some(1.15)),
+        };
+        config.temperature = temperature;
+        config.top_p = top_p;
+        config.frequency_penalty = frequency_penalty;
+        config.presence_penalty = presence_penalty;
+        config.repeat_penalty = repeat_penalty;
+    }
 }
 
 impl RequestConfig {
+    /// The range providers accept for [RequestConfig::frequency_penalty] and [RequestConfig::presence_penalty].
+    pub const PENALTY_RANGE: std::ops::RangeInclusive<f32> = -2.0..=2.0;
+    pub const DEFAULT_PRESENCE_PENALTY: f32 = 0.0;
+
     pub fn new(model_ctx_size: u64, inference_ctx_size: u64) -> Self {
         Self {
             model_ctx_size,
@@ -157,14 +272,74 @@ impl RequestConfig {
             requested_response_tokens: None,
             actual_request_tokens: None,
             frequency_penalty: None,
-            presence_penalty: 0.0,
+            presence_penalty: Self::DEFAULT_PRESENCE_PENALTY,
             temperature: 1.0,
             top_p: None,
+            repeat_penalty: None,
+            repeat_last_n: None,
+            top_logprobs: None,
             safety_tokens: 10,
             retry_after_fail_n_times: 3,
             increase_limit_on_fail: false,
-            cache_prompt: false,
+            cache_prompt: None,
+            capture_raw: false,
+            reasoning_budget: None,
+            seed: None,
+            stream_timeout: None,
+            end_user_id: None,
+        }
+    }
+
+    /// A config with the sampling settings of `preset`. See [SamplerPreset].
+    pub fn from_preset(
+        model_ctx_size: u64,
+        inference_ctx_size: u64,
+        preset: SamplerPreset,
+    ) -> Self {
+        let mut config = Self::new(model_ctx_size, inference_ctx_size);
+        preset.apply(&mut config);
+        config
+    }
+
+    /// Checks the sampling penalties are within [RequestConfig::PENALTY_RANGE], so an out of range value fails
+    /// with a clear error rather than being rejected by the provider.
+    pub fn validate(&self) -> crate::Result<(), CompletionError> {
+        let penalties = [
+            ("frequency_penalty", self.frequency_penalty),
+            ("presence_penalty", Some(self.presence_penalty)),
+        ];
+        for (name, value) in penalties {
+            if let Some(value) = value {
+                if !Self::PENALTY_RANGE.contains(&value) {
+                    return Err(CompletionError::RequestBuilderError(format!(
+                        "{name} must be between {} and {}, got {value}",
+                        Self::PENALTY_RANGE.start(),
+                        Self::PENALTY_RANGE.end()
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the prompt and [RequestConfig::requested_response_tokens] fit in [RequestConfig::model_ctx_size]
+    /// together, so a request that doesn't fails with the exact numbers rather than having its response silently
+    /// shortened or being rejected by the backend.
+    pub fn check_context_length(
+        &self,
+        total_prompt_tokens: u64,
+    ) -> crate::Result<(), CompletionError> {
+        let requested_response_tokens = self.requested_response_tokens.unwrap_or(0);
+        let total_tokens = total_prompt_tokens + requested_response_tokens;
+        if total_tokens <= self.model_ctx_size {
+            return Ok(());
         }
+        Err(CompletionError::ContextLengthExceeded {
+            prompt_tokens: total_prompt_tokens,
+            requested_response_tokens,
+            ctx_size: self.model_ctx_size,
+            overflow: total_tokens - self.model_ctx_size,
+        })
     }
 
     pub fn set_max_tokens_for_request(
@@ -179,9 +354,6 @@ impl RequestConfig {
             self.requested_response_tokens,
         )?;
         self.actual_request_tokens = Some(actual_request_tokens);
-        if self.requested_response_tokens.is_none() {
-            self.requested_response_tokens = Some(actual_request_tokens);
-        }
         Ok(())
     }
 
@@ -194,17 +366,16 @@ impl RequestConfig {
         let token_increase_factor = token_increase_factor.unwrap_or(Self::DEFAULT_INCREASE_FACTOR);
         crate::info!("Attempting to increase requested_response_tokens by {token_increase_factor} before retrying.");
 
-        if self.actual_request_tokens.is_none() || self.requested_response_tokens.is_none() {
-            self.set_max_tokens_for_request(total_prompt_tokens)?; // To ensure both token sets are set
+        if self.actual_request_tokens.is_none() {
+            self.set_max_tokens_for_request(total_prompt_tokens)?;
         }
+        let actual_request = self
+            .actual_request_tokens
+            .expect("requested_response_tokens");
 
         let initial_state = MaxTokenState {
-            actual_request: self
-                .actual_request_tokens
-                .expect("requested_response_tokens"),
-            requested_response: self
-                .requested_response_tokens
-                .expect("requested_response_tokens"),
+            actual_request,
+            requested_response: self.requested_response_tokens.unwrap_or(actual_request),
         };
 
         self.requested_response_tokens =
@@ -247,6 +418,12 @@ pub trait RequestConfigTrait {
 
     fn reset_request(&mut self);
 
+    /// Applies the sampling settings of `preset`. Setters called afterwards override individual settings.
+    fn sampler_preset(&mut self, preset: SamplerPreset) -> &mut Self {
+        preset.apply(self.config());
+        self
+    }
+
     /// Sets the value of [RequestConfig::requested_response_tokens].
     fn max_tokens(&mut self, max_tokens: u64) -> &mut Self {
         self.config().requested_response_tokens = Some(max_tokens);
@@ -261,10 +438,7 @@ pub trait RequestConfigTrait {
 
     /// Sets the value of [RequestConfig::presence_penalty].
     fn presence_penalty(&mut self, presence_penalty: f32) -> &mut Self {
-        match presence_penalty {
-            value if (-2.0..=2.0).contains(&value) => self.config().presence_penalty = value,
-            _ => self.config().presence_penalty = 0.0,
-        };
+        self.config().presence_penalty = presence_penalty;
         self
     }
 
@@ -283,6 +457,24 @@ pub trait RequestConfigTrait {
         self
     }
 
+    /// Sets the value of [RequestConfig::repeat_penalty].
+    fn repeat_penalty(&mut self, repeat_penalty: f32) -> &mut Self {
+        self.config().repeat_penalty = Some(repeat_penalty);
+        self
+    }
+
+    /// Sets the value of [RequestConfig::repeat_last_n].
+    fn repeat_last_n(&mut self, repeat_last_n: i32) -> &mut Self {
+        self.config().repeat_last_n = Some(repeat_last_n);
+        self
+    }
+
+    /// Sets the value of [RequestConfig::top_logprobs].
+    fn top_logprobs(&mut self, top_logprobs: u8) -> &mut Self {
+        self.config().top_logprobs = Some(top_logprobs);
+        self
+    }
+
     /// Sets the value of [RequestConfig::retry_after_fail_n_times].
     fn retry_after_fail_n_times(&mut self, retry_after_fail_n_times: u8) -> &mut Self {
         self.config().retry_after_fail_n_times = retry_after_fail_n_times;
@@ -295,9 +487,40 @@ pub trait RequestConfigTrait {
         self
     }
 
-    /// Sets the value of [RequestConfig::cache_prompt].
+    /// Sets the value of [RequestConfig::cache_prompt]. Pass `false` to process a one-off prompt without the
+    /// server's cached prefix.
     fn cache_prompt(&mut self, cache_prompt: bool) -> &mut Self {
-        self.config().cache_prompt = cache_prompt;
+        self.config().cache_prompt = Some(cache_prompt);
+        self
+    }
+
+    /// Sets the value of [RequestConfig::capture_raw].
+    fn capture_raw(&mut self, capture_raw: bool) -> &mut Self {
+        self.config().capture_raw = capture_raw;
+        self
+    }
+
+    /// Sets the value of [RequestConfig::reasoning_budget].
+    fn reasoning_budget(&mut self, reasoning_budget: u32) -> &mut Self {
+        self.config().reasoning_budget = Some(reasoning_budget);
+        self
+    }
+
+    /// Sets the value of [RequestConfig::seed].
+    fn seed(&mut self, seed: u64) -> &mut Self {
+        self.config().seed = Some(seed);
+        self
+    }
+
+    /// Sets the value of [RequestConfig::stream_timeout].
+    fn stream_timeout(&mut self, stream_timeout: std::time::Duration) -> &mut Self {
+        self.config().stream_timeout = Some(stream_timeout);
+        self
+    }
+
+    /// Sets the value of [RequestConfig::end_user_id].
+    fn end_user_id(&mut self, end_user_id: &str) -> &mut Self {
+        self.config().end_user_id = Some(end_user_id.to_owned());
         self
     }
 }
@@ -321,6 +544,9 @@ impl std::fmt::Display for RequestConfig {
         writeln!(f, "    presence_penalty: {:?}", self.presence_penalty)?;
         writeln!(f, "    temperature: {:?}", self.temperature)?;
         writeln!(f, "    top_p: {:?}", self.top_p)?;
+        writeln!(f, "    repeat_penalty: {:?}", self.repeat_penalty)?;
+        writeln!(f, "    repeat_last_n: {:?}", self.repeat_last_n)?;
+        writeln!(f, "    top_logprobs: {:?}", self.top_logprobs)?;
         writeln!(
             f,
             "    retry_after_fail_n_times: {:?}",
@@ -331,6 +557,11 @@ impl std::fmt::Display for RequestConfig {
             "    increase_limit_on_fail: {:?}",
             self.increase_limit_on_fail
         )?;
-        writeln!(f, "    cache_prompt: {:?}", self.cache_prompt)
+        writeln!(f, "    cache_prompt: {:?}", self.cache_prompt)?;
+        writeln!(f, "    capture_raw: {:?}", self.capture_raw)?;
+        writeln!(f, "    reasoning_budget: {:?}", self.reasoning_budget)?;
+        writeln!(f, "    seed: {:?}", self.seed)?;
+        writeln!(f, "    stream_timeout: {:?}", self.stream_timeout)?;
+        writeln!(f, "    end_user_id: {:?}", self.end_user_id)
     }
 }
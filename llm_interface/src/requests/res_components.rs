@@ -9,7 +9,7 @@ use crate::llms::local::llama_cpp::completion::LlamaCppCompletionResponse;
 use mistralrs::CompletionResponse as MistralCompletionResponse;
 
 /// The log probability of the completion.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct InferenceProbabilities {
     /// The token selected by the model.
     pub content: Option<String>,
@@ -17,7 +17,7 @@ pub struct InferenceProbabilities {
     pub top_probs: Vec<TopProbabilities>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TopProbabilities {
     /// The token.
     pub token: String,
@@ -26,6 +26,7 @@ pub struct TopProbabilities {
 }
 
 /// The settings used to generate the completion.
+#[derive(Clone)]
 pub struct GenerationSettings {
     /// The model used
     pub model: String,
@@ -146,6 +147,7 @@ impl std::fmt::Display for GenerationSettings {
 }
 
 /// Timing statistics for the completion request.
+#[derive(Clone)]
 pub struct TimingUsage {
     /// Timestamp of when the request was created.
     pub start_time: std::time::Instant,
@@ -249,9 +251,13 @@ impl std::fmt::Display for TimingUsage {
 }
 
 /// Token statistics for the completion request.
+#[derive(Clone)]
 pub struct TokenUsage {
     /// Number of tokens from the prompt which could be re-used from previous completion (n_past)
     pub tokens_cached: Option<u32>,
+    /// Number of prompt tokens reused from the server's prompt cache instead of being processed again. Only set by
+    /// the llama.cpp backend. See [crate::requests::req_components::RequestConfig::cache_prompt].
+    pub cached_prompt_tokens: Option<u32>,
     /// Number of tokens evaluated in total from the prompt. Same as tokens_evaluated.
     pub prompt_tokens: u32,
     /// Number of tokens in the generated completion. Same as predicted_n.
@@ -265,10 +271,25 @@ pub struct TokenUsage {
 }
 
 impl TokenUsage {
+    /// The fraction of the prompt reused from the server's prompt cache, from 0.0 to 1.0. `None` if the backend
+    /// doesn't report it.
+    pub fn cache_hit_ratio(&self) -> Option<f32> {
+        match self.cached_prompt_tokens {
+            Some(_) if self.prompt_tokens == 0 => Some(0.0),
+            Some(cached_prompt_tokens) => {
+                Some(cached_prompt_tokens as f32 / self.prompt_tokens as f32)
+            }
+            None => None,
+        }
+    }
+
     #[cfg(feature = "llama_cpp_backend")]
     pub fn new_from_llama(res: &LlamaCppCompletionResponse) -> Self {
         Self {
             tokens_cached: Some(res.tokens_cached as u32),
+            cached_prompt_tokens: Some(
+                (res.tokens_evaluated as u32).saturating_sub(res.timings.prompt_n as u32),
+            ),
             prompt_tokens: res.tokens_evaluated as u32,
             completion_tokens: res.timings.predicted_n as u32,
             total_tokens: res.tokens_evaluated as u32 + res.timings.predicted_n as u32,
@@ -280,6 +301,7 @@ impl TokenUsage {
     pub fn new_from_mistral(res: &MistralCompletionResponse) -> Self {
         Self {
             tokens_cached: None,
+            cached_prompt_tokens: None,
             prompt_tokens: res.usage.prompt_tokens as u32,
             completion_tokens: res.usage.completion_tokens as u32,
             total_tokens: res.usage.prompt_tokens as u32 + res.usage.completion_tokens as u32,
@@ -292,6 +314,7 @@ impl TokenUsage {
         if let Some(usage) = &res.usage {
             Self {
                 tokens_cached: None,
+                cached_prompt_tokens: None,
                 prompt_tokens: usage.prompt_tokens,
                 completion_tokens: usage.completion_tokens,
                 total_tokens: usage.total_tokens,
@@ -301,6 +324,7 @@ impl TokenUsage {
         } else {
             Self {
                 tokens_cached: None,
+                cached_prompt_tokens: None,
                 prompt_tokens: 0,
                 completion_tokens: 0,
                 total_tokens: 0,
@@ -313,6 +337,7 @@ impl TokenUsage {
     pub fn new_from_anthropic(res: &AnthropicCompletionResponse) -> Self {
         Self {
             tokens_cached: None,
+            cached_prompt_tokens: None,
             prompt_tokens: res.usage.input_tokens,
             completion_tokens: res.usage.output_tokens,
             total_tokens: res.usage.input_tokens + res.usage.output_tokens,
@@ -326,6 +351,11 @@ impl std::fmt::Display for TokenUsage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f)?;
         writeln!(f, "    tokens_cached: {:?}", self.tokens_cached)?;
+        writeln!(
+            f,
+            "    cached_prompt_tokens: {:?}",
+            self.cached_prompt_tokens
+        )?;
         writeln!(f, "    prompt_tokens: {:?}", self.prompt_tokens)?;
         writeln!(f, "    completion_tokens: {:?}", self.completion_tokens)?;
         writeln!(f, "    total_tokens: {:?}", self.total_tokens)?;
@@ -0,0 +1,57 @@
+use super::completion::CompletionError;
+
+/// How likely a model finds a given continuation of a prompt. See [crate::llms::LlmBackend::score_text].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextScore {
+    /// The natural log probability of each token of the continuation, in order.
+    pub token_logprobs: Vec<f32>,
+    /// The number of continuation tokens that were not among the most likely tokens returned by the backend.
+    /// Their log probability is the lowest returned one, so the score is an upper bound when this is not zero.
+    pub tokens_outside_top_probs: usize,
+}
+
+impl TextScore {
+    /// Records the log probability of the next continuation token from the most likely tokens the backend returned
+    /// for its position, as token ids and probabilities. A candidate's id is `None` if it couldn't be resolved. A
+    /// token missing from `top_probs` gets the lowest returned probability.
+    ///
+    /// Errors if `top_probs` is empty, since the token's probability is then unknown.
+    pub fn push_token(
+        &mut self,
+        token: u32,
+        top_probs: &[(Option<u32>, f32)],
+    ) -> crate::Result<(), CompletionError> {
+        let prob = match top_probs.iter().find(|(id, _)| *id == Some(token)) {
+            Some((_, prob)) => *prob,
+            None => {
+                let Some(lowest) = top_probs.iter().map(|(_, prob)| *prob).reduce(f32::min) else {
+                    return Err(CompletionError::LocalClientError(format!(
+                        "The backend returned no probabilities for the continuation token {token}"
+                    )));
+                };
+                self.tokens_outside_top_probs += 1;
+                lowest
+            }
+        };
+        self.token_logprobs.push(prob.max(f32::MIN_POSITIVE).ln());
+        Ok(())
+    }
+
+    /// The sum of the token log probabilities, i.e. the log probability of the whole continuation.
+    pub fn total_logprob(&self) -> f32 {
+        self.token_logprobs.iter().sum()
+    }
+
+    /// The average log probability per token. Comparable between continuations of different token lengths.
+    pub fn mean_logprob(&self) -> f32 {
+        if self.token_logprobs.is_empty() {
+            return 0.0;
+        }
+        self.total_logprob() / self.token_logprobs.len() as f32
+    }
+
+    /// `exp(-mean_logprob)`. Lower is more likely, with 1.0 meaning the model was certain of every token.
+    pub fn perplexity(&self) -> f32 {
+        (-self.mean_logprob()).exp()
+    }
+}
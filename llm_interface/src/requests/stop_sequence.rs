@@ -62,6 +62,28 @@ impl StopSequences {
         }
     }
 
+    /// Infers which stop sequence ended generation for APIs that only report that *a* stop sequence was hit (e.g. OpenAI's `finish_reason: "stop"`).
+    ///
+    /// The API reports a natural EOS the same way, so a stop sequence is only returned when the content shows one:
+    /// - An empty response means the model went straight to the no-result stop word.
+    /// - Content ending with a stop sequence, from servers that don't strip it, is that sequence.
+    ///
+    /// Otherwise `None` is returned and the stop is treated as EOS.
+    pub fn parse_api_stop(&self, content: &str) -> Option<StoppingSequence> {
+        let content = content.trim();
+        if content.is_empty() {
+            return self
+                .sequences
+                .iter()
+                .find(|s| matches!(s, StoppingSequence::NoResult(_)))
+                .cloned();
+        }
+        self.sequences
+            .iter()
+            .find(|s| !s.as_str().is_empty() && content.ends_with(s.as_str().trim()))
+            .cloned()
+    }
+
     pub fn error_on_required(&self) -> String {
         format!(
             "One of the sequences: {} is required, but response stopping_word is None.",
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+/// A JSON Schema object describing the arguments of a [Tool].
+pub type JsonSchema = serde_json::Value;
+
+/// A backend agnostic tool definition. Each backend translates it to its native format.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tool {
+    /// The name of the tool. Must be a-z, A-Z, 0-9, or contain underscores and dashes, with a maximum length of 64.
+    pub name: String,
+    /// A description of what the tool does, used by the model to choose when and how to call the tool.
+    pub description: Option<String>,
+    /// The arguments the tool accepts.
+    pub parameters: JsonSchema,
+}
+
+impl Tool {
+    pub fn new<S: Into<String>>(name: S, parameters: JsonSchema) -> Self {
+        Self {
+            name: name.into(),
+            description: None,
+            parameters,
+        }
+    }
+
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+/// A tool invocation requested by the model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    /// The backend assigned id of the call. Used to match a [ToolResult] to the call.
+    pub id: String,
+    /// The name of the tool to call.
+    pub name: String,
+    /// The arguments the model generated for the call.
+    pub arguments: serde_json::Value,
+}
+
+/// The output of running a [ToolCall], submitted back to the model on the follow-up turn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolResult {
+    /// The id of the [ToolCall] this is the result for.
+    pub tool_call_id: String,
+    /// The output of the tool.
+    pub content: String,
+}
+
+impl ToolResult {
+    pub fn new<S: Into<String>>(tool_call: &ToolCall, content: S) -> Self {
+        Self {
+            tool_call_id: tool_call.id.clone(),
+            content: content.into(),
+        }
+    }
+}
+
+/// A completed round of tool use: the calls the model made and the results for each call.
+/// Appended after the prompt messages when building the request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolTurn {
+    pub tool_calls: Vec<ToolCall>,
+    pub tool_results: Vec<ToolResult>,
+}
@@ -1,4 +1,19 @@
-use llm_interface::{requests::completion::CompletionRequest, LlmInterface};
+use llm_interface::{
+    llms::api::{
+        anthropic::completion::{
+            AnthropicCompletionRequest, AnthropicCompletionResponse, AnthropicMetadata, StopReason,
+        },
+        config::LlmApiConfigTrait,
+        error::ClientError,
+        openai::completion::OpenAiCompletionRequest,
+    },
+    llms::LlmBackend,
+    requests::completion::{
+        CompletionError, CompletionFinishReason, CompletionRequest, CompletionResponse,
+    },
+    LlmInterface,
+};
+use llm_testing::serve_json;
 use serial_test::serial;
 
 #[tokio::test]
@@ -42,3 +57,159 @@ async fn test_anthropic() {
     let res = req.request().await.unwrap();
     println!("{res}");
 }
+
+#[test]
+fn test_anthropic_stop_reason() {
+    let response = |stop_reason: &str| -> AnthropicCompletionResponse {
+        serde_json::from_value(serde_json::json!({
+            "id": "msg_1",
+            "content": [{"type": "text", "text": "Hello"}],
+            "model": "claude",
+            "stop_reason": stop_reason,
+            "stop_sequence": null,
+            "usage": {"input_tokens": 1, "output_tokens": 1},
+        }))
+        .unwrap()
+    };
+    assert_eq!(response("end_turn").stop_reason, StopReason::EndTurn);
+    assert_eq!(response("tool_use").stop_reason, StopReason::ToolUse);
+    assert_eq!(response("refusal").stop_reason, StopReason::Refusal);
+    assert_eq!(response("pause_turn").stop_reason, StopReason::PauseTurn);
+    assert_eq!(
+        response("model_context_window_exceeded").stop_reason,
+        StopReason::Unknown
+    );
+
+    let backend = LlmInterface::anthropic()
+        .with_api_key("sk-test")
+        .init()
+        .unwrap();
+    let req = CompletionRequest::new(backend);
+    let finish =
+        |stop_reason: &str| CompletionResponse::new_from_anthropic(&req, response(stop_reason));
+    assert!(matches!(
+        finish("end_turn").unwrap().finish_reason,
+        CompletionFinishReason::Eos
+    ));
+    assert!(matches!(
+        finish("tool_use").unwrap().finish_reason,
+        CompletionFinishReason::ToolCalls
+    ));
+    assert!(matches!(
+        finish("refusal"),
+        Err(CompletionError::Refused(refusal)) if refusal == "Hello"
+    ));
+    assert!(matches!(
+        finish("pause_turn"),
+        Err(CompletionError::StopReasonUnsupported(_))
+    ));
+    assert!(matches!(
+        finish("model_context_window_exceeded"),
+        Err(CompletionError::StopReasonUnsupported(_))
+    ));
+}
+
+fn end_user_request(backend: std::sync::Arc<LlmBackend>) -> CompletionRequest {
+    let mut req = CompletionRequest::new(backend);
+    req.prompt.add_user_message().unwrap().set_content("Hello");
+    req.config.set_max_tokens_for_request(10).unwrap();
+    req
+}
+
+#[test]
+fn test_end_user_id() {
+    let openai = || {
+        LlmInterface::openai()
+            .with_api_key("sk-test")
+            .init()
+            .unwrap()
+    };
+    let mut req = end_user_request(openai());
+    req.config.end_user_id = Some("user-hash".to_string());
+    let request = serde_json::to_value(OpenAiCompletionRequest::new(&req).unwrap()).unwrap();
+    assert_eq!(request["user"], "user-hash");
+    let request =
+        serde_json::to_value(OpenAiCompletionRequest::new(&end_user_request(openai())).unwrap())
+            .unwrap();
+    assert!(request.get("user").is_none());
+
+    let anthropic = || {
+        LlmInterface::anthropic()
+            .with_api_key("sk-test")
+            .init()
+            .unwrap()
+    };
+    let mut req = end_user_request(anthropic());
+    req.config.end_user_id = Some("user-hash".to_string());
+    let request = AnthropicCompletionRequest::new(&req).unwrap();
+    assert_eq!(
+        request.metadata,
+        Some(AnthropicMetadata {
+            user_id: "user-hash".to_string(),
+        })
+    );
+    assert_eq!(
+        serde_json::to_value(&request).unwrap()["metadata"]["user_id"],
+        "user-hash"
+    );
+    let request = AnthropicCompletionRequest::new(&end_user_request(anthropic())).unwrap();
+    assert_eq!(request.metadata, None);
+}
+
+#[test]
+fn test_invalid_user_agent() {
+    assert!(LlmInterface::openai()
+        .with_api_key("sk-test")
+        .with_user_agent("my-app/1.0")
+        .init()
+        .is_ok());
+    assert!(LlmInterface::openai()
+        .with_api_key("sk-test")
+        .with_user_agent("my-app\n1.0")
+        .init()
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_response_body_limit() {
+    let body = serde_json::json!({
+        "id": "chatcmpl-1",
+        "created": 0,
+        "model": "llama3",
+        "choices": [{
+            "index": 0,
+            "finish_reason": "stop",
+            "message": {"role": "assistant", "content": "a".repeat(1024)},
+        }],
+    })
+    .to_string();
+    for content_length in [true, false] {
+        let backend = LlmInterface::ollama()
+            .with_api_host("127.0.0.1")
+            .with_api_port(serve_json(200, body.clone(), content_length).to_string())
+            .with_max_response_bytes(Some(256))
+            .init()
+            .unwrap();
+        let mut req = CompletionRequest::new(backend);
+        req.prompt
+            .add_user_message()
+            .unwrap()
+            .set_content("Hello, world!");
+        match req.request().await.err() {
+            Some(CompletionError::ClientError(ClientError::ResponseTooLarge { limit })) => {
+                assert_eq!(limit, 256)
+            }
+            other => panic!("Expected ResponseTooLarge, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_api_backend_chat_template() {
+    let backend = LlmInterface::openai()
+        .with_api_key("sk-test")
+        .init()
+        .unwrap();
+    assert_eq!(backend.chat_template(), None);
+    assert!(backend.render_chat(&[]).is_err());
+}
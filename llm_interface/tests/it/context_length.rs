@@ -0,0 +1,43 @@
+use llm_interface::{
+    llms::local::RopeScaling,
+    requests::{completion::CompletionError, req_components::RequestConfig},
+};
+
+#[test]
+fn test_check_context_length() {
+    let mut config = RequestConfig::new(4096, 2048);
+    assert!(config.check_context_length(4000).is_ok());
+    assert!(config.check_context_length(4096).is_ok());
+
+    config.requested_response_tokens = Some(96);
+    assert!(config.check_context_length(4000).is_ok());
+
+    config.requested_response_tokens = Some(200);
+    match config.check_context_length(4000) {
+        Err(CompletionError::ContextLengthExceeded {
+            prompt_tokens,
+            requested_response_tokens,
+            ctx_size,
+            overflow,
+        }) => {
+            assert_eq!(prompt_tokens, 4000);
+            assert_eq!(requested_response_tokens, 200);
+            assert_eq!(ctx_size, 4096);
+            assert_eq!(overflow, 104);
+        }
+        res => panic!("expected ContextLengthExceeded, got {res:?}"),
+    }
+}
+
+#[test]
+fn test_rope_scaling_ctx_size() {
+    assert_eq!(RopeScaling::yarn(4.0).extended_ctx_size(8192), 32768);
+    assert_eq!(RopeScaling::linear(1.5).extended_ctx_size(4096), 6144);
+    // The trained context length overrides the one from the model's metadata.
+    assert_eq!(
+        RopeScaling::yarn(4.0)
+            .with_original_ctx_size(4096)
+            .extended_ctx_size(8192),
+        16384
+    );
+}
@@ -0,0 +1,36 @@
+use llm_interface::requests::completion::{determinism::DeterminismMismatch, DeterminismReport};
+
+#[test]
+fn test_determinism_report() {
+    let report = DeterminismReport::from_outputs(
+        42,
+        true,
+        vec![
+            "The answer is 4.".to_string(),
+            "The answer is 4.".to_string(),
+            "The answer is four.".to_string(),
+            "The answer is 4".to_string(),
+        ],
+    );
+    assert!(!report.is_deterministic());
+    assert_eq!(
+        report.mismatches,
+        vec![
+            DeterminismMismatch {
+                run: 2,
+                offset: 14,
+                expected: "4.".to_string(),
+                actual: "four.".to_string(),
+            },
+            DeterminismMismatch {
+                run: 3,
+                offset: 15,
+                expected: ".".to_string(),
+                actual: "".to_string(),
+            },
+        ]
+    );
+
+    let report = DeterminismReport::from_outputs(42, true, vec!["a".to_string(); 3]);
+    assert!(report.is_deterministic());
+}
@@ -0,0 +1,50 @@
+use llm_interface::{
+    llms::{api::error::ApiError, local::llama_cpp::embedding::LlamaCppEmbeddingResponse},
+    requests::embedding::{normalize_embedding, EmbeddingPooling},
+};
+
+#[test]
+fn test_normalize_embedding() {
+    let mut embedding = vec![3.0, 4.0];
+    normalize_embedding(&mut embedding);
+    assert_eq!(embedding, vec![0.6, 0.8]);
+
+    let mut zero = vec![0.0, 0.0];
+    normalize_embedding(&mut zero);
+    assert_eq!(zero, vec![0.0, 0.0]);
+    assert_eq!(EmbeddingPooling::Cls.as_str(), "cls");
+}
+
+#[test]
+fn test_embedding_response_shapes() {
+    let results: LlamaCppEmbeddingResponse = serde_json::from_str(
+        r#"{"results": [{"index": 1, "embedding": [0.0, 2.0]}, {"index": 0, "embedding": [3.0, 4.0]}]}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        results.into_embeddings(false).unwrap(),
+        vec![vec![3.0, 4.0], vec![0.0, 2.0]]
+    );
+
+    let list: LlamaCppEmbeddingResponse =
+        serde_json::from_str(r#"[{"index": 0, "embedding": [[3.0, 4.0]]}]"#).unwrap();
+    assert_eq!(list.into_embeddings(true).unwrap(), vec![vec![0.6, 0.8]]);
+
+    let single: LlamaCppEmbeddingResponse =
+        serde_json::from_str(r#"{"embedding": [1.0, 0.0]}"#).unwrap();
+    assert_eq!(single.into_embeddings(true).unwrap(), vec![vec![1.0, 0.0]]);
+
+    let token_embeddings: LlamaCppEmbeddingResponse =
+        serde_json::from_str(r#"[{"index": 0, "embedding": [[1.0], [2.0]]}]"#).unwrap();
+    assert!(token_embeddings.into_embeddings(false).is_err());
+}
+
+#[test]
+fn test_not_supported_error() {
+    let error: ApiError = serde_json::from_str(
+        r#"{"code": 501, "message": "This server does not support embeddings. Start it with `--embeddings`", "type": "not_supported_error"}"#,
+    )
+    .unwrap();
+    assert_eq!(error.code.as_deref(), Some("501"));
+    assert_eq!(error.r#type.as_deref(), Some("not_supported_error"));
+}
@@ -0,0 +1,25 @@
+use llm_interface::requests::completion::validate_gbnf;
+
+#[test]
+fn test_validate_gbnf() {
+    let gbnf = r#"
+# A tiny command language
+root ::= command ( ";" ws command ){0,4}
+command ::= verb ws [a-z]+ "\"" | "noop"
+verb ::= ( "go" | "take" )
+ws ::= [ \t]*
+"#;
+    assert_eq!(validate_gbnf(gbnf), Ok(()));
+    assert_eq!(
+        validate_gbnf("root ::= \" \" item-root\n\nitem-root ::= [0-9]+"),
+        Ok(())
+    );
+
+    assert!(validate_gbnf("command ::= \"go\"").is_err());
+    assert!(validate_gbnf("root ::= verb").is_err());
+    assert!(validate_gbnf("root ::= ( \"a\" | \"b\"").is_err());
+    assert!(validate_gbnf("root ::= \"a").is_err());
+    assert!(validate_gbnf("root ::= * \"a\"").is_err());
+    assert!(validate_gbnf("root ::= \"a\"\nroot ::= \"b\"").is_err());
+    assert!(validate_gbnf("root = \"a\"").is_err());
+}
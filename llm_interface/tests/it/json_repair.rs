@@ -0,0 +1,16 @@
+use llm_interface::requests::completion::{parse_json, repair_json};
+use serde_json::json;
+
+#[test]
+fn test_repair_json() {
+    let content = "Here you go:\n```json\n{\"a\": [1, 2,], \"b\": \"x,}\",}\n```\nAnything else?";
+    assert_eq!(repair_json(content), "{\"a\": [1, 2], \"b\": \"x,}\"}");
+    assert_eq!(
+        parse_json(content, true).unwrap(),
+        json!({"a": [1, 2], "b": "x,}"})
+    );
+    assert!(parse_json(content, false).is_err());
+    assert_eq!(parse_json("[1, 2 ,\n]", true).unwrap(), json!([1, 2]));
+    assert_eq!(parse_json(" {\"a\": 1} ", false).unwrap(), json!({"a": 1}));
+    assert!(parse_json("not json", true).is_err());
+}
@@ -0,0 +1,24 @@
+use llm_interface::requests::completion::JsonStreamTracker;
+
+#[test]
+fn test_json_stream_tracker() {
+    let mut tracker = JsonStreamTracker::new();
+    assert_eq!(tracker.push_delta("Sure! "), 6);
+    assert_eq!(tracker.push_delta("{\"a\": \"}\", "), 11);
+    assert_eq!(tracker.json(), None);
+    assert_eq!(tracker.push_delta("\"b\": {\"c\": [1]}"), 15);
+    assert_eq!(tracker.json(), None);
+    // The value completes inside the delta, so the trailing text isn't consumed.
+    assert_eq!(tracker.push_delta("}\nHope this helps"), 1);
+    assert_eq!(tracker.json(), Some("{\"a\": \"}\", \"b\": {\"c\": [1]}}"));
+    assert_eq!(tracker.push_delta("!"), 0);
+}
+
+#[test]
+fn test_json_stream_tracker_skips_invalid_values() {
+    let mut tracker = JsonStreamTracker::new();
+    let delta = "[not json] then [\"escaped \\\" ]\"] and more";
+    let consumed = tracker.push_delta(delta);
+    assert_eq!(&delta[..consumed], "[not json] then [\"escaped \\\" ]\"]");
+    assert_eq!(tracker.json(), Some("[\"escaped \\\" ]\"]"));
+}
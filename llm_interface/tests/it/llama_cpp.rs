@@ -3,10 +3,26 @@ use llm_devices::devices::CudaConfig;
 #[cfg(target_os = "macos")]
 use llm_devices::devices::MetalConfig;
 
-use llm_interface::llms::api::config::LlmApiConfigTrait;
-use llm_interface::llms::local::llama_cpp::server::{get_all_server_pids, kill_server_from_model};
-use llm_interface::llms::local::LlmLocalTrait;
-use llm_interface::{requests::completion::CompletionRequest, LlmInterface};
+use llm_interface::llms::api::{
+    config::LlmApiConfigTrait,
+    error::{ApiError, ClientError},
+};
+use llm_interface::llms::local::llama_cpp::server::{
+    get_all_server_pids, kill_server_from_model,
+    props::LlamaCppProps,
+    slots::{available_slots, LlamaCppSlot},
+    DraftModelConfig, LlamaCppServer, ServerStartupError,
+};
+use llm_interface::llms::local::llama_cpp::supervisor::is_server_failure;
+use llm_interface::llms::local::{LlmLocalTrait, LocalLlmConfig};
+use llm_interface::{
+    requests::{
+        completion::{CompletionError, CompletionFinishReason, CompletionRequest},
+        embedding::EmbeddingPooling,
+        res_components::TokenUsage,
+    },
+    LlmInterface,
+};
 use serial_test::serial;
 
 #[tokio::test]
@@ -57,6 +73,123 @@ async fn test_multiple_servers() {
     assert_eq!(pids.len(), 1);
 }
 
+#[tokio::test]
+#[serial]
+async fn test_shared_server() {
+    let loaded_1 = LlmInterface::llama_cpp()
+        .shared_server()
+        .init()
+        .await
+        .unwrap();
+    let loaded_2 = LlmInterface::llama_cpp()
+        .shared_server()
+        .with_api_port("8081")
+        .init()
+        .await
+        .unwrap();
+    let pids = get_all_server_pids().unwrap();
+    assert_eq!(pids.len(), 1);
+    std::mem::drop(loaded_1);
+    let pids = get_all_server_pids().unwrap();
+    assert_eq!(pids.len(), 1);
+    std::mem::drop(loaded_2);
+    let pids = get_all_server_pids().unwrap();
+    assert!(pids.is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_stream_timeout() {
+    let backend = LlmInterface::llama_cpp().init().await.unwrap();
+    let mut req = CompletionRequest::new(backend);
+    req.prompt
+        .add_user_message()
+        .unwrap()
+        .set_content("Write a long story about a lighthouse keeper.");
+    req.config.requested_response_tokens = Some(2000);
+    req.config.stream_timeout = Some(std::time::Duration::from_millis(500));
+
+    let mut streamed = String::new();
+    let res = req
+        .request_streaming(|delta| streamed.push_str(delta))
+        .await
+        .unwrap();
+    assert!(res.is_partial());
+    assert!(res.finish_reason == CompletionFinishReason::TimedOut);
+    assert_eq!(res.content, streamed);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_score_text() {
+    let backend = LlmInterface::llama_cpp().init().await.unwrap();
+    let likely = backend
+        .score_text("The capital of France is", " Paris")
+        .await
+        .unwrap();
+    let unlikely = backend
+        .score_text("The capital of France is", " a banana")
+        .await
+        .unwrap();
+    for score in [&likely, &unlikely] {
+        let mean_logprob = score.mean_logprob();
+        // Degenerate greedy probabilities give 0.0 or ln(f32::MIN_POSITIVE) for every token.
+        assert!(mean_logprob.is_finite());
+        assert!(mean_logprob < 0.0);
+        assert!(mean_logprob > f32::MIN_POSITIVE.ln());
+    }
+    assert!(likely.mean_logprob() > unlikely.mean_logprob());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_embeddings_server_mode() {
+    let backend = LlmInterface::llama_cpp().init().await.unwrap();
+    let err = backend
+        .embeddings(&["Hello, world!"], true)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, CompletionError::RequestBuilderError(_)));
+    std::mem::drop(backend);
+
+    let backend = LlmInterface::llama_cpp()
+        .embeddings(EmbeddingPooling::Mean)
+        .init()
+        .await
+        .unwrap();
+    let embeddings = backend
+        .embeddings(&["Hello, world!", "Goodbye."], true)
+        .await
+        .unwrap();
+    assert_eq!(embeddings.len(), 2);
+    let mut req = CompletionRequest::new(backend);
+    req.prompt
+        .add_user_message()
+        .unwrap()
+        .set_content("Hello, world!");
+    assert!(matches!(
+        req.request().await,
+        Err(CompletionError::RequestBuilderError(_))
+    ));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_extra_server_args() {
+    let err = LlmInterface::llama_cpp()
+        .extra_server_args(["--not-a-llama-cpp-flag"])
+        .init()
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(
+        err.downcast_ref::<ServerStartupError>(),
+        Some(ServerStartupError::Exited { .. })
+    ));
+    let pids = get_all_server_pids().unwrap();
+    assert!(pids.is_empty());
+}
+
 #[tokio::test]
 #[serial]
 async fn test_auto_gpu() {
@@ -193,3 +326,145 @@ async fn test_metal() {
     let res = req.request().await.unwrap();
     println!("{res}");
 }
+
+#[test]
+fn test_missing_model_file() {
+    let err = LlamaCppServer::new(
+        "missing",
+        LocalLlmConfig::default(),
+        "localhost",
+        &None,
+        std::time::Duration::from_secs(1),
+        Some(DraftModelConfig::new("/nonexistent/draft.gguf")),
+        None,
+    )
+    .err()
+    .unwrap();
+    assert!(matches!(
+        err.downcast_ref::<ServerStartupError>(),
+        Some(ServerStartupError::ModelNotFound(path)) if path == "/nonexistent/draft.gguf"
+    ));
+}
+
+#[test]
+fn test_parallel_slots_ctx_size() {
+    let server = |inference_ctx_size: u64, parallel_slots: Option<u32>| {
+        LlamaCppServer::new(
+            "test",
+            LocalLlmConfig {
+                inference_ctx_size,
+                ..Default::default()
+            },
+            "localhost",
+            &None,
+            std::time::Duration::from_secs(1),
+            None,
+            parallel_slots,
+        )
+        .unwrap()
+    };
+    assert_eq!(server(8192, None).inference_ctx_size, 8192);
+    assert_eq!(server(8192, Some(4)).inference_ctx_size, 8192);
+    // 3 slots of 2730 tokens each.
+    assert_eq!(server(8192, Some(3)).inference_ctx_size, 8190);
+}
+
+#[test]
+fn test_props_context_size() {
+    let props: LlamaCppProps = serde_json::from_str(
+        r#"{"default_generation_settings": {"n_ctx": 2048, "temperature": 0.8}, "total_slots": 2, "chat_template": "", "build_info": "b4000"}"#,
+    )
+    .unwrap();
+    assert_eq!(props.context_size(), Some(4096));
+    assert_eq!(props.build_info.as_deref(), Some("b4000"));
+
+    let props: LlamaCppProps = serde_json::from_str("{}").unwrap();
+    assert_eq!(props.context_size(), None);
+}
+
+#[test]
+fn test_props_check_context_size() {
+    let props = |n_ctx: u64, total_slots: u32| -> LlamaCppProps {
+        serde_json::from_value(serde_json::json!({
+            "default_generation_settings": {"n_ctx": n_ctx},
+            "total_slots": total_slots,
+        }))
+        .unwrap()
+    };
+    assert!(props(8192, 1).check_context_size(8192).is_ok());
+    // Padded up by the server.
+    assert!(props(8448, 1).check_context_size(8192).is_ok());
+    // 8192 split between 3 slots is floored to 2730 each.
+    assert!(props(2730, 3).check_context_size(8192).is_ok());
+    assert!(matches!(
+        props(2048, 3).check_context_size(8192),
+        Err(ServerStartupError::ContextSizeMismatch {
+            requested: 8192,
+            actual: 6144
+        })
+    ));
+    assert!(LlamaCppProps::default().check_context_size(8192).is_ok());
+}
+
+#[test]
+fn test_available_slots() {
+    let slots: Vec<LlamaCppSlot> = serde_json::from_str(
+        r#"[{"id": 0, "n_ctx": 2048, "is_processing": true}, {"id": 1, "n_ctx": 2048, "is_processing": false}, {"id": 2, "state": 1}, {"id": 3, "state": 0}]"#,
+    )
+    .unwrap();
+    assert_eq!(available_slots(&slots), 2);
+}
+
+#[test]
+fn test_cache_hit_ratio() {
+    let mut usage = TokenUsage {
+        tokens_cached: Some(120),
+        cached_prompt_tokens: Some(75),
+        prompt_tokens: 100,
+        completion_tokens: 20,
+        total_tokens: 120,
+        dollar_cost: None,
+        cents_cost: None,
+    };
+    assert_eq!(usage.cache_hit_ratio(), Some(0.75));
+    usage.cached_prompt_tokens = None;
+    assert_eq!(usage.cache_hit_ratio(), None);
+}
+
+#[tokio::test]
+async fn test_is_server_failure() {
+    let api_error = |r#type: &str, code: &str| {
+        ClientError::ApiError(ApiError {
+            message: "error".to_string(),
+            r#type: Some(r#type.to_string()),
+            param: None,
+            code: Some(code.to_string()),
+        })
+    };
+    assert!(is_server_failure(&api_error("server_error", "500")));
+    assert!(is_server_failure(&api_error("unavailable_error", "502")));
+    assert!(is_server_failure(&ClientError::ServiceUnavailable {
+        message: "Loading model".to_string()
+    }));
+    // Errors caused by the request don't restart a healthy server.
+    assert!(!is_server_failure(&api_error(
+        "invalid_request_error",
+        "400"
+    )));
+    assert!(!is_server_failure(&api_error(
+        "exceed_context_size_error",
+        "400"
+    )));
+    assert!(!is_server_failure(&ClientError::GenericError {
+        message: "error".to_string()
+    }));
+    assert!(!is_server_failure(&ClientError::ResponseTooLarge {
+        limit: 1
+    }));
+
+    // Nothing listens on port 1, so the connection is refused.
+    let connect_error = reqwest::get("http://127.0.0.1:1").await.unwrap_err();
+    assert!(is_server_failure(&ClientError::Reqwest(connect_error)));
+    let builder_error = reqwest::get("not a url").await.unwrap_err();
+    assert!(!is_server_failure(&ClientError::Reqwest(builder_error)));
+}
@@ -1,4 +1,18 @@
 mod api;
+mod context_length;
+mod determinism;
+mod embedding;
+mod grammar;
+mod json_repair;
+mod json_stream;
 mod llama_cpp;
 #[cfg(feature = "mistral_rs_backend")]
 mod mistral_rs;
+mod moderation;
+mod request_queue;
+mod response_cache;
+mod response_diff;
+mod score;
+mod sse;
+mod stop_sequence;
+mod tool;
@@ -0,0 +1,95 @@
+use llm_interface::{
+    llms::api::{
+        config::LlmApiConfigTrait,
+        moderation::{ModerationPolicy, OpenAiModerationResponse},
+    },
+    requests::completion::{CompletionError, CompletionRequest},
+    LlmInterface,
+};
+use llm_testing::serve_json;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+#[tokio::test]
+async fn test_classifier_moderation() {
+    let policy = ModerationPolicy::classifier(|text| {
+        if text.contains("forbidden") {
+            vec!["custom".to_string()]
+        } else {
+            Vec::new()
+        }
+    });
+    assert!(policy.check("Hello, world!").await.is_ok());
+    assert!(policy.check("   ").await.is_ok());
+    match policy.check("Something forbidden").await {
+        Err(CompletionError::ModerationBlocked { categories }) => {
+            assert_eq!(categories, vec!["custom".to_string()])
+        }
+        other => panic!("Expected ModerationBlocked, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_openai_moderation_response() {
+    let res: OpenAiModerationResponse = serde_json::from_str(
+        r#"{
+            "id": "modr-1",
+            "model": "omni-moderation-latest",
+            "results": [{
+                "flagged": true,
+                "categories": {"violence": true, "harassment": true, "sexual": false},
+                "category_scores": {"violence": 0.9, "harassment": 0.8, "sexual": 0.01}
+            }]
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(res.flagged_categories(None), vec!["harassment", "violence"]);
+    assert_eq!(
+        res.flagged_categories(Some(&["violence".to_string()])),
+        vec!["violence"]
+    );
+    assert!(res
+        .flagged_categories(Some(&["sexual".to_string()]))
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_moderation_once_per_request() {
+    let body = serde_json::json!({
+        "id": "chatcmpl-1",
+        "created": 0,
+        "model": "llama3",
+        "choices": [{
+            "index": 0,
+            "finish_reason": "stop",
+            "message": {"role": "assistant", "content": "Hello"},
+        }],
+    })
+    .to_string();
+    let checks = Arc::new(AtomicUsize::new(0));
+    let counter = checks.clone();
+    let backend = LlmInterface::ollama()
+        .with_api_host("127.0.0.1")
+        .with_api_port(serve_json(200, body, true).to_string())
+        .with_moderation(ModerationPolicy::classifier(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Vec::new()
+        }))
+        .init()
+        .unwrap();
+    let mut req = CompletionRequest::new(backend);
+    req.prompt
+        .add_user_message()
+        .unwrap()
+        .set_content("Hello, world!");
+    // The response never ends with the required stop word, so every attempt is retried.
+    req.stop_sequences.required = true;
+    req.stop_sequences.set_stop_word_done("DONE");
+    assert!(matches!(
+        req.request().await,
+        Err(CompletionError::ExceededRetryCount { .. })
+    ));
+    assert_eq!(checks.load(Ordering::SeqCst), 1);
+}
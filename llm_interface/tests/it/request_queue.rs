@@ -0,0 +1,73 @@
+use llm_interface::{
+    llms::local::request_queue::RequestQueue, requests::completion::CompletionError,
+};
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_drain() {
+    let queue = RequestQueue::new(1, None);
+    let permit = queue.acquire().await.unwrap();
+    assert!(!queue.drain(Duration::from_millis(10)).await);
+    assert!(matches!(
+        queue.acquire().await,
+        Err(CompletionError::ShuttingDown)
+    ));
+    drop(permit);
+
+    let queue = std::sync::Arc::new(RequestQueue::new(1, None));
+    let permit = queue.acquire().await.unwrap();
+    let draining = tokio::spawn({
+        let queue = queue.clone();
+        async move { queue.drain(Duration::from_secs(5)).await }
+    });
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    drop(permit);
+    assert!(draining.await.unwrap());
+}
+
+#[tokio::test]
+async fn test_concurrency_limit() {
+    let queue = RequestQueue::new(2, None);
+    let first = queue.acquire().await.unwrap();
+    let _second = queue.acquire().await.unwrap();
+    assert_eq!(queue.in_flight(), 2);
+    // The third request waits until a permit is released.
+    assert!(
+        tokio::time::timeout(Duration::from_millis(10), queue.acquire())
+            .await
+            .is_err()
+    );
+    assert_eq!(queue.queue_depth(), 0);
+    drop(first);
+    let _third = queue.acquire().await.unwrap();
+    assert_eq!(queue.in_flight(), 2);
+
+    // At least one request can always run.
+    let queue = RequestQueue::new(0, None);
+    let _permit = queue.acquire().await.unwrap();
+    assert_eq!(queue.in_flight(), 1);
+}
+
+#[tokio::test]
+async fn test_max_queue_depth() {
+    let queue = std::sync::Arc::new(RequestQueue::new(1, Some(1)));
+    let permit = queue.acquire().await.unwrap();
+    let waiting = tokio::spawn({
+        let queue = queue.clone();
+        async move { queue.acquire().await.is_ok() }
+    });
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(queue.queue_depth(), 1);
+
+    // The queue is full, so the next request fails instead of waiting.
+    assert!(matches!(
+        queue.acquire().await,
+        Err(CompletionError::QueueFull(_))
+    ));
+    assert_eq!(queue.queue_depth(), 1);
+
+    drop(permit);
+    assert!(waiting.await.unwrap());
+    assert_eq!(queue.queue_depth(), 0);
+    assert_eq!(queue.in_flight(), 0);
+}
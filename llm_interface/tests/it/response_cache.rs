@@ -0,0 +1,148 @@
+use llm_interface::{
+    llms::api::config::LlmApiConfigTrait,
+    requests::completion::{
+        CacheKey, CompletionFinishReason, CompletionRequest, CompletionResponse, ResponseCache,
+    },
+    LlmInterface,
+};
+use std::time::Duration;
+
+fn request(prompt: &str) -> CompletionRequest {
+    let backend = LlmInterface::openai()
+        .with_api_key("sk-test")
+        .init()
+        .unwrap();
+    let mut req = CompletionRequest::new(backend);
+    req.prompt.add_user_message().unwrap().set_content(prompt);
+    req.config.temperature = 0.0;
+    req
+}
+
+fn entry(prompt: &str, content: &str) -> (CacheKey, CompletionResponse) {
+    let req = request(prompt);
+    let key = ResponseCache::key(&req).unwrap();
+    let res = CompletionResponse::new_from_stream(
+        &req,
+        "test",
+        content.to_string(),
+        CompletionFinishReason::Eos,
+    );
+    (key, res)
+}
+
+#[test]
+fn test_cache_key() {
+    let (key, _) = entry("Hello", "");
+    assert_eq!(ResponseCache::key(&request("Hello")), Some(key.clone()));
+    assert_ne!(ResponseCache::key(&request("Hello!")), Some(key));
+
+    let mut req = request("Hello");
+    req.config.temperature = 0.7;
+    assert_eq!(ResponseCache::key(&req), None);
+}
+
+#[test]
+fn test_cache_lru_order() {
+    let cache = ResponseCache::new(2);
+    let (a, res_a) = entry("a", "A");
+    let (b, res_b) = entry("b", "B");
+    let (c, res_c) = entry("c", "C");
+    cache.insert(a.clone(), res_a);
+    cache.insert(b.clone(), res_b);
+    // Using `a` makes `b` the least recently used entry.
+    assert_eq!(cache.get(&a).unwrap().content, "A");
+    cache.insert(c.clone(), res_c);
+    assert_eq!(cache.len(), 2);
+    assert!(cache.get(&b).is_none());
+    assert_eq!(cache.get(&a).unwrap().content, "A");
+    assert_eq!(cache.get(&c).unwrap().content, "C");
+}
+
+#[test]
+fn test_cache_ttl_expiry() {
+    let cache = ResponseCache::new(4).with_ttl(Duration::from_millis(50));
+    let (a, res_a) = entry("a", "A");
+    cache.insert(a.clone(), res_a);
+    assert!(cache.get(&a).is_some());
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(cache.get(&a).is_none());
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn test_cache_zero_capacity() {
+    let cache = ResponseCache::new(0);
+    let (a, res_a) = entry("a", "A");
+    cache.insert(a.clone(), res_a);
+    assert!(cache.is_empty());
+    assert!(cache.get(&a).is_none());
+}
+
+#[test]
+fn test_cache_byte_budget() {
+    let (a, res_a) = entry("a", "A");
+    let (b, res_b) = entry("b", "B");
+    let (c, res_c) = entry("c", "C");
+
+    let probe = ResponseCache::new(4);
+    probe.insert(a.clone(), res_a.clone());
+    let entry_bytes = probe.cache_stats().bytes;
+    assert!(entry_bytes > 0);
+
+    // Room for two entries, so the third evicts the least recently used one.
+    let cache = ResponseCache::new(4).with_max_bytes(entry_bytes * 2 + entry_bytes / 2);
+    cache.insert(a.clone(), res_a);
+    cache.insert(b.clone(), res_b);
+    cache.insert(c.clone(), res_c);
+    let stats = cache.cache_stats();
+    assert_eq!(stats.entries, 2);
+    assert_eq!(stats.evictions, 1);
+    assert!(stats.bytes <= entry_bytes * 2);
+    assert!(cache.get(&a).is_none());
+    assert!(cache.get(&c).is_some());
+}
+
+#[test]
+fn test_cache_rejects_oversized_response() {
+    let (a, res_a) = entry("a", "A");
+    let (b, res_b) = entry("b", &"B".repeat(4096));
+
+    let probe = ResponseCache::new(4);
+    probe.insert(a.clone(), res_a.clone());
+    let cache = ResponseCache::new(4).with_max_bytes(probe.cache_stats().bytes * 2);
+    cache.insert(a.clone(), res_a);
+    cache.insert(b.clone(), res_b);
+    let stats = cache.cache_stats();
+    // The oversized response isn't cached and doesn't evict the entries that fit.
+    assert_eq!(stats.entries, 1);
+    assert_eq!(stats.evictions, 0);
+    assert!(cache.get(&b).is_none());
+    assert!(cache.get(&a).is_some());
+}
+
+#[test]
+fn test_cache_stats() {
+    let cache = ResponseCache::new(4).with_ttl(Duration::from_millis(50));
+    let (a, res_a) = entry("a", "A");
+    let (b, _) = entry("b", "B");
+    assert_eq!(cache.cache_stats().hit_ratio(), None);
+
+    cache.insert(a.clone(), res_a);
+    assert!(cache.get(&a).is_some());
+    assert!(cache.get(&b).is_none());
+    let stats = cache.cache_stats();
+    assert_eq!((stats.hits, stats.misses, stats.evictions), (1, 1, 0));
+    assert_eq!(stats.hit_ratio(), Some(0.5));
+
+    // An expired entry is a miss and counts as an eviction.
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(cache.get(&a).is_none());
+    let stats = cache.cache_stats();
+    assert_eq!((stats.hits, stats.misses, stats.evictions), (1, 2, 1));
+    assert_eq!((stats.entries, stats.bytes), (0, 0));
+
+    cache.reset_stats();
+    let stats = cache.cache_stats();
+    assert_eq!((stats.hits, stats.misses, stats.evictions), (0, 0, 0));
+    assert_eq!(stats.hit_ratio(), None);
+}
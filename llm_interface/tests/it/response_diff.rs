@@ -0,0 +1,64 @@
+use llm_interface::{
+    llms::api::config::LlmApiConfigTrait,
+    requests::completion::{
+        token_overlap, CompletionFinishReason, CompletionRequest, CompletionResponse,
+        ResultComparison,
+    },
+    LlmInterface,
+};
+
+fn response(content: &str, completion_tokens: u32) -> CompletionResponse {
+    let backend = LlmInterface::openai()
+        .with_api_key("sk-test")
+        .init()
+        .unwrap();
+    let req = CompletionRequest::new(backend);
+    let mut res = CompletionResponse::new_from_stream(
+        &req,
+        "test",
+        content.to_string(),
+        CompletionFinishReason::Eos,
+    );
+    res.token_usage.completion_tokens = completion_tokens;
+    res
+}
+
+#[test]
+fn test_token_overlap() {
+    assert_eq!(token_overlap("the cat sat", "the cat sat"), 1.0);
+    assert_eq!(token_overlap("", "  "), 1.0);
+    assert_eq!(token_overlap("the cat", "a dog"), 0.0);
+    assert_eq!(token_overlap("the cat sat", "the dog sat down"), 4.0 / 7.0);
+    // Repeated tokens are only shared as often as both texts contain them.
+    assert_eq!(token_overlap("yes yes", "yes"), 2.0 / 3.0);
+}
+
+#[test]
+fn test_compare() {
+    let diff = response(" 42 ", 3).compare(&response("42 apples", 5));
+    assert!(!diff.identical_content);
+    assert_eq!(diff.token_overlap, 2.0 / 3.0);
+    // The contents are trimmed before they are measured.
+    assert_eq!(diff.length_delta, 7);
+    assert_eq!(diff.completion_tokens_delta, 2);
+    assert!(diff.same_finish_reason);
+    assert_eq!(diff.same_result, ResultComparison::NotParsed);
+
+    let diff = response("42 apples", 5).compare(&response("42", 3));
+    assert_eq!((diff.length_delta, diff.completion_tokens_delta), (-7, -2));
+}
+
+#[test]
+fn test_compare_parsed() {
+    let parse = |content: &str| content.parse::<u32>();
+    let same_result = |content: &str, other: &str| {
+        response(content, 1)
+            .compare_parsed(&response(other, 1), parse)
+            .same_result
+    };
+    assert_eq!(same_result("42", " 42"), ResultComparison::Same);
+    assert_eq!(same_result("42", "43"), ResultComparison::Different);
+    assert_eq!(same_result("42", "many"), ResultComparison::OneFailed);
+    assert_eq!(same_result("few", "42"), ResultComparison::OneFailed);
+    assert_eq!(same_result("few", "many"), ResultComparison::BothFailed);
+}
@@ -0,0 +1,36 @@
+use llm_interface::requests::{completion::CompletionError, score::TextScore};
+
+fn empty_score() -> TextScore {
+    TextScore {
+        token_logprobs: Vec::new(),
+        tokens_outside_top_probs: 0,
+    }
+}
+
+#[test]
+fn test_text_score() {
+    let mut score = empty_score();
+    score
+        .push_token(9906, &[(Some(9906), 0.5), (Some(21694), 0.25)])
+        .unwrap();
+    // A token outside the top probs gets the lowest returned probability.
+    score
+        .push_token(1917, &[(Some(1070), 0.5), (None, 0.125)])
+        .unwrap();
+    assert_eq!(score.tokens_outside_top_probs, 1);
+    assert_eq!(score.token_logprobs, vec![0.5f32.ln(), 0.125f32.ln()]);
+    assert!(score.total_logprob() < 0.0);
+    assert_eq!(score.mean_logprob(), (0.5f32.ln() + 0.125f32.ln()) / 2.0);
+    assert!(score.perplexity() > 1.0);
+}
+
+#[test]
+fn test_text_score_without_probs() {
+    let mut score = empty_score();
+    assert!(matches!(
+        score.push_token(9906, &[]),
+        Err(CompletionError::LocalClientError(_))
+    ));
+    assert!(score.token_logprobs.is_empty());
+    assert_eq!(score.tokens_outside_top_probs, 0);
+}
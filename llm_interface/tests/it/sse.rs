@@ -0,0 +1,79 @@
+use llm_interface::llms::api::{
+    client::sse::{SseMessage, SseParser, Utf8StreamDecoder},
+    openai::completion::OpenAiCompletionChunk,
+};
+
+#[test]
+fn test_sse_parser_partial_chunks() {
+    let mut parser = SseParser::new();
+    assert!(parser.push(b"data: {\"a\"").is_empty());
+    assert!(parser.push(b":1}\r\n").is_empty());
+    assert_eq!(
+        parser.push(b"\r\n: keep-alive\n\ndata: [DONE]\n\n"),
+        vec![SseMessage::Data("{\"a\":1}".to_string()), SseMessage::Done]
+    );
+}
+
+#[test]
+fn test_sse_parser_multi_line_and_finish() {
+    let mut parser = SseParser::new();
+    assert_eq!(
+        parser.push(b"event: message\ndata: one\ndata: two\n\ndata: three"),
+        vec![SseMessage::Data("one\ntwo".to_string())]
+    );
+    assert_eq!(parser.finish(), Some(SseMessage::Data("three".to_string())));
+    assert_eq!(parser.finish(), None);
+}
+
+#[test]
+fn test_sse_parser_split_multi_byte_character() {
+    let mut parser = SseParser::new();
+    let bytes = "data: 日本\n\n".as_bytes();
+    assert!(parser.push(&bytes[..8]).is_empty());
+    assert_eq!(
+        parser.push(&bytes[8..]),
+        vec![SseMessage::Data("日本".to_string())]
+    );
+}
+
+#[test]
+fn test_utf8_stream_decoder_token_boundaries() {
+    let text = "こんにちは مرحبا";
+    let mut decoder = Utf8StreamDecoder::new();
+    let mut decoded = String::new();
+    for byte in text.as_bytes() {
+        decoded.push_str(&decoder.push(&[*byte]));
+    }
+    assert!(!decoder.has_pending());
+    assert_eq!(decoded, text);
+
+    let bytes = "ب".as_bytes();
+    assert_eq!(decoder.push(&[b'a', bytes[0]]), "a");
+    assert!(decoder.has_pending());
+    assert_eq!(decoder.push(&bytes[1..]), "ب");
+}
+
+#[test]
+fn test_utf8_stream_decoder_invalid_and_truncated() {
+    let mut decoder = Utf8StreamDecoder::new();
+    assert_eq!(decoder.push(&[b'a', 0xff, b'b']), "a\u{fffd}b");
+    assert_eq!(decoder.push(&"日".as_bytes()[..2]), "");
+    assert_eq!(decoder.finish(), "\u{fffd}");
+    assert!(!decoder.has_pending());
+}
+
+#[test]
+fn test_openai_usage_chunk() {
+    let chunk: OpenAiCompletionChunk = serde_json::from_str(
+        r#"{"id": "chatcmpl-1", "choices": [{"delta": {"content": "Hi"}, "finish_reason": null}]}"#,
+    )
+    .unwrap();
+    assert!(chunk.usage.is_none());
+
+    let chunk: OpenAiCompletionChunk = serde_json::from_str(
+        r#"{"id": "chatcmpl-1", "choices": [], "usage": {"prompt_tokens": 12, "completion_tokens": 3, "total_tokens": 15}}"#,
+    )
+    .unwrap();
+    assert!(chunk.choices.is_empty());
+    assert_eq!(chunk.usage.unwrap().total_tokens, 15);
+}
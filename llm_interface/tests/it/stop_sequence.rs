@@ -0,0 +1,29 @@
+use llm_interface::requests::stop_sequence::{StopSequences, StoppingSequence};
+
+fn stop_sequences() -> StopSequences {
+    let mut stop_sequences = StopSequences::new();
+    stop_sequences
+        .set_stop_word_done("Done.")
+        .set_stop_word_no_result("None.");
+    stop_sequences
+}
+
+#[test]
+fn test_parse_api_stop() {
+    let stop_sequences = stop_sequences();
+
+    // An empty response is the no-result stop word.
+    assert!(matches!(
+        stop_sequences.parse_api_stop("  "),
+        Some(StoppingSequence::NoResult(_))
+    ));
+
+    // Content ending with the done stop word, when the server didn't strip it.
+    assert!(matches!(
+        stop_sequences.parse_api_stop("The answer is 42. Done."),
+        Some(StoppingSequence::InferenceDone(_))
+    ));
+
+    // A natural EOS shows no stop word, so the required check can still catch it.
+    assert_eq!(stop_sequences.parse_api_stop("The answer is 42."), None);
+}
@@ -0,0 +1,229 @@
+use llm_interface::{
+    llms::api::{
+        anthropic::completion::{AnthropicCompletionRequest, AnthropicCompletionResponse},
+        config::LlmApiConfigTrait,
+        openai::completion::{
+            OpenAiCompletionChunk, OpenAiCompletionRequest, OpenAiCompletionResponse,
+            OpenAiToolCall,
+        },
+    },
+    requests::{
+        completion::{CompletionFinishReason, CompletionRequest, CompletionResponse},
+        tool::{Tool, ToolCall, ToolResult},
+    },
+    LlmInterface,
+};
+use serde_json::json;
+
+fn weather_tool() -> Tool {
+    Tool::new(
+        "get_weather",
+        json!({
+            "type": "object",
+            "properties": {"city": {"type": "string"}},
+            "required": ["city"],
+        }),
+    )
+    .description("Get the current weather for a city.")
+}
+
+fn weather_call() -> ToolCall {
+    ToolCall {
+        id: "call_1".to_string(),
+        name: "get_weather".to_string(),
+        arguments: json!({"city": "Paris"}),
+    }
+}
+
+fn tool_request(mut req: CompletionRequest) -> CompletionRequest {
+    req.prompt
+        .add_user_message()
+        .unwrap()
+        .set_content("What's the weather in Paris?");
+    req.with_tools(vec![weather_tool()]);
+    req.config.set_max_tokens_for_request(10).unwrap();
+    req
+}
+
+fn openai_request() -> CompletionRequest {
+    let backend = LlmInterface::openai()
+        .with_api_key("sk-test")
+        .init()
+        .unwrap();
+    tool_request(CompletionRequest::new(backend))
+}
+
+fn anthropic_request() -> CompletionRequest {
+    let backend = LlmInterface::anthropic()
+        .with_api_key("sk-test")
+        .init()
+        .unwrap();
+    tool_request(CompletionRequest::new(backend))
+}
+
+#[test]
+fn test_openai_tool_definitions() {
+    let request =
+        serde_json::to_value(OpenAiCompletionRequest::new(&openai_request()).unwrap()).unwrap();
+    assert_eq!(
+        request["tools"],
+        json!([{
+            "type": "function",
+            "function": {
+                "name": "get_weather",
+                "description": "Get the current weather for a city.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {"city": {"type": "string"}},
+                    "required": ["city"],
+                },
+            },
+        }])
+    );
+}
+
+#[test]
+fn test_openai_tool_call_round_trip() {
+    let mut req = openai_request();
+    let res: OpenAiCompletionResponse = serde_json::from_value(json!({
+        "id": "chatcmpl-1",
+        "created": 0,
+        "model": "gpt-4o-mini",
+        "choices": [{
+            "index": 0,
+            "finish_reason": "tool_calls",
+            "message": {
+                "role": "assistant",
+                "content": null,
+                "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"},
+                }],
+            },
+        }],
+    }))
+    .unwrap();
+    let res = CompletionResponse::new_from_openai(&req, res).unwrap();
+    assert!(matches!(
+        res.finish_reason,
+        CompletionFinishReason::ToolCalls
+    ));
+    let tool_calls = res.tool_calls.unwrap();
+    assert_eq!(tool_calls, vec![weather_call()]);
+
+    let tool_results = vec![ToolResult::new(&tool_calls[0], "Sunny, 21C")];
+    req.submit_tool_results(tool_calls, tool_results);
+    let request = serde_json::to_value(OpenAiCompletionRequest::new(&req).unwrap()).unwrap();
+    let messages = request["messages"].as_array().unwrap();
+    assert_eq!(
+        messages[messages.len() - 2..],
+        [
+            json!({
+                "role": "assistant",
+                "content": null,
+                "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"},
+                }],
+            }),
+            json!({"role": "tool", "content": "Sunny, 21C", "tool_call_id": "call_1"}),
+        ]
+    );
+}
+
+#[test]
+fn test_openai_streamed_tool_call() {
+    let chunk = |tool_calls: serde_json::Value| -> OpenAiCompletionChunk {
+        serde_json::from_value(json!({
+            "id": "chatcmpl-1",
+            "choices": [{"delta": {"tool_calls": tool_calls}, "finish_reason": null}],
+        }))
+        .unwrap()
+    };
+    let chunks = [
+        chunk(json!([{
+            "index": 0,
+            "id": "call_1",
+            "type": "function",
+            "function": {"name": "get_weather", "arguments": ""},
+        }])),
+        chunk(json!([{"index": 0, "function": {"arguments": "{\"city\":"}}])),
+        chunk(json!([{"index": 0, "function": {"arguments": "\"Paris\"}"}}])),
+    ];
+    let mut tool_calls: Vec<OpenAiToolCall> = Vec::new();
+    for chunk in &chunks {
+        for delta in chunk.choices[0].delta.tool_calls.iter().flatten() {
+            delta.merge_into(&mut tool_calls);
+        }
+    }
+    assert_eq!(tool_calls.len(), 1);
+    assert_eq!(tool_calls[0].to_tool_call().unwrap(), weather_call());
+}
+
+#[test]
+fn test_anthropic_tool_definitions() {
+    let request =
+        serde_json::to_value(AnthropicCompletionRequest::new(&anthropic_request()).unwrap())
+            .unwrap();
+    assert_eq!(
+        request["tools"],
+        json!([{
+            "name": "get_weather",
+            "description": "Get the current weather for a city.",
+            "input_schema": {
+                "type": "object",
+                "properties": {"city": {"type": "string"}},
+                "required": ["city"],
+            },
+        }])
+    );
+}
+
+#[test]
+fn test_anthropic_tool_call_round_trip() {
+    let mut req = anthropic_request();
+    let res: AnthropicCompletionResponse = serde_json::from_value(json!({
+        "id": "msg_1",
+        "model": "claude",
+        "content": [
+            {"type": "text", "text": "Let me check."},
+            {"type": "tool_use", "id": "call_1", "name": "get_weather", "input": {"city": "Paris"}},
+        ],
+        "stop_reason": "tool_use",
+        "stop_sequence": null,
+        "usage": {"input_tokens": 1, "output_tokens": 1},
+    }))
+    .unwrap();
+    let res = CompletionResponse::new_from_anthropic(&req, res).unwrap();
+    assert!(matches!(
+        res.finish_reason,
+        CompletionFinishReason::ToolCalls
+    ));
+    let tool_calls = res.tool_calls.unwrap();
+    assert_eq!(tool_calls, vec![weather_call()]);
+
+    let tool_results = vec![ToolResult::new(&tool_calls[0], "Sunny, 21C")];
+    req.submit_tool_results(tool_calls, tool_results);
+    let request = serde_json::to_value(AnthropicCompletionRequest::new(&req).unwrap()).unwrap();
+    let messages = request["messages"].as_array().unwrap();
+    assert_eq!(
+        messages[messages.len() - 2..],
+        [
+            json!({
+                "role": "assistant",
+                "content": [{
+                    "type": "tool_use",
+                    "id": "call_1",
+                    "name": "get_weather",
+                    "input": {"city": "Paris"},
+                }],
+            }),
+            json!({
+                "role": "user",
+                "content": [{"type": "tool_result", "tool_use_id": "call_1", "content": "Sunny, 21C"}],
+            }),
+        ]
+    );
+}
@@ -86,12 +86,17 @@ impl ApiLlmModel {
     }
 }
 
+/// Claude's tokenizer produces more tokens than tiktoken's cl100k encoding for the same text, so counts from the
+/// stand-in tokenizer are scaled up to keep context budget calculations on the safe side.
+pub const ANTHROPIC_TOKEN_COUNT_SCALE: f32 = 1.2;
+
+/// Anthropic doesn't publish a tokenizer, so token counts are estimated with tiktoken scaled by
+/// [ANTHROPIC_TOKEN_COUNT_SCALE]. Anthropic doesn't support logit bias, so real token ids aren't needed.
+/// For an exact count use the backend's token counting endpoint.
 pub fn model_tokenizer(_model_id: &str) -> Arc<LlmTokenizer> {
-    println!("Anthropic does not have a publically available tokenizer. See this for more information: https://github.com/javirandor/anthropic-tokenizer");
-    println!("However, since Anthropic does not support logit bias, we don't have a use for an actual tokenizer. So we can use TikToken to count tokens.");
     Arc::new(
-        LlmTokenizer::new_tiktoken("gpt-4")
-            .unwrap_or_else(|_| panic!("Failed to load tokenizer for gpt-4")),
+        LlmTokenizer::new_tiktoken_or_approximate("gpt-4")
+            .with_token_count_scale(ANTHROPIC_TOKEN_COUNT_SCALE),
     )
 }
 
@@ -10,8 +10,26 @@ impl ApiLlmModel {
             "gpt-4-turbo" => Self::gpt_4_turbo(),
             "gpt-4o" => Self::gpt_4_o(),
             "gpt-3.5-turbo" => Self::gpt_3_5_turbo(),
-            "gpt-4o-mini" => Self::gpt_3_5_turbo(),
-            _ => panic!("Model ID ({model_id}) not found for ApiLlmModel"),
+            "gpt-4o-mini" => Self::gpt_4_o_mini(),
+            _ => Self::openai_compatible_model(model_id),
+        }
+    }
+
+    /// A model served through an OpenAI compatible API that isn't one of the known OpenAI models.
+    ///
+    /// If no tiktoken encoding exists for the model, token counts are approximated.
+    pub fn openai_compatible_model(model_id: &str) -> ApiLlmModel {
+        ApiLlmModel {
+            model_base: LlmModelBase {
+                model_id: model_id.to_string(),
+                model_ctx_size: 8192,
+                inference_ctx_size: 4096,
+                tokenizer: model_tokenizer(model_id),
+            },
+            cost_per_m_in_tokens: 0.0,
+            cost_per_m_out_tokens: 0.0,
+            tokens_per_message: 3,
+            tokens_per_name: Some(1),
         }
     }
 
@@ -153,10 +171,7 @@ impl ApiLlmModel {
 }
 
 fn model_tokenizer(model_id: &str) -> Arc<LlmTokenizer> {
-    Arc::new(
-        LlmTokenizer::new_tiktoken(model_id)
-            .unwrap_or_else(|_| panic!("Failed to load tokenizer for {model_id}")),
-    )
+    Arc::new(LlmTokenizer::new_tiktoken_or_approximate(model_id))
 }
 
 pub trait OpenAiModelTrait {
@@ -68,6 +68,29 @@ impl LlmChatTemplate {
         Ok(chat_template)
     }
 
+    /// Renders the messages through the chat template without sending a request. Each message is a map with a `role`
+    /// and `content`. If `add_generation_prefix` is set, the text opening an assistant turn is appended, as it is
+    /// for a prompt sent to the model.
+    pub fn render(
+        &self,
+        messages: &[HashMap<String, String>],
+        add_generation_prefix: bool,
+    ) -> String {
+        let mut rendered = llm_prompt::apply_chat_template(
+            &messages.to_vec(),
+            &self.chat_template,
+            &self.bos_token,
+            &self.eos_token,
+            self.unk_token.as_deref(),
+        );
+        if add_generation_prefix {
+            if let Some(base_generation_prefix) = &self.base_generation_prefix {
+                rendered.push_str(base_generation_prefix);
+            }
+        }
+        rendered
+    }
+
     fn set_generation_prefix(&mut self) -> crate::Result<()> {
         let user_message_1 = HashMap::from([
             ("role".to_string(), "user".to_string()),
@@ -36,4 +36,11 @@ impl Architecture {
             Self::Llama(llama) => llama.context_length,
         }
     }
+
+    /// The context length the model was trained for, if its context was already extended with RoPE scaling.
+    pub fn original_context_length(&self) -> Option<u64> {
+        match self {
+            Self::Llama(llama) => llama.rope.scaling.original_context_length.map(u64::from),
+        }
+    }
 }
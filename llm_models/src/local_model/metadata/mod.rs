@@ -52,6 +52,10 @@ impl LocalLlmMetadata {
     pub fn context_length(&self) -> u64 {
         self.llm.context_length()
     }
+
+    pub fn original_context_length(&self) -> Option<u64> {
+        self.llm.original_context_length()
+    }
 }
 
 impl std::fmt::Debug for LocalLlmMetadata {
@@ -2,12 +2,18 @@ use super::local_model::hf_loader::{HfTokenTrait, HuggingFaceLoader};
 use anyhow::{anyhow, Result};
 use llm_prompt::PromptTokenizer;
 use std::{fmt, path::PathBuf};
-use tiktoken_rs::{get_bpe_from_model, CoreBPE};
+use tiktoken_rs::{
+    get_bpe_from_tokenizer,
+    tokenizer::{get_tokenizer, Tokenizer as TiktokenEncoding},
+    CoreBPE,
+};
 use tokenizers::Tokenizer as HFTokenizer;
 
 pub enum TokenizerBackend {
     HuggingFacesTokenizer(HFTokenizer),
     Tiktoken(CoreBPE),
+    /// No real tokenizer is available. Token counts are estimated from the character count.
+    Approximate,
 }
 
 impl fmt::Debug for TokenizerBackend {
@@ -19,6 +25,9 @@ impl fmt::Debug for TokenizerBackend {
             TokenizerBackend::Tiktoken(_) => {
                 write!(f, "TokenizerBackend::Tiktoken")
             }
+            TokenizerBackend::Approximate => {
+                write!(f, "TokenizerBackend::Approximate")
+            }
         }
     }
 }
@@ -29,20 +38,107 @@ pub struct LlmTokenizer {
     pub tokenizer_path: Option<PathBuf>,
     pub with_special_tokens: bool,
     pub white_space_token_id: u32,
+    /// Multiplier applied by [LlmTokenizer::count_tokens]. Used when the tokenizer stands in for a model's
+    /// unavailable tokenizer that is known to produce more tokens for the same text. Defaults to 1.0.
+    pub token_count_scale: f32,
+    /// The valid ids of a tiktoken encoding, which panics on unknown ids.
+    tiktoken_ids: Option<TiktokenIds>,
+}
+
+/// The ordinary tokens of a tiktoken encoding have the ids `0..ordinary`, and its special tokens follow.
+#[derive(Debug)]
+struct TiktokenIds {
+    ordinary: u32,
+    special: Vec<u32>,
+}
+
+impl TiktokenIds {
+    fn new(encoding: TiktokenEncoding, tokenizer: &CoreBPE) -> Self {
+        let ordinary = match encoding {
+            TiktokenEncoding::O200kBase => 199_998,
+            TiktokenEncoding::Cl100kBase => 100_256,
+            TiktokenEncoding::P50kBase | TiktokenEncoding::P50kEdit => 50_281,
+            TiktokenEncoding::R50kBase | TiktokenEncoding::Gpt2 => 50_256,
+        };
+        let special = [
+            tiktoken_rs::ENDOFTEXT,
+            tiktoken_rs::FIM_PREFIX,
+            tiktoken_rs::FIM_MIDDLE,
+            tiktoken_rs::FIM_SUFFIX,
+            tiktoken_rs::ENDOFPROMPT,
+        ]
+        .iter()
+        .filter_map(
+            |token| match tokenizer.encode_with_special_tokens(token)[..] {
+                [id] if id >= ordinary as usize => u32::try_from(id).ok(),
+                _ => None,
+            },
+        )
+        .collect();
+        Self { ordinary, special }
+    }
+
+    fn contains(&self, id: u32) -> bool {
+        id < self.ordinary || self.special.contains(&id)
+    }
 }
 
 impl LlmTokenizer {
     pub fn new_tiktoken<T: AsRef<str>>(model_id: T) -> Result<Self> {
-        let tokenizer = get_bpe_from_model(model_id.as_ref())?;
+        let encoding = get_tokenizer(model_id.as_ref())
+            .ok_or_else(|| anyhow!("No tokenizer found for model {}", model_id.as_ref()))?;
+        let tokenizer = get_bpe_from_tokenizer(encoding)?;
         let white_space_token_id = u32::try_from(tokenizer.encode_ordinary(" ").remove(0))?;
         Ok(Self {
+            tiktoken_ids: Some(TiktokenIds::new(encoding, &tokenizer)),
             tokenizer: TokenizerBackend::Tiktoken(tokenizer),
             tokenizer_path: None,
             with_special_tokens: false,
             white_space_token_id,
+            token_count_scale: 1.0,
         })
     }
 
+    /// A degraded tokenizer for models without an available encoding.
+    ///
+    /// Token counts are estimated as one token per four characters. Tokenizing returns placeholder ids
+    /// (only the length is meaningful), and detokenizing always fails, so features that need real
+    /// token ids, such as logit bias, are unavailable.
+    pub fn new_approximate() -> Self {
+        Self {
+            tokenizer: TokenizerBackend::Approximate,
+            tokenizer_path: None,
+            with_special_tokens: false,
+            white_space_token_id: 0,
+            token_count_scale: 1.0,
+            tiktoken_ids: None,
+        }
+    }
+
+    /// Load a tiktoken encoding for the model, falling back to [LlmTokenizer::new_approximate] if none exists.
+    pub fn new_tiktoken_or_approximate<T: AsRef<str>>(model_id: T) -> Self {
+        match Self::new_tiktoken(model_id.as_ref()) {
+            Ok(tokenizer) => tokenizer,
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to load tokenizer for {}: {e}. Falling back to approximate token counts.",
+                    model_id.as_ref()
+                );
+                Self::new_approximate()
+            }
+        }
+    }
+
+    /// Scales token counts, rounding up, for models whose real tokenizer isn't available.
+    pub fn with_token_count_scale(mut self, token_count_scale: f32) -> Self {
+        self.token_count_scale = token_count_scale;
+        self
+    }
+
+    pub fn is_approximate(&self) -> bool {
+        matches!(self.tokenizer, TokenizerBackend::Approximate)
+    }
+
     pub fn new_from_tokenizer(tokenizer: HFTokenizer) -> Result<Self> {
         let white_space_token_id = tokenizer.encode(" ", false).unwrap().get_ids()[0];
         Ok(Self {
@@ -50,6 +146,8 @@ impl LlmTokenizer {
             tokenizer_path: None,
             with_special_tokens: false,
             white_space_token_id,
+            token_count_scale: 1.0,
+            tiktoken_ids: None,
         })
     }
 
@@ -61,6 +159,8 @@ impl LlmTokenizer {
             tokenizer_path: Some(tokenizer_json_path.clone()),
             with_special_tokens: false,
             white_space_token_id,
+            token_count_scale: 1.0,
+            tiktoken_ids: None,
         })
     }
 
@@ -87,8 +187,16 @@ impl LlmTokenizer {
     }
 
     pub fn count_tokens(&self, str: &str) -> u32 {
-        let tokens = self.tokenize(str);
-        u32::try_from(tokens.len()).unwrap()
+        let count = if self.is_approximate() {
+            approximate_token_count(str)
+        } else {
+            u32::try_from(self.tokenize(str).len()).unwrap()
+        };
+        if self.token_count_scale == 1.0 {
+            count
+        } else {
+            (count as f32 * self.token_count_scale).ceil() as u32
+        }
     }
 
     pub fn try_from_single_token_id(&self, try_from_single_token_id: u32) -> Result<String> {
@@ -123,6 +231,101 @@ impl LlmTokenizer {
         }
     }
 
+    /// The id of the token whose text is exactly `token`, or `None` if `token` isn't a single token. Special tokens are
+    /// not matched.
+    pub fn token_to_id(&self, token: &str) -> Option<u32> {
+        let ids: Vec<u32> = match &self.tokenizer {
+            TokenizerBackend::HuggingFacesTokenizer(tokenizer) => {
+                tokenizer.encode(token, false).ok()?.get_ids().to_vec()
+            }
+            TokenizerBackend::Tiktoken(tokenizer) => tokenizer
+                .encode_ordinary(token)
+                .into_iter()
+                .map(|id| id as u32)
+                .collect(),
+            TokenizerBackend::Approximate => return None,
+        };
+        match ids[..] {
+            [id] if self.id_to_token(id).as_deref() == Some(token) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// The text of a single token, or `None` if the id isn't in the vocabulary. Bytes of a token that aren't valid
+    /// UTF-8 on their own, such as part of a multi-byte character, are replaced with `U+FFFD`.
+    pub fn id_to_token(&self, id: u32) -> Option<String> {
+        match &self.tokenizer {
+            TokenizerBackend::HuggingFacesTokenizer(tokenizer) => {
+                tokenizer.id_to_token(id)?;
+                tokenizer.decode(&[id], false).ok()
+            }
+            TokenizerBackend::Tiktoken(tokenizer) => {
+                if !self
+                    .tiktoken_ids
+                    .as_ref()
+                    .is_some_and(|ids| ids.contains(id))
+                {
+                    return None;
+                }
+                Some(
+                    String::from_utf8_lossy(&tokenizer._decode_native(&[id as usize])).into_owned(),
+                )
+            }
+            TokenizerBackend::Approximate => None,
+        }
+    }
+
+    /// The id and text of every token in the vocabulary, sorted by id. Errors with an approximate tokenizer.
+    pub fn vocabulary(&self) -> Result<Vec<(u32, String)>> {
+        match &self.tokenizer {
+            TokenizerBackend::HuggingFacesTokenizer(tokenizer) => {
+                let mut ids: Vec<u32> = tokenizer.get_vocab(true).into_values().collect();
+                ids.sort_unstable();
+                let sequences: Vec<&[u32]> = ids.iter().map(std::slice::from_ref).collect();
+                let texts = tokenizer
+                    .decode_batch(&sequences, false)
+                    .map_err(|e| anyhow!(e))?;
+                Ok(ids.into_iter().zip(texts).collect())
+            }
+            TokenizerBackend::Tiktoken(_) => {
+                let ids = self
+                    .tiktoken_ids
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("The tiktoken encoding's ids are unknown"))?;
+                let mut special = ids.special.clone();
+                special.sort_unstable();
+                Ok((0..ids.ordinary)
+                    .chain(special.into_iter().filter(|id| *id >= ids.ordinary))
+                    .filter_map(|id| Some((id, self.id_to_token(id)?)))
+                    .collect())
+            }
+            TokenizerBackend::Approximate => Err(anyhow!(
+                "The vocabulary isn't available with {:?}",
+                self.tokenizer
+            )),
+        }
+    }
+
+    /// The ids of the tokens whose text matches `predicate`, e.g. to build a logit bias that bans every token starting
+    /// with a digit. Errors with an approximate tokenizer, see [LlmTokenizer::vocabulary].
+    pub fn find_tokens<F>(&self, predicate: F) -> Result<Vec<u32>>
+    where
+        F: Fn(&str) -> bool,
+    {
+        Ok(self
+            .vocabulary()?
+            .into_iter()
+            .filter(|(_, token)| predicate(token))
+            .map(|(id, _)| id)
+            .collect())
+    }
+
+    /// The ids of the tokens whose text starts with `prefix`. Leading whitespace of a token is part of its text, so
+    /// `" the"` and `"the"` match different tokens.
+    pub fn tokens_with_prefix(&self, prefix: &str) -> Result<Vec<u32>> {
+        self.find_tokens(|token| token.starts_with(prefix))
+    }
+
     /// Creates a window of text normalized to the specified token size in the center of the text.
     ///
     /// # Arguments
@@ -205,6 +408,7 @@ impl LlmTokenizer {
         match &self.tokenizer {
             TokenizerBackend::HuggingFacesTokenizer(tokenizer) => self.encode_hf(tokenizer, str),
             TokenizerBackend::Tiktoken(tokenizer) => self.encode_tiktoken(tokenizer, str),
+            TokenizerBackend::Approximate => vec![0; approximate_token_count(str) as usize],
         }
     }
 
@@ -222,10 +426,17 @@ impl LlmTokenizer {
         match &self.tokenizer {
             TokenizerBackend::HuggingFacesTokenizer(tokenizer) => self.decode_hf(tokenizer, tokens),
             TokenizerBackend::Tiktoken(tokenizer) => self.decode_tiktoken(tokenizer, tokens),
+            TokenizerBackend::Approximate => Err(anyhow!(
+                "Decoding tokens is not available with an approximate tokenizer"
+            )),
         }
     }
 }
 
+fn approximate_token_count(str: &str) -> u32 {
+    u32::try_from(str.chars().count().div_ceil(4)).unwrap()
+}
+
 impl PromptTokenizer for LlmTokenizer {
     fn tokenize(&self, input: &str) -> Vec<u32> {
         self.tokenize(input)
@@ -1,3 +1,4 @@
 mod custom;
 mod metadata;
 mod preset;
+mod tokenizer;
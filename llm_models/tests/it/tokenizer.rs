@@ -0,0 +1,71 @@
+use llm_models::tokenizer::LlmTokenizer;
+
+#[test]
+fn test_token_id_lookup() {
+    let tokenizer = LlmTokenizer::new_tiktoken("gpt-4o").unwrap();
+    let id = tokenizer.token_to_id("hello").unwrap();
+    assert_eq!(tokenizer.id_to_token(id).as_deref(), Some("hello"));
+    assert_eq!(
+        tokenizer.id_to_token(199_999).as_deref(),
+        Some("<|endoftext|>")
+    );
+    // Out of the vocabulary, and between the ordinary and the special tokens.
+    assert_eq!(tokenizer.id_to_token(u32::MAX), None);
+    assert_eq!(tokenizer.id_to_token(200_000), None);
+    let cl100k = LlmTokenizer::new_tiktoken("gpt-4").unwrap();
+    assert!(cl100k.id_to_token(100_255).is_some());
+    assert_eq!(cl100k.id_to_token(100_256), None);
+    assert_eq!(
+        tokenizer.token_to_id("hello world, this is many tokens"),
+        None
+    );
+    assert_eq!(tokenizer.token_to_id(""), None);
+    let vocabulary = tokenizer.vocabulary().unwrap();
+    assert_eq!(vocabulary[0].0, 0);
+    assert_eq!(vocabulary[199_997].0, 199_997);
+    assert!(vocabulary.contains(&(199_999, "<|endoftext|>".to_string())));
+    let ones = tokenizer.tokens_with_prefix("1").unwrap();
+    assert!(ones.contains(&tokenizer.token_to_id("1").unwrap()));
+    assert!(ones.contains(&tokenizer.token_to_id("100").unwrap()));
+    assert!(ones
+        .iter()
+        .all(|id| tokenizer.id_to_token(*id).unwrap().starts_with('1')));
+
+    let approximate = LlmTokenizer::new_approximate();
+    assert_eq!(approximate.token_to_id("hello"), None);
+    assert_eq!(approximate.id_to_token(id), None);
+    assert!(approximate.tokens_with_prefix("1").is_err());
+}
+
+#[test]
+fn test_tokens_with_prefix() {
+    let tokenizer: tokenizers::Tokenizer = r#"{
+        "version": "1.0",
+        "truncation": null,
+        "padding": null,
+        "added_tokens": [],
+        "normalizer": null,
+        "pre_tokenizer": null,
+        "post_processor": null,
+        "decoder": null,
+        "model": {
+            "type": "WordLevel",
+            "vocab": {"[UNK]": 0, " ": 1, "1st": 2, "hello": 3, "2nd": 4},
+            "unk_token": "[UNK]"
+        }
+    }"#
+    .parse()
+    .unwrap();
+    let tokenizer = LlmTokenizer::new_from_tokenizer(tokenizer).unwrap();
+    assert_eq!(tokenizer.token_to_id("hello"), Some(3));
+    assert_eq!(tokenizer.id_to_token(2).as_deref(), Some("1st"));
+    assert_eq!(tokenizer.id_to_token(99), None);
+    assert_eq!(tokenizer.vocabulary().unwrap().len(), 5);
+    assert_eq!(
+        tokenizer
+            .find_tokens(|token| token.starts_with(|c: char| c.is_ascii_digit()))
+            .unwrap(),
+        vec![2, 4]
+    );
+    assert_eq!(tokenizer.tokens_with_prefix("he").unwrap(), vec![3]);
+}
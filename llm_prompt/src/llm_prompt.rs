@@ -75,6 +75,8 @@ impl LlmPrompt {
             crate::bail!(
                 "Cannot add assistant message when previous message is assistant message."
             );
+        } else if self.messages_ref().last().unwrap().message_type == PromptMessageType::System {
+            crate::bail!("Cannot add assistant message when previous message is system message.");
         };
         let message = PromptMessage::new(PromptMessageType::Assistant, self.concatenator_ref());
         self.messages_mut().push(message);
@@ -177,7 +179,7 @@ impl LlmPrompt {
             } else if last.message_type == PromptMessageType::System {
                 crate::bail!("Cannot build prompt when the current inference message is PromptMessageType::System")
             } else {
-                Ok(())
+                crate::prompt_message::check_message_roles(&self.messages_ref())
             }
         } else {
             crate::bail!("Cannot build prompt when there are no messages.")
@@ -113,6 +113,11 @@ impl PromptMessage {
         self.built_message_string_ref().clone()
     }
 
+    /// Empty messages are skipped when the prompt is built.
+    pub fn is_empty(&self) -> bool {
+        self.build_prompt_string().is_none()
+    }
+
     // Builder functions
     pub fn requires_build(&self) -> bool {
         !self.content_ref().is_empty() && self.built_message_hashmap_ref().is_empty()
@@ -175,32 +180,43 @@ impl PromptMessage {
     }
 }
 
-pub(crate) fn build_messages(messages: &mut [PromptMessage]) -> Vec<HashMap<String, String>> {
-    let mut prompt_messages: Vec<HashMap<String, String>> = Vec::new();
-    let mut last_message_type = None;
-    for (i, message) in messages.iter_mut().enumerate() {
+/// Chat templates require an optional system message followed by strictly alternating user and assistant messages.
+/// Empty messages are skipped when the prompt is built, so they are skipped here too: an empty assistant message
+/// between two user messages would otherwise render as two user turns in a row.
+pub(crate) fn check_message_roles(messages: &[PromptMessage]) -> crate::Result<()> {
+    let mut last_message_type: Option<&PromptMessageType> = None;
+    for message in messages.iter().filter(|message| !message.is_empty()) {
         let message_type = &message.message_type;
-        // Rule 1: System message can only be the first message
-        if *message_type == PromptMessageType::System && i != 0 {
-            panic!("System message can only be the first message.");
-        }
-        // Rule 2: First message must be either System or User
-        if i == 0
-            && *message_type != PromptMessageType::System
-            && *message_type != PromptMessageType::User
-        {
-            panic!("Conversation must start with either a System or User message.");
-        }
-        // Rule 3: Ensure alternating User/Assistant messages after the first message
-        if i > 0 {
-            match (last_message_type, message_type) {
-                (Some(PromptMessageType::User), PromptMessageType::Assistant) => {},
-                (Some(PromptMessageType::Assistant), PromptMessageType::User) => {},
-                (Some(PromptMessageType::System), PromptMessageType::User) => {},
-                _ => panic!("Messages must alternate between User and Assistant after the first message (which can be System)."),
+        match (last_message_type, message_type) {
+            (None, PromptMessageType::System | PromptMessageType::User) => (),
+            (None, PromptMessageType::Assistant) => {
+                crate::bail!("Conversation must start with either a System or User message.")
             }
-        }
-        last_message_type = Some(message_type.clone());
+            (Some(_), PromptMessageType::System) => {
+                crate::bail!("System message can only be the first message.")
+            }
+            (
+                Some(PromptMessageType::System | PromptMessageType::Assistant),
+                PromptMessageType::User,
+            )
+            | (Some(PromptMessageType::User), PromptMessageType::Assistant) => (),
+            (Some(last_message_type), message_type) => crate::bail!(
+                "Messages must alternate between User and Assistant, but a {} message follows a {} message.",
+                message_type.as_str(),
+                last_message_type.as_str()
+            ),
+        }
+        last_message_type = Some(message_type);
+    }
+    Ok(())
+}
+
+pub(crate) fn build_messages(messages: &mut [PromptMessage]) -> Vec<HashMap<String, String>> {
+    if let Err(e) = check_message_roles(messages) {
+        panic!("{e}");
+    }
+    let mut prompt_messages: Vec<HashMap<String, String>> = Vec::new();
+    for message in messages.iter_mut() {
         if message.requires_build() {
             message.build();
         }
@@ -68,22 +68,28 @@ fn available_tokens(
 ) -> Result<u64, RequestTokenLimitError> {
     let safety_tokens = safety_tokens.unwrap_or(DEFAULT_SAFETY_TOKENS);
 
-    if total_prompt_tokens as u64 >= ctx_size - safety_tokens {
+    // Checked arithmetic so an oversized prompt or a tiny context returns an error instead of overflowing.
+    if total_prompt_tokens >= ctx_size.saturating_sub(safety_tokens) {
         return Err(RequestTokenLimitError::PromptTokensExceeds {
             total_prompt_tokens,
-            ctx_size: ctx_size - safety_tokens,
+            ctx_size: ctx_size.saturating_sub(safety_tokens),
         });
     }
 
+    let remaining_tokens = ctx_size - total_prompt_tokens;
     let available_tokens = if let Some(inference_ctx_size) = inference_ctx_size {
-        std::cmp::min(ctx_size - total_prompt_tokens, inference_ctx_size) - safety_tokens
+        std::cmp::min(remaining_tokens, inference_ctx_size)
     } else {
-        ctx_size - total_prompt_tokens - safety_tokens
+        remaining_tokens
     };
-    if available_tokens == 0 {
-        panic!("available_tokens == 0. This should never happen.",);
+    match available_tokens.checked_sub(safety_tokens * 2) {
+        Some(available_tokens) if available_tokens > 0 => Ok(available_tokens),
+        _ => Err(RequestTokenLimitError::GenericPromptError {
+            e: format!(
+                "No tokens left for the response: total_prompt_tokens ({total_prompt_tokens}), ctx_size ({ctx_size}), inference_ctx_size ({inference_ctx_size:?}), safety_tokens ({safety_tokens})"
+            ),
+        }),
     }
-    Ok(available_tokens - safety_tokens)
 }
 
 pub(crate) fn total_prompt_tokens_openai_format(
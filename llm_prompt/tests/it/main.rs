@@ -1,5 +1,6 @@
 mod chat_template;
 mod open_ai;
+mod token_count;
 
 #[allow(unused_imports)]
 pub(crate) use anyhow::{anyhow, bail, Error, Result};
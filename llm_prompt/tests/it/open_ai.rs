@@ -52,3 +52,30 @@ fn test_openai() -> crate::Result<()> {
     assert_eq!(39, token_count);
     Ok(())
 }
+
+#[test]
+fn test_alternating_roles() -> crate::Result<()> {
+    let model = ApiLlmModel::gpt_3_5_turbo();
+    let prompt = LlmPrompt::new_openai_prompt(
+        Some(model.tokens_per_message),
+        model.tokens_per_name,
+        model.model_base.tokenizer.clone(),
+    );
+
+    prompt
+        .add_system_message()?
+        .set_content("test system content");
+    assert!(prompt.add_assistant_message().is_err());
+    prompt
+        .add_user_message()?
+        .set_content("test user content 1");
+    assert!(prompt.add_user_message().is_err());
+
+    // The empty assistant message is skipped, which would leave two user messages in a row.
+    prompt.add_assistant_message()?;
+    prompt
+        .add_user_message()?
+        .set_content("test user content 2");
+    assert!(prompt.get_built_prompt_hashmap().is_err());
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use llm_prompt::{check_and_get_max_tokens, RequestTokenLimitError};
+
+#[test]
+fn test_check_and_get_max_tokens() {
+    // The remaining context, less the safety tokens before and after the response.
+    assert_eq!(
+        check_and_get_max_tokens(1000, None, 100, Some(10), None).unwrap(),
+        880
+    );
+    // Limited by the inference context.
+    assert_eq!(
+        check_and_get_max_tokens(1000, Some(500), 100, Some(10), None).unwrap(),
+        480
+    );
+    // Requested tokens are used when they fit, and clamped when they don't.
+    assert_eq!(
+        check_and_get_max_tokens(1000, None, 100, Some(10), Some(50)).unwrap(),
+        50
+    );
+    assert_eq!(
+        check_and_get_max_tokens(1000, None, 100, Some(10), Some(5000)).unwrap(),
+        880
+    );
+    assert_eq!(
+        check_and_get_max_tokens(1000, None, 975, Some(10), None).unwrap(),
+        5
+    );
+}
+
+#[test]
+fn test_check_and_get_max_tokens_errors() {
+    assert!(matches!(
+        check_and_get_max_tokens(1000, None, 990, Some(10), None),
+        Err(RequestTokenLimitError::PromptTokensExceeds {
+            total_prompt_tokens: 990,
+            ctx_size: 990,
+        })
+    ));
+    // No room left for the response after the safety tokens.
+    assert!(matches!(
+        check_and_get_max_tokens(1000, None, 980, Some(10), None),
+        Err(RequestTokenLimitError::GenericPromptError { .. })
+    ));
+    // A context smaller than the safety tokens errors instead of overflowing.
+    assert!(matches!(
+        check_and_get_max_tokens(5, None, 0, Some(10), None),
+        Err(RequestTokenLimitError::PromptTokensExceeds { .. })
+    ));
+}
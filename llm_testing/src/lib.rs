@@ -4,12 +4,14 @@ pub use anyhow::{anyhow, bail, Result};
 use llm_client::LlmPrompt;
 
 pub use backends::*;
+pub use server::*;
 pub use test_loader::*;
 pub use test_types::*;
 #[allow(unused_imports)]
 pub use tracing::{debug, error, info, span, trace, warn, Level};
 
 pub mod backends;
+mod server;
 pub mod speed_bench;
 mod test_loader;
 mod test_types;
@@ -0,0 +1,23 @@
+/// Serves `body` as JSON with `status` to every connection on a local port, and returns the port. The response has a
+/// `Content-Length` header if `content_length` is set, and otherwise ends when the connection closes.
+pub fn serve_json(status: u16, body: String, content_length: bool) -> u16 {
+    use std::io::{Read, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut request = [0; 8192];
+            let _ = stream.read(&mut request);
+            let header = if content_length {
+                format!("Content-Length: {}\r\n", body.len())
+            } else {
+                "Connection: close\r\n".to_string()
+            };
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {status} OK\r\nContent-Type: application/json\r\n{header}\r\n{body}"
+            );
+        }
+    });
+    port
+}